@@ -50,15 +50,27 @@ fn parse_lock_args() -> Result<([u8; 32], ChallengeLockArgs), Error> {
 ///   * after CHALLENGE_MATURITY_BLOCKS, the submitter can resume rollup to running status and revert the invalid rollup states
 /// * cancel challenge
 ///   * during the rollup halting, anyone can submit context to run verification on-chain and cancel this challenge
+///   * a forced withdrawal challenge is cancelled by proving the request is invalid on the account state
 ///   * the cancel-challenge tx must contains a verifier cell in the inputs which cell's lock script equals to the account.script
 ///   * the lock script of verifier cell reads the context from tx.witnesses and run verification
+/// * bisection move
+///   * without the rollup cell, the producer and the challenger of a tx execution challenge take turns to bisect the execution trace
+///   * a tx execution challenge in the bisection game is cancelled if the challenger times out or the disputed step is verified
 pub fn main() -> Result<(), Error> {
     let (rollup_script_hash, lock_args) = parse_lock_args()?;
 
     // check rollup cell
     let mut rollup_action_witness = [0u8; MAX_ROLLUP_WITNESS_SIZE];
-    let index =
-        search_rollup_cell(&rollup_script_hash, Source::Output).ok_or(Error::RollupCellNotFound)?;
+    let index = match search_rollup_cell(&rollup_script_hash, Source::Output) {
+        Some(index) => index,
+        None => {
+            debug!("[challenge-lock] bisection move");
+            return crate::verifications::bisection::verify_bisection_move(
+                &rollup_script_hash,
+                &lock_args,
+            );
+        }
+    };
     let action = parse_rollup_action(&mut rollup_action_witness, index, Source::Output)?;
     match action.to_enum() {
         RollupActionUnionReader::RollupEnterChallenge(_)
@@ -74,11 +86,9 @@ pub fn main() -> Result<(), Error> {
     }
 
     // load rollup config
-    let rollup_config = {
-        let prev_global_state = search_rollup_state(&rollup_script_hash, Source::Input)?
-            .ok_or(Error::RollupCellNotFound)?;
-        load_rollup_config(&prev_global_state.rollup_config_hash().unpack())?
-    };
+    let prev_global_state = search_rollup_state(&rollup_script_hash, Source::Input)?
+        .ok_or(Error::RollupCellNotFound)?;
+    let rollup_config = load_rollup_config(&prev_global_state.rollup_config_hash().unpack())?;
 
     // unlock via cancel challenge
     let challenge_target = lock_args.target();
@@ -90,7 +100,15 @@ pub fn main() -> Result<(), Error> {
     match target_type {
         ChallengeTargetType::TxExecution => {
             debug!("[challenge-lock] target: tx execution");
-            crate::verifications::tx_execution::verify_tx_execution(&rollup_config, &lock_args)?;
+            if !crate::verifications::bisection::verify_bisection_cancel(
+                &rollup_config,
+                &lock_args,
+            )? {
+                crate::verifications::tx_execution::verify_tx_execution(
+                    &rollup_config,
+                    &lock_args,
+                )?;
+            }
         }
         ChallengeTargetType::TxSignature => {
             debug!("[challenge-lock] target: tx signature");
@@ -108,6 +126,22 @@ pub fn main() -> Result<(), Error> {
                 &lock_args,
             )?;
         }
+        ChallengeTargetType::Deposit => {
+            debug!("[challenge-lock] target: deposit");
+            crate::verifications::deposit::verify_deposit(
+                &rollup_script_hash,
+                &rollup_config,
+                &lock_args,
+            )?;
+        }
+        ChallengeTargetType::ForcedWithdrawal => {
+            debug!("[challenge-lock] target: forced withdrawal");
+            crate::verifications::forced_withdrawal::verify_forced_withdrawal(
+                &rollup_script_hash,
+                &rollup_config,
+                &prev_global_state,
+            )?;
+        }
     }
 
     Ok(())
@@ -0,0 +1,305 @@
+use core::result::Result;
+use gw_types::{
+    core::ChallengeTargetType,
+    packed::{
+        BisectionState, BisectionStepWitness, ChallengeLockArgs, OutPoint, RawL2Block,
+        RawL2BlockReader, RollupConfig,
+    },
+    prelude::*,
+};
+use gw_utils::{
+    bisection::{
+        disputed_steps, is_challenger_turn, middle_step, parse_bisection_state, CHALLENGER_TURN,
+        PRODUCER_TURN,
+    },
+    cells::{
+        rollup::{load_rollup_config, search_rollup_state},
+        utils::search_lock_hash,
+    },
+    ckb_std::{
+        ckb_constants::Source,
+        ckb_types::{bytes::Bytes, prelude::Unpack as CKBUnpack},
+        debug,
+        high_level::{
+            load_cell_capacity, load_cell_data, load_cell_lock_hash, load_cell_type_hash,
+            load_input_out_point, load_input_since, load_script_hash, load_witness_args, QueryIter,
+        },
+        since::{LockValue, Since},
+    },
+    error::Error,
+    gw_types,
+};
+
+/// Load the rollup config from the rollup cell in the cell deps,
+/// the bisection moves don't consume the rollup cell
+fn load_bisection_config(rollup_script_hash: &[u8; 32]) -> Result<RollupConfig, Error> {
+    let global_state = search_rollup_state(rollup_script_hash, Source::CellDep)?
+        .ok_or(Error::RollupCellNotFound)?;
+    let rollup_config = load_rollup_config(&global_state.rollup_config_hash().unpack())?;
+    let timeout_blocks: u64 = rollup_config.bisection_timeout_blocks().unpack();
+    if timeout_blocks == 0 {
+        debug!("[bisection] bisection game is disabled");
+        return Err(Error::InvalidRollupConfig);
+    }
+    Ok(rollup_config)
+}
+
+/// Load the bisection state of the re-created challenge cell
+fn load_output_state() -> Result<BisectionState, Error> {
+    let script_hash = load_script_hash()?;
+    if QueryIter::new(load_cell_lock_hash, Source::GroupInput).count() != 1 {
+        debug!("[bisection] expect exactly one challenge cell in the inputs");
+        return Err(Error::InvalidChallengeCell);
+    }
+    let mut outputs = QueryIter::new(load_cell_lock_hash, Source::Output)
+        .enumerate()
+        .filter(|(_i, lock_hash)| lock_hash == &script_hash)
+        .map(|(i, _lock_hash)| i);
+    let index = match (outputs.next(), outputs.next()) {
+        (Some(index), None) => index,
+        _ => {
+            debug!("[bisection] expect exactly one challenge cell in the outputs");
+            return Err(Error::InvalidOutput);
+        }
+    };
+    if load_cell_capacity(index, Source::Output)? != load_cell_capacity(0, Source::GroupInput)?
+        || load_cell_type_hash(index, Source::Output)?
+            != load_cell_type_hash(0, Source::GroupInput)?
+    {
+        debug!("[bisection] challenge cell must be re-created unchanged");
+        return Err(Error::InvalidOutput);
+    }
+    parse_bisection_state(&load_cell_data(index, Source::Output)?)
+        .map_err(|_| Error::InvalidOutput)?
+        .ok_or(Error::InvalidOutput)
+}
+
+fn check_challenger(lock_args: &ChallengeLockArgs) -> Result<(), Error> {
+    let challenger_lock_hash = lock_args.rewards_receiver_lock().hash();
+    if search_lock_hash(&challenger_lock_hash, Source::Input).is_none() {
+        debug!("[bisection] challenger's cell not found");
+        return Err(Error::OwnerCellNotFound);
+    }
+    Ok(())
+}
+
+fn check_producer(state: &BisectionState) -> Result<(), Error> {
+    if search_lock_hash(&state.producer_lock_hash().unpack(), Source::Input).is_none() {
+        debug!("[bisection] producer's cell not found");
+        return Err(Error::OwnerCellNotFound);
+    }
+    Ok(())
+}
+
+/// The challenger opens the bisection game over the execution of the challenged tx
+fn open_bisection(
+    lock_args: &ChallengeLockArgs,
+    output_state: &BisectionState,
+) -> Result<(), Error> {
+    check_challenger(lock_args)?;
+
+    let witness_args: Bytes = load_witness_args(0, Source::GroupInput)?
+        .lock()
+        .to_opt()
+        .ok_or(Error::InvalidArgs)?
+        .unpack();
+    let raw_block = match RawL2BlockReader::verify(&witness_args, false) {
+        Ok(_) => RawL2Block::new_unchecked(witness_args),
+        Err(_) => return Err(Error::InvalidArgs),
+    };
+    let target = lock_args.target();
+    if raw_block.hash() != target.block_hash().as_slice() {
+        debug!("[bisection] wrong challenged block_hash");
+        return Err(Error::InvalidBlock);
+    }
+
+    // the commitments of the first and the last step are the state checkpoints around the tx
+    let tx_index: u32 = target.target_index().unpack();
+    let offset: u32 = raw_block.submit_withdrawals().withdrawal_count().unpack();
+    let checkpoint_list = raw_block.state_checkpoint_list();
+    let start_commitment = match tx_index.checked_sub(1) {
+        Some(prev_tx_index) => checkpoint_list
+            .get((offset + prev_tx_index) as usize)
+            .ok_or(Error::InvalidStateCheckpoint)?,
+        None => raw_block.submit_transactions().prev_state_checkpoint(),
+    };
+    let end_commitment = checkpoint_list
+        .get((offset + tx_index) as usize)
+        .ok_or(Error::InvalidStateCheckpoint)?;
+
+    // the trace length is left to the producer, the end_step is committed by its first move
+    let expected_state = BisectionState::new_builder()
+        .producer_lock_hash(raw_block.stake_cell_owner_lock_hash())
+        .start_step(0u64.pack())
+        .end_step(0u64.pack())
+        .start_commitment(start_commitment)
+        .end_commitment(end_commitment)
+        .turn(PRODUCER_TURN.into())
+        .build();
+    if output_state.as_slice() != expected_state.as_slice() {
+        debug!("[bisection] invalid opening state");
+        return Err(Error::InvalidOutput);
+    }
+    Ok(())
+}
+
+/// The producer posts the commitment at the middle step
+///
+/// The first move of the producer commits the trace length as the end_step. A trace of one
+/// step has no middle step, the producer keeps the turn to prove the step.
+fn producer_move(state: &BisectionState, output_state: &BisectionState) -> Result<(), Error> {
+    check_producer(state)?;
+    let mut next_state = state.clone().as_builder();
+    let end_step: u64 = state.end_step().unpack();
+    if end_step == 0 {
+        let trace_length: u64 = output_state.end_step().unpack();
+        if trace_length == 0 {
+            debug!("[bisection] the trace length must be committed");
+            return Err(Error::InvalidOutput);
+        }
+        next_state = next_state.end_step(trace_length.pack());
+        if trace_length == 1 {
+            if output_state.as_slice() != next_state.build().as_slice() {
+                debug!("[bisection] invalid producer move");
+                return Err(Error::InvalidOutput);
+            }
+            return Ok(());
+        }
+    } else if disputed_steps(state) <= 1 {
+        debug!("[bisection] the disputed step must be proved");
+        return Err(Error::InvalidOutput);
+    }
+    let expected_state = next_state
+        .mid_commitment(output_state.mid_commitment())
+        .turn(CHALLENGER_TURN.into())
+        .build();
+    if output_state.as_slice() != expected_state.as_slice() {
+        debug!("[bisection] invalid producer move");
+        return Err(Error::InvalidOutput);
+    }
+    Ok(())
+}
+
+/// The challenger agrees or disagrees with the middle commitment, which narrows the dispute
+/// to the second or the first half
+fn challenger_move(
+    lock_args: &ChallengeLockArgs,
+    state: &BisectionState,
+    output_state: &BisectionState,
+) -> Result<(), Error> {
+    check_challenger(lock_args)?;
+    let mid_step = middle_step(state);
+    let next_state = state
+        .clone()
+        .as_builder()
+        .mid_commitment(Default::default())
+        .turn(PRODUCER_TURN.into());
+    let agree = next_state
+        .clone()
+        .start_step(mid_step.pack())
+        .start_commitment(state.mid_commitment())
+        .build();
+    let disagree = next_state
+        .end_step(mid_step.pack())
+        .end_commitment(state.mid_commitment())
+        .build();
+    if output_state.as_slice() != agree.as_slice() && output_state.as_slice() != disagree.as_slice()
+    {
+        debug!("[bisection] invalid challenger move");
+        return Err(Error::InvalidOutput);
+    }
+    Ok(())
+}
+
+/// Verify a move of the bisection game, the challenge cell is re-created with the next state
+pub fn verify_bisection_move(
+    rollup_script_hash: &[u8; 32],
+    lock_args: &ChallengeLockArgs,
+) -> Result<(), Error> {
+    let target_type: u8 = lock_args.target().target_type().into();
+    if target_type != ChallengeTargetType::TxExecution as u8 {
+        debug!("[bisection] only tx execution challenges can be bisected");
+        return Err(Error::InvalidArgs);
+    }
+    load_bisection_config(rollup_script_hash)?;
+
+    let output_state = load_output_state()?;
+    match parse_bisection_state(&load_cell_data(0, Source::GroupInput)?)? {
+        None => open_bisection(lock_args, &output_state),
+        Some(state) if is_challenger_turn(&state) => {
+            challenger_move(lock_args, &state, &output_state)
+        }
+        Some(state) => producer_move(&state, &output_state),
+    }
+}
+
+/// Search the step verifier cell whose witness lock commits to the disputed step
+fn search_step_verifier(
+    verifier_lock_hash: &[u8; 32],
+    step_witness: &BisectionStepWitness,
+) -> Result<Option<usize>, Error> {
+    for (index, lock_hash) in QueryIter::new(load_cell_lock_hash, Source::Input).enumerate() {
+        if &lock_hash != verifier_lock_hash {
+            continue;
+        }
+        let witness: Option<Bytes> = load_witness_args(index, Source::Input)?
+            .lock()
+            .to_opt()
+            .map(|witness| witness.unpack());
+        if witness.as_deref() == Some(step_witness.as_slice()) {
+            return Ok(Some(index));
+        }
+    }
+    Ok(None)
+}
+
+/// Cancel a tx execution challenge which is in the bisection game
+///
+/// Returns false if the bisection game isn't opened
+pub fn verify_bisection_cancel(
+    rollup_config: &RollupConfig,
+    lock_args: &ChallengeLockArgs,
+) -> Result<bool, Error> {
+    let state = match parse_bisection_state(&load_cell_data(0, Source::GroupInput)?)? {
+        Some(state) => state,
+        None => return Ok(false),
+    };
+
+    if is_challenger_turn(&state) {
+        // the challenger didn't move in time
+        let timeout_blocks: u64 = rollup_config.bisection_timeout_blocks().unpack();
+        let since = Since::new(load_input_since(0, Source::GroupInput)?);
+        match since.extract_lock_value() {
+            Some(LockValue::BlockNumber(n)) if since.is_relative() && n >= timeout_blocks => {}
+            _ => {
+                debug!("[bisection] challenger can still move");
+                return Err(Error::InvalidSince);
+            }
+        }
+        return Ok(true);
+    }
+
+    if disputed_steps(&state) != 1 {
+        debug!("[bisection] the disputed step isn't determined");
+        return Err(Error::InvalidChallengeCell);
+    }
+    // the step verifier checks the disputed step from start_commitment to end_commitment,
+    // its witness must commit to this challenge cell and the disputed step, so a verifier cell
+    // of another challenge can't cancel this one
+    let challenge_out_point = load_input_out_point(0, Source::GroupInput)?;
+    let target = lock_args.target();
+    let step_witness = BisectionStepWitness::new_builder()
+        .challenge_out_point(OutPoint::new_unchecked(challenge_out_point.as_bytes()))
+        .block_hash(target.block_hash())
+        .target_index(target.target_index())
+        .step(state.start_step())
+        .start_commitment(state.start_commitment())
+        .end_commitment(state.end_commitment())
+        .build();
+    let verifier_lock_hash = rollup_config.bisection_step_verifier_lock_hash().unpack();
+    if search_step_verifier(&verifier_lock_hash, &step_witness)?.is_none() {
+        debug!("[bisection] step verifier cell of the disputed step not found");
+        return Err(Error::AccountScriptCellNotFound);
+    }
+    Ok(true)
+}
@@ -1,7 +1,6 @@
 use core::result::Result;
 use gw_common::{
-    builtins::ETH_REGISTRY_ACCOUNT_ID, merkle_utils::calculate_state_checkpoint,
-    registry_address::RegistryAddress, state::State, H256,
+    merkle_utils::calculate_state_checkpoint, registry_address::RegistryAddress, state::State, H256,
 };
 use gw_state::kv_state::KVState;
 use gw_types::{
@@ -9,7 +8,7 @@ use gw_types::{
     packed::{ChallengeTarget, L2Transaction, RawL2Block, RollupConfig, ScriptVec},
     prelude::*,
 };
-use gw_utils::{ckb_std::debug, error::Error, gw_types::packed::Script};
+use gw_utils::{ckb_std::debug, error::Error, gw_types::packed::Script, registry::eoa_registry_id};
 use gw_utils::{gw_common::merkle_utils::ckb_merkle_leaf_hash, gw_types};
 use gw_utils::{
     gw_common::{self, merkle_utils::CBMTMerkleProof},
@@ -167,8 +166,10 @@ pub fn verify_tx_context(input: TxContextInput) -> Result<TxContext, Error> {
         return Err(Error::MerkleProof);
     }
 
+    let sender_registry_id = eoa_registry_id(rollup_config, &sender_script.code_hash())
+        .ok_or(Error::UnknownEOAScript)?;
     let sender_address = kv_state
-        .get_registry_address_by_script_hash(ETH_REGISTRY_ACCOUNT_ID, &sender_script_hash)?
+        .get_registry_address_by_script_hash(sender_registry_id, &sender_script_hash)?
         .ok_or(Error::RegistryAddressNotFound)?;
 
     let tx_ctx = TxContext {
@@ -0,0 +1,106 @@
+use core::result::Result;
+use gw_state::kv_state::KVState;
+use gw_state::{ckb_smt::smt::Pair, constants::GW_MAX_KV_PAIRS};
+use gw_types::packed::ChallengeLockArgs;
+use gw_utils::gw_types::{
+    self,
+    packed::{CCDepositWitness, CCDepositWitnessReader, RollupConfig},
+    prelude::*,
+};
+use gw_utils::{
+    ckb_std::{
+        ckb_constants::Source,
+        ckb_types::{bytes::Bytes, prelude::Unpack as CKBUnpack},
+        debug,
+        high_level::load_witness_args,
+    },
+    deposit::{calculate_deposit_checkpoint, get_deposit_checkpoint, mint_deposit},
+    error::Error,
+    gw_common::{merkle_utils::calculate_state_checkpoint, state::State, H256},
+};
+
+/// Verify the deposit minting of a block
+///
+/// The minted deposit requests are re-executed from the state checkpoint after the withdrawals,
+/// the result must equal to the `submit_transactions.prev_state_checkpoint`.
+pub fn verify_deposit(
+    rollup_script_hash: &[u8; 32],
+    rollup_config: &RollupConfig,
+    lock_args: &ChallengeLockArgs,
+) -> Result<(), Error> {
+    let witness_args: Bytes = load_witness_args(0, Source::GroupInput)?
+        .lock()
+        .to_opt()
+        .ok_or(Error::InvalidArgs)?
+        .unpack();
+    let unlock_args = match CCDepositWitnessReader::verify(&witness_args, false) {
+        Ok(_) => CCDepositWitness::new_unchecked(witness_args),
+        Err(_) => return Err(Error::InvalidArgs),
+    };
+
+    // verify block hash
+    let raw_block = unlock_args.raw_l2block();
+    if raw_block.hash() != lock_args.target().block_hash().as_slice() {
+        debug!(
+            "Wrong challenged block_hash, block_hash: {:?}, target block hash: {:?}",
+            raw_block.hash(),
+            lock_args.target().block_hash()
+        );
+        return Err(Error::InvalidBlock);
+    }
+
+    // verify the deposit requests are all the minted deposits of the block
+    let deposit_requests = unlock_args.deposit_requests();
+    let deposit_checkpoint =
+        get_deposit_checkpoint(&raw_block.as_reader()).ok_or(Error::InvalidStateCheckpoint)?;
+    if calculate_deposit_checkpoint(deposit_requests.clone())? != deposit_checkpoint {
+        debug!("[verify deposit] deposit checkpoint verify error");
+        return Err(Error::MerkleProof);
+    }
+
+    // check kv state, the deposits are minted after the withdrawals
+    let withdrawal_count: u32 = raw_block.submit_withdrawals().withdrawal_count().unpack();
+    let prev_state_checkpoint: H256 = match withdrawal_count.checked_sub(1) {
+        Some(last_withdrawal_index) => raw_block
+            .state_checkpoint_list()
+            .get(last_withdrawal_index as usize)
+            .ok_or(Error::InvalidStateCheckpoint)?
+            .unpack(),
+        None => {
+            let prev_account = raw_block.prev_account();
+            calculate_state_checkpoint(
+                &prev_account.merkle_root().unpack(),
+                prev_account.count().unpack(),
+            )
+        }
+    };
+    let mut tree_buffer = [Pair::default(); GW_MAX_KV_PAIRS];
+    let kv_state_proof: Bytes = unlock_args.kv_state_proof().unpack();
+    let mut kv_state = KVState::build(
+        &mut tree_buffer,
+        unlock_args.kv_state().as_reader(),
+        &kv_state_proof,
+        unlock_args.account_count().unpack(),
+        None,
+    )?;
+    if kv_state.calculate_state_checkpoint()? != prev_state_checkpoint {
+        debug!("[verify deposit] mismatch prev state checkpoint");
+        return Err(Error::MerkleProof);
+    }
+
+    // re-execute the deposit minting
+    let rollup_script_hash: H256 = (*rollup_script_hash).into();
+    for request in deposit_requests.into_iter() {
+        mint_deposit(&rollup_script_hash, rollup_config, &mut kv_state, &request)?;
+    }
+    let post_state_checkpoint: H256 = raw_block
+        .submit_transactions()
+        .prev_state_checkpoint()
+        .unpack();
+    if kv_state.calculate_state_checkpoint()? != post_state_checkpoint {
+        debug!("[verify deposit] mismatch the state checkpoint after deposits");
+        return Err(Error::InvalidStateCheckpoint);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,68 @@
+use core::result::Result;
+use gw_common::H256;
+use gw_state::kv_state::KVState;
+use gw_state::{ckb_smt::smt::Pair, constants::GW_MAX_KV_PAIRS};
+use gw_utils::gw_types::{
+    packed::{GlobalState, RollupConfig, WithdrawalRequest, WithdrawalRequestReader},
+    prelude::*,
+};
+use gw_utils::{
+    ckb_std::{
+        ckb_constants::Source,
+        ckb_types::{bytes::Bytes, prelude::Unpack as CKBUnpack},
+        debug,
+        high_level::{load_cell_data, load_witness_args},
+    },
+    error::Error,
+    forced_withdrawal::{is_invalid_request, parse_rejection},
+    gw_common,
+};
+
+/// Verify the request of a forced withdrawal challenge is invalid on the current account state,
+/// the challenge cell carries the request as its data
+pub fn verify_forced_withdrawal(
+    rollup_script_hash: &[u8; 32],
+    rollup_config: &RollupConfig,
+    prev_global_state: &GlobalState,
+) -> Result<(), Error> {
+    let request = {
+        let data: Bytes = load_cell_data(0, Source::GroupInput)?.into();
+        match WithdrawalRequestReader::verify(&data, false) {
+            Ok(()) => WithdrawalRequest::new_unchecked(data),
+            Err(_) => return Err(Error::InvalidChallengeCell),
+        }
+    };
+    let witness_args: Bytes = load_witness_args(0, Source::GroupInput)?
+        .lock()
+        .to_opt()
+        .ok_or(Error::InvalidArgs)?
+        .unpack();
+    let rejection = parse_rejection(witness_args)?;
+
+    // check kv state
+    let mut tree_buffer = [Pair::default(); GW_MAX_KV_PAIRS];
+    let kv_state_proof: Bytes = rejection.kv_state_proof().unpack();
+    let kv_state = KVState::build(
+        &mut tree_buffer,
+        rejection.kv_state().as_reader(),
+        &kv_state_proof,
+        prev_global_state.account().count().unpack(),
+        None,
+    )?;
+    let account_root: H256 = prev_global_state.account().merkle_root().unpack();
+    if kv_state.calculate_root()? != account_root {
+        debug!("[verify forced withdrawal] kv state doesn't match the account root");
+        return Err(Error::MerkleProof);
+    }
+
+    if !is_invalid_request(
+        &(*rollup_script_hash).into(),
+        rollup_config,
+        &kv_state,
+        &request.raw(),
+    )? {
+        debug!("[verify forced withdrawal] request is valid");
+        return Err(Error::InvalidForcedWithdrawalCell);
+    }
+    Ok(())
+}
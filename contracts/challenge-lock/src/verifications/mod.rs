@@ -1,5 +1,7 @@
+pub mod bisection;
 pub mod context;
-mod eip712;
+pub mod deposit;
+pub mod forced_withdrawal;
 pub mod tx_execution;
 pub mod tx_signature;
 pub mod withdrawal;
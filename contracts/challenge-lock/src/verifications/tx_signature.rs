@@ -1,5 +1,4 @@
 use crate::verifications::context::{verify_tx_context, TxContext, TxContextInput};
-use crate::verifications::eip712::{traits::EIP712Encode, types::EIP712Domain};
 use alloc::vec;
 use core::result::Result;
 use gw_state::{ckb_smt::smt::Pair, constants::GW_MAX_KV_PAIRS, kv_state::KVState};
@@ -7,12 +6,14 @@ use gw_types::{
     packed::{ChallengeLockArgs, RollupConfig},
     prelude::*,
 };
+use gw_utils::eip712::{traits::EIP712Encode, types::EIP712Domain};
 use gw_utils::{
     ckb_std::{
         ckb_constants::Source,
         ckb_types::{bytes::Bytes, prelude::Unpack as CKBUnpack},
         high_level::load_witness_args,
     },
+    deposit::DEPOSIT_CALL_SIGNATURE,
     error::Error,
     gw_types::{
         core::SigningType,
@@ -23,7 +24,7 @@ use gw_utils::{
     signature::check_l2_account_signature_cell,
 };
 use gw_utils::{
-    gw_common::H256,
+    gw_common::{builtins::ETH_REGISTRY_ACCOUNT_ID, H256},
     gw_types::{self, packed::RawL2Transaction},
 };
 use sha3::{Digest, Keccak256};
@@ -73,6 +74,7 @@ pub fn verify_tx_signature(
     let raw_block = unlock_args.raw_l2block();
     let tx_proof = unlock_args.tx_proof();
     let raw_tx = tx.raw();
+    let is_deposit_call = tx.signature().raw_data() == DEPOSIT_CALL_SIGNATURE;
 
     let input = TxContextInput {
         tx,
@@ -92,6 +94,22 @@ pub fn verify_tx_signature(
         sender_address,
     } = verify_tx_context(input)?;
 
+    // deposit calls are authorized by the deposit cells, the state-validator has checked
+    // them against the deposits which create the sender accounts when submitting the block
+    if is_deposit_call {
+        return Ok(());
+    }
+
+    // the EOA of non-ETH registries sign the raw tx hash
+    if sender_address.registry_id != ETH_REGISTRY_ACCOUNT_ID {
+        check_l2_account_signature_cell(
+            &sender_script_hash,
+            SigningType::Raw,
+            raw_tx.hash().into(),
+        )?;
+        return Ok(());
+    }
+
     let (message, signing_type) = match try_assemble_polyjuice_args(&raw_tx, receiver) {
         Some(rlp_data) => {
             let mut hasher = Keccak256::new();
@@ -103,7 +121,7 @@ pub fn verify_tx_signature(
         }
         None => {
             let chain_id = raw_tx.chain_id().unpack();
-            let typed_tx = crate::verifications::eip712::types::L2Transaction::from_raw(
+            let typed_tx = gw_utils::eip712::types::L2Transaction::from_raw(
                 raw_tx,
                 sender_address,
                 receiver_script_hash,
@@ -1,8 +1,5 @@
-use crate::verifications::eip712::traits::EIP712Encode;
 use core::result::Result;
-use gw_common::{
-    builtins::ETH_REGISTRY_ACCOUNT_ID, registry_address::RegistryAddress, state::State, H256,
-};
+use gw_common::{registry_address::RegistryAddress, state::State, H256};
 use gw_state::kv_state::KVState;
 use gw_state::{ckb_smt::smt::Pair, constants::GW_MAX_KV_PAIRS};
 use gw_types::packed::ChallengeLockArgs;
@@ -20,6 +17,7 @@ use gw_utils::{
     },
     error::Error,
     signature::check_l2_account_signature_cell,
+    withdrawal::calculate_withdrawal_message,
 };
 use gw_utils::{
     gw_common::{
@@ -29,8 +27,6 @@ use gw_utils::{
     gw_types::packed::{CCWithdrawalWitness, CCWithdrawalWitnessReader},
 };
 
-use super::eip712::types::EIP712Domain;
-
 struct WithdrawalContext {
     withdrawal: WithdrawalRequest,
     sender_script_hash: H256,
@@ -107,7 +103,10 @@ fn verify_withdrawal_proof(lock_args: &ChallengeLockArgs) -> Result<WithdrawalCo
     )?;
 
     let withdrawal_address = kv_state
-        .get_registry_address_by_script_hash(ETH_REGISTRY_ACCOUNT_ID, &sender_script_hash)?
+        .get_registry_address_by_script_hash(
+            raw_withdrawal.registry_id().unpack(),
+            &sender_script_hash,
+        )?
         .ok_or(Error::RegistryAddressNotFound)?;
 
     let context = WithdrawalContext {
@@ -142,20 +141,12 @@ pub fn verify_withdrawal(
         return Err(Error::WrongSignature);
     }
 
-    // calculate EIP-712 message
-    let typed_message = crate::verifications::eip712::types::Withdrawal::from_raw(
-        withdrawal.raw(),
-        owner_lock,
-        withdrawal_address,
-    )?;
-    let message = typed_message.eip712_message(
-        EIP712Domain::domain_with_chain_id(raw_withdrawal.chain_id().unpack()).hash_struct(),
-    );
+    let message = calculate_withdrawal_message(&raw_withdrawal, owner_lock, withdrawal_address)?;
     // verify sender's script is in the input
     check_l2_account_signature_cell(
         &sender_script_hash,
         gw_types::core::SigningType::Raw,
-        message.into(),
+        message,
     )?;
     Ok(())
 }
@@ -4,25 +4,28 @@ use core::result::Result;
 use gw_utils::{
     cells::{
         rollup::{
-            load_rollup_config, parse_rollup_action, search_rollup_cell, search_rollup_state,
-            MAX_ROLLUP_WITNESS_SIZE,
+            check_reverted_block, load_rollup_config, parse_rollup_action, search_rollup_cell,
+            search_rollup_state, MAX_ROLLUP_WITNESS_SIZE,
         },
         utils::search_lock_hash,
     },
     ckb_std::high_level::load_cell_lock,
-    gw_types::packed::{DepositLockArgs, DepositLockArgsReader, RollupActionUnionReader},
+    custodian::{parse_lock_args as parse_custodian_lock_args, CustodianLockArgsWithBatchDeposit},
+    deposit::parse_lock_args as parse_deposit_lock_args,
+    escape::{load_abandoned_rollup, AbandonedRollup},
+    finality::is_finalized,
 };
 
 // Import CKB syscalls and structures
 // https://nervosnetwork.github.io/ckb-std/riscv64imac-unknown-none-elf/doc/ckb_std/index.html
 use crate::ckb_std::{
-    ckb_constants::Source, ckb_types::bytes::Bytes, ckb_types::prelude::Unpack as CKBUnpack,
+    ckb_constants::Source, ckb_types::bytes::Bytes, ckb_types::prelude::Unpack as CKBUnpack, debug,
     high_level::load_script, high_level::load_witness_args,
 };
 use gw_types::{
     core::ScriptHashType,
     packed::{
-        CustodianLockArgs, CustodianLockArgsReader, UnlockCustodianViaRevertWitness,
+        BatchDepositLockArgs, CustodianLockArgs, UnlockCustodianViaRevertWitness,
         UnlockCustodianViaRevertWitnessReader,
     },
     prelude::*,
@@ -31,8 +34,8 @@ use gw_utils::gw_types;
 
 use crate::error::Error;
 
-/// args: rollup_type_hash | custodian lock args
-fn parse_lock_args() -> Result<([u8; 32], CustodianLockArgs), Error> {
+/// args: rollup_type_hash | custodian lock args | batch deposit lock args or deposit call (optional)
+fn parse_lock_args() -> Result<([u8; 32], CustodianLockArgsWithBatchDeposit), Error> {
     let script = load_script()?;
     let args: Bytes = script.args().unpack();
 
@@ -41,29 +44,33 @@ fn parse_lock_args() -> Result<([u8; 32], CustodianLockArgs), Error> {
         return Err(Error::InvalidArgs);
     }
     rollup_type_hash.copy_from_slice(&args[..32]);
-    match CustodianLockArgsReader::verify(&args.slice(32..), false) {
-        Ok(()) => Ok((
-            rollup_type_hash,
-            CustodianLockArgs::new_unchecked(args.slice(32..)),
-        )),
-        Err(_) => Err(Error::InvalidArgs),
-    }
+    let lock_args = parse_custodian_lock_args(&args)?;
+    Ok((rollup_type_hash, lock_args))
 }
 
 pub fn main() -> Result<(), Error> {
-    let (rollup_type_hash, lock_args) = parse_lock_args()?;
+    let (
+        rollup_type_hash,
+        CustodianLockArgsWithBatchDeposit {
+            lock_args,
+            batch_deposit_args,
+            call,
+        },
+    ) = parse_lock_args()?;
 
     // read global state from rollup cell
     let global_state = match search_rollup_state(&rollup_type_hash, Source::Input)? {
         Some(state) => state,
-        None => return Err(Error::RollupCellNotFound),
+        // try unlock via the escape hatch if the rollup is abandoned
+        None => {
+            return unlock_via_escape(&rollup_type_hash, &lock_args, batch_deposit_args.as_ref())
+        }
     };
 
     let deposit_block_number: u64 = lock_args.deposit_block_number().unpack();
-    let last_finalized_block_number: u64 = global_state.last_finalized_block_number().unpack();
-
-    if deposit_block_number <= last_finalized_block_number {
-        // this custodian lock is already finalized, rollup will handle the logic
+    if is_finalized(&global_state, deposit_block_number) {
+        // this custodian lock is already finalized, rollup will handle the logic,
+        // including the finalized custodians which are Nervos DAO cells
         return Ok(());
     }
 
@@ -88,47 +95,71 @@ pub fn main() -> Result<(), Error> {
         search_lock_hash(&unlock_args.deposit_lock_hash().unpack(), Source::Output)
             .ok_or(Error::InvalidOutput)?;
     let deposit_lock = load_cell_lock(deposit_cell_index, Source::Output)?;
-    let deposit_lock_args = {
-        let args: Bytes = deposit_lock.args().unpack();
-        if args.len() < rollup_type_hash.len() {
-            return Err(Error::InvalidArgs);
-        }
-        if args[..32] != rollup_type_hash {
-            return Err(Error::InvalidArgs);
+    let deposit_args: Bytes = deposit_lock.args().unpack();
+    if deposit_args.len() < rollup_type_hash.len() {
+        return Err(Error::InvalidArgs);
+    }
+    if deposit_args[..32] != rollup_type_hash {
+        return Err(Error::InvalidArgs);
+    }
+    match batch_deposit_args {
+        // the custodian of a batch deposit is reverted to the batch deposit cell
+        Some(batch_deposit_args) => {
+            if deposit_lock.code_hash().as_slice()
+                != config.batch_deposit_script_type_hash().as_slice()
+                || deposit_lock.hash_type() != ScriptHashType::Type.into()
+                || &deposit_args[32..] != batch_deposit_args.as_slice()
+            {
+                return Err(Error::InvalidOutput);
+            }
         }
-
-        match DepositLockArgsReader::verify(&args.slice(32..), false) {
-            Ok(_) => DepositLockArgs::new_unchecked(args.slice(32..)),
-            Err(_) => return Err(Error::InvalidOutput),
+        None => {
+            let deposit_lock_args =
+                parse_deposit_lock_args(&deposit_args).map_err(|_| Error::InvalidOutput)?;
+            // the reverted deposit carries the call recorded by the custodian
+            if deposit_lock.code_hash().as_slice() != config.deposit_script_type_hash().as_slice()
+                || deposit_lock.hash_type() != ScriptHashType::Type.into()
+                || deposit_lock_args.lock_args.as_slice()
+                    != lock_args.deposit_lock_args().as_slice()
+                || deposit_lock_args.call.as_ref().map(|call| call.as_slice())
+                    != call.as_ref().map(|call| call.as_slice())
+            {
+                return Err(Error::InvalidOutput);
+            }
         }
-    };
-    if deposit_lock.code_hash().as_slice() != config.deposit_script_type_hash().as_slice()
-        || deposit_lock.hash_type() != ScriptHashType::Type.into()
-        || deposit_lock_args.as_slice() != lock_args.deposit_lock_args().as_slice()
-    {
-        return Err(Error::InvalidOutput);
     }
 
     // check deposit block is reverted
-    let deposit_block_hash = lock_args.deposit_block_hash();
     let mut rollup_action_witness = [0u8; MAX_ROLLUP_WITNESS_SIZE];
     let rollup_action = {
         let index = search_rollup_cell(&rollup_type_hash, Source::Output)
             .ok_or(Error::RollupCellNotFound)?;
         parse_rollup_action(&mut rollup_action_witness, index, Source::Output)?
     };
+    let deposit_block_hash: [u8; 32] = lock_args.deposit_block_hash().unpack();
+    check_reverted_block(&global_state, &rollup_action, &deposit_block_hash)
+}
 
-    match rollup_action.to_enum() {
-        RollupActionUnionReader::RollupSubmitBlock(args) => {
-            if args
-                .reverted_block_hashes()
-                .iter()
-                .any(|hash| hash.as_slice() == deposit_block_hash.as_slice())
-            {
-                return Ok(());
-            }
-            Err(Error::InvalidRevertedBlocks)
-        }
-        _ => Err(Error::InvalidRevertedBlocks),
+/// Reclaim the custodian of an unfinalized deposit when the rollup is abandoned,
+/// the deposit owner authorizes it by an input cell
+fn unlock_via_escape(
+    rollup_type_hash: &[u8; 32],
+    lock_args: &CustodianLockArgs,
+    batch_deposit_args: Option<&BatchDepositLockArgs>,
+) -> Result<(), Error> {
+    let AbandonedRollup { global_state, .. } = load_abandoned_rollup(rollup_type_hash)?;
+    let deposit_block_number: u64 = lock_args.deposit_block_number().unpack();
+    if is_finalized(&global_state, deposit_block_number) {
+        debug!("[via escape] deposit is finalized, the custodian belongs to the rollup");
+        return Err(Error::InvalidArgs);
+    }
+
+    let owner_lock_hash: [u8; 32] = match batch_deposit_args {
+        Some(batch_deposit_args) => batch_deposit_args.owner_lock_hash().unpack(),
+        None => lock_args.deposit_lock_args().owner_lock_hash().unpack(),
+    };
+    if search_lock_hash(&owner_lock_hash, Source::Input).is_none() {
+        return Err(Error::OwnerCellNotFound);
     }
+    Ok(())
 }
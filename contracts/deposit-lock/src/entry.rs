@@ -3,7 +3,7 @@
 //! The cell can be unlocked by the rollup cell which match the rollup_type_hash,
 //! or can be unlocked by user.
 //!
-//! Args: DepositLockArgs
+//! Args: DepositLockArgs | DepositCall (optional)
 
 // Import from `core` instead of from `std` since we are in no-std mode
 use core::result::Result;
@@ -22,16 +22,14 @@ use crate::ckb_std::{
 };
 
 use gw_utils::cells::{rollup::search_rollup_cell, utils::search_lock_hash};
+use gw_utils::deposit::{parse_lock_args as parse_deposit_lock_args, DepositLockArgsWithCall};
 
-use gw_types::{
-    packed::{DepositLockArgs, DepositLockArgsReader},
-    prelude::*,
-};
+use gw_types::{packed::DepositLockArgs, prelude::*};
 use gw_utils::gw_types;
 
 use crate::error::Error;
 
-/// args: rollup_type_hash | deposit lock args
+/// args: rollup_type_hash | deposit lock args | deposit call (optional)
 fn parse_lock_args() -> Result<([u8; 32], DepositLockArgs), Error> {
     let mut rollup_type_hash = [0u8; 32];
     let script = load_script()?;
@@ -40,13 +38,9 @@ fn parse_lock_args() -> Result<([u8; 32], DepositLockArgs), Error> {
         return Err(Error::InvalidArgs);
     }
     rollup_type_hash.copy_from_slice(&args[..32]);
-    match DepositLockArgsReader::verify(&args.slice(32..), false) {
-        Ok(()) => Ok((
-            rollup_type_hash,
-            DepositLockArgs::new_unchecked(args.slice(32..)),
-        )),
-        Err(_) => Err(Error::InvalidArgs),
-    }
+    // the deposit call is checked by the rollup state validator
+    let DepositLockArgsWithCall { lock_args, .. } = parse_deposit_lock_args(&args)?;
+    Ok((rollup_type_hash, lock_args))
 }
 
 // We have two unlock paths
@@ -0,0 +1,52 @@
+//! Bisection game
+//!
+//! A tx which can't be re-executed on-chain is challenged by a bisection game over its
+//! execution trace. The challenge cell carries `BisectionState` as its data, the producer posts
+//! the machine state commitment at the middle step of the disputed range, and the challenger
+//! chooses the half it disputes, until one VM step is disputed and verified on-chain.
+//!
+//! The commitments at the first and the last step are the state checkpoints before and after
+//! the tx, the step verifier maps them to the machine states.
+
+use ckb_std::debug;
+use gw_types::{
+    packed::{BisectionState, BisectionStateReader},
+    prelude::*,
+};
+
+use crate::error::Error;
+
+pub const PRODUCER_TURN: u8 = 0;
+pub const CHALLENGER_TURN: u8 = 1;
+
+/// Parse the bisection state from the data of a challenge cell,
+/// returns None if the bisection game isn't opened
+pub fn parse_bisection_state(data: &[u8]) -> Result<Option<BisectionState>, Error> {
+    if data.is_empty() {
+        return Ok(None);
+    }
+    match BisectionStateReader::verify(data, false) {
+        Ok(()) => Ok(Some(BisectionState::new_unchecked(data.to_vec().into()))),
+        Err(_) => {
+            debug!("[bisection] invalid bisection state");
+            Err(Error::InvalidChallengeCell)
+        }
+    }
+}
+
+pub fn is_challenger_turn(state: &BisectionState) -> bool {
+    let turn: u8 = state.turn().into();
+    turn == CHALLENGER_TURN
+}
+
+/// The number of steps in dispute
+pub fn disputed_steps(state: &BisectionState) -> u64 {
+    let start_step: u64 = state.start_step().unpack();
+    let end_step: u64 = state.end_step().unpack();
+    end_step.saturating_sub(start_step)
+}
+
+pub fn middle_step(state: &BisectionState) -> u64 {
+    let start_step: u64 = state.start_step().unpack();
+    start_step + disputed_steps(state) / 2
+}
@@ -1,10 +1,15 @@
 //! Lock cells
 
 use super::types::{
-    BurnCell, CellValue, ChallengeCell, CustodianCell, DepositRequestCell, StakeCell,
-    WithdrawalCell,
+    BatchDepositRequestCell, BurnCell, CellValue, ChallengeCell, CustodianCell, DaoState,
+    DepositRequestCell, ForcedWithdrawalCell, StakeCell, WithdrawalCell,
 };
+use crate::custodian::{
+    parse_lock_args as parse_custodian_lock_args, CustodianLockArgsWithBatchDeposit,
+};
+use crate::deposit::{parse_lock_args as parse_deposit_lock_args, DepositLockArgsWithCall};
 use crate::error::Error;
+use crate::stake_pool::parse_stake_pool;
 use alloc::vec::Vec;
 use ckb_std::{
     ckb_constants::Source,
@@ -19,7 +24,10 @@ use gw_common::{CKB_SUDT_SCRIPT_ARGS, H256};
 use gw_types::{
     bytes::Bytes,
     core::ScriptHashType,
-    packed::{Byte32, Byte32Reader, DepositLockArgs, RollupConfig, StakeLockArgs},
+    packed::{
+        BatchDepositLockArgs, Byte32, Byte32Reader, ForcedWithdrawalLockArgs, RollupConfig,
+        StakeLockArgs, WithdrawalRequest, WithdrawalRequestReader,
+    },
     prelude::*,
 };
 
@@ -41,24 +49,32 @@ fn fetch_sudt_script_hash(
     }
 }
 
-/// used in filter_map
-fn extract_args_from_lock<ArgsType: Entity>(
+/// lock args: rollup_type_hash | args
+fn is_rollup_lock(
     lock: &crate::ckb_std::ckb_types::packed::Script,
     rollup_type_hash: &H256,
     lock_script_type_hash: &Byte32,
-) -> Option<Result<ArgsType, Error>> {
+) -> bool {
     let lock_args: Bytes = lock.args().unpack();
-    let is_lock = lock_args.len() > 32
+    lock_args.len() > 32
         && &lock_args[..32] == rollup_type_hash.as_slice()
         && lock.code_hash().as_slice() == lock_script_type_hash.as_slice()
-        && lock.hash_type() == ScriptHashType::Type.into();
+        && lock.hash_type() == ScriptHashType::Type.into()
+}
 
+/// used in filter_map
+fn extract_args_from_lock<ArgsType: Entity>(
+    lock: &crate::ckb_std::ckb_types::packed::Script,
+    rollup_type_hash: &H256,
+    lock_script_type_hash: &Byte32,
+) -> Option<Result<ArgsType, Error>> {
     // return none to skip this cell
-    if !is_lock {
+    if !is_rollup_lock(lock, rollup_type_hash, lock_script_type_hash) {
         return None;
     }
 
     // parse the remaining lock_args
+    let lock_args: Bytes = lock.args().unpack();
     let raw_args = lock_args[32..].to_vec();
     Some(ArgsType::from_slice(&raw_args).map_err(|_err| {
         debug!("Fail to extract args, lock args parsing err");
@@ -94,6 +110,42 @@ pub fn fetch_capacity_and_sudt_value(
     Ok(value)
 }
 
+/// fetch the Nervos DAO phase of a cell, return none if the cell isn't a DAO cell
+fn fetch_dao_state(
+    config: &RollupConfig,
+    index: usize,
+    source: Source,
+) -> Result<Option<DaoState>, Error> {
+    let dao_script_type_hash = config.dao_script_type_hash();
+    if dao_script_type_hash.as_slice() == H256::zero().as_slice() {
+        return Ok(None);
+    }
+    let is_dao_cell = match load_cell_type(index, source)? {
+        Some(type_) => {
+            type_.hash_type() == ScriptHashType::Type.into()
+                && type_.code_hash().as_slice() == dao_script_type_hash.as_slice()
+        }
+        None => false,
+    };
+    if !is_dao_cell {
+        return Ok(None);
+    }
+    // the data of a DAO cell is the block number of the deposit, zero means deposited
+    let data = load_cell_data(index, source)?;
+    if data.len() != 8 {
+        debug!("invalid DAO cell data");
+        return Err(Error::InvalidCustodianCell);
+    }
+    let mut buf = [0u8; 8];
+    buf.copy_from_slice(&data);
+    let deposit_block_number = u64::from_le_bytes(buf);
+    if deposit_block_number == 0 {
+        Ok(Some(DaoState::Deposited))
+    } else {
+        Ok(Some(DaoState::Withdrawing(deposit_block_number)))
+    }
+}
+
 pub fn collect_stake_cells(
     rollup_type_hash: &H256,
     config: &RollupConfig,
@@ -120,10 +172,18 @@ pub fn collect_stake_cells(
                 debug!("found a stake cell with simple UDT");
                 return Some(Err(Error::InvalidStakeCell));
             }
+            let pool = match load_cell_data(index, source)
+                .map_err(Error::from)
+                .and_then(|data| parse_stake_pool(&data))
+            {
+                Ok(pool) => pool,
+                Err(err) => return Some(Err(err)),
+            };
             let cell = StakeCell {
                 index,
                 args,
                 capacity: value.capacity,
+                pool,
             };
             Some(Ok(cell))
         });
@@ -161,12 +221,12 @@ pub fn find_block_producer_stake_cell(
     Ok(cells.pop())
 }
 
-pub fn find_challenge_cell(
+pub fn collect_challenge_cells(
     rollup_type_hash: &H256,
     config: &RollupConfig,
     source: Source,
-) -> Result<Option<ChallengeCell>, Error> {
-    let iter = QueryIter::new(load_cell_lock, source)
+) -> Result<Vec<ChallengeCell>, Error> {
+    QueryIter::new(load_cell_lock, source)
         .enumerate()
         .filter_map(|(index, lock)| {
             let args = match extract_args_from_lock(
@@ -191,9 +251,19 @@ pub fn find_challenge_cell(
             let cell = ChallengeCell { index, args, value };
             Some(Ok(cell))
         })
-        .take(2);
-    // reject if found multiple stake cells
-    let mut cells = iter.collect::<Result<Vec<_>, Error>>()?;
+        .collect()
+}
+
+/// Find challenge cell
+/// this function return Option<ChallengeCell> if we have 1 or zero challenge cell,
+/// otherwise return an error.
+pub fn find_challenge_cell(
+    rollup_type_hash: &H256,
+    config: &RollupConfig,
+    source: Source,
+) -> Result<Option<ChallengeCell>, Error> {
+    let mut cells = collect_challenge_cells(rollup_type_hash, config, source)?;
+    // reject if found multiple challenge cells
     if cells.len() > 1 {
         return Err(Error::InvalidChallengeCell);
     }
@@ -241,20 +311,48 @@ pub fn collect_custodian_locks(
     QueryIter::new(load_cell_lock, source)
         .enumerate()
         .filter_map(|(index, lock)| {
-            let args = match extract_args_from_lock(
+            if !is_rollup_lock(
                 &lock,
                 rollup_type_hash,
                 &config.custodian_script_type_hash(),
             ) {
-                Some(Ok(args)) => args,
-                Some(Err(err)) => return Some(Err(err)),
-                None => return None,
+                return None;
+            }
+            let CustodianLockArgsWithBatchDeposit {
+                lock_args,
+                batch_deposit_args,
+                call,
+            } = match parse_custodian_lock_args(&lock.args().unpack()) {
+                Ok(args) => args,
+                Err(err) => return Some(Err(err)),
             };
-            let value = match fetch_capacity_and_sudt_value(config, index, source) {
-                Ok(value) => value,
+            let dao_state = match fetch_dao_state(config, index, source) {
+                Ok(dao_state) => dao_state,
                 Err(err) => return Some(Err(err)),
             };
-            let cell = CustodianCell { index, args, value };
+            let value = if dao_state.is_some() {
+                match load_cell_capacity(index, source) {
+                    Ok(capacity) => CellValue {
+                        sudt_script_hash: H256::zero(),
+                        amount: 0,
+                        capacity,
+                    },
+                    Err(err) => return Some(Err(err.into())),
+                }
+            } else {
+                match fetch_capacity_and_sudt_value(config, index, source) {
+                    Ok(value) => value,
+                    Err(err) => return Some(Err(err)),
+                }
+            };
+            let cell = CustodianCell {
+                index,
+                args: lock_args,
+                batch_deposit_args,
+                call,
+                dao_state,
+                value,
+            };
             Some(Ok(cell))
         })
         .collect::<Result<_, Error>>()
@@ -268,10 +366,45 @@ pub fn collect_deposit_locks(
     QueryIter::new(load_cell_lock, source)
         .enumerate()
         .filter_map(|(index, lock)| {
-            let args: DepositLockArgs = match extract_args_from_lock(
+            if !is_rollup_lock(&lock, rollup_type_hash, &config.deposit_script_type_hash()) {
+                return None;
+            }
+            let DepositLockArgsWithCall { lock_args, call } =
+                match parse_deposit_lock_args(&lock.args().unpack()) {
+                    Ok(args) => args,
+                    Err(err) => return Some(Err(err)),
+                };
+            let value = match fetch_capacity_and_sudt_value(config, index, source) {
+                Ok(value) => value,
+                Err(err) => return Some(Err(err)),
+            };
+            let account_script = lock_args.layer2_lock();
+            let account_script_hash = account_script.hash().into();
+            let cell = DepositRequestCell {
+                index,
+                args: lock_args,
+                call,
+                value,
+                account_script,
+                account_script_hash,
+            };
+            Some(Ok(cell))
+        })
+        .collect::<Result<_, Error>>()
+}
+
+pub fn collect_batch_deposit_locks(
+    rollup_type_hash: &H256,
+    config: &RollupConfig,
+    source: Source,
+) -> Result<Vec<BatchDepositRequestCell>, Error> {
+    QueryIter::new(load_cell_lock, source)
+        .enumerate()
+        .filter_map(|(index, lock)| {
+            let args: BatchDepositLockArgs = match extract_args_from_lock(
                 &lock,
                 rollup_type_hash,
-                &config.deposit_script_type_hash(),
+                &config.batch_deposit_script_type_hash(),
             ) {
                 Some(Ok(args)) => args,
                 Some(Err(err)) => return Some(Err(err)),
@@ -281,14 +414,48 @@ pub fn collect_deposit_locks(
                 Ok(value) => value,
                 Err(err) => return Some(Err(err)),
             };
-            let account_script = args.layer2_lock();
-            let account_script_hash = account_script.hash().into();
-            let cell = DepositRequestCell {
+            let cell = BatchDepositRequestCell { index, args, value };
+            Some(Ok(cell))
+        })
+        .collect::<Result<_, Error>>()
+}
+
+pub fn collect_forced_withdrawal_locks(
+    rollup_type_hash: &H256,
+    config: &RollupConfig,
+    source: Source,
+) -> Result<Vec<ForcedWithdrawalCell>, Error> {
+    QueryIter::new(load_cell_lock, source)
+        .enumerate()
+        .filter_map(|(index, lock)| {
+            let args: ForcedWithdrawalLockArgs = match extract_args_from_lock(
+                &lock,
+                rollup_type_hash,
+                &config.forced_withdrawal_script_type_hash(),
+            ) {
+                Some(Ok(args)) => args,
+                Some(Err(err)) => return Some(Err(err)),
+                None => return None,
+            };
+            let capacity = match load_cell_capacity(index, source) {
+                Ok(capacity) => capacity,
+                Err(err) => return Some(Err(err.into())),
+            };
+            // the cell data is the signed withdrawal request
+            let data: Bytes = match load_cell_data(index, source) {
+                Ok(data) => data.into(),
+                Err(err) => return Some(Err(err.into())),
+            };
+            if WithdrawalRequestReader::verify(&data, false).is_err() {
+                debug!("Fail to parsing forced withdrawal request");
+                return Some(Err(Error::Encoding));
+            }
+            let request = WithdrawalRequest::new_unchecked(data);
+            let cell = ForcedWithdrawalCell {
                 index,
                 args,
-                value,
-                account_script,
-                account_script_hash,
+                capacity,
+                request,
             };
             Some(Ok(cell))
         })
@@ -312,3 +479,18 @@ pub fn collect_burn_cells(config: &RollupConfig, source: Source) -> Result<Vec<B
         })
         .collect::<Result<_, Error>>()
 }
+
+/// Check the cell is locked by the rollup bound lock,
+/// which args is the rollup_type_hash and is unlocked only along with the rollup cell
+pub fn is_rollup_bound_cell(
+    rollup_type_hash: &H256,
+    config: &RollupConfig,
+    index: usize,
+    source: Source,
+) -> Result<bool, Error> {
+    let lock = load_cell_lock(index, source)?;
+    let lock_args: Bytes = lock.args().unpack();
+    Ok(lock_args.as_ref() == rollup_type_hash.as_slice()
+        && lock.code_hash().as_slice() == config.rollup_bound_script_type_hash().as_slice()
+        && lock.hash_type() == ScriptHashType::Type.into())
+}
@@ -1,19 +1,27 @@
+use alloc::vec::Vec;
 use ckb_std::{
     ckb_constants::Source,
     debug,
     high_level::{load_cell_data, load_cell_data_hash, load_cell_type_hash, QueryIter},
     syscalls::{load_witness, SysError},
 };
+use gw_common::{
+    h256_ext::H256Ext, smt::Blake2bHasher, sparse_merkle_tree::CompiledMerkleProof, H256,
+};
 use gw_types::{
     packed::{
         GlobalState, GlobalStateReader, GlobalStateV0, GlobalStateV0Reader, RollupActionReader,
-        RollupConfig, RollupConfigReader, WitnessArgsReader,
+        RollupActionUnionReader, RollupConfig, RollupConfigReader, RollupConfigV0,
+        RollupConfigV0Reader, WitnessArgsReader,
     },
     prelude::*,
 };
 
 use crate::error::Error;
 
+/// The max global state version supported by the scripts
+pub const MAX_ROLLUP_VERSION: u8 = 2;
+
 /// 524_288 we choose this value because it is smaller than the MAX_BLOCK_BYTES which is 597K
 pub const MAX_ROLLUP_WITNESS_SIZE: usize = 1 << 19;
 
@@ -27,18 +35,51 @@ fn search_rollup_config_cell(rollup_config_hash: &[u8; 32]) -> Option<usize> {
         .position(|data_hash| data_hash.as_ref() == rollup_config_hash)
 }
 
+/// Load the rollup config of the hash from cell deps
+///
+/// `RollupConfigV0` is converted with the fields introduced by later versions set to zero.
 pub fn load_rollup_config(rollup_config_hash: &[u8; 32]) -> Result<RollupConfig, Error> {
     let index = search_rollup_config_cell(rollup_config_hash).ok_or(Error::RollupConfigNotFound)?;
     let data = load_cell_data(index, Source::CellDep)?;
-    match RollupConfigReader::verify(&data, false) {
-        Ok(_) => Ok(RollupConfig::new_unchecked(data.into())),
-        Err(_) => {
-            debug!("Invalid encoding of RollupConfig");
-            Err(Error::Encoding)
-        }
+    if RollupConfigReader::verify(&data, false).is_ok() {
+        Ok(RollupConfig::new_unchecked(data.into()))
+    } else if RollupConfigV0Reader::verify(&data, false).is_ok() {
+        Ok(convert_rollup_config_v0(RollupConfigV0::new_unchecked(
+            data.into(),
+        )))
+    } else {
+        debug!("Invalid encoding of RollupConfig");
+        Err(Error::Encoding)
     }
 }
 
+/// Return true if the rollup config of the hash is encoded as `RollupConfigV0`
+pub fn is_rollup_config_v0(rollup_config_hash: &[u8; 32]) -> Result<bool, Error> {
+    let index = search_rollup_config_cell(rollup_config_hash).ok_or(Error::RollupConfigNotFound)?;
+    let data = load_cell_data(index, Source::CellDep)?;
+    Ok(RollupConfigV0Reader::verify(&data, false).is_ok())
+}
+
+fn convert_rollup_config_v0(config: RollupConfigV0) -> RollupConfig {
+    RollupConfig::new_builder()
+        .l1_sudt_script_type_hash(config.l1_sudt_script_type_hash())
+        .custodian_script_type_hash(config.custodian_script_type_hash())
+        .deposit_script_type_hash(config.deposit_script_type_hash())
+        .withdrawal_script_type_hash(config.withdrawal_script_type_hash())
+        .challenge_script_type_hash(config.challenge_script_type_hash())
+        .stake_script_type_hash(config.stake_script_type_hash())
+        .l2_sudt_validator_script_type_hash(config.l2_sudt_validator_script_type_hash())
+        .burn_lock_hash(config.burn_lock_hash())
+        .required_staking_capacity(config.required_staking_capacity())
+        .challenge_maturity_blocks(config.challenge_maturity_blocks())
+        .finality_blocks(config.finality_blocks())
+        .reward_burn_rate(config.reward_burn_rate())
+        .chain_id(config.chain_id())
+        .allowed_eoa_type_hashes(config.allowed_eoa_type_hashes())
+        .allowed_contract_type_hashes(config.allowed_contract_type_hashes())
+        .build()
+}
+
 pub fn search_rollup_state(
     rollup_type_hash: &[u8; 32],
     source: Source,
@@ -49,18 +90,64 @@ pub fn search_rollup_state(
         Some(i) => i,
         None => return Ok(None),
     };
+    parse_global_state(index, source).map(Some)
+}
+
+/// Load and decode the global state of the cell
+pub fn parse_global_state(index: usize, source: Source) -> Result<GlobalState, SysError> {
     let data = load_cell_data(index, source)?;
-    match GlobalStateReader::verify(&data, false) {
-        Ok(_) => Ok(Some(GlobalState::new_unchecked(data.into()))),
-        Err(_) if GlobalStateV0Reader::verify(&data, false).is_ok() => {
-            let global_state_v0 = GlobalStateV0::new_unchecked(data.into());
-            Ok(Some(GlobalState::from(global_state_v0)))
+    decode_global_state(data)
+}
+
+/// Decode the global state of any supported version into the latest `GlobalState`
+///
+/// `GlobalStateV0` is converted with the fields introduced by later versions set to zero,
+/// the state validator guarantees the version isn't greater than `MAX_ROLLUP_VERSION`.
+pub fn decode_global_state(data: Vec<u8>) -> Result<GlobalState, SysError> {
+    let global_state = if GlobalStateReader::verify(&data, false).is_ok() {
+        GlobalState::new_unchecked(data.into())
+    } else if GlobalStateV0Reader::verify(&data, false).is_ok() {
+        GlobalState::from(GlobalStateV0::new_unchecked(data.into()))
+    } else {
+        debug!("Invalid encoding of Global state");
+        return Err(SysError::Encoding);
+    };
+    Ok(global_state)
+}
+
+/// Check the version rules from the prev global state to the post global state
+///
+/// The version can only be upgraded, and the fields introduced by a version must follow
+/// the rules of the post version; rules for the fields of a new version go here,
+/// so the lock scripts only see the decoded `GlobalState`.
+pub fn check_global_state_upgrade(
+    prev_global_state: &GlobalState,
+    post_global_state: &GlobalState,
+) -> Result<(), Error> {
+    let prev_version: u8 = prev_global_state.version().into();
+    let post_version: u8 = post_global_state.version().into();
+    if post_version < prev_version {
+        debug!("downgrade rollup version");
+        return Err(Error::InvalidPostGlobalState);
+    }
+    match post_version {
+        version if version > MAX_ROLLUP_VERSION => {
+            debug!("exceeded max rollup version");
+            return Err(Error::InvalidPostGlobalState);
         }
-        Err(_) => {
-            debug!("Invalid encoding of Global state");
-            Err(SysError::Encoding)
+        // version 0 doesn't have `tip_block_timestamp`
+        0 => {
+            let tip_block_timestamp: u64 = post_global_state.tip_block_timestamp().unpack();
+            if tip_block_timestamp != 0 {
+                debug!("v0 global state tip block timestamp isn't 0");
+                return Err(Error::InvalidPostGlobalState);
+            }
         }
+        // version 1 introduces `tip_block_timestamp`, which is set by the submitted blocks,
+        // version 2 blocks commit the deposits at the end of the `state_checkpoint_list`
+        _ => {}
     }
+    Ok(())
 }
 
 pub fn parse_rollup_action(
@@ -68,6 +155,59 @@ pub fn parse_rollup_action(
     index: usize,
     source: Source,
 ) -> Result<RollupActionReader, Error> {
+    parse_rollup_witness(buf, index, source).map(|(action, _input)| action)
+}
+
+/// Check the block is reverted by the rollup action of submitting blocks
+///
+/// The block hash must be listed in the `reverted_block_hashes`, and be proven in the
+/// `reverted_block_root` of the global state. The hash is pruned from the root once the
+/// block number is finalized, then the proof fails and the cells of the block can't be
+/// unlocked via revert.
+pub fn check_reverted_block(
+    global_state: &GlobalState,
+    rollup_action: &RollupActionReader,
+    block_hash: &[u8; 32],
+) -> Result<(), Error> {
+    let (reverted_block_hashes, reverted_block_proof) = match rollup_action.to_enum() {
+        RollupActionUnionReader::RollupSubmitBlock(args) => {
+            (args.reverted_block_hashes(), args.reverted_block_proof())
+        }
+        RollupActionUnionReader::RollupSubmitBlocks(args) => {
+            (args.reverted_block_hashes(), args.reverted_block_proof())
+        }
+        _ => return Err(Error::InvalidRevertedBlocks),
+    };
+    if !reverted_block_hashes
+        .iter()
+        .any(|hash| hash.as_slice() == block_hash)
+    {
+        return Err(Error::InvalidRevertedBlocks);
+    }
+    let leaves: Vec<(H256, H256)> = reverted_block_hashes
+        .iter()
+        .map(|hash| {
+            let hash: [u8; 32] = hash.unpack();
+            (hash.into(), H256::one())
+        })
+        .collect();
+    let is_reverted_block = CompiledMerkleProof(reverted_block_proof.unpack())
+        .verify::<Blake2bHasher>(&global_state.reverted_block_root().unpack(), leaves)
+        .unwrap_or(false);
+    if !is_reverted_block {
+        debug!("reverted block hash isn't in the reverted block root, it may be pruned");
+        return Err(Error::PrunedRevertedBlock);
+    }
+    Ok(())
+}
+
+/// Parse the rollup witness, returns the rollup action in `WitnessArgs#output_type`
+/// and the optional raw data in `WitnessArgs#input_type`
+pub fn parse_rollup_witness(
+    buf: &mut [u8; MAX_ROLLUP_WITNESS_SIZE],
+    index: usize,
+    source: Source,
+) -> Result<(RollupActionReader, Option<&[u8]>), Error> {
     let loaded_len = load_witness(buf, 0, index, source)?;
     debug!("load rollup witness, loaded len: {}", loaded_len);
 
@@ -83,5 +223,9 @@ pub fn parse_rollup_action(
         debug!("output is not a valid RollupActionReader");
         Error::Encoding
     })?;
-    Ok(action)
+    let input = witness_args
+        .input_type()
+        .to_opt()
+        .map(|input| input.raw_data());
+    Ok((action, input))
 }
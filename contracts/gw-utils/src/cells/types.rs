@@ -2,8 +2,9 @@
 
 use crate::gw_common::sparse_merkle_tree::H256;
 use crate::gw_types::packed::{
-    ChallengeLockArgs, CustodianLockArgs, DepositLockArgs, Script, StakeLockArgs,
-    WithdrawalLockArgs,
+    BatchDepositLockArgs, ChallengeLockArgs, CustodianLockArgs, DepositCall, DepositLockArgs,
+    ForcedWithdrawalLockArgs, Script, StakeLockArgs, StakePool, WithdrawalLockArgs,
+    WithdrawalRequest,
 };
 
 #[derive(PartialEq, Eq, Debug, Clone)]
@@ -24,15 +25,38 @@ pub struct WithdrawalCell {
 pub struct DepositRequestCell {
     pub index: usize,
     pub args: DepositLockArgs,
+    pub call: Option<DepositCall>,
     pub value: CellValue,
     pub account_script: Script,
     pub account_script_hash: H256,
 }
 
+pub struct BatchDepositRequestCell {
+    pub index: usize,
+    pub args: BatchDepositLockArgs,
+    pub value: CellValue,
+}
+
+/// The Nervos DAO phase of a custodian cell
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum DaoState {
+    /// deposited into the DAO
+    Deposited,
+    /// withdrawing from the DAO, contains the block number of the deposit
+    Withdrawing(u64),
+}
+
 #[derive(Debug)]
 pub struct CustodianCell {
     pub index: usize,
     pub args: CustodianLockArgs,
+    /// the args of the batch deposit if the custodian is created from a batch deposit
+    pub batch_deposit_args: Option<BatchDepositLockArgs>,
+    /// the call of the deposit if the custodian is created from a deposit carrying a call
+    pub call: Option<DepositCall>,
+    /// the DAO phase if the custodian is a Nervos DAO cell,
+    /// the value of a DAO custodian is the principal, the interest isn't counted
+    pub dao_state: Option<DaoState>,
     pub value: CellValue,
 }
 
@@ -40,6 +64,8 @@ pub struct StakeCell {
     pub index: usize,
     pub args: StakeLockArgs,
     pub capacity: u64,
+    /// the delegators of a stake pool cell
+    pub pool: Option<StakePool>,
 }
 
 pub struct ChallengeCell {
@@ -48,6 +74,13 @@ pub struct ChallengeCell {
     pub value: CellValue,
 }
 
+pub struct ForcedWithdrawalCell {
+    pub index: usize,
+    pub args: ForcedWithdrawalLockArgs,
+    pub capacity: u64,
+    pub request: WithdrawalRequest,
+}
+
 pub struct BurnCell {
     pub index: usize,
     pub value: CellValue,
@@ -11,6 +11,8 @@ use gw_types::{
     prelude::*,
 };
 
+use crate::error::Error;
+
 pub fn search_lock_hashes(owner_lock_hash: &[u8; 32], source: Source) -> Vec<usize> {
     QueryIter::new(load_cell_lock_hash, source)
         .enumerate()
@@ -28,6 +30,32 @@ pub fn search_lock_hash(owner_lock_hash: &[u8; 32], source: Source) -> Option<us
     QueryIter::new(load_cell_lock_hash, source).position(|lock_hash| &lock_hash == owner_lock_hash)
 }
 
+/// Split the lock args `rollup_type_hash | molecule table | tail (optional)`,
+/// the caller verifies the types of the table and the tail
+pub fn split_lock_args(args: &Bytes) -> Result<(Bytes, Option<Bytes>), Error> {
+    let table_start = 32;
+    // the first 4 bytes of a molecule table is the total size
+    let header_end = table_start + 4;
+    if args.len() < header_end {
+        return Err(Error::InvalidArgs);
+    }
+    let mut table_size_buf = [0u8; 4];
+    table_size_buf.copy_from_slice(&args[table_start..header_end]);
+    let table_size = u32::from_le_bytes(table_size_buf) as usize;
+    let table_end = table_start
+        .checked_add(table_size)
+        .ok_or(Error::InvalidArgs)?;
+    if args.len() < table_end {
+        return Err(Error::InvalidArgs);
+    }
+
+    let table = args.slice(table_start..table_end);
+    if args.len() == table_end {
+        return Ok((table, None));
+    }
+    Ok((table, Some(args.slice(table_end..))))
+}
+
 pub fn build_l2_sudt_script(
     rollup_script_hash: &H256,
     config: &RollupConfig,
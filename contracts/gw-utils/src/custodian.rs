@@ -0,0 +1,55 @@
+use ckb_std::debug;
+use gw_types::{
+    bytes::Bytes,
+    packed::{
+        BatchDepositLockArgs, BatchDepositLockArgsReader, CustodianLockArgs,
+        CustodianLockArgsReader, DepositCall, DepositCallReader,
+    },
+    prelude::{Entity, Reader},
+};
+
+use crate::cells::utils::split_lock_args;
+use crate::error::Error;
+
+pub struct CustodianLockArgsWithBatchDeposit {
+    pub lock_args: CustodianLockArgs,
+    pub batch_deposit_args: Option<BatchDepositLockArgs>,
+    /// the call of the deposit, which is recreated when the custodian is reverted
+    pub call: Option<DepositCall>,
+}
+
+/// args: rollup_type_hash | custodian lock args | batch deposit lock args or deposit call (optional)
+pub fn parse_lock_args(args: &Bytes) -> Result<CustodianLockArgsWithBatchDeposit, Error> {
+    let (raw_args, raw_extension) = split_lock_args(args)?;
+    let lock_args = match CustodianLockArgsReader::verify(&raw_args, false) {
+        Ok(()) => CustodianLockArgs::new_unchecked(raw_args),
+        Err(_) => return Err(Error::InvalidArgs),
+    };
+
+    let raw_extension = match raw_extension {
+        Some(raw_extension) => raw_extension,
+        None => {
+            return Ok(CustodianLockArgsWithBatchDeposit {
+                lock_args,
+                batch_deposit_args: None,
+                call: None,
+            })
+        }
+    };
+    if BatchDepositLockArgsReader::verify(&raw_extension, false).is_ok() {
+        return Ok(CustodianLockArgsWithBatchDeposit {
+            lock_args,
+            batch_deposit_args: Some(BatchDepositLockArgs::new_unchecked(raw_extension)),
+            call: None,
+        });
+    }
+    if DepositCallReader::verify(&raw_extension, false).is_ok() {
+        return Ok(CustodianLockArgsWithBatchDeposit {
+            lock_args,
+            batch_deposit_args: None,
+            call: Some(DepositCall::new_unchecked(raw_extension)),
+        });
+    }
+    debug!("[parse custodian] invalid batch deposit lock args or deposit call");
+    Err(Error::InvalidArgs)
+}
@@ -0,0 +1,233 @@
+use alloc::vec::Vec;
+use ckb_std::debug;
+use gw_common::{
+    blake2b::new_blake2b,
+    builtins::CKB_SUDT_ACCOUNT_ID,
+    ckb_decimal::CKBCapacity,
+    merkle_utils::{calculate_ckb_merkle_root, calculate_state_checkpoint, ckb_merkle_leaf_hash},
+    state::State,
+    CKB_SUDT_SCRIPT_ARGS, H256,
+};
+use gw_types::{
+    bytes::Bytes,
+    core::ScriptHashType,
+    packed::{
+        DepositCall, DepositCallReader, DepositLockArgs, DepositLockArgsReader, DepositRequest,
+        RawL2BlockReader, RollupConfig,
+    },
+    prelude::*,
+};
+
+use crate::cells::types::{BatchDepositRequestCell, CellValue, DepositRequestCell};
+use crate::cells::utils::{build_l2_sudt_script, split_lock_args};
+use crate::error::Error;
+use crate::registry::extract_registry_address_from_deposit;
+
+/// The signature of a deposit call transaction,
+/// deposit calls are authorized by the deposit cells instead of the account lock
+pub const DEPOSIT_CALL_SIGNATURE: &[u8] = b"GODWOKEN_DEPOSIT_CALL";
+
+pub struct DepositLockArgsWithCall {
+    pub lock_args: DepositLockArgs,
+    pub call: Option<DepositCall>,
+}
+
+/// args: rollup_type_hash | deposit lock args | deposit call (optional)
+pub fn parse_lock_args(args: &Bytes) -> Result<DepositLockArgsWithCall, Error> {
+    let (raw_args, raw_call) = split_lock_args(args)?;
+    let lock_args = match DepositLockArgsReader::verify(&raw_args, false) {
+        Ok(()) => DepositLockArgs::new_unchecked(raw_args),
+        Err(_) => return Err(Error::InvalidArgs),
+    };
+
+    let call = match raw_call {
+        Some(raw_call) => match DepositCallReader::verify(&raw_call, false) {
+            Ok(()) => Some(DepositCall::new_unchecked(raw_call)),
+            Err(_) => {
+                debug!("[parse deposit] invalid deposit call");
+                return Err(Error::InvalidArgs);
+            }
+        },
+        None => None,
+    };
+    Ok(DepositLockArgsWithCall { lock_args, call })
+}
+
+/// Expand a batch deposit into the deposits of its recipients,
+/// the sum of entries must equal to the batch deposit cell
+pub fn expand_batch_deposit(
+    cell: &BatchDepositRequestCell,
+) -> Result<Vec<DepositRequestCell>, Error> {
+    let entries = cell.args.entries();
+    if entries.is_empty() {
+        debug!("[expand batch deposit] empty entries");
+        return Err(Error::InvalidDepositCell);
+    }
+    let mut total_capacity = 0u64;
+    let mut total_amount = 0u128;
+    let mut deposits = Vec::with_capacity(entries.len());
+    for entry in entries.into_iter() {
+        let capacity: u64 = entry.capacity().unpack();
+        let amount: u128 = entry.amount().unpack();
+        total_capacity = total_capacity
+            .checked_add(capacity)
+            .ok_or(Error::AmountOverflow)?;
+        total_amount = total_amount
+            .checked_add(amount)
+            .ok_or(Error::AmountOverflow)?;
+        let args = DepositLockArgs::new_builder()
+            .owner_lock_hash(cell.args.owner_lock_hash())
+            .layer2_lock(entry.layer2_lock())
+            .cancel_timeout(cell.args.cancel_timeout())
+            .registry_id(entry.registry_id())
+            .build();
+        let account_script = entry.layer2_lock();
+        let account_script_hash = account_script.hash().into();
+        deposits.push(DepositRequestCell {
+            index: cell.index,
+            args,
+            call: None,
+            value: CellValue {
+                sudt_script_hash: cell.value.sudt_script_hash,
+                amount,
+                capacity,
+            },
+            account_script,
+            account_script_hash,
+        });
+    }
+    if total_capacity != cell.value.capacity || total_amount != cell.value.amount {
+        debug!(
+            "[expand batch deposit] entries capacity {} amount {} mismatch the cell",
+            total_capacity, total_amount
+        );
+        return Err(Error::InvalidDepositCell);
+    }
+    Ok(deposits)
+}
+
+/// Build the deposit request of a deposit cell, which is committed in the block
+pub fn build_deposit_request(cell: &DepositRequestCell) -> DepositRequest {
+    let sudt_script_hash: [u8; 32] = cell.value.sudt_script_hash.into();
+    DepositRequest::new_builder()
+        .capacity(cell.value.capacity.pack())
+        .amount(cell.value.amount.pack())
+        .sudt_script_hash(sudt_script_hash.pack())
+        .script(cell.account_script.clone())
+        .registry_id(cell.args.registry_id())
+        .build()
+}
+
+/// Calculate the deposit_root of the deposit requests in the order of minting
+pub fn calculate_deposit_root<I: IntoIterator<Item = DepositRequest>>(
+    requests: I,
+) -> Result<H256, Error> {
+    let leaves = requests
+        .into_iter()
+        .enumerate()
+        .map(|(idx, request)| {
+            let mut hash = [0u8; 32];
+            let mut hasher = new_blake2b();
+            hasher.update(request.as_slice());
+            hasher.finalize(&mut hash);
+            ckb_merkle_leaf_hash(idx as u32, &hash.into())
+        })
+        .collect();
+    let root = calculate_ckb_merkle_root(leaves)?;
+    Ok(root)
+}
+
+/// Calculate the deposit checkpoint hash(deposit_root | deposit_count) of the deposit requests
+pub fn calculate_deposit_checkpoint<I: IntoIterator<Item = DepositRequest>>(
+    requests: I,
+) -> Result<H256, Error> {
+    let requests: Vec<DepositRequest> = requests.into_iter().collect();
+    let deposit_count = requests.len() as u32;
+    let deposit_root = calculate_deposit_root(requests)?;
+    Ok(calculate_state_checkpoint(&deposit_root, deposit_count))
+}
+
+/// Return the deposit checkpoint of the block, which follows the state checkpoints of
+/// the withdrawals and the transactions since global state version 2
+///
+/// Returns None if the block doesn't commit the deposits.
+pub fn get_deposit_checkpoint(raw_block: &RawL2BlockReader) -> Option<H256> {
+    let withdrawal_count: u32 = raw_block.submit_withdrawals().withdrawal_count().unpack();
+    let tx_count: u32 = raw_block.submit_transactions().tx_count().unpack();
+    let index = withdrawal_count as usize + tx_count as usize;
+    let checkpoint_list = raw_block.state_checkpoint_list();
+    if checkpoint_list.len() != index + 1 {
+        return None;
+    }
+    checkpoint_list
+        .get(index)
+        .map(|checkpoint| checkpoint.unpack())
+}
+
+/// Mint the deposit request on layer2, the account of the depositor is created if it doesn't exist
+///
+/// Returns the account id of the depositor.
+pub fn mint_deposit<S: State>(
+    rollup_type_hash: &H256,
+    config: &RollupConfig,
+    state: &mut S,
+    request: &DepositRequest,
+) -> Result<u32, Error> {
+    let account_script = request.script();
+    // check that account's script is a valid EOA script
+    if account_script.hash_type() != ScriptHashType::Type.into() {
+        return Err(Error::UnknownEOAScript);
+    }
+    let account_script_hash: H256 = account_script.hash().into();
+    let registry_id: u32 = request.registry_id().unpack();
+
+    // find or create EOA
+    let (account_id, address) = match state.get_account_id_by_script_hash(&account_script_hash)? {
+        Some(id) => {
+            // account is exist, query registry address
+            let addr = state
+                .get_registry_address_by_script_hash(registry_id, &account_script_hash)?
+                .ok_or(Error::RegistryAddressNotFound)?;
+            (id, addr)
+        }
+        None => {
+            // account isn't exist
+            let new_id = state.create_account(account_script_hash)?;
+            let addr = extract_registry_address_from_deposit(config, registry_id, &account_script)?;
+            // mapping addr to script hash
+            state.mapping_registry_address_to_script_hash(addr.clone(), account_script_hash)?;
+            (new_id, addr)
+        }
+    };
+
+    // mint CKB
+    let capacity: u64 = request.capacity().unpack();
+    state.mint_sudt(
+        CKB_SUDT_ACCOUNT_ID,
+        &address,
+        CKBCapacity::from_layer1(capacity).to_layer2(),
+    )?;
+    let amount: u128 = request.amount().unpack();
+    let sudt_script_hash: H256 = request.sudt_script_hash().unpack();
+    if sudt_script_hash.as_slice() == CKB_SUDT_SCRIPT_ARGS {
+        if amount != 0 {
+            // SUDT amount must equals to zero if sudt script hash is equals to CKB_SUDT_SCRIPT_ARGS
+            return Err(Error::InvalidDepositCell);
+        }
+        return Ok(account_id);
+    }
+    // find or create Simple UDT account
+    let l2_sudt_script = build_l2_sudt_script(rollup_type_hash, config, &sudt_script_hash);
+    let l2_sudt_script_hash: [u8; 32] = l2_sudt_script.hash();
+    let sudt_id = match state.get_account_id_by_script_hash(&l2_sudt_script_hash.into())? {
+        Some(id) => id,
+        None => state.create_account(l2_sudt_script_hash.into())?,
+    };
+    // prevent fake CKB SUDT, the caller should filter these invalid deposits
+    if sudt_id == CKB_SUDT_ACCOUNT_ID {
+        return Err(Error::InvalidDepositCell);
+    }
+    // mint SUDT
+    state.mint_sudt(sudt_id, &address, amount.into())?;
+    Ok(account_id)
+}
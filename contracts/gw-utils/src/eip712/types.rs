@@ -1,14 +1,12 @@
 use core::convert::{TryFrom, TryInto};
 
+use crate::error::Error;
 use alloc::{
     string::{String, ToString},
     vec::Vec,
 };
-use gw_utils::{
-    ckb_std::debug,
-    error::Error,
-    gw_types::{core::ScriptHashType, packed::RawWithdrawalRequest, prelude::Unpack},
-};
+use ckb_std::debug;
+use gw_types::{core::ScriptHashType, packed::RawWithdrawalRequest, prelude::Unpack};
 use sha3::{Digest, Keccak256};
 
 use super::traits::EIP712Encode;
@@ -83,7 +81,7 @@ impl AddressRegistry {
 
     pub fn from_registry_id(registry_id: u32) -> Result<Self, Error> {
         match registry_id {
-            gw_utils::gw_common::builtins::ETH_REGISTRY_ACCOUNT_ID => Ok(Self::ETH),
+            gw_common::builtins::ETH_REGISTRY_ACCOUNT_ID => Ok(Self::ETH),
             _ => {
                 debug!("Unsupported registry id : {}", registry_id);
                 Err(Error::InvalidArgs)
@@ -99,9 +97,7 @@ pub struct RegistryAddress {
 }
 
 impl RegistryAddress {
-    fn from_address(
-        address: gw_utils::gw_common::registry_address::RegistryAddress,
-    ) -> Result<Self, Error> {
+    fn from_address(address: gw_common::registry_address::RegistryAddress) -> Result<Self, Error> {
         let registry = AddressRegistry::from_registry_id(address.registry_id)?;
         if address.address.len() != 20 {
             debug!(
@@ -177,9 +173,9 @@ impl EIP712Encode for L2Transaction {
 
 impl L2Transaction {
     pub fn from_raw(
-        data: gw_utils::gw_types::packed::RawL2Transaction,
-        sender_address: gw_utils::gw_common::registry_address::RegistryAddress,
-        to_script_hash: gw_utils::gw_common::H256,
+        data: gw_types::packed::RawL2Transaction,
+        sender_address: gw_common::registry_address::RegistryAddress,
+        to_script_hash: gw_common::H256,
     ) -> Result<Self, Error> {
         let sender_address = RegistryAddress::from_address(sender_address)?;
         let tx = L2Transaction {
@@ -238,8 +234,8 @@ impl EIP712Encode for Withdrawal {
 impl Withdrawal {
     pub fn from_raw(
         data: RawWithdrawalRequest,
-        owner_lock: gw_utils::gw_types::packed::Script,
-        address: gw_utils::gw_common::registry_address::RegistryAddress,
+        owner_lock: gw_types::packed::Script,
+        address: gw_common::registry_address::RegistryAddress,
     ) -> Result<Self, Error> {
         let hash_type =
             match ScriptHashType::try_from(owner_lock.hash_type()).map_err(|hash_type| {
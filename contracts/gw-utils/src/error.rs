@@ -52,6 +52,31 @@ pub enum Error {
     DuplicatedScriptHash,
     RegistryAddressNotFound,
     DuplicatedRegistryAddress,
+    InvalidRollupConfig,
+    // deposit policy
+    DepositCapacityTooLow,
+    DepositSUDTNotAllowed,
+    TooManyDeposits,
+    // withdrawal limit
+    InvalidWithdrawalLimitCell,
+    WithdrawalLimitExceeded,
+    // producer schedule
+    InvalidProducerRegistryCell,
+    InvalidBlockProducer,
+    // challenge queue
+    InvalidChallengeQueueCell,
+    // forced withdrawal
+    InvalidForcedWithdrawalCell,
+    // finality
+    InvalidTimeFinalityProof,
+    // reverted block hash may be pruned
+    PrunedRevertedBlock,
+    // deposit call
+    InvalidDepositCall,
+    // proof of reserves
+    InsufficientReserves,
+    // Nervos DAO custodian
+    InsufficientLiquidity,
 }
 
 impl From<SysError> for Error {
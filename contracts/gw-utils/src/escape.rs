@@ -0,0 +1,66 @@
+//! Escape hatch
+//!
+//! If the rollup cell doesn't move for `RollupConfig#escape_timeout_blocks` layer1 blocks,
+//! anyone can abandon the rollup by the `RollupAbandon` action. The abandonment is terminal,
+//! the state-validator rejects any action of an abandoned rollup.
+//!
+//! Users exit the abandoned rollup with its last global state, the rollup cell is referenced
+//! as a cell dep. Only the finalized blocks are final, a challenge may be pending on the others.
+
+use ckb_std::{ckb_constants::Source, debug};
+use gw_types::{
+    core::Status,
+    packed::{GlobalState, RollupConfig},
+    prelude::*,
+};
+
+use crate::{
+    cells::rollup::{load_rollup_config, parse_global_state, search_rollup_cell},
+    error::Error,
+};
+
+pub struct AbandonedRollup {
+    pub global_state: GlobalState,
+    pub config: RollupConfig,
+}
+
+/// Return the escape timeout of the config
+///
+/// The timeout must be greater than the finality blocks, so the last blocks of the rollup
+/// can be challenged before the rollup is abandoned.
+pub fn load_escape_timeout_blocks(config: &RollupConfig) -> Result<u64, Error> {
+    let escape_timeout_blocks: u64 = config.escape_timeout_blocks().unpack();
+    if escape_timeout_blocks == 0 {
+        debug!("[escape] escape hatch is disabled");
+        return Err(Error::InvalidRollupConfig);
+    }
+    let finality_blocks: u64 = config.finality_blocks().unpack();
+    if escape_timeout_blocks <= finality_blocks {
+        debug!(
+            "[escape] escape timeout {} isn't greater than finality {}",
+            escape_timeout_blocks, finality_blocks
+        );
+        return Err(Error::InvalidRollupConfig);
+    }
+    Ok(escape_timeout_blocks)
+}
+
+/// Load the last state of the abandoned rollup from cell deps
+pub fn load_abandoned_rollup(rollup_type_hash: &[u8; 32]) -> Result<AbandonedRollup, Error> {
+    let index =
+        search_rollup_cell(rollup_type_hash, Source::CellDep).ok_or(Error::RollupCellNotFound)?;
+    let global_state = parse_global_state(index, Source::CellDep)?;
+    let abandoned: u8 = Status::Abandoned.into();
+    let status: u8 = global_state.status().into();
+    if status != abandoned {
+        debug!("[escape] rollup isn't abandoned, status: {}", status);
+        return Err(Error::InvalidStatus);
+    }
+    let config = load_rollup_config(&global_state.rollup_config_hash().unpack())?;
+    load_escape_timeout_blocks(&config)?;
+
+    Ok(AbandonedRollup {
+        global_state,
+        config,
+    })
+}
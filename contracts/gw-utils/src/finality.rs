@@ -0,0 +1,16 @@
+//! Finality
+//!
+//! A layer-2 block is finalized if its number isn't greater than the
+//! `GlobalState#last_finalized_block_number`.
+//!
+//! The state validator advances the number by the `RollupConfig#finality_blocks` rule,
+//! and optionally by the `RollupConfig#finality_time_ms` rule, so the lock scripts
+//! share one finality rule regardless of how the number is advanced.
+
+use gw_types::{packed::GlobalState, prelude::*};
+
+/// Return true if the block is finalized under the global state
+pub fn is_finalized(global_state: &GlobalState, block_number: u64) -> bool {
+    let last_finalized_block_number: u64 = global_state.last_finalized_block_number().unpack();
+    block_number <= last_finalized_block_number
+}
@@ -0,0 +1,95 @@
+//! Forced withdrawal requests
+//!
+//! The request of a forced withdrawal cell is processed against the layer2 state
+//! in the same way as the withdrawals of a submitted block.
+
+use ckb_std::debug;
+use gw_common::{builtins::CKB_SUDT_ACCOUNT_ID, ckb_decimal::CKBCapacity, state::State, H256};
+use gw_types::{
+    bytes::Bytes,
+    packed::{
+        ForcedWithdrawalHaltWitness, ForcedWithdrawalHaltWitnessReader, ForcedWithdrawalRejection,
+        ForcedWithdrawalRejectionReader, RawWithdrawalRequest, RollupConfig,
+    },
+    prelude::*,
+    U256,
+};
+
+use crate::cells::utils::build_l2_sudt_script;
+use crate::error::Error;
+
+pub fn parse_rejection(witness: Bytes) -> Result<ForcedWithdrawalRejection, Error> {
+    match ForcedWithdrawalRejectionReader::verify(&witness, false) {
+        Ok(()) => Ok(ForcedWithdrawalRejection::new_unchecked(witness)),
+        Err(_) => Err(Error::InvalidArgs),
+    }
+}
+
+pub fn parse_halt_witness(witness: Bytes) -> Result<ForcedWithdrawalHaltWitness, Error> {
+    match ForcedWithdrawalHaltWitnessReader::verify(&witness, false) {
+        Ok(()) => Ok(ForcedWithdrawalHaltWitness::new_unchecked(witness)),
+        Err(_) => Err(Error::InvalidArgs),
+    }
+}
+
+/// Check whether a forced withdrawal request can't be processed on the state,
+/// follows the withdrawal processing of the submitted block:
+/// the sender and the sUDT account must exist, the nonce must equal to the sender's nonce,
+/// and the balances must cover the fee, the capacity and the amount.
+pub fn is_invalid_request<S: State>(
+    rollup_type_hash: &H256,
+    config: &RollupConfig,
+    state: &S,
+    raw: &RawWithdrawalRequest,
+) -> Result<bool, Error> {
+    let account_script_hash: H256 = raw.account_script_hash().unpack();
+    let id = match state.get_account_id_by_script_hash(&account_script_hash)? {
+        Some(id) => id,
+        None => {
+            debug!("[forced withdrawal] sender doesn't exist");
+            return Ok(true);
+        }
+    };
+    let address = match state
+        .get_registry_address_by_script_hash(raw.registry_id().unpack(), &account_script_hash)?
+    {
+        Some(address) => address,
+        None => {
+            debug!("[forced withdrawal] registry address doesn't exist");
+            return Ok(true);
+        }
+    };
+    let nonce: u32 = raw.nonce().unpack();
+    if state.get_nonce(id)? != nonce {
+        debug!("[forced withdrawal] nonce mismatch");
+        return Ok(true);
+    }
+    let l2_sudt_script_hash: [u8; 32] =
+        build_l2_sudt_script(rollup_type_hash, config, &raw.sudt_script_hash().unpack()).hash();
+    let sudt_id = match state.get_account_id_by_script_hash(&l2_sudt_script_hash.into())? {
+        Some(id) => id,
+        None => {
+            debug!("[forced withdrawal] sUDT account doesn't exist");
+            return Ok(true);
+        }
+    };
+
+    let fee: u128 = raw.fee().unpack();
+    let amount: u128 = raw.amount().unpack();
+    let mut required_ckb = U256::from(fee)
+        .checked_add(CKBCapacity::from_layer1(raw.capacity().unpack()).to_layer2())
+        .ok_or(Error::AmountOverflow)?;
+    if sudt_id == CKB_SUDT_ACCOUNT_ID {
+        required_ckb = required_ckb
+            .checked_add(amount.into())
+            .ok_or(Error::AmountOverflow)?;
+    } else if state.get_sudt_balance(sudt_id, &address)? < amount.into() {
+        debug!("[forced withdrawal] insufficient sUDT balance");
+        return Ok(true);
+    }
+    if state.get_sudt_balance(CKB_SUDT_ACCOUNT_ID, &address)? < required_ckb {
+        debug!("[forced withdrawal] insufficient CKB balance");
+        return Ok(true);
+    }
+    Ok(false)
+}
@@ -9,8 +9,17 @@ pub use ckb_std;
 pub use gw_common;
 pub use gw_types;
 
+pub mod bisection;
 pub mod cells;
+pub mod custodian;
+pub mod deposit;
+pub mod eip712;
 pub mod error;
+pub mod escape;
+pub mod finality;
+pub mod forced_withdrawal;
+pub mod registry;
 pub mod signature;
+pub mod stake_pool;
 pub mod type_id;
 pub mod withdrawal;
@@ -0,0 +1,74 @@
+//! Registry
+//!
+//! An EOA is registered under the ETH registry if its type hash is an `Eth` type
+//! in the `RollupConfig#allowed_eoa_type_hashes`, other EOA type hashes are
+//! registered under the registry declared in the `RollupConfig#eoa_registries`,
+//! which must be allowed in the `RollupConfig#allowed_eoa_type_hashes` as well.
+//!
+//! The address of an EOA under a non-ETH registry is the script args after the rollup type hash.
+
+use ckb_std::debug;
+use gw_common::{
+    builtins::ETH_REGISTRY_ACCOUNT_ID, registry::context::RegistryContext,
+    registry_address::RegistryAddress,
+};
+use gw_types::{
+    bytes::Bytes,
+    core::AllowedEoaType,
+    packed::{Byte32, RollupConfig, Script},
+    prelude::*,
+};
+
+use crate::error::Error;
+
+/// Return the registry id of the EOA type hash, return none if the EOA type hash isn't allowed
+pub fn eoa_registry_id(config: &RollupConfig, eoa_type_hash: &Byte32) -> Option<u32> {
+    let allowed_type_hash = config
+        .allowed_eoa_type_hashes()
+        .into_iter()
+        .find(|type_hash| type_hash.hash().as_slice() == eoa_type_hash.as_slice())?;
+    if let Some(registry) = config
+        .eoa_registries()
+        .into_iter()
+        .find(|registry| registry.eoa_type_hash().as_slice() == eoa_type_hash.as_slice())
+    {
+        return Some(registry.registry_id().unpack());
+    }
+    let type_: u8 = allowed_type_hash.type_().into();
+    if type_ == AllowedEoaType::Eth as u8 {
+        return Some(ETH_REGISTRY_ACCOUNT_ID);
+    }
+    None
+}
+
+/// Extract the registry address of the deposit account script under the `registry_id`
+pub fn extract_registry_address_from_deposit(
+    config: &RollupConfig,
+    registry_id: u32,
+    script: &Script,
+) -> Result<RegistryAddress, Error> {
+    if registry_id == ETH_REGISTRY_ACCOUNT_ID {
+        let registry_ctx =
+            RegistryContext::new(config.allowed_eoa_type_hashes().into_iter().collect());
+        let addr = registry_ctx.extract_registry_address_from_deposit(
+            registry_id,
+            &script.code_hash(),
+            &script.args().raw_data(),
+        )?;
+        return Ok(addr);
+    }
+
+    if eoa_registry_id(config, &script.code_hash()) != Some(registry_id) {
+        debug!(
+            "[extract registry address] the EOA type hash isn't registered in registry {}",
+            registry_id
+        );
+        return Err(Error::UnknownEOAScript);
+    }
+    let args: Bytes = script.args().unpack();
+    if args.len() <= 32 {
+        debug!("[extract registry address] invalid EOA args");
+        return Err(Error::InvalidArgs);
+    }
+    Ok(RegistryAddress::new(registry_id, args[32..].to_vec()))
+}
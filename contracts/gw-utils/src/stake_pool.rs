@@ -0,0 +1,60 @@
+//! Stake pool
+//!
+//! A stake cell carries `StakePool` as its data if the stake is funded by delegators,
+//! the stake is paid back to the delegators pro rata to their shares.
+
+use alloc::collections::BTreeMap;
+use ckb_std::debug;
+use gw_types::{
+    packed::{StakePool, StakePoolReader},
+    prelude::*,
+};
+
+use crate::error::Error;
+
+/// Parse the stake pool from the data of a stake cell, returns None for a plain stake cell
+pub fn parse_stake_pool(data: &[u8]) -> Result<Option<StakePool>, Error> {
+    if data.is_empty() {
+        return Ok(None);
+    }
+    if StakePoolReader::verify(data, false).is_err() {
+        debug!("[stake pool] invalid stake pool data");
+        return Err(Error::InvalidStakeCell);
+    }
+    let pool = StakePool::new_unchecked(data.to_vec().into());
+    if total_shares(&pool) == 0 {
+        debug!("[stake pool] no delegator shares");
+        return Err(Error::InvalidStakeCell);
+    }
+    Ok(Some(pool))
+}
+
+fn total_shares(pool: &StakePool) -> u128 {
+    pool.delegators()
+        .into_iter()
+        .map(|delegator| {
+            let share: u64 = delegator.share().unpack();
+            share as u128
+        })
+        .sum()
+}
+
+/// Split the capacity pro rata to the delegator shares, and add it to the payouts
+pub fn add_delegator_payouts(
+    pool: &StakePool,
+    capacity: u64,
+    payouts: &mut BTreeMap<[u8; 32], u128>,
+) {
+    let total_shares = total_shares(pool);
+    if total_shares == 0 {
+        return;
+    }
+    for delegator in pool.delegators().into_iter() {
+        let share: u64 = delegator.share().unpack();
+        let amount = (capacity as u128).saturating_mul(share.into()) / total_shares;
+        let payout = payouts
+            .entry(delegator.owner_lock_hash().unpack())
+            .or_default();
+        *payout = payout.saturating_add(amount);
+    }
+}
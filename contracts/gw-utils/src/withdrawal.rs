@@ -1,10 +1,17 @@
 use ckb_std::debug;
+use gw_common::{builtins::ETH_REGISTRY_ACCOUNT_ID, registry_address::RegistryAddress, H256};
 use gw_types::{
     bytes::Bytes,
-    packed::{Script, ScriptReader, WithdrawalLockArgs, WithdrawalLockArgsReader},
+    packed::{
+        RawWithdrawalRequest, Script, ScriptReader, WithdrawalLockArgs, WithdrawalLockArgsReader,
+    },
     prelude::{Entity, Reader, Unpack},
 };
 
+use crate::eip712::{
+    traits::EIP712Encode,
+    types::{EIP712Domain, Withdrawal},
+};
 use crate::error::Error;
 
 pub struct WithdrawalLockArgsWithOwnerLock {
@@ -60,3 +67,22 @@ pub fn parse_lock_args(args: &Bytes) -> Result<WithdrawalLockArgsWithOwnerLock,
         owner_lock,
     })
 }
+
+/// Calculate the message signed by the withdrawal sender,
+/// the EIP-712 message for the ETH registry, the EOA of other registries sign the raw withdrawal hash
+pub fn calculate_withdrawal_message(
+    raw_withdrawal: &RawWithdrawalRequest,
+    owner_lock: Script,
+    withdrawal_address: RegistryAddress,
+) -> Result<H256, Error> {
+    let message = if withdrawal_address.registry_id == ETH_REGISTRY_ACCOUNT_ID {
+        let typed_message =
+            Withdrawal::from_raw(raw_withdrawal.clone(), owner_lock, withdrawal_address)?;
+        typed_message.eip712_message(
+            EIP712Domain::domain_with_chain_id(raw_withdrawal.chain_id().unpack()).hash_struct(),
+        )
+    } else {
+        raw_withdrawal.hash()
+    };
+    Ok(message.into())
+}
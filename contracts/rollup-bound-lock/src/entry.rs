@@ -0,0 +1,45 @@
+//! Rollup-bound-lock
+//! The lock of the cells which are only updated by the rollup actions,
+//! e.g. the withdrawal limit state cell and the challenge queue cell.
+//! The cell can only be unlocked by the rollup cell which match the rollup_type_hash,
+//! the following verification will be handled by rollup state validator.
+//!
+//! Args: rollup_type_hash
+
+// Import from `core` instead of from `std` since we are in no-std mode
+use core::result::Result;
+
+// Import CKB syscalls and structures
+// https://nervosnetwork.github.io/ckb-std/riscv64imac-unknown-none-elf/doc/ckb_std/index.html
+use crate::ckb_std::{
+    ckb_constants::Source,
+    ckb_types::{bytes::Bytes, prelude::Unpack as CKBTypeUnpack},
+    high_level::load_script,
+};
+
+use gw_utils::cells::rollup::search_rollup_cell;
+
+use crate::error::Error;
+
+/// args: rollup_type_hash
+fn parse_lock_args() -> Result<[u8; 32], Error> {
+    let mut rollup_type_hash = [0u8; 32];
+    let script = load_script()?;
+    let args: Bytes = script.args().unpack();
+    if args.len() != rollup_type_hash.len() {
+        return Err(Error::InvalidArgs);
+    }
+    rollup_type_hash.copy_from_slice(&args);
+    Ok(rollup_type_hash)
+}
+
+pub fn main() -> Result<(), Error> {
+    let rollup_type_hash = parse_lock_args()?;
+
+    // Unlock by Rollup cell
+    if search_rollup_cell(&rollup_type_hash, Source::Input).is_some() {
+        return Ok(());
+    }
+
+    Err(Error::RollupCellNotFound)
+}
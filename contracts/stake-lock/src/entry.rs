@@ -5,21 +5,24 @@ use core::result::Result;
 
 // Import heap related library from `alloc`
 // https://doc.rust-lang.org/alloc/index.html
-// use alloc::{vec, vec::Vec};
+use alloc::collections::BTreeMap;
 
 // Import CKB syscalls and structures
 // https://nervosnetwork.github.io/ckb-std/riscv64imac-unknown-none-elf/doc/ckb_std/index.html
 use crate::ckb_std::{
     ckb_constants::Source,
     ckb_types::{bytes::Bytes, prelude::Unpack as CKBTypeUnpack},
-    high_level::load_script,
+    debug,
+    high_level::{load_cell_capacity, load_cell_data, load_script, QueryIter},
 };
 
 use gw_utils::cells::{
     rollup::{search_rollup_cell, search_rollup_state},
-    utils::search_lock_hash,
+    utils::{search_lock_hash, search_lock_hashes},
 };
+use gw_utils::finality::is_finalized;
 use gw_utils::gw_types;
+use gw_utils::stake_pool::{add_delegator_payouts, parse_stake_pool};
 
 use gw_types::{
     packed::{StakeLockArgs, StakeLockArgsReader},
@@ -53,14 +56,15 @@ pub fn main() -> Result<(), Error> {
     // read global state from rollup cell in deps
     if let Some(global_state) = search_rollup_state(&rollup_type_hash, Source::CellDep)? {
         let stake_block_number: u64 = lock_args.stake_block_number().unpack();
-        let last_finalized_block_number: u64 = global_state.last_finalized_block_number().unpack();
 
         // 1. check if stake_block_number is finalized
-        // 2. check if owner_lock_hash exists in input cells
-        if stake_block_number <= last_finalized_block_number
-            && search_lock_hash(&lock_args.owner_lock_hash().unpack(), Source::Input).is_some()
-        {
-            return Ok(());
+        // 2. check if owner_lock_hash exists in input cells,
+        //    or the stake pool cells are paid back to the delegators
+        if is_finalized(&global_state, stake_block_number) {
+            if search_lock_hash(&lock_args.owner_lock_hash().unpack(), Source::Input).is_some() {
+                return check_delegator_payouts(false);
+            }
+            return check_delegator_payouts(true);
         }
     }
 
@@ -73,3 +77,44 @@ pub fn main() -> Result<(), Error> {
 
     Err(Error::InvalidStakeCellUnlock)
 }
+
+/// Check each delegator of the stake pool cells receives its share of the stake,
+/// a delegator can unlock the stake pool cells by an input cell
+fn check_delegator_payouts(require_delegator: bool) -> Result<(), Error> {
+    let mut payouts: BTreeMap<[u8; 32], u128> = BTreeMap::new();
+    for (index, data) in QueryIter::new(load_cell_data, Source::GroupInput).enumerate() {
+        match parse_stake_pool(&data)? {
+            Some(pool) => {
+                let capacity = load_cell_capacity(index, Source::GroupInput)?;
+                add_delegator_payouts(&pool, capacity, &mut payouts);
+            }
+            None if require_delegator => return Err(Error::InvalidStakeCellUnlock),
+            None => {}
+        }
+    }
+    if require_delegator
+        && !payouts
+            .keys()
+            .any(|lock_hash| search_lock_hash(lock_hash, Source::Input).is_some())
+    {
+        return Err(Error::InvalidStakeCellUnlock);
+    }
+
+    for (lock_hash, expected_capacity) in payouts {
+        let input_capacity = lock_hashes_capacity(&lock_hash, Source::Input)?;
+        let output_capacity = lock_hashes_capacity(&lock_hash, Source::Output)?;
+        if output_capacity.saturating_sub(input_capacity) < expected_capacity {
+            debug!("[stake pool] delegator doesn't receive its share");
+            return Err(Error::InvalidOutput);
+        }
+    }
+    Ok(())
+}
+
+fn lock_hashes_capacity(lock_hash: &[u8; 32], source: Source) -> Result<u128, Error> {
+    let mut capacity: u128 = 0;
+    for index in search_lock_hashes(lock_hash, source) {
+        capacity = capacity.saturating_add(load_cell_capacity(index, source)?.into());
+    }
+    Ok(capacity)
+}
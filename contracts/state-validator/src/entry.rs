@@ -3,14 +3,17 @@ use core::result::Result;
 
 // Import heap related library from `alloc`
 // https://doc.rust-lang.org/alloc/index.html
+use alloc::vec::Vec;
 use gw_utils::{
-    cells::rollup::{load_rollup_config, parse_rollup_action, MAX_ROLLUP_WITNESS_SIZE},
+    cells::rollup::{
+        check_global_state_upgrade, load_rollup_config, parse_global_state, parse_rollup_witness,
+        MAX_ROLLUP_WITNESS_SIZE,
+    },
     ckb_std::{
         ckb_types::prelude::Unpack as CKBUnpack,
-        debug,
-        high_level::{load_cell_capacity, load_cell_data, load_script},
+        high_level::{load_cell_capacity, load_script},
     },
-    gw_types::packed::{GlobalStateV0, GlobalStateV0Reader, RollupActionUnionReader},
+    gw_types::{core::Status, packed::RollupActionUnionReader},
     type_id::{check_type_id, TYPE_ID_SIZE},
 };
 
@@ -21,39 +24,18 @@ use crate::{
     verifications,
 };
 
-use gw_types::{
-    bytes::Bytes,
-    packed::{GlobalState, GlobalStateReader},
-    prelude::*,
-};
+use gw_types::{bytes::Bytes, prelude::*};
 use gw_utils::gw_types;
 
 use gw_utils::error::Error;
 
-const MAX_ROLLUP_VERSION: u8 = 1;
-
-pub fn parse_global_state(source: Source) -> Result<GlobalState, Error> {
-    let data = load_cell_data(0, source)?;
-    match GlobalStateReader::verify(&data, false) {
-        Ok(_) => Ok(GlobalState::new_unchecked(data.into())),
-        Err(_) if GlobalStateV0Reader::verify(&data, false).is_ok() => {
-            let global_state_v0 = GlobalStateV0::new_unchecked(data.into());
-            Ok(GlobalState::from(global_state_v0))
-        }
-        Err(_) => {
-            debug!("Fail to parsing global state");
-            Err(Error::Encoding)
-        }
-    }
-}
-
 /// return true if we are in the initialization, otherwise return false
 fn check_initialization() -> Result<bool, Error> {
     if load_cell_capacity(0, Source::GroupInput).is_ok() {
         return Ok(false);
     }
     // no input Rollup cell, which represents we are in the initialization
-    let post_global_state = parse_global_state(Source::GroupOutput)?;
+    let post_global_state = parse_global_state(0, Source::GroupOutput)?;
     // check config cell exists
     let _rollup_config = load_rollup_config(&post_global_state.rollup_config_hash().unpack())?;
     Ok(true)
@@ -76,24 +58,24 @@ pub fn main() -> Result<(), Error> {
         return Ok(());
     }
     // basic verification
-    let prev_global_state = parse_global_state(Source::GroupInput)?;
-    let post_global_state = parse_global_state(Source::GroupOutput)?;
+    let prev_global_state = parse_global_state(0, Source::GroupInput)?;
+    let post_global_state = parse_global_state(0, Source::GroupOutput)?;
     let rollup_config = load_rollup_config(&prev_global_state.rollup_config_hash().unpack())?;
     let rollup_type_hash = load_script_hash()?.into();
 
-    let post_version: u8 = post_global_state.version().into();
-    if post_version > MAX_ROLLUP_VERSION {
-        debug!("exceeded max rollup version");
-        return Err(Error::InvalidPostGlobalState);
-    }
-    if post_version < prev_global_state.version().into() {
-        debug!("downgrade rollup version");
-        return Err(Error::InvalidPostGlobalState);
+    check_global_state_upgrade(&prev_global_state, &post_global_state)?;
+
+    // the abandonment is terminal, users may have escaped with the custodians
+    let abandoned: u8 = Status::Abandoned.into();
+    let prev_status: u8 = prev_global_state.status().into();
+    if prev_status == abandoned {
+        return Err(Error::InvalidStatus);
     }
 
     // load rollup action
     let mut rollup_witness_buf = [0u8; MAX_ROLLUP_WITNESS_SIZE];
-    let action = parse_rollup_action(&mut rollup_witness_buf, 0, Source::GroupOutput)?;
+    let (action, input) = parse_rollup_witness(&mut rollup_witness_buf, 0, Source::GroupOutput)?;
+    let time_finality_proof = verifications::finality::parse_time_finality_proof(input)?;
     match action.to_enum() {
         RollupActionUnionReader::RollupSubmitBlock(args) => {
             // verify submit block
@@ -103,6 +85,7 @@ pub fn main() -> Result<(), Error> {
                 &args.block(),
                 &prev_global_state,
                 &post_global_state,
+                time_finality_proof.as_ref(),
             )?;
             // merkle verify reverted_block_hashes,
             // other rollup locks will check reverted blocks by compare block hash with this field
@@ -141,6 +124,126 @@ pub fn main() -> Result<(), Error> {
                 &post_global_state,
             )?;
         }
+        RollupActionUnionReader::RollupSubmitBlocks(args) => {
+            // verify submit blocks
+            let blocks: Vec<_> = args.blocks().iter().collect();
+            verifications::submit_block::verify_blocks(
+                rollup_type_hash,
+                &rollup_config,
+                &blocks,
+                &prev_global_state,
+                &post_global_state,
+                time_finality_proof.as_ref(),
+            )?;
+            verifications::submit_block::verify_reverted_block_hashes(
+                args.reverted_block_hashes().unpack(),
+                args.reverted_block_proof().unpack(),
+                &prev_global_state,
+            )?;
+        }
+        RollupActionUnionReader::RollupUpgradeConfig(_args) => {
+            // verify upgrade rollup config
+            verifications::upgrade_config::verify(
+                rollup_type_hash,
+                &rollup_config,
+                &prev_global_state,
+                &post_global_state,
+            )?;
+        }
+        RollupActionUnionReader::RollupRebalanceCustodians(_args) => {
+            // verify rebalance custodian cells
+            verifications::rebalance_custodian::verify(
+                rollup_type_hash,
+                &rollup_config,
+                &prev_global_state,
+                &post_global_state,
+            )?;
+        }
+        RollupActionUnionReader::RollupSetPaused(args) => {
+            // verify pause / resume
+            verifications::pause::verify(
+                rollup_type_hash,
+                &rollup_config,
+                args,
+                &prev_global_state,
+                &post_global_state,
+            )?;
+        }
+        RollupActionUnionReader::RollupForceHalt(_args) => {
+            // verify force halt
+            verifications::forced_withdrawal::verify_force_halt(
+                rollup_type_hash,
+                &rollup_config,
+                &prev_global_state,
+                &post_global_state,
+            )?;
+        }
+        RollupActionUnionReader::RollupPruneRevertedBlocks(args) => {
+            // verify prune reverted blocks
+            verifications::prune_reverted_blocks::verify(
+                rollup_type_hash,
+                &rollup_config,
+                args,
+                &prev_global_state,
+                &post_global_state,
+            )?;
+        }
+        RollupActionUnionReader::RollupDaoCustodians(args) => {
+            // verify Nervos DAO custodians
+            verifications::dao_custodian::verify(
+                rollup_type_hash,
+                &rollup_config,
+                args,
+                &prev_global_state,
+                &post_global_state,
+            )?;
+        }
+        RollupActionUnionReader::RollupProveReserves(args) => {
+            // verify proof of reserves
+            verifications::proof_of_reserves::verify(
+                rollup_type_hash,
+                &rollup_config,
+                args,
+                &prev_global_state,
+                &post_global_state,
+            )?;
+        }
+        RollupActionUnionReader::RollupAbandon(_args) => {
+            // verify abandon
+            verifications::abandon::verify(
+                rollup_type_hash,
+                &rollup_config,
+                &prev_global_state,
+                &post_global_state,
+            )?;
+        }
+    }
+
+    // the withdrawal limit state cell is only updated by submitting blocks
+    if !matches!(
+        action.to_enum(),
+        RollupActionUnionReader::RollupSubmitBlock(_)
+            | RollupActionUnionReader::RollupSubmitBlocks(_)
+    ) {
+        verifications::withdrawal_limit::check_withdrawal_limit_state_unchanged(
+            &rollup_type_hash,
+            &rollup_config,
+        )?;
+    }
+
+    // the challenge queue cell is only updated by entering, cancelling and reverting challenges,
+    // a force halt enters a forced withdrawal challenge
+    if !matches!(
+        action.to_enum(),
+        RollupActionUnionReader::RollupEnterChallenge(_)
+            | RollupActionUnionReader::RollupCancelChallenge(_)
+            | RollupActionUnionReader::RollupRevert(_)
+            | RollupActionUnionReader::RollupForceHalt(_)
+    ) {
+        verifications::challenge_queue::check_challenge_queue_unchanged(
+            &rollup_type_hash,
+            &rollup_config,
+        )?;
     }
 
     Ok(())
@@ -0,0 +1,73 @@
+//! Abandon the rollup
+//!
+//! If the rollup cell doesn't move for `RollupConfig#escape_timeout_blocks` layer1 blocks,
+//! anyone can change the status to abandoned, then users exit by the escape hatch of the
+//! custodian lock and the withdrawal lock.
+//! The abandonment is terminal, the entry rejects any action of an abandoned rollup,
+//! otherwise the custodians reclaimed by the escape would leave the layer2 state unbacked.
+
+use gw_common::H256;
+use gw_types::{
+    core::Status,
+    packed::{GlobalState, RollupConfig},
+    prelude::*,
+};
+use gw_utils::gw_common;
+use gw_utils::gw_types;
+use gw_utils::{
+    ckb_std::{
+        ckb_constants::Source,
+        debug,
+        high_level::load_input_since,
+        since::{LockValue, Since},
+    },
+    error::Error,
+    escape::load_escape_timeout_blocks,
+};
+
+use super::check_rollup_lock_cells;
+
+/// Check the rollup cell hasn't moved for the escape timeout
+fn check_escape_timeout(config: &RollupConfig) -> Result<(), Error> {
+    let escape_timeout_blocks = load_escape_timeout_blocks(config)?;
+    let since = Since::new(load_input_since(0, Source::GroupInput)?);
+    if let Some(LockValue::BlockNumber(n)) = since.extract_lock_value() {
+        if since.is_relative() && n >= escape_timeout_blocks {
+            return Ok(());
+        }
+    }
+    debug!(
+        "[abandon] rollup cell isn't timeout, since: {}",
+        since.as_u64()
+    );
+    Err(Error::InvalidSince)
+}
+
+/// Verify abandon
+/// 1. the rollup input has a relative block number since of at least the escape timeout
+/// 2. check post global state only changes the status to abandoned
+pub fn verify(
+    rollup_type_hash: H256,
+    config: &RollupConfig,
+    prev_global_state: &GlobalState,
+    post_global_state: &GlobalState,
+) -> Result<(), Error> {
+    // check rollup lock cells
+    check_rollup_lock_cells(&rollup_type_hash, config)?;
+    check_escape_timeout(config)?;
+
+    // check post global state
+    let actual_post_global_state = {
+        let status: u8 = Status::Abandoned.into();
+        prev_global_state
+            .clone()
+            .as_builder()
+            .status(status.into())
+            .build()
+    };
+    if post_global_state != &actual_post_global_state {
+        debug!("[abandon] mismatch post global state");
+        return Err(Error::InvalidPostGlobalState);
+    }
+    Ok(())
+}
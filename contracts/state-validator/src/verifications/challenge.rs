@@ -8,8 +8,10 @@ use gw_types::{
 };
 use gw_utils::{
     cells::lock_cells::{collect_burn_cells, find_challenge_cell},
-    ckb_std::{ckb_constants::Source, debug},
+    ckb_std::{ckb_constants::Source, debug, high_level::load_cell_data},
+    deposit::get_deposit_checkpoint,
     error::Error,
+    finality::is_finalized,
 };
 use gw_utils::{cells::types::ChallengeCell, gw_types};
 use gw_utils::{
@@ -17,7 +19,12 @@ use gw_utils::{
     gw_types::packed::{RawL2Block, RollupEnterChallengeReader},
 };
 
-use super::{check_rollup_lock_cells, check_status};
+use super::{
+    challenge_queue::{
+        is_challenge_queue_enabled, status_after_closing_challenges, update_challenge_queue,
+    },
+    check_rollup_lock_cells, check_status,
+};
 
 pub fn verify_enter_challenge(
     rollup_type_hash: H256,
@@ -26,7 +33,11 @@ pub fn verify_enter_challenge(
     prev_global_state: &GlobalState,
     post_global_state: &GlobalState,
 ) -> Result<(), Error> {
-    check_status(prev_global_state, Status::Running)?;
+    // with the challenge queue, more challenges can be entered during the rollup halting
+    let is_halting = check_status(prev_global_state, Status::Halting).is_ok();
+    if !(is_challenge_queue_enabled(config) && is_halting) {
+        check_status(prev_global_state, Status::Running)?;
+    }
     // check challenge cells
     let has_input_challenge =
         find_challenge_cell(&rollup_type_hash, config, Source::Input)?.is_some();
@@ -35,13 +46,22 @@ pub fn verify_enter_challenge(
     }
     let challenge_cell = find_challenge_cell(&rollup_type_hash, config, Source::Output)?
         .ok_or(Error::InvalidChallengeCell)?;
+    // the bisection game is opened by the challenge-lock after entering challenge
+    if !load_cell_data(challenge_cell.index, Source::Output)?.is_empty() {
+        debug!("enter challenge with non-empty challenge cell data");
+        return Err(Error::InvalidChallengeCell);
+    }
+    // a challenge without reward weight can't share the rewards of a revert
+    let reward_weight: u32 = challenge_cell.args.reward_weight().unpack();
+    if reward_weight == 0 {
+        debug!("enter challenge with zero reward weight");
+        return Err(Error::InvalidChallengeCell);
+    }
     // check that challenge target is exists
     let witness = args.witness();
     let challenged_block = witness.raw_l2block();
     // check challenged block isn't finazlied
-    if prev_global_state.last_finalized_block_number().unpack()
-        >= challenged_block.number().unpack()
-    {
+    if is_finalized(prev_global_state, challenged_block.number().unpack()) {
         debug!("enter challenge finalized block error");
         return Err(Error::InvalidChallengeTarget);
     }
@@ -84,7 +104,20 @@ pub fn verify_enter_challenge(
                 return Err(Error::InvalidChallengeTarget);
             }
         }
+        ChallengeTargetType::Deposit => {
+            // the deposit minting of a block is challenged as a whole,
+            // blocks before version 2 don't commit the deposits
+            if target_index != 0 || get_deposit_checkpoint(&challenged_block).is_none() {
+                return Err(Error::InvalidChallengeTarget);
+            }
+        }
+        ChallengeTargetType::ForcedWithdrawal => {
+            // forced withdrawal challenges are entered by the force halt
+            return Err(Error::InvalidChallengeTarget);
+        }
     }
+    // append the challenge to the queue
+    update_challenge_queue(&rollup_type_hash, config, &[], Some(&challenge_target))?;
     // check rollup lock cells
     check_rollup_lock_cells(&rollup_type_hash, config)?;
     // check post global state
@@ -123,12 +156,19 @@ pub fn verify_cancel_challenge(
     let challenge_cell = find_challenge_cell(&rollup_type_hash, config, Source::Input)?
         .ok_or(Error::InvalidChallengeCell)?;
     check_cancel_burn(config, &challenge_cell)?;
+    // remove the challenge from the queue
+    let open_challenges = update_challenge_queue(
+        &rollup_type_hash,
+        config,
+        &[challenge_cell.args.target()],
+        None,
+    )?;
 
     // check rollup lock cells
     check_rollup_lock_cells(&rollup_type_hash, config)?;
     // check post global state
     let actual_post_global_state = {
-        let status: u8 = Status::Running.into();
+        let status: u8 = status_after_closing_challenges(open_challenges.as_deref()).into();
         prev_global_state
             .clone()
             .as_builder()
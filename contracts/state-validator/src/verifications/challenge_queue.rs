@@ -0,0 +1,188 @@
+//! Challenge queue
+//!
+//! The targets of open challenges are recorded in the challenge queue cell, so several
+//! challenges against different blocks or targets can be opened at the same time. The rollup
+//! stays halting until every open challenge is cancelled or reverted.
+
+use alloc::vec::Vec;
+use gw_common::H256;
+use gw_types::{
+    bytes::Bytes,
+    core::Status,
+    packed::{
+        ChallengeQueue, ChallengeQueueReader, ChallengeTarget, ChallengeTargetVec, RollupConfig,
+    },
+    prelude::*,
+};
+use gw_utils::gw_common;
+use gw_utils::gw_types;
+use gw_utils::{
+    cells::lock_cells::is_rollup_bound_cell,
+    ckb_std::{
+        ckb_constants::Source,
+        debug,
+        high_level::{load_cell_capacity, load_cell_data, load_cell_type_hash, QueryIter},
+    },
+    error::Error,
+};
+
+use super::is_cell_unchanged;
+
+/// Find the challenge queue cell, the queue cell must be unique
+fn find_queue_cell(queue_type_hash: &[u8; 32], source: Source) -> Result<Option<usize>, Error> {
+    let mut cells = QueryIter::new(load_cell_type_hash, source)
+        .enumerate()
+        .filter(|(_index, type_hash)| type_hash.as_ref() == Some(queue_type_hash))
+        .map(|(index, _type_hash)| index);
+    match (cells.next(), cells.next()) {
+        (index, None) => Ok(index),
+        _ => {
+            debug!("[challenge queue] queue cell isn't unique");
+            Err(Error::InvalidChallengeQueueCell)
+        }
+    }
+}
+
+/// Find the input and output queue cells, which must be locked by the rollup bound lock
+fn find_queue_cells(
+    rollup_type_hash: &H256,
+    config: &RollupConfig,
+) -> Result<Option<(usize, usize)>, Error> {
+    let queue_type_hash: [u8; 32] = config.challenge_queue_type_hash().unpack();
+    let input_index = match find_queue_cell(&queue_type_hash, Source::Input)? {
+        Some(index) => index,
+        None => return Ok(None),
+    };
+    let output_index = find_queue_cell(&queue_type_hash, Source::Output)?.ok_or_else(|| {
+        debug!("[challenge queue] output queue cell not found");
+        Error::InvalidChallengeQueueCell
+    })?;
+    if !is_rollup_bound_cell(rollup_type_hash, config, input_index, Source::Input)?
+        || !is_rollup_bound_cell(rollup_type_hash, config, output_index, Source::Output)?
+    {
+        debug!("[challenge queue] queue cell isn't locked by the rollup bound lock");
+        return Err(Error::InvalidChallengeQueueCell);
+    }
+    if load_cell_capacity(input_index, Source::Input)?
+        != load_cell_capacity(output_index, Source::Output)?
+    {
+        debug!("[challenge queue] queue cell capacity is changed");
+        return Err(Error::InvalidChallengeQueueCell);
+    }
+    Ok(Some((input_index, output_index)))
+}
+
+fn load_queue(index: usize, source: Source) -> Result<ChallengeQueue, Error> {
+    let data: Bytes = load_cell_data(index, source)?.into();
+    match ChallengeQueueReader::verify(&data, false) {
+        Ok(_) => Ok(ChallengeQueue::new_unchecked(data)),
+        Err(_) => {
+            debug!("[challenge queue] invalid queue cell data");
+            Err(Error::InvalidChallengeQueueCell)
+        }
+    }
+}
+
+pub fn is_challenge_queue_enabled(config: &RollupConfig) -> bool {
+    config.challenge_queue_type_hash().as_slice() != [0u8; 32]
+}
+
+/// Load the targets of the input challenge queue in the challenge order,
+/// returns `None` if the challenge queue is disabled
+pub fn load_challenge_queue(config: &RollupConfig) -> Result<Option<Vec<ChallengeTarget>>, Error> {
+    if !is_challenge_queue_enabled(config) {
+        return Ok(None);
+    }
+    let queue_type_hash: [u8; 32] = config.challenge_queue_type_hash().unpack();
+    let input_index = find_queue_cell(&queue_type_hash, Source::Input)?.ok_or_else(|| {
+        debug!("[challenge queue] input queue cell not found");
+        Error::InvalidChallengeQueueCell
+    })?;
+    let targets = load_queue(input_index, Source::Input)?
+        .targets()
+        .into_iter()
+        .collect();
+    Ok(Some(targets))
+}
+
+/// Remove the `removed` targets from and append the `added` target to the challenge queue
+///
+/// Returns the remaining open challenges, or `None` if the challenge queue is disabled
+pub fn update_challenge_queue(
+    rollup_type_hash: &H256,
+    config: &RollupConfig,
+    removed: &[ChallengeTarget],
+    added: Option<&ChallengeTarget>,
+) -> Result<Option<Vec<ChallengeTarget>>, Error> {
+    if !is_challenge_queue_enabled(config) {
+        return Ok(None);
+    }
+
+    let (input_index, output_index) =
+        find_queue_cells(rollup_type_hash, config)?.ok_or_else(|| {
+            debug!("[challenge queue] input queue cell not found");
+            Error::InvalidChallengeQueueCell
+        })?;
+    let mut targets: Vec<ChallengeTarget> = load_queue(input_index, Source::Input)?
+        .targets()
+        .into_iter()
+        .collect();
+
+    for target in removed {
+        let position = targets
+            .iter()
+            .position(|t| t.as_slice() == target.as_slice())
+            .ok_or_else(|| {
+                debug!("[challenge queue] removed challenge isn't in the queue");
+                Error::InvalidChallengeCell
+            })?;
+        targets.remove(position);
+    }
+    if let Some(target) = added {
+        if targets.iter().any(|t| t.as_slice() == target.as_slice()) {
+            debug!("[challenge queue] duplicated challenge target");
+            return Err(Error::InvalidChallengeCell);
+        }
+        targets.push(target.to_owned());
+    }
+
+    // check post queue
+    let post_queue = ChallengeQueue::new_builder()
+        .targets(
+            ChallengeTargetVec::new_builder()
+                .extend(targets.iter().cloned())
+                .build(),
+        )
+        .build();
+    if load_cell_data(output_index, Source::Output)?.as_slice() != post_queue.as_slice() {
+        debug!("[challenge queue] mismatch post queue");
+        return Err(Error::InvalidChallengeQueueCell);
+    }
+    Ok(Some(targets))
+}
+
+/// The queue cell is only updated by entering, cancelling and reverting challenges,
+/// other rollup actions must keep it unchanged if it's in the inputs
+pub fn check_challenge_queue_unchanged(
+    rollup_type_hash: &H256,
+    config: &RollupConfig,
+) -> Result<(), Error> {
+    if !is_challenge_queue_enabled(config) {
+        return Ok(());
+    }
+    if let Some((input_index, output_index)) = find_queue_cells(rollup_type_hash, config)? {
+        if !is_cell_unchanged(input_index, output_index)? {
+            debug!("[challenge queue] queue cell is changed");
+            return Err(Error::InvalidChallengeQueueCell);
+        }
+    }
+    Ok(())
+}
+
+/// The rollup status after closing challenges, the rollup keeps halting if any challenge is open
+pub fn status_after_closing_challenges(open_challenges: Option<&[ChallengeTarget]>) -> Status {
+    match open_challenges {
+        Some(targets) if !targets.is_empty() => Status::Halting,
+        _ => Status::Running,
+    }
+}
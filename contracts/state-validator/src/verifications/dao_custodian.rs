@@ -0,0 +1,210 @@
+//! Nervos DAO custodian cells
+//!
+//! Finalized custodian cells can be deposited into the Nervos DAO to earn interest.
+//! The Nervos DAO type script verifies the phases and the interest,
+//! the state validator verifies the custodian assets are unchanged,
+//! and the interest is sent to the `RollupConfig#dao_interest_lock_hash`.
+//! Depositing must leave `RollupConfig#dao_min_liquid_ratio` percent of the layer2 CKB
+//! total supply in plain custodian cells, so the withdrawals aren't blocked by the DAO.
+
+use gw_common::{builtins::CKB_SUDT_ACCOUNT_ID, ckb_decimal::CKBCapacity, state::State, H256};
+use gw_state::{ckb_smt::smt::Pair, constants::GW_MAX_KV_PAIRS, kv_state::KVState};
+use gw_types::{
+    core::Status,
+    packed::{GlobalState, RollupConfig, RollupDaoCustodiansReader},
+    prelude::*,
+    U256,
+};
+use gw_utils::gw_common;
+use gw_utils::gw_types;
+use gw_utils::{
+    cells::{
+        rollup::search_rollup_cell,
+        types::{CustodianCell, DaoState},
+    },
+    ckb_std::{
+        ckb_constants::Source,
+        debug,
+        high_level::{load_cell_lock_hash, QueryIter},
+    },
+    error::Error,
+};
+
+use super::{
+    check_status,
+    rebalance_custodian::{
+        check_custodian_assets_unchanged, check_no_other_rollup_cells,
+        collect_finalized_custodian_cells,
+    },
+};
+
+const DAO_DEPOSIT: u8 = 0;
+const DAO_WITHDRAW: u8 = 1;
+const DAO_CLAIM: u8 = 2;
+
+fn check_dao_states<F: Fn(Option<DaoState>) -> bool>(
+    cells: &[CustodianCell],
+    f: F,
+) -> Result<(), Error> {
+    if cells.iter().any(|cell| !f(cell.dao_state)) {
+        debug!("[DAO custodian] unexpected DAO state");
+        return Err(Error::InvalidCustodianCell);
+    }
+    Ok(())
+}
+
+/// The interest is the only output which isn't a custodian or the rollup cell,
+/// it must be sent to the dao_interest_lock_hash
+fn check_dao_interest_cells(
+    rollup_type_hash: &H256,
+    config: &RollupConfig,
+    output_cells: &[CustodianCell],
+) -> Result<(), Error> {
+    let rollup_index = search_rollup_cell(&(*rollup_type_hash).into(), Source::Output)
+        .ok_or(Error::RollupCellNotFound)?;
+    let interest_lock_hash = config.dao_interest_lock_hash();
+    for (index, lock_hash) in QueryIter::new(load_cell_lock_hash, Source::Output).enumerate() {
+        if index == rollup_index || output_cells.iter().any(|cell| cell.index == index) {
+            continue;
+        }
+        if &lock_hash[..] != interest_lock_hash.as_slice() {
+            debug!(
+                "[DAO custodian] output {} isn't sent to the interest lock",
+                index
+            );
+            return Err(Error::InvalidOutput);
+        }
+    }
+    Ok(())
+}
+
+/// The plain CKB custodian outputs must cover the `dao_min_liquid_ratio` of the layer2 CKB,
+/// the total supply is proven against the account root
+fn check_min_liquidity(
+    config: &RollupConfig,
+    args: &RollupDaoCustodiansReader,
+    prev_global_state: &GlobalState,
+    output_cells: &[CustodianCell],
+) -> Result<(), Error> {
+    let account_count: u32 = prev_global_state.account().count().unpack();
+    let account_root: H256 = prev_global_state.account().merkle_root().unpack();
+    let mut tree_buffer = [Pair::default(); GW_MAX_KV_PAIRS];
+    let kv_state_proof: &[u8] = args.kv_state_proof().raw_data();
+    let kv_state = KVState::build(
+        &mut tree_buffer,
+        args.kv_state(),
+        kv_state_proof,
+        account_count,
+        None,
+    )?;
+    if kv_state.calculate_root()? != account_root {
+        debug!("[DAO custodian] kv state doesn't match the account root");
+        return Err(Error::MerkleProof);
+    }
+    let total_supply = kv_state.get_sudt_total_supply(CKB_SUDT_ACCOUNT_ID)?;
+
+    let mut liquid_capacity = 0u64;
+    for cell in output_cells.iter().filter(|cell| cell.dao_state.is_none()) {
+        liquid_capacity = liquid_capacity
+            .checked_add(cell.value.capacity)
+            .ok_or(Error::AmountOverflow)?;
+    }
+    let ratio: u8 = config.dao_min_liquid_ratio().into();
+    // layer2 CKB has 18 decimals
+    let liquidity = CKBCapacity::from_layer1(liquid_capacity)
+        .to_layer2()
+        .checked_mul(U256::from(100u8))
+        .ok_or(Error::AmountOverflow)?;
+    let required_liquidity = total_supply
+        .checked_mul(U256::from(ratio))
+        .ok_or(Error::AmountOverflow)?;
+    if liquidity < required_liquidity {
+        debug!(
+            "[DAO custodian] liquid custodians {} are less than {}% of the total supply",
+            liquid_capacity, ratio
+        );
+        return Err(Error::InsufficientLiquidity);
+    }
+    Ok(())
+}
+
+/// Verify Nervos DAO custodian cells
+/// 1. the transaction only contains finalized custodian cells
+/// 2. the DAO state of custodian cells matches the operation
+/// 3. input finalized assets == output finalized assets, the interest goes to the interest lock
+/// 4. the plain custodians cover the minimal liquid ratio after depositing
+/// 5. global state is unchanged
+pub fn verify(
+    rollup_type_hash: H256,
+    config: &RollupConfig,
+    args: RollupDaoCustodiansReader,
+    prev_global_state: &GlobalState,
+    post_global_state: &GlobalState,
+) -> Result<(), Error> {
+    check_status(prev_global_state, Status::Running)?;
+    if prev_global_state != post_global_state {
+        debug!("[DAO custodian] global state is changed");
+        return Err(Error::InvalidPostGlobalState);
+    }
+    if config.dao_script_type_hash().as_slice() == H256::zero().as_slice() {
+        debug!("[DAO custodian] Nervos DAO is disabled");
+        return Err(Error::InvalidRollupConfig);
+    }
+    check_no_other_rollup_cells(&rollup_type_hash, config)?;
+
+    let input_cells = collect_finalized_custodian_cells(
+        &rollup_type_hash,
+        config,
+        prev_global_state,
+        Source::Input,
+    )?;
+    if input_cells.is_empty() {
+        debug!("[DAO custodian] no input custodian cells");
+        return Err(Error::InvalidCustodianCell);
+    }
+    let output_cells = collect_finalized_custodian_cells(
+        &rollup_type_hash,
+        config,
+        prev_global_state,
+        Source::Output,
+    )?;
+
+    let operation: u8 = args.operation().into();
+    match operation {
+        DAO_DEPOSIT => {
+            // only CKB custodians can be deposited
+            if input_cells
+                .iter()
+                .any(|cell| cell.value.sudt_script_hash != H256::zero())
+            {
+                debug!("[DAO custodian] deposit sUDT custodian");
+                return Err(Error::InvalidCustodianCell);
+            }
+            check_dao_states(&input_cells, |state| state.is_none())?;
+            // the plain outputs are the liquid custodians
+            check_dao_states(&output_cells, |state| {
+                matches!(state, None | Some(DaoState::Deposited))
+            })?;
+            check_min_liquidity(config, &args, prev_global_state, &output_cells)?;
+        }
+        DAO_WITHDRAW => {
+            check_dao_states(&input_cells, |state| state == Some(DaoState::Deposited))?;
+            check_dao_states(&output_cells, |state| {
+                matches!(state, Some(DaoState::Withdrawing(_)))
+            })?;
+        }
+        DAO_CLAIM => {
+            check_dao_states(&input_cells, |state| {
+                matches!(state, Some(DaoState::Withdrawing(_)))
+            })?;
+            check_dao_states(&output_cells, |state| state.is_none())?;
+            check_dao_interest_cells(&rollup_type_hash, config, &output_cells)?;
+        }
+        _ => {
+            debug!("[DAO custodian] unknown operation {}", operation);
+            return Err(Error::InvalidArgs);
+        }
+    }
+
+    check_custodian_assets_unchanged(&input_cells, &output_cells)
+}
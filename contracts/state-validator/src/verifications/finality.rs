@@ -0,0 +1,94 @@
+//! Time-based finality
+//!
+//! Besides the `finality_blocks` rule, a block is finalized once the tip block is
+//! `finality_time_ms` newer than it. The block producer proves a block under the rule by a
+//! `TimeFinalityProof` in the rollup witness `WitnessArgs#input_type`, the last finalized block
+//! number is advanced to the proven block if it is higher than the block number rule.
+
+use alloc::vec;
+use gw_common::{smt::Blake2bHasher, sparse_merkle_tree::CompiledMerkleProof};
+use gw_types::{
+    packed::{GlobalState, RawL2Block, RollupConfig, TimeFinalityProofReader},
+    prelude::*,
+};
+use gw_utils::gw_common;
+use gw_utils::gw_types;
+use gw_utils::{ckb_std::debug, error::Error};
+
+/// Calculate the last finalized block number after the block `number`,
+/// blocks finalized by the previous global state or by the time-based rule stay finalized
+pub fn calculate_last_finalized_block_number(
+    config: &RollupConfig,
+    prev_global_state: &GlobalState,
+    number: u64,
+    time_finalized_number: Option<u64>,
+) -> u64 {
+    let prev_finalized_number: u64 = prev_global_state.last_finalized_block_number().unpack();
+    number
+        .saturating_sub(config.finality_blocks().unpack())
+        .max(prev_finalized_number)
+        .max(time_finalized_number.unwrap_or(0))
+}
+
+/// Parse the time finality proof from the rollup witness `WitnessArgs#input_type`
+pub fn parse_time_finality_proof(
+    input: Option<&[u8]>,
+) -> Result<Option<TimeFinalityProofReader>, Error> {
+    match input {
+        Some(data) => {
+            let proof = TimeFinalityProofReader::from_slice(data).map_err(|_err| {
+                debug!("[time finality] input is not a valid TimeFinalityProof");
+                Error::Encoding
+            })?;
+            Ok(Some(proof))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Verify the time finality proof against the post global state,
+/// returns the number of the proven block
+pub fn check_time_finality_proof(
+    config: &RollupConfig,
+    proof: Option<&TimeFinalityProofReader>,
+    post_global_state: &GlobalState,
+) -> Result<Option<u64>, Error> {
+    let proof = match proof {
+        Some(proof) => proof,
+        None => return Ok(None),
+    };
+    let finality_time_ms: u64 = config.finality_time_ms().unpack();
+    if finality_time_ms == 0 {
+        debug!("[time finality] time-based finality is disabled");
+        return Err(Error::InvalidRollupConfig);
+    }
+
+    // check the block is old enough
+    let block = proof.block();
+    let block_timestamp: u64 = block.timestamp().unpack();
+    let tip_block_timestamp: u64 = post_global_state.tip_block_timestamp().unpack();
+    if block_timestamp.saturating_add(finality_time_ms) > tip_block_timestamp {
+        debug!(
+            "[time finality] block isn't finalized, block timestamp: {}, tip block timestamp: {}",
+            block_timestamp, tip_block_timestamp
+        );
+        return Err(Error::InvalidTimeFinalityProof);
+    }
+
+    // check the block exists
+    let number: u64 = block.number().unpack();
+    let valid = {
+        let merkle_proof = CompiledMerkleProof(proof.block_proof().unpack());
+        let leaves = vec![(
+            RawL2Block::compute_smt_key(number).into(),
+            block.hash().into(),
+        )];
+        merkle_proof
+            .verify::<Blake2bHasher>(&post_global_state.block().merkle_root().unpack(), leaves)?
+    };
+    if !valid {
+        debug!("[time finality] block merkle proof error");
+        return Err(Error::InvalidTimeFinalityProof);
+    }
+    Ok(Some(number))
+}
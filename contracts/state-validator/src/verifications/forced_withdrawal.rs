@@ -0,0 +1,317 @@
+//! Forced withdrawal requests
+//!
+//! A user can send a withdrawal request through a forced withdrawal cell on the layer1,
+//! the block producer must include the request in a block and consume the cell,
+//! or reject the request by proving it is invalid on the account state, the bond of
+//! a rejected request is taken by the block producer.
+//! If the cell isn't consumed in `forced_withdrawal_timeout_l1_blocks` layer1 blocks,
+//! anyone can prove the signature of the request and halt the rollup by the `RollupForceHalt` action,
+//! the request becomes a forced withdrawal challenge of the tip block.
+
+use alloc::{collections::BTreeMap, vec::Vec};
+use gw_common::{state::State, H256};
+use gw_state::{ckb_smt::smt::Pair, constants::GW_MAX_KV_PAIRS, kv_state::KVState};
+use gw_types::{
+    core::{ChallengeTargetType, SigningType, Status},
+    packed::{ChallengeTarget, GlobalState, KVPairVecReader, L2BlockReader, RollupConfig},
+    prelude::*,
+};
+use gw_utils::gw_common;
+use gw_utils::gw_types;
+use gw_utils::{
+    cells::{
+        lock_cells::{collect_forced_withdrawal_locks, find_challenge_cell},
+        types::ForcedWithdrawalCell,
+    },
+    ckb_std::{
+        ckb_constants::Source,
+        ckb_types::{bytes::Bytes, prelude::Unpack as CKBUnpack},
+        debug,
+        high_level::{load_cell_data, load_input_since, load_witness_args},
+        since::{LockValue, Since},
+    },
+    error::Error,
+    forced_withdrawal::{is_invalid_request, parse_halt_witness, parse_rejection},
+    signature::check_l2_account_signature_cell,
+    withdrawal::calculate_withdrawal_message,
+};
+
+use super::{
+    challenge_queue::{is_challenge_queue_enabled, update_challenge_queue},
+    check_rollup_lock_cells_except_forced_withdrawal, check_status, revert,
+};
+
+/// Check the capacity of forced withdrawal cells are returned to the owners
+fn check_owner_refunds(
+    config: &RollupConfig,
+    cells: &[&ForcedWithdrawalCell],
+) -> Result<(), Error> {
+    let mut expected_refunds: BTreeMap<[u8; 32], u128> = BTreeMap::new();
+    for cell in cells {
+        let refund = expected_refunds
+            .entry(cell.args.owner_lock_hash().unpack())
+            .or_default();
+        *refund = refund.saturating_add(cell.capacity.into());
+    }
+    for (owner_lock_hash, expected_refund) in expected_refunds {
+        let input_capacity =
+            revert::get_receiver_cells_capacity(config, &owner_lock_hash, Source::Input)?;
+        let output_capacity =
+            revert::get_receiver_cells_capacity(config, &owner_lock_hash, Source::Output)?;
+        if output_capacity.saturating_sub(input_capacity) < expected_refund {
+            debug!("[forced withdrawal] capacity isn't returned to the owner");
+            return Err(Error::InvalidForcedWithdrawalCell);
+        }
+    }
+    Ok(())
+}
+
+/// Load the witness lock of a forced withdrawal cell
+fn load_witness_lock(cell: &ForcedWithdrawalCell) -> Result<Bytes, Error> {
+    let witness_lock: Bytes = load_witness_args(cell.index, Source::Input)?
+        .lock()
+        .to_opt()
+        .ok_or(Error::InvalidArgs)?
+        .unpack();
+    Ok(witness_lock)
+}
+
+/// Build the kv state and check it against the account of the global state
+fn build_account_state<'a>(
+    tree_buffer: &'a mut [Pair],
+    kv_state: KVPairVecReader<'a>,
+    kv_state_proof: &'a [u8],
+    global_state: &GlobalState,
+) -> Result<KVState<'a>, Error> {
+    let account = global_state.account();
+    let kv_state = KVState::build(
+        tree_buffer,
+        kv_state,
+        kv_state_proof,
+        account.count().unpack(),
+        None,
+    )?;
+    if kv_state.calculate_root()? != account.merkle_root().unpack() {
+        debug!("[forced withdrawal] kv state doesn't match the account root");
+        return Err(Error::MerkleProof);
+    }
+    Ok(kv_state)
+}
+
+/// Check the request of a rejected forced withdrawal cell is invalid on the account state
+fn check_rejection(
+    rollup_type_hash: &H256,
+    config: &RollupConfig,
+    prev_global_state: &GlobalState,
+    cell: &ForcedWithdrawalCell,
+) -> Result<(), Error> {
+    let rejection = parse_rejection(load_witness_lock(cell)?)?;
+    let mut tree_buffer = [Pair::default(); GW_MAX_KV_PAIRS];
+    let kv_state_proof: Bytes = rejection.kv_state_proof().unpack();
+    let kv_state = build_account_state(
+        &mut tree_buffer,
+        rejection.kv_state().as_reader(),
+        &kv_state_proof,
+        prev_global_state,
+    )?;
+    if !is_invalid_request(rollup_type_hash, config, &kv_state, &cell.request.raw())? {
+        debug!(
+            "[forced withdrawal] request of cell {} is rejected without proof",
+            cell.index
+        );
+        return Err(Error::InvalidForcedWithdrawalCell);
+    }
+    Ok(())
+}
+
+/// Check forced withdrawal cells of the submitted blocks,
+/// each consumed request must be included in the blocks, or be proved invalid
+pub fn check_forced_withdrawal_cells(
+    rollup_type_hash: &H256,
+    config: &RollupConfig,
+    prev_global_state: &GlobalState,
+    blocks: &[L2BlockReader],
+) -> Result<(), Error> {
+    if !collect_forced_withdrawal_locks(rollup_type_hash, config, Source::Output)?.is_empty() {
+        debug!("[forced withdrawal] unexpected output forced withdrawal cell");
+        return Err(Error::InvalidForcedWithdrawalCell);
+    }
+    let cells = collect_forced_withdrawal_locks(rollup_type_hash, config, Source::Input)?;
+    let mut included_cells = Vec::with_capacity(cells.len());
+    for cell in &cells {
+        let request_hash = cell.request.raw().hash();
+        let is_included = blocks.iter().any(|block| {
+            block
+                .withdrawals()
+                .iter()
+                .any(|withdrawal| withdrawal.raw().hash() == request_hash)
+        });
+        if is_included {
+            included_cells.push(cell);
+        } else {
+            check_rejection(rollup_type_hash, config, prev_global_state, cell)?;
+        }
+    }
+    check_owner_refunds(config, &included_cells)
+}
+
+/// Check the forced withdrawal cell has been alive for the timeout blocks(on the layer1)
+fn check_forced_withdrawal_timeout(
+    config: &RollupConfig,
+    cell: &ForcedWithdrawalCell,
+) -> Result<(), Error> {
+    let timeout_blocks: u64 = config.forced_withdrawal_timeout_l1_blocks().unpack();
+    let since = Since::new(load_input_since(cell.index, Source::Input)?);
+    if let Some(LockValue::BlockNumber(n)) = since.extract_lock_value() {
+        if since.is_relative() && n >= timeout_blocks {
+            return Ok(());
+        }
+    }
+    debug!(
+        "[forced withdrawal] cell {} isn't timeout, since: {}",
+        cell.index,
+        since.as_u64()
+    );
+    Err(Error::InvalidSince)
+}
+
+/// Check the request of the forced withdrawal cell is signed by the sender
+fn check_request_signature(
+    config: &RollupConfig,
+    prev_global_state: &GlobalState,
+    cell: &ForcedWithdrawalCell,
+) -> Result<(), Error> {
+    let witness = parse_halt_witness(load_witness_lock(cell)?)?;
+    let raw_withdrawal = cell.request.raw();
+    let expected_chain_id: u64 = config.chain_id().unpack();
+    let chain_id: u64 = raw_withdrawal.chain_id().unpack();
+    if chain_id != expected_chain_id {
+        debug!("[forced withdrawal] request using wrong rollup_chain_id");
+        return Err(Error::WrongSignature);
+    }
+    let owner_lock = witness.owner_lock();
+    if owner_lock.hash() != raw_withdrawal.owner_lock_hash().as_slice() {
+        debug!("[forced withdrawal] mismatch owner lock hash");
+        return Err(Error::InvalidArgs);
+    }
+    let mut tree_buffer = [Pair::default(); GW_MAX_KV_PAIRS];
+    let kv_state_proof: Bytes = witness.kv_state_proof().unpack();
+    let kv_state = build_account_state(
+        &mut tree_buffer,
+        witness.kv_state().as_reader(),
+        &kv_state_proof,
+        prev_global_state,
+    )?;
+    let sender_script_hash: H256 = raw_withdrawal.account_script_hash().unpack();
+    let withdrawal_address = kv_state
+        .get_registry_address_by_script_hash(
+            raw_withdrawal.registry_id().unpack(),
+            &sender_script_hash,
+        )?
+        .ok_or(Error::RegistryAddressNotFound)?;
+    let message = calculate_withdrawal_message(&raw_withdrawal, owner_lock, withdrawal_address)?;
+    check_l2_account_signature_cell(&sender_script_hash, SigningType::Raw, message)
+}
+
+/// Check the forced withdrawal challenge cell, the request challenges the tip block,
+/// the bond becomes the challenge capacity and the owner receives the rewards
+fn check_challenge_cell(
+    rollup_type_hash: &H256,
+    config: &RollupConfig,
+    prev_global_state: &GlobalState,
+    cell: &ForcedWithdrawalCell,
+) -> Result<ChallengeTarget, Error> {
+    if find_challenge_cell(rollup_type_hash, config, Source::Input)?.is_some() {
+        return Err(Error::InvalidChallengeCell);
+    }
+    let challenge_cell = find_challenge_cell(rollup_type_hash, config, Source::Output)?
+        .ok_or(Error::InvalidChallengeCell)?;
+    let expected_target = ChallengeTarget::new_builder()
+        .block_hash(prev_global_state.tip_block_hash())
+        .target_index(0u32.pack())
+        .target_type((ChallengeTargetType::ForcedWithdrawal as u8).into())
+        .build();
+    let challenge_target = challenge_cell.args.target();
+    if challenge_target.as_slice() != expected_target.as_slice() {
+        debug!("[forced withdrawal] challenge target isn't the tip block");
+        return Err(Error::InvalidChallengeTarget);
+    }
+    if challenge_cell.args.rewards_receiver_lock().hash() != cell.args.owner_lock_hash().as_slice()
+    {
+        debug!("[forced withdrawal] rewards receiver isn't the owner");
+        return Err(Error::InvalidChallengeCell);
+    }
+    if challenge_cell.value.capacity < cell.capacity
+        || load_cell_data(challenge_cell.index, Source::Output)?.as_slice()
+            != cell.request.as_slice()
+    {
+        debug!("[forced withdrawal] challenge cell doesn't carry the bonded request");
+        return Err(Error::InvalidChallengeCell);
+    }
+    Ok(challenge_target)
+}
+
+/// Verify force halt
+/// 1. check the forced withdrawal request is timeout and bonded
+/// 2. check the signature of the request
+/// 3. check the request is converted to a forced withdrawal challenge of the tip block
+/// 4. check the rollup is halting
+pub fn verify_force_halt(
+    rollup_type_hash: H256,
+    config: &RollupConfig,
+    prev_global_state: &GlobalState,
+    post_global_state: &GlobalState,
+) -> Result<(), Error> {
+    // with the challenge queue, more challenges can be entered during the rollup halting
+    let is_halting = check_status(prev_global_state, Status::Halting).is_ok();
+    if !(is_challenge_queue_enabled(config) && is_halting) {
+        check_status(prev_global_state, Status::Running)?;
+    }
+    let timeout_blocks: u64 = config.forced_withdrawal_timeout_l1_blocks().unpack();
+    if timeout_blocks == 0 {
+        debug!("[forced withdrawal] forced withdrawal is disabled");
+        return Err(Error::InvalidRollupConfig);
+    }
+    // check forced withdrawal cells
+    let mut cells = collect_forced_withdrawal_locks(&rollup_type_hash, config, Source::Input)?;
+    if cells.len() != 1 {
+        debug!("[forced withdrawal] force halt requires one forced withdrawal cell");
+        return Err(Error::InvalidForcedWithdrawalCell);
+    }
+    let cell = cells.remove(0);
+    check_forced_withdrawal_timeout(config, &cell)?;
+    let bond_capacity: u64 = config.forced_withdrawal_bond_capacity().unpack();
+    if cell.capacity < bond_capacity {
+        debug!(
+            "[forced withdrawal] cell capacity {} is less than the bond {}",
+            cell.capacity, bond_capacity
+        );
+        return Err(Error::InvalidForcedWithdrawalCell);
+    }
+    if !collect_forced_withdrawal_locks(&rollup_type_hash, config, Source::Output)?.is_empty() {
+        debug!("[forced withdrawal] unexpected output forced withdrawal cell");
+        return Err(Error::InvalidForcedWithdrawalCell);
+    }
+    check_request_signature(config, prev_global_state, &cell)?;
+    // check challenge cell
+    let challenge_target =
+        check_challenge_cell(&rollup_type_hash, config, prev_global_state, &cell)?;
+    // append the challenge to the queue
+    update_challenge_queue(&rollup_type_hash, config, &[], Some(&challenge_target))?;
+    // check rollup lock cells
+    check_rollup_lock_cells_except_forced_withdrawal(&rollup_type_hash, config)?;
+    // check post global state
+    let actual_post_global_state = {
+        let status: u8 = Status::Halting.into();
+        prev_global_state
+            .clone()
+            .as_builder()
+            .status(status.into())
+            .build()
+    };
+    if post_global_state != &actual_post_global_state {
+        debug!("[forced withdrawal] mismatch post global state");
+        return Err(Error::InvalidPostGlobalState);
+    }
+    Ok(())
+}
@@ -7,19 +7,35 @@ use gw_utils::gw_common;
 use gw_utils::gw_types;
 use gw_utils::{
     cells::lock_cells::{
-        collect_custodian_locks, collect_deposit_locks, collect_stake_cells,
-        collect_withdrawal_locks,
+        collect_batch_deposit_locks, collect_custodian_locks, collect_deposit_locks,
+        collect_forced_withdrawal_locks, collect_stake_cells, collect_withdrawal_locks,
+    },
+    ckb_std::{
+        ckb_constants::Source,
+        ckb_types::prelude::Entity as CKBEntity,
+        debug,
+        high_level::{load_cell, load_cell_data},
     },
-    ckb_std::{ckb_constants::Source, debug},
     error::Error,
 };
 
+pub mod abandon;
 pub mod challenge;
+pub mod challenge_queue;
+pub mod dao_custodian;
+pub mod finality;
+pub mod forced_withdrawal;
+pub mod pause;
+pub mod producer_schedule;
+pub mod proof_of_reserves;
+pub mod prune_reverted_blocks;
+pub mod rebalance_custodian;
 pub mod revert;
 pub mod submit_block;
+pub mod upgrade_config;
+pub mod withdrawal_limit;
 
-/// this function ensure transaction doesn't contains any deposit / withdrawal / custodian
-pub fn check_rollup_lock_cells_except_stake(
+fn check_no_deposit_withdrawal_custodian_cells(
     rollup_type_hash: &H256,
     config: &RollupConfig,
 ) -> Result<(), Error> {
@@ -29,6 +45,12 @@ pub fn check_rollup_lock_cells_except_stake(
     if !collect_deposit_locks(rollup_type_hash, config, Source::Output)?.is_empty() {
         return Err(Error::InvalidDepositCell);
     }
+    if !collect_batch_deposit_locks(rollup_type_hash, config, Source::Input)?.is_empty() {
+        return Err(Error::InvalidDepositCell);
+    }
+    if !collect_batch_deposit_locks(rollup_type_hash, config, Source::Output)?.is_empty() {
+        return Err(Error::InvalidDepositCell);
+    }
     if !collect_withdrawal_locks(rollup_type_hash, config, Source::Input)?.is_empty() {
         return Err(Error::InvalidWithdrawalCell);
     }
@@ -44,12 +66,7 @@ pub fn check_rollup_lock_cells_except_stake(
     Ok(())
 }
 
-/// this function ensure transaction doesn't contains any deposit / withdrawal / custodian / stake cells
-pub fn check_rollup_lock_cells(
-    rollup_type_hash: &H256,
-    config: &RollupConfig,
-) -> Result<(), Error> {
-    check_rollup_lock_cells_except_stake(rollup_type_hash, config)?;
+fn check_no_stake_cells(rollup_type_hash: &H256, config: &RollupConfig) -> Result<(), Error> {
     if !collect_stake_cells(rollup_type_hash, config, Source::Input)?.is_empty() {
         debug!("unexpected input stake cell");
         return Err(Error::InvalidStakeCell);
@@ -61,6 +78,51 @@ pub fn check_rollup_lock_cells(
     Ok(())
 }
 
+fn check_no_forced_withdrawal_cells(
+    rollup_type_hash: &H256,
+    config: &RollupConfig,
+) -> Result<(), Error> {
+    if !collect_forced_withdrawal_locks(rollup_type_hash, config, Source::Input)?.is_empty() {
+        debug!("unexpected input forced withdrawal cell");
+        return Err(Error::InvalidForcedWithdrawalCell);
+    }
+    if !collect_forced_withdrawal_locks(rollup_type_hash, config, Source::Output)?.is_empty() {
+        debug!("unexpected output forced withdrawal cell");
+        return Err(Error::InvalidForcedWithdrawalCell);
+    }
+    Ok(())
+}
+
+/// this function ensure transaction doesn't contains any deposit / withdrawal / custodian / forced withdrawal
+pub fn check_rollup_lock_cells_except_stake(
+    rollup_type_hash: &H256,
+    config: &RollupConfig,
+) -> Result<(), Error> {
+    check_no_deposit_withdrawal_custodian_cells(rollup_type_hash, config)?;
+    check_no_forced_withdrawal_cells(rollup_type_hash, config)?;
+    Ok(())
+}
+
+/// this function ensure transaction doesn't contains any deposit / withdrawal / custodian / stake cells
+pub fn check_rollup_lock_cells_except_forced_withdrawal(
+    rollup_type_hash: &H256,
+    config: &RollupConfig,
+) -> Result<(), Error> {
+    check_no_deposit_withdrawal_custodian_cells(rollup_type_hash, config)?;
+    check_no_stake_cells(rollup_type_hash, config)?;
+    Ok(())
+}
+
+/// this function ensure transaction doesn't contains any deposit / withdrawal / custodian / stake / forced withdrawal cells
+pub fn check_rollup_lock_cells(
+    rollup_type_hash: &H256,
+    config: &RollupConfig,
+) -> Result<(), Error> {
+    check_rollup_lock_cells_except_stake(rollup_type_hash, config)?;
+    check_no_stake_cells(rollup_type_hash, config)?;
+    Ok(())
+}
+
 pub fn check_status(global_state: &GlobalState, status: Status) -> Result<(), Error> {
     let expected_status: u8 = status.into();
     let status: u8 = global_state.status().into();
@@ -69,3 +131,12 @@ pub fn check_status(global_state: &GlobalState, status: Status) -> Result<(), Er
     }
     Ok(())
 }
+
+/// Check the output cell keeps the lock, type, capacity and data of the input cell
+pub fn is_cell_unchanged(input_index: usize, output_index: usize) -> Result<bool, Error> {
+    let unchanged = load_cell(input_index, Source::Input)?.as_slice()
+        == load_cell(output_index, Source::Output)?.as_slice()
+        && load_cell_data(input_index, Source::Input)?
+            == load_cell_data(output_index, Source::Output)?;
+    Ok(unchanged)
+}
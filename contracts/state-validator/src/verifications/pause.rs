@@ -0,0 +1,85 @@
+//! Pause / resume the rollup
+//!
+//! The governance (owner) or the guardian can pause a running rollup in emergency,
+//! only the governance can resume a paused rollup.
+//! No blocks can be submitted while the rollup is paused,
+//! finalized withdrawals are still unlockable because the withdrawal lock doesn't check the status.
+
+use gw_common::H256;
+use gw_types::{
+    core::Status,
+    packed::{GlobalState, RollupConfig, RollupSetPausedReader},
+    prelude::*,
+};
+use gw_utils::gw_common;
+use gw_utils::gw_types;
+use gw_utils::{
+    cells::utils::search_lock_hash,
+    ckb_std::{ckb_constants::Source, debug},
+    error::Error,
+};
+
+use super::{check_rollup_lock_cells, check_status};
+
+/// Check there is an input cell of one of the authorized locks
+fn check_authorization(authorized_lock_hashes: &[[u8; 32]]) -> Result<(), Error> {
+    let authorized = authorized_lock_hashes
+        .iter()
+        .filter(|lock_hash| *lock_hash != &[0u8; 32])
+        .any(|lock_hash| search_lock_hash(lock_hash, Source::Input).is_some());
+    if !authorized {
+        debug!("[set paused] owner cell not found");
+        return Err(Error::OwnerCellNotFound);
+    }
+    Ok(())
+}
+
+/// Verify set paused
+/// 1. pause: Running -> Paused, authorized by the governance or the guardian
+/// 2. resume: Paused -> Running, authorized by the governance
+/// 3. check post global state only changes the status
+pub fn verify(
+    rollup_type_hash: H256,
+    config: &RollupConfig,
+    args: RollupSetPausedReader,
+    prev_global_state: &GlobalState,
+    post_global_state: &GlobalState,
+) -> Result<(), Error> {
+    // check rollup lock cells
+    check_rollup_lock_cells(&rollup_type_hash, config)?;
+
+    let governance_lock_hash: [u8; 32] = config.governance_lock_hash().unpack();
+    let guardian_lock_hash: [u8; 32] = config.guardian_lock_hash().unpack();
+    let paused: u8 = args.paused().to_entity().into();
+    let post_status = match paused {
+        1 => {
+            check_status(prev_global_state, Status::Running)?;
+            check_authorization(&[governance_lock_hash, guardian_lock_hash])?;
+            Status::Paused
+        }
+        0 => {
+            check_status(prev_global_state, Status::Paused)?;
+            check_authorization(&[governance_lock_hash])?;
+            Status::Running
+        }
+        _ => {
+            debug!("[set paused] invalid paused flag {}", paused);
+            return Err(Error::InvalidArgs);
+        }
+    };
+
+    // check post global state
+    let actual_post_global_state = {
+        let status: u8 = post_status.into();
+        prev_global_state
+            .clone()
+            .as_builder()
+            .status(status.into())
+            .build()
+    };
+    if post_global_state != &actual_post_global_state {
+        debug!("[set paused] mismatch post global state");
+        return Err(Error::InvalidPostGlobalState);
+    }
+    Ok(())
+}
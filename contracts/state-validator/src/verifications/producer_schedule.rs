@@ -0,0 +1,121 @@
+//! Permissioned block producers
+//!
+//! The registered producers take turns to produce blocks, the slot is keyed by the layer-1 time,
+//! which is the timestamp of the rollup input since, and the block timestamp must sit in the same
+//! slot. The block timestamp is chosen by the producer, keying the slot on it would let a producer
+//! backdate a block into its own slot. If the layer-1 time is `fallback_timeout` after the tip
+//! block, any registered producer can produce the next block.
+
+use gw_types::{
+    bytes::Bytes,
+    packed::{GlobalState, L2BlockReader, ProducerRegistry, ProducerRegistryReader, RollupConfig},
+    prelude::*,
+};
+use gw_utils::gw_types;
+use gw_utils::{
+    ckb_std::{
+        ckb_constants::Source,
+        debug,
+        high_level::{load_cell_data, load_cell_type_hash, load_input_since, QueryIter},
+        since::{LockValue, Since},
+    },
+    error::Error,
+};
+
+/// Load the producer registry from cell deps
+fn load_producer_registry(registry_type_hash: &[u8; 32]) -> Result<ProducerRegistry, Error> {
+    let index = QueryIter::new(load_cell_type_hash, Source::CellDep)
+        .position(|type_hash| type_hash.as_ref() == Some(registry_type_hash))
+        .ok_or_else(|| {
+            debug!("[producer schedule] registry cell not found");
+            Error::InvalidProducerRegistryCell
+        })?;
+    let data: Bytes = load_cell_data(index, Source::CellDep)?.into();
+    match ProducerRegistryReader::verify(&data, false) {
+        Ok(_) => Ok(ProducerRegistry::new_unchecked(data)),
+        Err(_) => {
+            debug!("[producer schedule] invalid registry cell data");
+            Err(Error::InvalidProducerRegistryCell)
+        }
+    }
+}
+
+/// Load the layer-1 time from the rollup input since
+fn load_layer1_timestamp() -> Result<u64, Error> {
+    let since = Since::new(load_input_since(0, Source::GroupInput)?);
+    match since.extract_lock_value() {
+        Some(LockValue::Timestamp(time)) if since.is_absolute() => Ok(time),
+        _ => {
+            debug!("[producer schedule] rollup input since isn't an absolute timestamp");
+            Err(Error::InvalidSince)
+        }
+    }
+}
+
+/// Check the block producer is registered and in its slot
+pub fn check_block_producer_schedule(
+    config: &RollupConfig,
+    block: &L2BlockReader,
+    prev_global_state: &GlobalState,
+) -> Result<(), Error> {
+    let schedule_config = config.producer_schedule();
+    let registry_type_hash: [u8; 32] = schedule_config.registry_type_hash().unpack();
+    if registry_type_hash == [0u8; 32] {
+        return Ok(());
+    }
+    let registry = load_producer_registry(&registry_type_hash)?;
+    let producers = registry.producers();
+    if producers.is_empty() {
+        debug!("[producer schedule] no registered producers");
+        return Err(Error::InvalidProducerRegistryCell);
+    }
+
+    let raw_block = block.raw();
+    let producer = raw_block.stake_cell_owner_lock_hash();
+    if !producers
+        .clone()
+        .into_iter()
+        .any(|lock_hash| lock_hash.as_slice() == producer.as_slice())
+    {
+        debug!("[producer schedule] producer isn't registered");
+        return Err(Error::InvalidBlockProducer);
+    }
+
+    // check the slot at the layer-1 time
+    let layer1_timestamp = load_layer1_timestamp()?;
+    let block_timestamp: u64 = raw_block.timestamp().unpack();
+    let slot_duration: u64 = schedule_config.slot_duration().unpack();
+    if slot_duration == 0 {
+        debug!("[producer schedule] slot duration is zero");
+        return Err(Error::InvalidRollupConfig);
+    }
+    let slot_round = layer1_timestamp / slot_duration;
+    let slot = slot_round % producers.len() as u64;
+    let scheduled_producer = producers
+        .get(slot as usize)
+        .ok_or(Error::InvalidProducerRegistryCell)?;
+    if scheduled_producer.as_slice() == producer.as_slice() {
+        if block_timestamp / slot_duration != slot_round {
+            debug!(
+                "[producer schedule] block timestamp {} is out of the slot at {}",
+                block_timestamp, layer1_timestamp
+            );
+            return Err(Error::InvalidBlockProducer);
+        }
+        return Ok(());
+    }
+
+    // fallback after the timeout
+    let tip_block_timestamp: u64 = prev_global_state.tip_block_timestamp().unpack();
+    let fallback_timeout: u64 = schedule_config.fallback_timeout().unpack();
+    if layer1_timestamp >= tip_block_timestamp.saturating_add(fallback_timeout) {
+        debug!("[producer schedule] fallback producer");
+        return Ok(());
+    }
+
+    debug!(
+        "[producer schedule] producer isn't scheduled in slot {}",
+        slot
+    );
+    Err(Error::InvalidBlockProducer)
+}
@@ -0,0 +1,169 @@
+//! Proof of reserves
+//!
+//! Attest the finalized custodian cells cover the layer2 sUDT total supply.
+//! The total supplies are proven against the account root of the global state,
+//! the custodian cells are consumed and recreated without changing the assets,
+//! and an output cell carries the `ReservesAttestation` as its data.
+//!
+//! The attestation cell isn't bound to the rollup, anyone can create a cell with the same data,
+//! so a verifier must check the transaction which creates the attestation cell
+//! consumes the rollup cell with the `RollupProveReserves` action.
+//! Nervos DAO custodians are excluded, the CKB held in the DAO isn't counted in the reserves.
+
+use core::convert::TryFrom;
+
+use gw_common::{ckb_decimal::CKBCapacity, state::State, CKB_SUDT_SCRIPT_ARGS, H256};
+use gw_state::{ckb_smt::smt::Pair, constants::GW_MAX_KV_PAIRS, kv_state::KVState};
+use gw_types::{
+    core::Status,
+    packed::{GlobalState, RollupConfig, RollupProveReservesReader},
+    prelude::*,
+    U256,
+};
+use gw_utils::gw_common;
+use gw_utils::gw_types;
+use gw_utils::{
+    cells::utils::build_l2_sudt_script,
+    ckb_std::{
+        ckb_constants::Source,
+        debug,
+        high_level::{load_cell_data, QueryIter},
+    },
+    error::Error,
+};
+
+use super::{
+    check_status,
+    rebalance_custodian::{
+        check_custodian_assets_unchanged, check_no_other_rollup_cells,
+        collect_finalized_custodian_cells,
+    },
+    submit_block::build_assets_map_from_cells,
+};
+
+/// Verify proof of reserves
+/// 1. the transaction only contains finalized custodian cells, which aren't Nervos DAO cells
+/// 2. input finalized assets == output finalized assets
+/// 3. the total supplies are proven against the account root
+/// 4. the reserve of each sUDT equals the input custodian assets and covers the total supply
+/// 5. an output cell carries the attestation and the global state is unchanged
+pub fn verify(
+    rollup_type_hash: H256,
+    config: &RollupConfig,
+    args: RollupProveReservesReader,
+    prev_global_state: &GlobalState,
+    post_global_state: &GlobalState,
+) -> Result<(), Error> {
+    check_status(prev_global_state, Status::Running)?;
+    if prev_global_state != post_global_state {
+        debug!("[proof of reserves] global state is changed");
+        return Err(Error::InvalidPostGlobalState);
+    }
+    check_no_other_rollup_cells(&rollup_type_hash, config)?;
+
+    let input_cells = collect_finalized_custodian_cells(
+        &rollup_type_hash,
+        config,
+        prev_global_state,
+        Source::Input,
+    )?;
+    let output_cells = collect_finalized_custodian_cells(
+        &rollup_type_hash,
+        config,
+        prev_global_state,
+        Source::Output,
+    )?;
+    if input_cells
+        .iter()
+        .chain(output_cells.iter())
+        .any(|cell| cell.dao_state.is_some())
+    {
+        debug!("[proof of reserves] unexpected DAO custodian cell");
+        return Err(Error::InvalidCustodianCell);
+    }
+    check_custodian_assets_unchanged(&input_cells, &output_cells)?;
+    let assets = build_assets_map_from_cells(input_cells.iter().map(|c| &c.value))?;
+
+    // check the attestation is built on the current state
+    let attestation = args.attestation();
+    let account_count: u32 = prev_global_state.account().count().unpack();
+    let account_root: H256 = prev_global_state.account().merkle_root().unpack();
+    if attestation.account_root().as_slice() != account_root.as_slice()
+        || attestation.last_finalized_block_number().as_slice()
+            != prev_global_state.last_finalized_block_number().as_slice()
+    {
+        debug!("[proof of reserves] attestation mismatch the global state");
+        return Err(Error::InvalidArgs);
+    }
+    let reserves = attestation.reserves();
+    let sudt_script_hashes = args.sudt_script_hashes();
+    if reserves.is_empty() || reserves.len() != sudt_script_hashes.len() {
+        debug!("[proof of reserves] invalid reserves");
+        return Err(Error::InvalidArgs);
+    }
+
+    // prove the total supplies against the account root
+    let mut tree_buffer = [Pair::default(); GW_MAX_KV_PAIRS];
+    let kv_state_proof: &[u8] = args.kv_state_proof().raw_data();
+    let kv_state = KVState::build(
+        &mut tree_buffer,
+        args.kv_state(),
+        kv_state_proof,
+        account_count,
+        None,
+    )?;
+    if kv_state.calculate_root()? != account_root {
+        debug!("[proof of reserves] kv state doesn't match the account root");
+        return Err(Error::MerkleProof);
+    }
+
+    for (reserve, l1_sudt_script_hash) in reserves.iter().zip(sudt_script_hashes.iter()) {
+        let sudt_id: u32 = reserve.sudt_id().unpack();
+        let l1_sudt_script_hash: H256 = l1_sudt_script_hash.unpack();
+        let l2_sudt_script_hash: H256 =
+            build_l2_sudt_script(&rollup_type_hash, config, &l1_sudt_script_hash)
+                .hash()
+                .into();
+        if kv_state.get_script_hash(sudt_id)? != l2_sudt_script_hash {
+            debug!(
+                "[proof of reserves] sUDT {} mismatch the script hash",
+                sudt_id
+            );
+            return Err(Error::InvalidArgs);
+        }
+        let total_supply = kv_state.get_sudt_total_supply(sudt_id)?;
+        let attested_total_supply = U256::from_little_endian(reserve.total_supply().as_slice());
+        if total_supply != attested_total_supply {
+            debug!("[proof of reserves] sUDT {} mismatch total supply", sudt_id);
+            return Err(Error::InvalidArgs);
+        }
+        let amount = assets.get(&l1_sudt_script_hash).copied().unwrap_or(0);
+        let attested_amount: u128 = reserve.reserve().unpack();
+        if amount != attested_amount {
+            debug!("[proof of reserves] sUDT {} mismatch reserve", sudt_id);
+            return Err(Error::InvalidArgs);
+        }
+        // layer2 CKB has 18 decimals
+        let layer2_amount = if l1_sudt_script_hash.as_slice() == CKB_SUDT_SCRIPT_ARGS {
+            let capacity = u64::try_from(amount).map_err(|_| Error::AmountOverflow)?;
+            CKBCapacity::from_layer1(capacity).to_layer2()
+        } else {
+            U256::from(amount)
+        };
+        if layer2_amount < total_supply {
+            debug!(
+                "[proof of reserves] sUDT {} reserve is less than the total supply",
+                sudt_id
+            );
+            return Err(Error::InsufficientReserves);
+        }
+    }
+
+    // an output cell must carry the attestation
+    let attestation_slice = attestation.as_slice();
+    if !QueryIter::new(load_cell_data, Source::Output).any(|data| data == attestation_slice) {
+        debug!("[proof of reserves] no attestation cell");
+        return Err(Error::InvalidOutput);
+    }
+    Ok(())
+}
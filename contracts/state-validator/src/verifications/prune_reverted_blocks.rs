@@ -0,0 +1,96 @@
+//! Prune reverted blocks
+//!
+//! The hashes of reverted blocks are kept in the `reverted_block_root` so the reverted
+//! deposit / withdrawal / custodian cells can be proven. Once the number of a reverted block
+//! is finalized, the block producer must have reverted these cells, the block hash can be
+//! removed from the SMT to keep the proofs small.
+
+use alloc::vec::Vec;
+use gw_common::{smt::Blake2bHasher, sparse_merkle_tree::CompiledMerkleProof, H256};
+use gw_types::{
+    core::Status,
+    packed::{GlobalState, RollupConfig, RollupPruneRevertedBlocksReader},
+    prelude::*,
+};
+use gw_utils::gw_common;
+use gw_utils::gw_types;
+use gw_utils::{
+    cells::lock_cells::find_challenge_cell,
+    ckb_std::{ckb_constants::Source, debug},
+    error::Error,
+    finality::is_finalized,
+};
+
+use super::{check_rollup_lock_cells, check_status};
+
+/// Verify prune reverted blocks
+/// 1. the reverted blocks are finalized by the block number
+/// 2. the reverted block hashes are removed from the reverted block root
+/// 3. check post global state only changes the reverted block root
+pub fn verify(
+    rollup_type_hash: H256,
+    config: &RollupConfig,
+    args: RollupPruneRevertedBlocksReader,
+    prev_global_state: &GlobalState,
+    post_global_state: &GlobalState,
+) -> Result<(), Error> {
+    check_status(prev_global_state, Status::Running)?;
+    // check rollup lock cells
+    check_rollup_lock_cells(&rollup_type_hash, config)?;
+    if find_challenge_cell(&rollup_type_hash, config, Source::Input)?.is_some()
+        || find_challenge_cell(&rollup_type_hash, config, Source::Output)?.is_some()
+    {
+        return Err(Error::InvalidChallengeCell);
+    }
+
+    // check reverted blocks are finalized
+    let reverted_blocks = args.reverted_blocks();
+    if reverted_blocks.is_empty() {
+        debug!("[prune reverted blocks] no reverted blocks");
+        return Err(Error::InvalidRevertedBlocks);
+    }
+    if reverted_blocks
+        .iter()
+        .any(|block| !is_finalized(prev_global_state, block.number().unpack()))
+    {
+        debug!("[prune reverted blocks] reverted block isn't finalized");
+        return Err(Error::InvalidRevertedBlocks);
+    }
+    let reverted_block_hashes: Vec<H256> =
+        reverted_blocks.iter().map(|b| b.hash().into()).collect();
+
+    // prove the blocks are in the prev reverted block root
+    let reverted_block_merkle_proof = CompiledMerkleProof(args.reverted_block_proof().unpack());
+    let is_prev_reverted_block = {
+        let leaves: Vec<_> = reverted_block_hashes
+            .iter()
+            .map(|hash| (*hash, H256::one()))
+            .collect();
+        reverted_block_merkle_proof
+            .verify::<Blake2bHasher>(&prev_global_state.reverted_block_root().unpack(), leaves)?
+    };
+    if !is_prev_reverted_block {
+        debug!("[prune reverted blocks] block isn't in the prev reverted block root");
+        return Err(Error::InvalidRevertedBlocks);
+    }
+    // calculate the post reverted block root
+    let reverted_block_root = {
+        let leaves: Vec<_> = reverted_block_hashes
+            .into_iter()
+            .map(|hash| (hash, H256::zero()))
+            .collect();
+        reverted_block_merkle_proof.compute_root::<Blake2bHasher>(leaves)?
+    };
+
+    // check post global state
+    let actual_post_global_state = prev_global_state
+        .clone()
+        .as_builder()
+        .reverted_block_root(reverted_block_root.pack())
+        .build();
+    if post_global_state != &actual_post_global_state {
+        debug!("[prune reverted blocks] mismatch post global state");
+        return Err(Error::InvalidPostGlobalState);
+    }
+    Ok(())
+}
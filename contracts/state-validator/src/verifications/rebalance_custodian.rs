@@ -0,0 +1,144 @@
+//! Rebalance custodian cells
+//!
+//! Merge or split finalized custodian cells without submitting a block,
+//! the total assets of the finalized custodian cells must be unchanged.
+
+use alloc::vec::Vec;
+use gw_common::H256;
+use gw_types::{
+    core::Status,
+    packed::{GlobalState, RollupConfig},
+    prelude::*,
+};
+use gw_utils::gw_common;
+use gw_utils::gw_types;
+use gw_utils::{
+    cells::{
+        lock_cells::{
+            collect_batch_deposit_locks, collect_custodian_locks, collect_deposit_locks,
+            collect_forced_withdrawal_locks, collect_stake_cells, collect_withdrawal_locks,
+            find_challenge_cell,
+        },
+        types::CustodianCell,
+    },
+    ckb_std::{ckb_constants::Source, debug},
+    error::Error,
+    finality::is_finalized,
+};
+
+use super::{check_status, submit_block::build_assets_map_from_cells};
+
+/// this function ensure transaction doesn't contains any deposit / withdrawal / stake / challenge / forced withdrawal cells
+pub(crate) fn check_no_other_rollup_cells(
+    rollup_type_hash: &H256,
+    config: &RollupConfig,
+) -> Result<(), Error> {
+    for source in [Source::Input, Source::Output].iter() {
+        if !collect_deposit_locks(rollup_type_hash, config, *source)?.is_empty() {
+            return Err(Error::InvalidDepositCell);
+        }
+        if !collect_batch_deposit_locks(rollup_type_hash, config, *source)?.is_empty() {
+            return Err(Error::InvalidDepositCell);
+        }
+        if !collect_withdrawal_locks(rollup_type_hash, config, *source)?.is_empty() {
+            return Err(Error::InvalidWithdrawalCell);
+        }
+        if !collect_stake_cells(rollup_type_hash, config, *source)?.is_empty() {
+            return Err(Error::InvalidStakeCell);
+        }
+        if find_challenge_cell(rollup_type_hash, config, *source)?.is_some() {
+            return Err(Error::InvalidChallengeCell);
+        }
+        if !collect_forced_withdrawal_locks(rollup_type_hash, config, *source)?.is_empty() {
+            return Err(Error::InvalidForcedWithdrawalCell);
+        }
+    }
+    Ok(())
+}
+
+/// Collect custodian cells, all of them must be finalized
+pub(crate) fn collect_finalized_custodian_cells(
+    rollup_type_hash: &H256,
+    config: &RollupConfig,
+    global_state: &GlobalState,
+    source: Source,
+) -> Result<Vec<CustodianCell>, Error> {
+    let cells = collect_custodian_locks(rollup_type_hash, config, source)?;
+    if cells.iter().any(|cell| {
+        let number: u64 = cell.args.deposit_block_number().unpack();
+        !is_finalized(global_state, number)
+    }) {
+        debug!("[rebalance custodian] unfinalized custodian cell");
+        return Err(Error::InvalidCustodianCell);
+    }
+    Ok(cells)
+}
+
+/// Verify rebalance custodian cells
+/// 1. the transaction only contains finalized custodian cells, which aren't Nervos DAO cells
+/// 2. input finalized assets == output finalized assets
+/// 3. global state is unchanged
+pub fn verify(
+    rollup_type_hash: H256,
+    config: &RollupConfig,
+    prev_global_state: &GlobalState,
+    post_global_state: &GlobalState,
+) -> Result<(), Error> {
+    check_status(prev_global_state, Status::Running)?;
+    if prev_global_state != post_global_state {
+        debug!("[rebalance custodian] global state is changed");
+        return Err(Error::InvalidPostGlobalState);
+    }
+    check_no_other_rollup_cells(&rollup_type_hash, config)?;
+
+    let input_cells = collect_finalized_custodian_cells(
+        &rollup_type_hash,
+        config,
+        prev_global_state,
+        Source::Input,
+    )?;
+    if input_cells.is_empty() {
+        debug!("[rebalance custodian] no input custodian cells");
+        return Err(Error::InvalidCustodianCell);
+    }
+    let output_cells = collect_finalized_custodian_cells(
+        &rollup_type_hash,
+        config,
+        prev_global_state,
+        Source::Output,
+    )?;
+
+    // Nervos DAO custodians are only moved by the RollupDaoCustodians action
+    if input_cells
+        .iter()
+        .chain(output_cells.iter())
+        .any(|cell| cell.dao_state.is_some())
+    {
+        debug!("[rebalance custodian] unexpected DAO custodian cell");
+        return Err(Error::InvalidCustodianCell);
+    }
+
+    check_custodian_assets_unchanged(&input_cells, &output_cells)
+}
+
+/// Check input finalized assets == output finalized assets
+pub(crate) fn check_custodian_assets_unchanged(
+    input_cells: &[CustodianCell],
+    output_cells: &[CustodianCell],
+) -> Result<(), Error> {
+    let input_assets = build_assets_map_from_cells(input_cells.iter().map(|c| &c.value))?;
+    let mut output_assets = build_assets_map_from_cells(output_cells.iter().map(|c| &c.value))?;
+    // 1. output finalized assets - input finalized assets
+    for (k, v) in input_assets {
+        let balance = output_assets.entry(k).or_insert(0);
+        *balance = balance
+            .checked_sub(v)
+            .ok_or(Error::InsufficientOutputFinalizedAssets)?;
+    }
+    // 2. check output finalized assets is empty
+    if !output_assets.iter().all(|(_k, v)| v == &0) {
+        return Err(Error::InsufficientInputFinalizedAssets);
+    }
+
+    Ok(())
+}
@@ -10,10 +10,11 @@ use gw_types::{
 };
 use gw_utils::gw_types;
 use gw_utils::{
+    bisection::{is_challenger_turn, parse_bisection_state},
     cells::{
         lock_cells::{
-            collect_burn_cells, collect_stake_cells, fetch_capacity_and_sudt_value,
-            find_challenge_cell,
+            collect_burn_cells, collect_challenge_cells, collect_stake_cells,
+            fetch_capacity_and_sudt_value, find_challenge_cell,
         },
         types::ChallengeCell,
         utils::search_lock_hashes,
@@ -21,57 +22,112 @@ use gw_utils::{
     ckb_std::{
         ckb_constants::Source,
         debug,
-        high_level::load_input_since,
+        high_level::{load_cell_data, load_input_since},
         since::{LockValue, Since},
     },
+    finality::is_finalized,
 };
 use gw_utils::{
     gw_common,
     gw_types::packed::{RawL2BlockReader, RollupRevertReader},
 };
 
-use super::{check_rollup_lock_cells_except_stake, check_status};
-use alloc::{collections::BTreeSet, vec::Vec};
+use super::{
+    challenge_queue::{
+        load_challenge_queue, status_after_closing_challenges, update_challenge_queue,
+    },
+    check_rollup_lock_cells_except_stake, check_status,
+    finality::calculate_last_finalized_block_number,
+};
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    vec::Vec,
+};
 use gw_utils::error::Error;
 
 /// Check challenge cell is maturity(on the layer1)
+///
+/// A challenge in the bisection game can't be matured while the challenger is to move,
+/// and it is matured if the producer doesn't move in `bisection_timeout_blocks`.
 fn check_challenge_maturity(
     config: &RollupConfig,
     challenge_cell: &ChallengeCell,
 ) -> Result<(), Error> {
-    let challenge_maturity_blocks: u64 = config.challenge_maturity_blocks().unpack();
+    let data = load_cell_data(challenge_cell.index, Source::Input)?;
+    let maturity_blocks: u64 = match parse_bisection_state(&data)? {
+        Some(state) if is_challenger_turn(&state) => {
+            debug!("revert, challenger's turn in the bisection game");
+            return Err(Error::InvalidChallengeCell);
+        }
+        Some(_) => config.bisection_timeout_blocks().unpack(),
+        None => config.challenge_maturity_blocks().unpack(),
+    };
     let since = Since::new(load_input_since(challenge_cell.index, Source::Input)?);
     if let Some(LockValue::BlockNumber(n)) = since.extract_lock_value() {
-        if since.is_relative() && n >= challenge_maturity_blocks {
+        if since.is_relative() && n >= maturity_blocks {
             return Ok(());
         }
     }
     Err(Error::InvalidChallengeCell)
 }
 
-fn check_challenge_cell(
+/// Check challenge cells, a mature challenge cell must prove the first reverted block is invalid
+///
+/// All challenge cells in the inputs must target the reverted blocks, the challenge cells target
+/// to the later blocks are closed together without maturity.
+fn check_challenge_cells(
     rollup_type_hash: &H256,
     config: &RollupConfig,
-    challenge_cell: &ChallengeCell,
-    revert_target_block_hash: &H256,
+    challenge_cells: &[ChallengeCell],
+    reverted_block_hashes: &[H256],
 ) -> Result<(), Error> {
-    // check challenge maturity
-    check_challenge_maturity(config, challenge_cell)?;
     // check other challenge cells
     let has_output_challenge =
         find_challenge_cell(rollup_type_hash, config, Source::Output)?.is_some();
     if has_output_challenge {
         return Err(Error::InvalidChallengeCell);
     }
-    // check challenge target
-    let challenge_target = challenge_cell.args.target();
-    let challenge_block_hash: H256 = challenge_target.block_hash().unpack();
-    if &challenge_block_hash != revert_target_block_hash {
+    // check challenge targets
+    for challenge_cell in challenge_cells {
+        let challenge_block_hash: H256 = challenge_cell.args.target().block_hash().unpack();
+        if !reverted_block_hashes.contains(&challenge_block_hash) {
+            debug!("[verify revert] challenged block isn't reverted");
+            return Err(Error::InvalidChallengeCell);
+        }
+    }
+    // the first reverted block is challenged target block
+    let revert_target_block_hash = reverted_block_hashes
+        .get(0)
+        .ok_or(Error::InvalidRevertedBlocks)?;
+    let has_mature_challenge = challenge_cells.iter().any(|challenge_cell| {
+        let challenge_block_hash: H256 = challenge_cell.args.target().block_hash().unpack();
+        &challenge_block_hash == revert_target_block_hash
+            && check_challenge_maturity(config, challenge_cell).is_ok()
+    });
+    if !has_mature_challenge {
         return Err(Error::InvalidChallengeCell);
     }
     Ok(())
 }
 
+/// Sort challenge cells by the challenge order, which is the order of the challenge queue,
+/// or the order of the inputs if the challenge queue is disabled
+fn sort_by_challenge_order<'a>(
+    config: &RollupConfig,
+    mut challenge_cells: Vec<&'a ChallengeCell>,
+) -> Result<Vec<&'a ChallengeCell>, Error> {
+    if let Some(targets) = load_challenge_queue(config)? {
+        // the challenges which aren't in the queue are rejected when updating the queue
+        challenge_cells.sort_by_key(|challenge_cell| {
+            let target = challenge_cell.args.target();
+            targets
+                .iter()
+                .position(|t| t.as_slice() == target.as_slice())
+        });
+    }
+    Ok(challenge_cells)
+}
+
 pub fn get_receiver_cells_capacity(
     config: &RollupConfig,
     lock_hash: &[u8; 32],
@@ -89,11 +145,18 @@ pub fn get_receiver_cells_capacity(
 }
 
 /// Check rewards
+///
+/// Every challenger receives the capacity of its challenge cell back,
+/// and the rewards are split among the challengers of the mature challenges by the
+/// `reward_weight` of the challenge cells, a weight is capped by the weights in front of it in
+/// the challenge order, so a later challenger is never weighted above an earlier one.
+/// The immature challenges are closed without rewards.
+/// Every stake cell is slashed entirely, a stake pool loses each delegator share pro rata.
 fn check_rewards(
     rollup_type_hash: &H256,
     config: &RollupConfig,
     reverted_blocks: &[RawL2BlockReader],
-    challenge_cell: &ChallengeCell,
+    challenge_cells: &[ChallengeCell],
 ) -> Result<(), Error> {
     let reverted_block_stake_set: BTreeSet<_> = reverted_blocks
         .iter()
@@ -111,26 +174,64 @@ fn check_rewards(
         return Err(Error::InvalidStakeCell);
     }
 
-    // calculate rewards assets & burn assets
+    // calculate the slashed capacity, every stake cell is slashed entirely,
+    // the slash of a stake pool is charged against each delegator share pro rata
+    let mut expected_received_capacity: BTreeMap<[u8; 32], u128> = BTreeMap::new();
     let total_stake_capacity: u128 = stake_cells.iter().map(|cell| cell.capacity as u128).sum();
+
+    // calculate rewards assets & burn assets
     let reward_burn_rate: u8 = config.reward_burn_rate().into();
     let expected_reward_capacity =
         total_stake_capacity.saturating_mul(reward_burn_rate.into()) / 100;
     let expected_burn_capacity = total_stake_capacity.saturating_sub(expected_reward_capacity);
-    // collect rewards receiver cells capacity
-    let received_capacity: u128 = {
+    // calculate the expected capacity of each rewards receiver,
+    // the remainder of the split goes to the first challenger
+    let (mature_challenge_cells, immature_challenge_cells): (Vec<_>, Vec<_>) = challenge_cells
+        .iter()
+        .partition(|challenge_cell| check_challenge_maturity(config, challenge_cell).is_ok());
+    for challenge_cell in immature_challenge_cells {
         let rewards_receiver_lock_hash = challenge_cell.args.rewards_receiver_lock().hash();
+        let expected_capacity = expected_received_capacity
+            .entry(rewards_receiver_lock_hash)
+            .or_default();
+        *expected_capacity = expected_capacity.saturating_add(challenge_cell.value.capacity.into());
+    }
+    let challenge_cells = sort_by_challenge_order(config, mature_challenge_cells)?;
+    let weights: Vec<u128> = challenge_cells
+        .iter()
+        .scan(u32::MAX, |max_weight, challenge_cell| {
+            let reward_weight: u32 = challenge_cell.args.reward_weight().unpack();
+            *max_weight = reward_weight.min(*max_weight);
+            Some(*max_weight as u128)
+        })
+        .collect();
+    let total_weights: u128 = weights.iter().sum();
+    let rewards: Vec<u128> = weights
+        .iter()
+        .map(|weight| expected_reward_capacity.saturating_mul(*weight) / total_weights.max(1))
+        .collect();
+    let reward_remainder = expected_reward_capacity.saturating_sub(rewards.iter().sum());
+    for (k, (challenge_cell, reward)) in challenge_cells.into_iter().zip(rewards).enumerate() {
+        let mut capacity: u128 = challenge_cell.value.capacity.into();
+        capacity = capacity.saturating_add(reward);
+        if k == 0 {
+            capacity = capacity.saturating_add(reward_remainder);
+        }
+        let rewards_receiver_lock_hash = challenge_cell.args.rewards_receiver_lock().hash();
+        let expected_capacity = expected_received_capacity
+            .entry(rewards_receiver_lock_hash)
+            .or_default();
+        *expected_capacity = expected_capacity.saturating_add(capacity);
+    }
+    // make sure rewards are sent to the challengers
+    for (rewards_receiver_lock_hash, expected_capacity) in expected_received_capacity {
         let input_capacity =
             get_receiver_cells_capacity(config, &rewards_receiver_lock_hash, Source::Input)?;
         let output_capacity =
             get_receiver_cells_capacity(config, &rewards_receiver_lock_hash, Source::Output)?;
-        output_capacity.saturating_sub(input_capacity)
-    };
-    // make sure rewards are sent to the challenger
-    if received_capacity
-        < expected_reward_capacity.saturating_add(challenge_cell.value.capacity.into())
-    {
-        return Err(Error::InvalidChallengeReward);
+        if output_capacity.saturating_sub(input_capacity) < expected_capacity {
+            return Err(Error::InvalidChallengeReward);
+        }
     }
     // check burned assets
     let burned_capacity: u128 = {
@@ -156,6 +257,7 @@ fn check_reverted_blocks(
     revert_args: &RollupRevertReader,
     prev_global_state: &GlobalState,
     post_global_state: &GlobalState,
+    post_status: Status,
 ) -> Result<GlobalState, Error> {
     if reverted_blocks.is_empty() {
         return Err(Error::InvalidRevertedBlocks);
@@ -166,6 +268,11 @@ fn check_reverted_blocks(
         .iter()
         .map(|b| RawL2Block::compute_smt_key(b.number().unpack()).into())
         .collect();
+    // finalized blocks can't be reverted
+    if is_finalized(prev_global_state, reverted_blocks[0].number().unpack()) {
+        debug!("[verify revert] reverted block is finalized");
+        return Err(Error::InvalidRevertedBlocks);
+    }
     // check reverted_blocks is continues
     {
         let mut prev_hash: Byte32 = reverted_blocks[0].hash().pack();
@@ -247,28 +354,37 @@ fn check_reverted_blocks(
     };
     let account_merkle_state = reverted_blocks[0].prev_account();
     let tip_block_hash = reverted_blocks[0].parent_block_hash();
+    // the finalized blocks stay finalized after the revert
     let last_finalized_block_number = {
         let number: u64 = reverted_blocks[0].number().unpack();
-        number
-            .saturating_sub(1)
-            .saturating_sub(config.finality_blocks().unpack())
+        calculate_last_finalized_block_number(
+            config,
+            prev_global_state,
+            number.saturating_sub(1),
+            None,
+        )
     };
     let new_tip_block = revert_args.new_tip_block();
     if new_tip_block.hash() != tip_block_hash.as_slice() {
         debug!("[verify revert] reverted new_tip_block doesn't match");
         return Err(Error::InvalidRevertedBlocks);
     }
-    let tip_block_timestamp = new_tip_block.timestamp();
+    // version 0 doesn't have `tip_block_timestamp`
+    let tip_block_timestamp = if prev_global_state.version() == 0.into() {
+        0u64.pack()
+    } else {
+        new_tip_block.timestamp().to_entity()
+    };
     // check post global state
     let reverted_post_global_state = {
-        let status: u8 = Status::Running.into();
+        let status: u8 = post_status.into();
         prev_global_state
             .clone()
             .as_builder()
             .account(account_merkle_state.to_entity())
             .block(block_merkle_state)
             .tip_block_hash(tip_block_hash.to_entity())
-            .tip_block_timestamp(tip_block_timestamp.to_entity())
+            .tip_block_timestamp(tip_block_timestamp)
             .last_finalized_block_number(last_finalized_block_number.pack())
             .reverted_block_root(reverted_block_root)
             .status(status.into())
@@ -301,23 +417,45 @@ pub fn verify(
     let reverted_blocks_vec = revert_args.reverted_blocks();
     let reverted_blocks: Vec<_> = reverted_blocks_vec.iter().collect();
     // check challenge cells
-    let challenge_cell = find_challenge_cell(&rollup_type_hash, config, Source::Input)?
-        .ok_or(Error::InvalidChallengeCell)?;
-    // the first reverted block is challenged target block
-    let challenged_block = reverted_blocks.get(0).ok_or(Error::InvalidRevertedBlocks)?;
-    check_challenge_cell(
+    let challenge_cells = collect_challenge_cells(&rollup_type_hash, config, Source::Input)?;
+    let reverted_block_hashes: Vec<H256> =
+        reverted_blocks.iter().map(|b| b.hash().into()).collect();
+    check_challenge_cells(
         &rollup_type_hash,
         config,
-        &challenge_cell,
-        &challenged_block.hash().into(),
+        &challenge_cells,
+        &reverted_block_hashes,
     )?;
-    check_rewards(&rollup_type_hash, config, &reverted_blocks, &challenge_cell)?;
+    check_rewards(
+        &rollup_type_hash,
+        config,
+        &reverted_blocks,
+        &challenge_cells,
+    )?;
+    // remove the closed challenges from the queue,
+    // the remaining challenges must not target to the reverted blocks
+    let closed_challenges: Vec<_> = challenge_cells
+        .iter()
+        .map(|challenge_cell| challenge_cell.args.target())
+        .collect();
+    let open_challenges =
+        update_challenge_queue(&rollup_type_hash, config, &closed_challenges, None)?;
+    if let Some(open_challenges) = open_challenges.as_ref() {
+        if open_challenges.iter().any(|target| {
+            let challenge_block_hash: H256 = target.block_hash().unpack();
+            reverted_block_hashes.contains(&challenge_block_hash)
+        }) {
+            debug!("[verify revert] challenges of reverted blocks aren't closed");
+            return Err(Error::InvalidChallengeCell);
+        }
+    }
     let reverted_global_state = check_reverted_blocks(
         config,
         &reverted_blocks,
         &revert_args,
         prev_global_state,
         post_global_state,
+        status_after_closing_challenges(open_challenges.as_deref()),
     )?;
     if post_global_state != &reverted_global_state {
         return Err(Error::InvalidPostGlobalState);
@@ -9,7 +9,7 @@ use gw_state::constants::GW_MAX_KV_PAIRS;
 use gw_utils::ckb_std::high_level::load_input_since;
 use gw_utils::ckb_std::since::{LockValue, Since};
 use gw_utils::gw_common::registry_address::RegistryAddress;
-use gw_utils::gw_types::packed::{L2BlockReader, WithdrawalRequestReader};
+use gw_utils::gw_types::packed::{L2BlockReader, TimeFinalityProofReader, WithdrawalRequestReader};
 
 // Import CKB syscalls and structures
 // https://nervosnetwork.github.io/ckb-std/riscv64imac-unknown-none-elf/doc/ckb_std/index.html
@@ -18,17 +18,30 @@ use gw_state::kv_state::KVState;
 use gw_utils::gw_common::{self, ckb_decimal::CKBCapacity};
 use gw_utils::gw_types::{self, U256};
 
-use super::check_status;
+use super::{
+    check_status,
+    finality::{calculate_last_finalized_block_number, check_time_finality_proof},
+    forced_withdrawal::check_forced_withdrawal_cells,
+    producer_schedule::check_block_producer_schedule,
+    withdrawal_limit::check_withdrawal_limit,
+};
 use crate::types::BlockContext;
 use gw_utils::{
     cells::{
         lock_cells::{
-            collect_custodian_locks, collect_deposit_locks, collect_withdrawal_locks,
-            find_block_producer_stake_cell, find_challenge_cell,
+            collect_batch_deposit_locks, collect_custodian_locks, collect_deposit_locks,
+            collect_stake_cells, collect_withdrawal_locks, find_block_producer_stake_cell,
+            find_challenge_cell,
+        },
+        types::{
+            BatchDepositRequestCell, CellValue, CustodianCell, DepositRequestCell, WithdrawalCell,
         },
-        types::{CellValue, DepositRequestCell, WithdrawalCell},
         utils::build_l2_sudt_script,
     },
+    deposit::{
+        build_deposit_request, calculate_deposit_checkpoint, expand_batch_deposit,
+        get_deposit_checkpoint, mint_deposit, DEPOSIT_CALL_SIGNATURE,
+    },
     error::Error,
 };
 
@@ -42,12 +55,17 @@ use gw_common::{
 };
 use gw_types::{
     bytes::Bytes,
-    core::{ScriptHashType, Status},
-    packed::{Byte32, GlobalState, RawL2Block, RollupConfig},
+    core::Status,
+    packed::{
+        BlockMerkleState, Byte, Byte32, DepositCall, DepositRequest, GlobalState, RawL2Block,
+        RollupConfig,
+    },
     prelude::*,
 };
 
-fn build_assets_map_from_cells<'a, I: Iterator<Item = &'a CellValue>>(
+/// Build the assets map of cells, the value of a Nervos DAO custodian is its principal,
+/// the accrued interest isn't counted as user assets
+pub(crate) fn build_assets_map_from_cells<'a, I: Iterator<Item = &'a CellValue>>(
     cells: I,
 ) -> Result<BTreeMap<H256, u128>, Error> {
     let mut assets = BTreeMap::new();
@@ -64,10 +82,10 @@ fn build_assets_map_from_cells<'a, I: Iterator<Item = &'a CellValue>>(
     Ok(assets)
 }
 
-fn check_withdrawal_cells<'a>(
+fn check_withdrawal_cells<'a, 'b, I: Iterator<Item = &'b WithdrawalCell>>(
     context: &BlockContext,
     mut withdrawal_requests: Vec<WithdrawalRequestReader<'a>>,
-    withdrawal_cells: &[WithdrawalCell],
+    withdrawal_cells: I,
 ) -> Result<(), Error> {
     // iter outputs withdrawal cells, check each cell has a corresponded withdrawal request
     for cell in withdrawal_cells {
@@ -120,29 +138,28 @@ fn check_input_custodian_cells(
     output_withdrawal_cells: Vec<WithdrawalCell>,
 ) -> Result<BTreeMap<H256, u128>, Error> {
     // collect input custodian cells
+    let custodian_cells =
+        collect_custodian_locks(&context.rollup_type_hash, config, Source::Input)?;
+    // Nervos DAO custodians are only moved by the RollupDaoCustodians action
+    if custodian_cells.iter().any(|cell| cell.dao_state.is_some()) {
+        debug!("unexpected DAO custodian cell");
+        return Err(Error::InvalidCustodianCell);
+    }
     let (finalized_custodian_cells, unfinalized_custodian_cells): (Vec<_>, Vec<_>) =
-        collect_custodian_locks(&context.rollup_type_hash, config, Source::Input)?
-            .into_iter()
-            .partition(|cell| {
-                let number: u64 = cell.args.deposit_block_number().unpack();
-                number <= context.finalized_number
-            });
+        custodian_cells.into_iter().partition(|cell| {
+            let number: u64 = cell.args.deposit_block_number().unpack();
+            number <= context.finalized_number
+        });
     // check unfinalized custodian cells == reverted deposit requests
-    let mut reverted_deposit_cells =
+    let reverted_deposit_cells =
         collect_deposit_locks(&context.rollup_type_hash, config, Source::Output)?;
-    for custodian_cell in unfinalized_custodian_cells {
-        let index = reverted_deposit_cells
-            .iter()
-            .position(|cell| {
-                custodian_cell.args.deposit_lock_args() == cell.args
-                    && custodian_cell.value == cell.value
-            })
-            .ok_or(Error::InvalidCustodianCell)?;
-        reverted_deposit_cells.remove(index);
-    }
-    if !reverted_deposit_cells.is_empty() {
-        return Err(Error::InvalidDepositCell);
-    }
+    let reverted_batch_deposit_cells =
+        collect_batch_deposit_locks(&context.rollup_type_hash, config, Source::Output)?;
+    check_unfinalized_custodian_cells(
+        unfinalized_custodian_cells,
+        reverted_deposit_cells,
+        reverted_batch_deposit_cells,
+    )?;
     // check input finalized custodian cells >= withdrawal cells
     let withdrawal_assets =
         build_assets_map_from_cells(output_withdrawal_cells.iter().map(|c| &c.value))?;
@@ -158,34 +175,71 @@ fn check_input_custodian_cells(
     Ok(input_finalized_assets)
 }
 
+/// Check each unfinalized custodian cell is corresponded to a deposit or a batch deposit,
+/// the custodian of a deposit carrying a call records the same call
+fn check_unfinalized_custodian_cells(
+    unfinalized_custodian_cells: Vec<CustodianCell>,
+    mut deposit_cells: Vec<DepositRequestCell>,
+    mut batch_deposit_cells: Vec<BatchDepositRequestCell>,
+) -> Result<(), Error> {
+    for custodian_cell in unfinalized_custodian_cells {
+        match custodian_cell.batch_deposit_args {
+            Some(batch_deposit_args) => {
+                let index = batch_deposit_cells
+                    .iter()
+                    .position(|cell| {
+                        batch_deposit_args.as_slice() == cell.args.as_slice()
+                            && custodian_cell.value == cell.value
+                    })
+                    .ok_or(Error::InvalidCustodianCell)?;
+                batch_deposit_cells.remove(index);
+            }
+            None => {
+                let index = deposit_cells
+                    .iter()
+                    .position(|cell| {
+                        custodian_cell.args.deposit_lock_args() == cell.args
+                            && custodian_cell.call.as_ref().map(|call| call.as_slice())
+                                == cell.call.as_ref().map(|call| call.as_slice())
+                            && custodian_cell.value == cell.value
+                    })
+                    .ok_or(Error::InvalidCustodianCell)?;
+                deposit_cells.remove(index);
+            }
+        }
+    }
+    if !deposit_cells.is_empty() || !batch_deposit_cells.is_empty() {
+        return Err(Error::InvalidDepositCell);
+    }
+    Ok(())
+}
+
 fn check_output_custodian_cells(
     config: &RollupConfig,
     context: &BlockContext,
-    mut deposit_cells: Vec<DepositRequestCell>,
+    deposit_cells: Vec<DepositRequestCell>,
+    batch_deposit_cells: Vec<BatchDepositRequestCell>,
     input_finalized_assets: BTreeMap<H256, u128>,
 ) -> Result<(), Error> {
     // collect output custodian cells
+    let custodian_cells =
+        collect_custodian_locks(&context.rollup_type_hash, config, Source::Output)?;
+    // Nervos DAO custodians are only moved by the RollupDaoCustodians action
+    if custodian_cells.iter().any(|cell| cell.dao_state.is_some()) {
+        debug!("unexpected DAO custodian cell");
+        return Err(Error::InvalidCustodianCell);
+    }
     let (finalized_custodian_cells, unfinalized_custodian_cells): (Vec<_>, Vec<_>) =
-        collect_custodian_locks(&context.rollup_type_hash, config, Source::Output)?
-            .into_iter()
-            .partition(|cell| {
-                let number: u64 = cell.args.deposit_block_number().unpack();
-                number <= context.finalized_number
-            });
+        custodian_cells.into_iter().partition(|cell| {
+            let number: u64 = cell.args.deposit_block_number().unpack();
+            number <= context.finalized_number
+        });
     // check deposits request cells == unfinalized custodian cells
-    for custodian_cell in unfinalized_custodian_cells {
-        let index = deposit_cells
-            .iter()
-            .position(|cell| {
-                custodian_cell.args.deposit_lock_args() == cell.args
-                    && custodian_cell.value == cell.value
-            })
-            .ok_or(Error::InvalidCustodianCell)?;
-        deposit_cells.remove(index);
-    }
-    if !deposit_cells.is_empty() {
-        return Err(Error::InvalidDepositCell);
-    }
+    check_unfinalized_custodian_cells(
+        unfinalized_custodian_cells,
+        deposit_cells,
+        batch_deposit_cells,
+    )?;
     // check reverted withdrawals <= finalized custodian cells
     {
         let reverted_withdrawals =
@@ -218,75 +272,118 @@ fn check_output_custodian_cells(
     Ok(())
 }
 
-fn check_layer2_deposit(
-    rollup_type_hash: &H256,
+fn check_deposit_policy(
     config: &RollupConfig,
-    kv_state: &mut KVState,
     deposit_cells: &[DepositRequestCell],
 ) -> Result<(), Error> {
-    let registry_ctx = gw_common::registry::context::RegistryContext::new(
-        config.allowed_eoa_type_hashes().into_iter().collect(),
-    );
+    let policy = config.deposit_policy();
+    let max_deposits: u32 = policy.max_deposits_per_block().unpack();
+    if max_deposits != 0 && deposit_cells.len() > max_deposits as usize {
+        debug!(
+            "[check deposit policy] too many deposits: {}, max: {}",
+            deposit_cells.len(),
+            max_deposits
+        );
+        return Err(Error::TooManyDeposits);
+    }
+    let min_capacity: u64 = policy.min_deposit_capacity().unpack();
+    let allowed_sudt_script_hashes = policy.allowed_sudt_script_hashes();
     for request in deposit_cells {
-        // check that account's script is a valid EOA script
-        if request.account_script.hash_type() != ScriptHashType::Type.into() {
-            return Err(Error::UnknownEOAScript);
+        if request.value.capacity < min_capacity {
+            debug!(
+                "[check deposit policy] deposit capacity {} is lower than {}",
+                request.value.capacity, min_capacity
+            );
+            return Err(Error::DepositCapacityTooLow);
         }
-        let registry_id: u32 = request.args.registry_id().unpack();
-
-        // find or create EOA
-        let address = match kv_state.get_account_id_by_script_hash(&request.account_script_hash)? {
-            Some(_id) => {
-                // account is exist, query registry address
-                kv_state
-                    .get_registry_address_by_script_hash(registry_id, &request.account_script_hash)?
-                    .ok_or(Error::RegistryAddressNotFound)?
-            }
-            None => {
-                // account isn't exist
-                let _new_id = kv_state.create_account(request.account_script_hash)?;
-                let script = &request.account_script;
-                let addr = registry_ctx.extract_registry_address_from_deposit(
-                    registry_id,
-                    &script.code_hash(),
-                    &script.args().raw_data(),
-                )?;
-                // mapping addr to script hash
-                kv_state.mapping_registry_address_to_script_hash(
-                    addr.clone(),
-                    request.account_script_hash,
-                )?;
-                addr
-            }
-        };
+        if request.value.sudt_script_hash.as_slice() != CKB_SUDT_SCRIPT_ARGS
+            && !allowed_sudt_script_hashes.is_empty()
+            && !allowed_sudt_script_hashes
+                .clone()
+                .into_iter()
+                .any(|hash| hash.as_slice() == request.value.sudt_script_hash.as_slice())
+        {
+            debug!("[check deposit policy] sUDT is not allowed");
+            return Err(Error::DepositSUDTNotAllowed);
+        }
+    }
+    Ok(())
+}
 
-        // mint CKB
-        kv_state.mint_sudt(
-            CKB_SUDT_ACCOUNT_ID,
-            &address,
-            CKBCapacity::from_layer1(request.value.capacity).to_layer2(),
-        )?;
-        if request.value.sudt_script_hash.as_slice() == CKB_SUDT_SCRIPT_ARGS {
-            if request.value.amount != 0 {
-                // SUDT amount must equals to zero if sudt script hash is equals to CKB_SUDT_SCRIPT_ARGS
-                return Err(Error::InvalidDepositCell);
+/// Mint deposits, return the depositor account id and the call of deposits which carry a call
+fn check_layer2_deposit(
+    rollup_type_hash: &H256,
+    config: &RollupConfig,
+    kv_state: &mut KVState,
+    block: &L2BlockReader,
+    deposit_cells: &[DepositRequestCell],
+) -> Result<Vec<(u32, DepositCall)>, Error> {
+    let deposit_requests: Vec<DepositRequest> =
+        deposit_cells.iter().map(build_deposit_request).collect();
+    check_block_deposits(block, &deposit_requests)?;
+
+    let mut deposit_calls = Vec::new();
+    for (cell, request) in deposit_cells.iter().zip(deposit_requests.iter()) {
+        // the call is sent without a signature, so it is only allowed from the account
+        // created by the deposit, and no other deposit of the block is minted to the account
+        if cell.call.is_some() {
+            let is_new_account = kv_state
+                .get_account_id_by_script_hash(&cell.account_script_hash)?
+                .is_none();
+            let is_shared_account = deposit_cells
+                .iter()
+                .filter(|c| c.account_script_hash == cell.account_script_hash)
+                .count()
+                > 1;
+            if !is_new_account || is_shared_account {
+                debug!("[check layer2 deposit] deposit call from an existing account");
+                return Err(Error::InvalidDepositCall);
             }
-            continue;
         }
-        // find or create Simple UDT account
-        let l2_sudt_script =
-            build_l2_sudt_script(rollup_type_hash, config, &request.value.sudt_script_hash);
-        let l2_sudt_script_hash: [u8; 32] = l2_sudt_script.hash();
-        let sudt_id = match kv_state.get_account_id_by_script_hash(&l2_sudt_script_hash.into())? {
-            Some(id) => id,
-            None => kv_state.create_account(l2_sudt_script_hash.into())?,
+        let account_id = mint_deposit(rollup_type_hash, config, kv_state, request)?;
+        if let Some(call) = cell.call.as_ref() {
+            deposit_calls.push((account_id, call.clone()));
+        }
+    }
+
+    Ok(deposit_calls)
+}
+
+/// The deposit calls must be the leading transactions of the block in the order of deposits,
+/// other transactions can't use the deposit call signature
+fn check_deposit_calls(
+    block: &L2BlockReader,
+    deposit_calls: &[(u32, DepositCall)],
+) -> Result<(), Error> {
+    let transactions = block.transactions();
+    if transactions.len() < deposit_calls.len() {
+        debug!(
+            "[check deposit calls] expected {} deposit calls, transactions: {}",
+            deposit_calls.len(),
+            transactions.len()
+        );
+        return Err(Error::InvalidDepositCall);
+    }
+    for (index, tx) in transactions.iter().enumerate() {
+        let is_deposit_call_tx = tx.signature().raw_data() == DEPOSIT_CALL_SIGNATURE;
+        let (from_id, call) = match deposit_calls.get(index) {
+            Some(deposit_call) => deposit_call,
+            None if is_deposit_call_tx => {
+                debug!("[check deposit calls] unexpected deposit call tx {}", index);
+                return Err(Error::InvalidDepositCall);
+            }
+            None => continue,
         };
-        // prevent fake CKB SUDT, the caller should filter these invalid deposits
-        if sudt_id == CKB_SUDT_ACCOUNT_ID {
-            return Err(Error::InvalidDepositCell);
+        let raw_tx = tx.raw();
+        let tx_from_id: u32 = raw_tx.from_id().unpack();
+        if !is_deposit_call_tx
+            || tx_from_id != *from_id
+            || raw_tx.to_id().as_slice() != call.to_id().as_slice()
+            || raw_tx.args().as_slice() != call.args().as_slice()
+        {
+            debug!("[check deposit calls] mismatch deposit call tx {}", index);
+            return Err(Error::InvalidDepositCall);
         }
-        // mint SUDT
-        kv_state.mint_sudt(sudt_id, &address, request.value.amount.into())?;
     }
 
     Ok(())
@@ -370,6 +467,7 @@ fn load_block_context_and_state<'a>(
     l2block: &L2BlockReader,
     prev_global_state: &GlobalState,
     post_global_state: &GlobalState,
+    time_finalized_number: Option<u64>,
 ) -> Result<(BlockContext, KVState<'a>), Error> {
     let raw_block = l2block.raw();
 
@@ -456,7 +554,12 @@ fn load_block_context_and_state<'a>(
     // Generate context
     let account_count: u32 = prev_global_state.account().count().unpack();
     let prev_account_root = prev_global_state.account().merkle_root().unpack();
-    let finalized_number = number.saturating_sub(config.finality_blocks().unpack());
+    let finalized_number = calculate_last_finalized_block_number(
+        config,
+        prev_global_state,
+        number,
+        time_finalized_number,
+    );
 
     // Check pre account merkle proof
     let kv_state = KVState::build(
@@ -490,15 +593,33 @@ fn verify_block_producer(
 ) -> Result<(), Error> {
     let raw_block = block.raw();
     let owner_lock_hash = raw_block.stake_cell_owner_lock_hash();
-    // make sure we have one stake cell in the output
-    let output_stake_cell = find_block_producer_stake_cell(
+    let input_stake_cell = find_block_producer_stake_cell(
         &context.rollup_type_hash,
         config,
-        Source::Output,
+        Source::Input,
         &owner_lock_hash,
-    )?
-    .ok_or(Error::InvalidStakeCell)?;
+    )?;
+    // the first output stake cell is the active stake, an optional second one is
+    // the partially unstaked part, which keeps the finalized stake block number of the input
+    let output_stake_cells =
+        collect_stake_cells(&context.rollup_type_hash, config, Source::Output)?;
+    let max_output_stake_cells = if input_stake_cell.is_some() { 2 } else { 1 };
+    if output_stake_cells.is_empty() || output_stake_cells.len() > max_output_stake_cells {
+        debug!(
+            "[verify block producer] unexpected output stake cells {}",
+            output_stake_cells.len()
+        );
+        return Err(Error::InvalidStakeCell);
+    }
+    if output_stake_cells
+        .iter()
+        .any(|cell| cell.args.owner_lock_hash().as_slice() != owner_lock_hash.as_slice())
+    {
+        debug!("[verify block producer] found stake cell with unexpected owner_lock_hash");
+        return Err(Error::InvalidStakeCell);
+    }
     // check stake cell capacity
+    let output_stake_cell = &output_stake_cells[0];
     let required_staking_capacity: u64 = config.required_staking_capacity().unpack();
     if output_stake_cell.capacity < required_staking_capacity {
         debug!(
@@ -507,37 +628,69 @@ fn verify_block_producer(
         );
         return Err(Error::InvalidStakeCell);
     }
-    // make sure input stake cell is identical to the output stake cell if we have one
-    if let Some(input_stake_cell) = find_block_producer_stake_cell(
-        &context.rollup_type_hash,
-        config,
-        Source::Input,
-        &owner_lock_hash,
-    )? {
+    // make sure the output stake cells are corresponded to the input stake cell if we have one,
+    // the stake can be topped up or partially unstaked
+    if let Some(input_stake_cell) = input_stake_cell {
         let expected_stake_lock_args = input_stake_cell
             .args
+            .clone()
             .as_builder()
             .stake_block_number(raw_block.number().to_entity())
             .build();
-        if expected_stake_lock_args != output_stake_cell.args
-            || input_stake_cell.capacity > output_stake_cell.capacity
+        // the delegators of a stake pool can't be changed
+        if output_stake_cell.args != expected_stake_lock_args
+            || output_stake_cell.pool != input_stake_cell.pool
         {
             debug!("the output stake cell isn't corresponded to the input one");
             return Err(Error::InvalidStakeCell);
         }
+        // the unstaked part isn't at stake for this block, the active stake is slashed if the
+        // block is reverted. Only a stake of finalized blocks can be unstaked, otherwise it
+        // escapes the slash of reverting the previous blocks
+        if let Some(unstaked_cell) = output_stake_cells.get(1) {
+            let input_stake_block_number: u64 = input_stake_cell.args.stake_block_number().unpack();
+            if input_stake_block_number > context.finalized_number {
+                debug!(
+                    "[verify block producer] unstake from an unfinalized stake block {}",
+                    input_stake_block_number
+                );
+                return Err(Error::InvalidStakeCell);
+            }
+            if unstaked_cell.args != input_stake_cell.args
+                || unstaked_cell.pool != input_stake_cell.pool
+            {
+                debug!("the unstaked cell isn't corresponded to the input one");
+                return Err(Error::InvalidStakeCell);
+            }
+        }
+        let mut output_capacity: u64 = 0;
+        for cell in output_stake_cells.iter() {
+            output_capacity = output_capacity
+                .checked_add(cell.capacity)
+                .ok_or(Error::AmountOverflow)?;
+        }
+        if input_stake_cell.capacity > output_capacity {
+            debug!(
+                "[verify block producer] output stake capacity {} is less than the input {}",
+                output_capacity, input_stake_cell.capacity
+            );
+            return Err(Error::InvalidStakeCell);
+        }
     }
 
     Ok(())
 }
 
-fn check_state_checkpoints(block: &L2BlockReader) -> Result<(), Error> {
+fn check_state_checkpoints(block: &L2BlockReader, version: u8) -> Result<(), Error> {
     let raw_block = block.raw();
     let checkpoint_list = raw_block.state_checkpoint_list();
 
     let transactions = block.transactions();
     let withdrawals = block.withdrawals();
 
-    if checkpoint_list.len() != withdrawals.len() + transactions.len() {
+    // version 2 blocks commit the deposits after the state checkpoints
+    let deposit_checkpoints = if version >= 2 { 1 } else { 0 };
+    if checkpoint_list.len() != withdrawals.len() + transactions.len() + deposit_checkpoints {
         debug!(
             "Wrong checkpoint length, checkpoints_list: {}, withdrawals: {} transactions: {}",
             checkpoint_list.len(),
@@ -553,8 +706,7 @@ fn check_state_checkpoints(block: &L2BlockReader) -> Result<(), Error> {
     } else {
         // return last transaction state checkpoint
         checkpoint_list
-            .iter()
-            .last()
+            .get(withdrawals.len() + transactions.len() - 1)
             .ok_or(Error::InvalidStateCheckpoint)?
     };
     let block_state_checkpoint: Byte32 = {
@@ -618,8 +770,7 @@ fn check_block_transactions(block: &L2BlockReader, kv_state: &KVState) -> Result
     } else {
         raw_block
             .state_checkpoint_list()
-            .iter()
-            .last()
+            .get(block.withdrawals().len() + block.transactions().len() - 1)
             .map(|checkpoint| checkpoint.unpack())
             .ok_or(Error::InvalidStateCheckpoint)?
     };
@@ -671,6 +822,26 @@ fn check_block_withdrawals(block: &L2BlockReader) -> Result<(), Error> {
     Ok(())
 }
 
+fn check_block_deposits(
+    block: &L2BlockReader,
+    deposit_requests: &[DepositRequest],
+) -> Result<(), Error> {
+    // the checkpoint length is checked by the version, blocks before version 2 don't commit
+    // the deposits
+    let deposit_checkpoint = match get_deposit_checkpoint(&block.raw()) {
+        Some(checkpoint) => checkpoint,
+        None => return Ok(()),
+    };
+
+    let checkpoint = calculate_deposit_checkpoint(deposit_requests.iter().cloned())?;
+    if deposit_checkpoint != checkpoint {
+        debug!("failed to check block deposit checkpoint");
+        return Err(Error::MerkleProof);
+    }
+
+    Ok(())
+}
+
 fn check_block_timestamp(
     prev_global_state: &GlobalState,
     post_global_state: &GlobalState,
@@ -679,12 +850,7 @@ fn check_block_timestamp(
     let prev_version: u8 = prev_global_state.version().into();
     let post_version: u8 = post_global_state.version().into();
 
-    if 0 == post_version && post_global_state.tip_block_timestamp().unpack() != 0 {
-        debug!("v0 global state tip block timestamp isn't 0");
-        return Err(Error::InvalidPostGlobalState);
-    }
-
-    // NOTE: Downgrade already checked in main
+    // NOTE: Downgrade and the v0 tip block timestamp are already checked in main
     if 0 == post_version {
         debug!("[check block timestamp] skip block timestamp");
         return Ok(());
@@ -728,6 +894,75 @@ fn check_block_timestamp(
     Ok(())
 }
 
+/// Calculate the block merkle state after the block is appended,
+/// the block proof is checked against the prev block merkle root in `load_block_context_and_state`
+fn calculate_post_block_merkle_state(l2block: &L2BlockReader) -> Result<BlockMerkleState, Error> {
+    let raw_block = l2block.raw();
+    let number: u64 = raw_block.number().unpack();
+    let block_proof: Bytes = l2block.block_proof().unpack();
+    let mut buf = [Pair::default(); 256];
+    let mut block_tree = Tree::new(&mut buf);
+    block_tree
+        .update(&RawL2Block::compute_smt_key(number), &raw_block.hash())
+        .map_err(|err| {
+            debug!("[calculate block merkle state] update kv error: {}", err);
+            Error::MerkleProof
+        })?;
+    block_tree.normalize();
+    let block_root = block_tree.calculate_root(&block_proof).map_err(|err| {
+        debug!(
+            "[calculate block merkle state] calculate root error: {}",
+            err
+        );
+        Error::MerkleProof
+    })?;
+    let block_merkle_state = BlockMerkleState::new_builder()
+        .merkle_root(block_root.pack())
+        .count((number + 1).pack())
+        .build();
+    Ok(block_merkle_state)
+}
+
+/// Build the global state after applying the block
+fn build_post_global_state(
+    config: &RollupConfig,
+    block: &L2BlockReader,
+    prev_global_state: &GlobalState,
+    block_merkle_state: BlockMerkleState,
+    version: Byte,
+    time_finalized_number: Option<u64>,
+) -> GlobalState {
+    let raw_block = block.raw();
+    // because of the optimistic challenge mechanism,
+    // we just believe the post account in the block,
+    // if the post account state is invalid then someone will send a challenge
+    let account_merkle_state = raw_block.post_account();
+    // last finalized block number
+    let number: u64 = raw_block.number().unpack();
+    let last_finalized_block_number = calculate_last_finalized_block_number(
+        config,
+        prev_global_state,
+        number,
+        time_finalized_number,
+    );
+    let tip_block_timestamp = if version == 0.into() {
+        0
+    } else {
+        raw_block.timestamp().unpack()
+    };
+
+    prev_global_state
+        .clone()
+        .as_builder()
+        .account(account_merkle_state.to_entity())
+        .block(block_merkle_state)
+        .tip_block_hash(raw_block.hash().pack())
+        .tip_block_timestamp(tip_block_timestamp.pack())
+        .last_finalized_block_number(last_finalized_block_number.pack())
+        .version(version)
+        .build()
+}
+
 /// Verify Deposit & Withdrawal
 pub fn verify(
     rollup_type_hash: H256,
@@ -735,43 +970,189 @@ pub fn verify(
     block: &L2BlockReader,
     prev_global_state: &GlobalState,
     post_global_state: &GlobalState,
+    time_finality_proof: Option<&TimeFinalityProofReader>,
 ) -> Result<(), Error> {
-    check_status(prev_global_state, Status::Running)?;
-
-    // check checkpoints
-    check_state_checkpoints(block)?;
-
-    // Check withdrawals root
-    check_block_withdrawals(block)?;
-
-    let mut tree_buffer = [Pair::default(); GW_MAX_KV_PAIRS];
-    let kv_state_proof: Bytes = block.kv_state_proof().unpack();
-
-    let (context, mut kv_state) = load_block_context_and_state(
+    verify_blocks(
         rollup_type_hash,
         config,
-        &mut tree_buffer,
-        &kv_state_proof,
-        block,
+        &[*block],
         prev_global_state,
         post_global_state,
-    )?;
-    // Verify block producer
-    verify_block_producer(config, &context, block)?;
+        time_finality_proof,
+    )
+}
+
+/// Verify a batch of blocks which are submitted in one transaction
+///
+/// Blocks are verified one by one upon the global state of the previous block,
+/// deposits are minted in the first block of the batch,
+/// and the deposit / withdrawal / custodian cells are checked once for the whole batch.
+pub fn verify_blocks(
+    rollup_type_hash: H256,
+    config: &RollupConfig,
+    blocks: &[L2BlockReader],
+    prev_global_state: &GlobalState,
+    post_global_state: &GlobalState,
+    time_finality_proof: Option<&TimeFinalityProofReader>,
+) -> Result<(), Error> {
+    // blocks are refused while the rollup is halting or paused
+    check_status(prev_global_state, Status::Running)?;
+    // the proven block is finalized after the last block of the batch
+    let time_finalized_number =
+        check_time_finality_proof(config, time_finality_proof, post_global_state)?;
+
+    let last_block = blocks.last().ok_or_else(|| {
+        debug!("[verify blocks] no blocks");
+        Error::InvalidBlock
+    })?;
+    // blocks in the batch share one stake cell
+    let stake_cell_owner_lock_hash = last_block.raw().stake_cell_owner_lock_hash();
+    if blocks.iter().any(|block| {
+        block.raw().stake_cell_owner_lock_hash().as_slice() != stake_cell_owner_lock_hash.as_slice()
+    }) {
+        debug!("[verify blocks] blocks have different stake cell owners");
+        return Err(Error::InvalidStakeCell);
+    }
+
     // collect withdrawal cells
     let withdrawal_cells: Vec<_> =
-        collect_withdrawal_locks(&context.rollup_type_hash, config, Source::Output)?;
-    // collect deposit cells
-    let deposit_cells = collect_deposit_locks(&context.rollup_type_hash, config, Source::Input)?;
+        collect_withdrawal_locks(&rollup_type_hash, config, Source::Output)?;
+    // collect deposit cells, batch deposits are expanded into the deposits of recipients
+    let deposit_cells = collect_deposit_locks(&rollup_type_hash, config, Source::Input)?;
+    let batch_deposit_cells =
+        collect_batch_deposit_locks(&rollup_type_hash, config, Source::Input)?;
+    let mut mint_deposit_cells = deposit_cells.clone();
+    for cell in batch_deposit_cells.iter() {
+        mint_deposit_cells.extend(expand_batch_deposit(cell)?);
+    }
+    check_deposit_policy(config, &mint_deposit_cells)?;
+    // forced withdrawal requests must be included in the blocks or rejected
+    check_forced_withdrawal_cells(&rollup_type_hash, config, prev_global_state, blocks)?;
+    // each withdrawal cell must belong to a block of the batch
+    if withdrawal_cells.iter().any(|cell| {
+        !blocks
+            .iter()
+            .any(|block| block.raw().hash() == cell.args.withdrawal_block_hash().as_slice())
+    }) {
+        debug!("withdrawal cell mismatch block_hash");
+        return Err(Error::InvalidWithdrawalCell);
+    }
+
+    let version: u8 = post_global_state.version().into();
+    let mut block_prev_global_state = prev_global_state.clone();
+    let mut last_context = None;
+    let mut withdrawn_assets = Vec::with_capacity(blocks.len());
+    for (index, block) in blocks.iter().enumerate() {
+        // check checkpoints
+        check_state_checkpoints(block, version)?;
+
+        // Check withdrawals root
+        check_block_withdrawals(block)?;
+
+        // the post global state of the last block is the rollup cell output,
+        // others are calculated from the block
+        let block_post_global_state = if index + 1 == blocks.len() {
+            post_global_state.clone()
+        } else {
+            let block_merkle_state = calculate_post_block_merkle_state(block)?;
+            build_post_global_state(
+                config,
+                block,
+                &block_prev_global_state,
+                block_merkle_state,
+                post_global_state.version(),
+                None,
+            )
+        };
+
+        // the time finality proof is checked against the last block of the batch
+        let block_time_finalized_number = if index + 1 == blocks.len() {
+            time_finalized_number
+        } else {
+            None
+        };
+
+        let mut tree_buffer = [Pair::default(); GW_MAX_KV_PAIRS];
+        let kv_state_proof: Bytes = block.kv_state_proof().unpack();
+
+        let (context, mut kv_state) = load_block_context_and_state(
+            rollup_type_hash,
+            config,
+            &mut tree_buffer,
+            &kv_state_proof,
+            block,
+            &block_prev_global_state,
+            &block_post_global_state,
+            block_time_finalized_number,
+        )?;
+        // Check the block producer is scheduled
+        check_block_producer_schedule(config, block, &block_prev_global_state)?;
+        // Check withdrawal cells of the block
+        let withdrawal_requests_vec = block.withdrawals();
+        let withdrawal_requests = withdrawal_requests_vec.iter().collect();
+        let is_block_withdrawal_cell = |cell: &&WithdrawalCell| {
+            let withdrawal_block_hash: H256 = cell.args.withdrawal_block_hash().unpack();
+            withdrawal_block_hash == context.block_hash
+        };
+        check_withdrawal_cells(
+            &context,
+            withdrawal_requests,
+            withdrawal_cells.iter().filter(is_block_withdrawal_cell),
+        )?;
+        let block_withdrawn_assets = build_assets_map_from_cells(
+            withdrawal_cells
+                .iter()
+                .filter(is_block_withdrawal_cell)
+                .map(|c| &c.value),
+        )?;
+        withdrawn_assets.push((context.number, block_withdrawn_assets));
+
+        // Withdrawal token: Layer2 SUDT -> withdrawals
+        check_layer2_withdrawal(&rollup_type_hash, config, &mut kv_state, block)?;
+        // Mint token: deposit requests -> layer2 SUDT
+        let block_deposit_cells: &[DepositRequestCell] =
+            if index == 0 { &mint_deposit_cells } else { &[] };
+        let deposit_calls = check_layer2_deposit(
+            &rollup_type_hash,
+            config,
+            &mut kv_state,
+            block,
+            block_deposit_cells,
+        )?;
+        // Check transactions
+        check_block_transactions(block, &kv_state)?;
+        check_deposit_calls(block, &deposit_calls)?;
+
+        // Verify Post state
+        let actual_post_global_state = build_post_global_state(
+            config,
+            block,
+            &block_prev_global_state,
+            // we have verified the post block merkle state
+            block_post_global_state.block(),
+            block_post_global_state.version(),
+            block_time_finalized_number,
+        );
+        if actual_post_global_state != block_post_global_state {
+            return Err(Error::InvalidPostGlobalState);
+        }
+
+        block_prev_global_state = block_post_global_state;
+        last_context = Some(context);
+    }
+    let context = last_context.ok_or(Error::InvalidBlock)?;
+
+    // Check withdrawal rate limit
+    check_withdrawal_limit(&rollup_type_hash, config, &withdrawn_assets)?;
+    // Verify block producer
+    verify_block_producer(config, &context, last_block)?;
     // Check new cells and reverted cells: deposit / withdrawal / custodian
-    let withdrawal_requests_vec = block.withdrawals();
-    let withdrawal_requests = withdrawal_requests_vec.iter().collect();
-    check_withdrawal_cells(&context, withdrawal_requests, &withdrawal_cells)?;
     let input_finalized_assets = check_input_custodian_cells(config, &context, withdrawal_cells)?;
     check_output_custodian_cells(
         config,
         &context,
-        deposit_cells.clone(),
+        deposit_cells,
+        batch_deposit_cells,
         input_finalized_assets,
     )?;
     // Ensure no challenge cells in submitting block transaction
@@ -781,46 +1162,6 @@ pub fn verify(
         return Err(Error::InvalidChallengeCell);
     }
 
-    // Withdrawal token: Layer2 SUDT -> withdrawals
-    check_layer2_withdrawal(&rollup_type_hash, config, &mut kv_state, block)?;
-    // Mint token: deposit requests -> layer2 SUDT
-    check_layer2_deposit(&rollup_type_hash, config, &mut kv_state, &deposit_cells)?;
-    // Check transactions
-    check_block_transactions(block, &kv_state)?;
-
-    // Verify Post state
-    let actual_post_global_state = {
-        // because of the optimistic challenge mechanism,
-        // we just believe the post account in the block,
-        // if the post account state is invalid then someone will send a challenge
-        let account_merkle_state = block.raw().post_account();
-        // we have verified the post block merkle state
-        let block_merkle_state = post_global_state.block();
-        // last finalized block number
-        let last_finalized_block_number = context.finalized_number;
-        let version = post_global_state.version();
-        let tip_block_timestamp = if version == 0.into() {
-            0
-        } else {
-            context.timestamp
-        };
-
-        prev_global_state
-            .clone()
-            .as_builder()
-            .account(account_merkle_state.to_entity())
-            .block(block_merkle_state)
-            .tip_block_hash(context.block_hash.pack())
-            .tip_block_timestamp(tip_block_timestamp.pack())
-            .last_finalized_block_number(last_finalized_block_number.pack())
-            .version(version)
-            .build()
-    };
-
-    if &actual_post_global_state != post_global_state {
-        return Err(Error::InvalidPostGlobalState);
-    }
-
     Ok(())
 }
 
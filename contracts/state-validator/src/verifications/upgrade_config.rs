@@ -0,0 +1,155 @@
+//! Upgrade rollup config
+//!
+//! The governance proposes a new config by creating a proposal cell,
+//! which lock hash is `governance_lock_hash` and data is the new rollup config hash.
+//! After the proposal cell has been on-chain for `config_upgrade_delay`,
+//! the governance consumes it to move `rollup_config_hash` to the new config cell.
+//!
+//! A config cell deployed as `RollupConfigV0` has no governance, it is migrated once to
+//! a new config by the rollup cell lock alone, without the proposal cell.
+
+use gw_common::H256;
+use gw_types::{
+    core::Status,
+    packed::{GlobalState, RollupConfig},
+    prelude::*,
+};
+use gw_utils::gw_common;
+use gw_utils::gw_types;
+use gw_utils::{
+    cells::{
+        rollup::{is_rollup_config_v0, load_rollup_config},
+        utils::search_lock_hashes,
+    },
+    ckb_std::{
+        ckb_constants::Source,
+        debug,
+        high_level::{load_cell_data, load_input_since},
+        since::Since,
+    },
+    error::Error,
+};
+
+use super::{check_rollup_lock_cells, check_status};
+
+/// Find the input proposal cell of the new rollup config
+fn find_upgrade_proposal_cell(
+    config: &RollupConfig,
+    new_rollup_config_hash: &[u8; 32],
+) -> Result<usize, Error> {
+    let governance_lock_hash: [u8; 32] = config.governance_lock_hash().unpack();
+    if governance_lock_hash == [0u8; 32] {
+        debug!("[upgrade config] governance is disabled");
+        return Err(Error::InvalidRollupConfig);
+    }
+    for index in search_lock_hashes(&governance_lock_hash, Source::Input) {
+        let data = load_cell_data(index, Source::Input)?;
+        if data.as_slice() == new_rollup_config_hash {
+            return Ok(index);
+        }
+    }
+    debug!("[upgrade config] proposal cell not found");
+    Err(Error::OwnerCellNotFound)
+}
+
+/// Check the proposal cell has been on-chain for at least config_upgrade_delay
+fn check_upgrade_delay(config: &RollupConfig, proposal_index: usize) -> Result<(), Error> {
+    let delay = Since::new(config.config_upgrade_delay().unpack());
+    if !delay.is_relative() {
+        debug!("[upgrade config] config_upgrade_delay must be a relative since");
+        return Err(Error::InvalidRollupConfig);
+    }
+    let input_since = Since::new(load_input_since(proposal_index, Source::Input)?);
+    if input_since.flags() != delay.flags() || input_since.as_u64() < delay.as_u64() {
+        debug!("[upgrade config] proposal cell is immature");
+        return Err(Error::InvalidSince);
+    }
+    Ok(())
+}
+
+/// Existing rollup lock cells are bound to these fields,
+/// changing them would make these cells unlockable.
+///
+/// The type hashes introduced after `RollupConfigV0` are zero in a legacy config,
+/// no cells are bound to them before the migration.
+fn check_new_rollup_config(
+    prev_config: &RollupConfig,
+    new_config: &RollupConfig,
+    migrate_v0: bool,
+) -> Result<(), Error> {
+    let v0_immutable_fields = |config: &RollupConfig| {
+        [
+            config.l1_sudt_script_type_hash().as_bytes(),
+            config.custodian_script_type_hash().as_bytes(),
+            config.deposit_script_type_hash().as_bytes(),
+            config.withdrawal_script_type_hash().as_bytes(),
+            config.challenge_script_type_hash().as_bytes(),
+            config.stake_script_type_hash().as_bytes(),
+            config.l2_sudt_validator_script_type_hash().as_bytes(),
+            config.chain_id().as_bytes(),
+        ]
+    };
+    let immutable_fields = |config: &RollupConfig| {
+        [
+            config.rollup_bound_script_type_hash().as_bytes(),
+            config.withdrawal_limit().state_type_hash().as_bytes(),
+            config.challenge_queue_type_hash().as_bytes(),
+            config.forced_withdrawal_script_type_hash().as_bytes(),
+            config.batch_deposit_script_type_hash().as_bytes(),
+            config.dao_script_type_hash().as_bytes(),
+        ]
+    };
+    if v0_immutable_fields(prev_config) != v0_immutable_fields(new_config)
+        || (!migrate_v0 && immutable_fields(prev_config) != immutable_fields(new_config))
+    {
+        debug!("[upgrade config] new config changes immutable fields");
+        return Err(Error::InvalidRollupConfig);
+    }
+    Ok(())
+}
+
+/// Verify upgrade rollup config
+/// 1. check governance proposal cell and upgrade delay, unless migrating a legacy config
+/// 2. check the new rollup config
+/// 3. check post global state only changes the rollup_config_hash
+pub fn verify(
+    rollup_type_hash: H256,
+    config: &RollupConfig,
+    prev_global_state: &GlobalState,
+    post_global_state: &GlobalState,
+) -> Result<(), Error> {
+    check_status(prev_global_state, Status::Running)?;
+    // check rollup lock cells
+    check_rollup_lock_cells(&rollup_type_hash, config)?;
+
+    let new_rollup_config_hash: [u8; 32] = post_global_state.rollup_config_hash().unpack();
+    if prev_global_state.rollup_config_hash().as_slice() == new_rollup_config_hash {
+        debug!("[upgrade config] rollup config is unchanged");
+        return Err(Error::InvalidRollupConfig);
+    }
+    let migrate_v0 = is_rollup_config_v0(&prev_global_state.rollup_config_hash().unpack())?;
+    if migrate_v0 {
+        debug!("[upgrade config] migrate the legacy config");
+    } else {
+        let proposal_index = find_upgrade_proposal_cell(config, &new_rollup_config_hash)?;
+        check_upgrade_delay(config, proposal_index)?;
+    }
+    if is_rollup_config_v0(&new_rollup_config_hash)? {
+        debug!("[upgrade config] new config is encoded as the legacy config");
+        return Err(Error::InvalidRollupConfig);
+    }
+    let new_config = load_rollup_config(&new_rollup_config_hash)?;
+    check_new_rollup_config(config, &new_config, migrate_v0)?;
+
+    // check post global state
+    let actual_post_global_state = prev_global_state
+        .clone()
+        .as_builder()
+        .rollup_config_hash(new_rollup_config_hash.pack())
+        .build();
+    if post_global_state != &actual_post_global_state {
+        debug!("[upgrade config] mismatch post global state");
+        return Err(Error::InvalidPostGlobalState);
+    }
+    Ok(())
+}
@@ -0,0 +1,198 @@
+//! Withdrawal rate limiting
+//!
+//! The withdrawn amount of each limited asset is recorded in the withdrawal limit state cell,
+//! the sum of records in the sliding window of `window_blocks` layer2 blocks
+//! must not exceed the limit, otherwise the submitted block is rejected.
+
+use alloc::{collections::BTreeMap, vec::Vec};
+use gw_common::H256;
+use gw_types::{
+    bytes::Bytes,
+    packed::{
+        RollupConfig, WithdrawalLimitState, WithdrawalLimitStateReader, WithdrawalRecord,
+        WithdrawalRecordVec,
+    },
+    prelude::*,
+};
+use gw_utils::gw_common;
+use gw_utils::gw_types;
+use gw_utils::{
+    cells::lock_cells::is_rollup_bound_cell,
+    ckb_std::{
+        ckb_constants::Source,
+        debug,
+        high_level::{load_cell_capacity, load_cell_data, load_cell_type_hash, QueryIter},
+    },
+    error::Error,
+};
+
+use super::is_cell_unchanged;
+
+/// Find the withdrawal limit state cell, the state cell must be unique
+fn find_state_cell(state_type_hash: &[u8; 32], source: Source) -> Result<Option<usize>, Error> {
+    let mut cells = QueryIter::new(load_cell_type_hash, source)
+        .enumerate()
+        .filter(|(_index, type_hash)| type_hash.as_ref() == Some(state_type_hash))
+        .map(|(index, _type_hash)| index);
+    match (cells.next(), cells.next()) {
+        (index, None) => Ok(index),
+        _ => {
+            debug!("[withdrawal limit] state cell isn't unique");
+            Err(Error::InvalidWithdrawalLimitCell)
+        }
+    }
+}
+
+/// Find the input and output state cells, which must be locked by the rollup bound lock
+fn find_state_cells(
+    rollup_type_hash: &H256,
+    config: &RollupConfig,
+) -> Result<Option<(usize, usize)>, Error> {
+    let state_type_hash: [u8; 32] = config.withdrawal_limit().state_type_hash().unpack();
+    let input_index = match find_state_cell(&state_type_hash, Source::Input)? {
+        Some(index) => index,
+        None => return Ok(None),
+    };
+    let output_index = find_state_cell(&state_type_hash, Source::Output)?.ok_or_else(|| {
+        debug!("[withdrawal limit] output state cell not found");
+        Error::InvalidWithdrawalLimitCell
+    })?;
+    if !is_rollup_bound_cell(rollup_type_hash, config, input_index, Source::Input)?
+        || !is_rollup_bound_cell(rollup_type_hash, config, output_index, Source::Output)?
+    {
+        debug!("[withdrawal limit] state cell isn't locked by the rollup bound lock");
+        return Err(Error::InvalidWithdrawalLimitCell);
+    }
+    if load_cell_capacity(input_index, Source::Input)?
+        != load_cell_capacity(output_index, Source::Output)?
+    {
+        debug!("[withdrawal limit] state cell capacity is changed");
+        return Err(Error::InvalidWithdrawalLimitCell);
+    }
+    Ok(Some((input_index, output_index)))
+}
+
+fn load_state(index: usize, source: Source) -> Result<WithdrawalLimitState, Error> {
+    let data: Bytes = load_cell_data(index, source)?.into();
+    match WithdrawalLimitStateReader::verify(&data, false) {
+        Ok(_) => Ok(WithdrawalLimitState::new_unchecked(data)),
+        Err(_) => {
+            debug!("[withdrawal limit] invalid state cell data");
+            Err(Error::InvalidWithdrawalLimitCell)
+        }
+    }
+}
+
+/// Check the withdrawals of the submitted blocks
+///
+/// `withdrawn_assets` is the withdrawn assets of each block, sorted by block number
+pub fn check_withdrawal_limit(
+    rollup_type_hash: &H256,
+    config: &RollupConfig,
+    withdrawn_assets: &[(u64, BTreeMap<H256, u128>)],
+) -> Result<(), Error> {
+    let limit_config = config.withdrawal_limit();
+    let window_blocks: u64 = limit_config.window_blocks().unpack();
+    if window_blocks == 0 {
+        return Ok(());
+    }
+    let limits: Vec<(H256, u128)> = limit_config
+        .limits()
+        .into_iter()
+        .map(|limit| {
+            let sudt_script_hash: [u8; 32] = limit.sudt_script_hash().unpack();
+            (sudt_script_hash.into(), limit.amount().unpack())
+        })
+        .collect();
+
+    let (input_index, output_index) =
+        find_state_cells(rollup_type_hash, config)?.ok_or_else(|| {
+            debug!("[withdrawal limit] input state cell not found");
+            Error::InvalidWithdrawalLimitCell
+        })?;
+    let prev_state = load_state(input_index, Source::Input)?;
+
+    let mut records: Vec<(u64, H256, u128)> = prev_state
+        .records()
+        .into_iter()
+        .map(|record| {
+            let sudt_script_hash: [u8; 32] = record.sudt_script_hash().unpack();
+            (
+                record.block_number().unpack(),
+                sudt_script_hash.into(),
+                record.amount().unpack(),
+            )
+        })
+        .collect();
+    for (block_number, assets) in withdrawn_assets {
+        // remove records out of the window
+        records.retain(|(number, _, _)| number.saturating_add(window_blocks) > *block_number);
+        // record withdrawals of the limited assets
+        for (sudt_script_hash, _limit) in &limits {
+            match assets.get(sudt_script_hash) {
+                Some(amount) if *amount > 0 => {
+                    records.push((*block_number, *sudt_script_hash, *amount))
+                }
+                _ => {}
+            }
+        }
+        // check the withdrawn amount in the window
+        for (sudt_script_hash, limit) in &limits {
+            let mut withdrawn = 0u128;
+            for (_number, hash, amount) in &records {
+                if hash == sudt_script_hash {
+                    withdrawn = withdrawn
+                        .checked_add(*amount)
+                        .ok_or(Error::AmountOverflow)?;
+                }
+            }
+            if withdrawn > *limit {
+                debug!(
+                    "[withdrawal limit] block {} withdrawn {} exceeds limit {}",
+                    block_number, withdrawn, limit
+                );
+                return Err(Error::WithdrawalLimitExceeded);
+            }
+        }
+    }
+
+    // check post state
+    let post_state = {
+        let records = records
+            .into_iter()
+            .map(|(block_number, sudt_script_hash, amount)| {
+                WithdrawalRecord::new_builder()
+                    .block_number(block_number.pack())
+                    .sudt_script_hash(sudt_script_hash.pack())
+                    .amount(amount.pack())
+                    .build()
+            });
+        WithdrawalLimitState::new_builder()
+            .records(WithdrawalRecordVec::new_builder().extend(records).build())
+            .build()
+    };
+    if load_cell_data(output_index, Source::Output)?.as_slice() != post_state.as_slice() {
+        debug!("[withdrawal limit] mismatch post state");
+        return Err(Error::InvalidWithdrawalLimitCell);
+    }
+    Ok(())
+}
+
+/// The state cell is only updated by submitting blocks,
+/// other rollup actions must keep it unchanged if it's in the inputs
+pub fn check_withdrawal_limit_state_unchanged(
+    rollup_type_hash: &H256,
+    config: &RollupConfig,
+) -> Result<(), Error> {
+    let window_blocks: u64 = config.withdrawal_limit().window_blocks().unpack();
+    if window_blocks == 0 {
+        return Ok(());
+    }
+    if let Some((input_index, output_index)) = find_state_cells(rollup_type_hash, config)? {
+        if !is_cell_unchanged(input_index, output_index)? {
+            debug!("[withdrawal limit] state cell is changed");
+            return Err(Error::InvalidWithdrawalLimitCell);
+        }
+    }
+    Ok(())
+}
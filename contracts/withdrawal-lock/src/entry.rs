@@ -1,19 +1,25 @@
 // Import from `core` instead of from `std` since we are in no-std mode
 use core::result::Result;
 
+use alloc::{collections::BTreeMap, vec, vec::Vec};
+
 use gw_types::{
     packed::{UnlockWithdrawalWitness, UnlockWithdrawalWitnessReader},
     prelude::*,
 };
 use gw_utils::cells::rollup::{
-    load_rollup_config, parse_rollup_action, search_rollup_cell, search_rollup_state,
+    check_reverted_block, load_rollup_config, parse_rollup_action, search_rollup_cell,
+    search_rollup_state,
 };
 use gw_utils::ckb_std::{
     debug,
     high_level::{load_cell_lock_hash, QueryIter},
 };
+use gw_utils::escape::{load_abandoned_rollup, AbandonedRollup};
+use gw_utils::finality::is_finalized;
+use gw_utils::gw_common::{smt::Blake2bHasher, sparse_merkle_tree::CompiledMerkleProof};
 use gw_utils::gw_types::packed::{
-    CustodianLockArgs, CustodianLockArgsReader, RollupActionUnionReader,
+    CustodianLockArgs, CustodianLockArgsReader, GlobalState, RawL2Block,
     UnlockWithdrawalWitnessUnion, WithdrawalLockArgs,
 };
 use gw_utils::{
@@ -86,28 +92,17 @@ pub fn main() -> Result<(), Error> {
     // execute verification
     match unlock_args.to_enum() {
         UnlockWithdrawalWitnessUnion::UnlockWithdrawalViaRevert(unlock_args) => {
+            let global_state = search_rollup_state(&rollup_type_hash, Source::Input)?
+                .ok_or(Error::RollupCellNotFound)?;
             let mut rollup_action_witness = [0u8; MAX_ROLLUP_WITNESS_SIZE];
-            let withdrawal_block_hash = lock_args.withdrawal_block_hash();
             // prove the block is reverted
             let rollup_action = {
                 let index = search_rollup_cell(&rollup_type_hash, Source::Output)
                     .ok_or(Error::RollupCellNotFound)?;
                 parse_rollup_action(&mut rollup_action_witness, index, Source::Output)?
             };
-            match rollup_action.to_enum() {
-                RollupActionUnionReader::RollupSubmitBlock(args) => {
-                    if !args
-                        .reverted_block_hashes()
-                        .iter()
-                        .any(|hash| hash.as_slice() == withdrawal_block_hash.as_slice())
-                    {
-                        return Err(Error::InvalidRevertedBlocks);
-                    }
-                }
-                _ => {
-                    return Err(Error::InvalidRevertedBlocks);
-                }
-            }
+            let withdrawal_block_hash: [u8; 32] = lock_args.withdrawal_block_hash().unpack();
+            check_reverted_block(&global_state, &rollup_action, &withdrawal_block_hash)?;
             let custodian_lock_hash: [u8; 32] = unlock_args.custodian_lock_hash().unpack();
             // check there are a reverted custodian lock in the output
             let custodian_cell_index = match search_lock_hash(&custodian_lock_hash, Source::Output)
@@ -136,8 +131,6 @@ pub fn main() -> Result<(), Error> {
                 custodian_lock_args.deposit_block_hash().unpack();
             let custodian_deposit_block_number: u64 =
                 custodian_lock_args.deposit_block_number().unpack();
-            let global_state = search_rollup_state(&rollup_type_hash, Source::Input)?
-                .ok_or(Error::RollupCellNotFound)?;
             let config = load_rollup_config(&global_state.rollup_config_hash().unpack())?;
             if custodian_lock.code_hash().as_slice()
                 != config.custodian_script_type_hash().as_slice()
@@ -153,57 +146,150 @@ pub fn main() -> Result<(), Error> {
             Ok(())
         }
         UnlockWithdrawalWitnessUnion::UnlockWithdrawalViaFinalize(_unlock_args) => {
-            // try search rollup state from deps
-            let global_state = match search_rollup_state(&rollup_type_hash, Source::CellDep)? {
-                Some(state) => state,
-                None => {
-                    // then try search rollup state from inputs
-                    search_rollup_state(&rollup_type_hash, Source::Input)?
-                        .ok_or(Error::RollupCellNotFound)?
-                }
-            };
+            let global_state = load_rollup_state(&rollup_type_hash)?;
             // check finality
+            // NOTE: finalized withdrawals are unlockable regardless of the rollup status,
+            // users can still exit while the rollup is paused
             let withdrawal_block_number: u64 = lock_args.withdrawal_block_number().unpack();
-            let last_finalized_block_number: u64 =
-                global_state.last_finalized_block_number().unpack();
-
-            if withdrawal_block_number > last_finalized_block_number {
+            if !is_finalized(&global_state, withdrawal_block_number) {
                 // not yet finalized
                 return Err(Error::InvalidArgs);
             }
 
             // withdrawal lock is finalized, unlock for owner
-            {
-                // check whether output cell at same index only change lock script
-                let withdrawal_lock_hash = load_cell_lock_hash(0, Source::GroupInput)?;
+            unlock_for_owner(&owner_lock_hash)
+        }
+        UnlockWithdrawalWitnessUnion::UnlockWithdrawalViaTrade(unlock_args) => {
+            // a finalized withdrawal is unlocked by the owner, there is nothing to trade
+            let global_state = load_rollup_state(&rollup_type_hash)?;
+            let withdrawal_block_number: u64 = lock_args.withdrawal_block_number().unpack();
+            if is_finalized(&global_state, withdrawal_block_number) {
+                debug!("[via trade] withdrawal is finalized");
+                return Err(Error::InvalidArgs);
+            }
 
-                let mut invalid_output_found = false;
-                for (index, _) in QueryIter::new(load_cell_lock_hash, Source::Input)
-                    .enumerate()
-                    .filter(|(_idx, lock_hash)| lock_hash == &withdrawal_lock_hash)
+            // the owner authorizes the trade by an input cell
+            let prev_owner_lock_hash: [u8; 32] = lock_args.owner_lock_hash().unpack();
+            if search_lock_hash(&prev_owner_lock_hash, Source::Input).is_none() {
+                return Err(Error::OwnerCellNotFound);
+            }
+
+            // the withdrawal cells are re-created with the liquidity provider as the new owner,
+            // the block hash and number are kept, so the finality and revert rules still hold
+            let new_owner_lock = unlock_args.owner_lock();
+            let new_lock_args = lock_args
+                .as_builder()
+                .owner_lock_hash(new_owner_lock.hash().pack())
+                .build();
+            let mut new_args = Vec::new();
+            new_args.extend_from_slice(&rollup_type_hash);
+            new_args.extend_from_slice(new_lock_args.as_slice());
+            new_args.extend_from_slice(&(new_owner_lock.as_slice().len() as u32).to_be_bytes());
+            new_args.extend_from_slice(new_owner_lock.as_slice());
+
+            let withdrawal_lock_hash = load_cell_lock_hash(0, Source::GroupInput)?;
+            for (index, _) in QueryIter::new(load_cell_lock_hash, Source::Input)
+                .enumerate()
+                .filter(|(_idx, lock_hash)| lock_hash == &withdrawal_lock_hash)
+            {
+                check_output_cell_has_same_content(index, Source::Input, index)?;
+                let output_lock = load_cell_lock(index, Source::Output)?;
+                let output_args: Bytes = output_lock.args().unpack();
+                if output_lock.code_hash().as_slice() != script.code_hash().as_slice()
+                    || output_lock.hash_type() != script.hash_type()
+                    || output_args.as_ref() != new_args.as_slice()
                 {
-                    if check_output_cell_has_same_content(index, Source::Input, index).is_err() {
-                        debug!("[via finalize] output cell content not match, fallback to input owner cell");
-                        invalid_output_found = true;
-                        break;
-                    }
+                    debug!("[via trade] output withdrawal lock not match");
+                    return Err(Error::InvalidOutput);
+                }
+            }
 
-                    let maybe_output_lock_hash = load_cell_lock_hash(index, Source::Output);
-                    if maybe_output_lock_hash != Ok(owner_lock_hash) {
-                        debug!("[via finalize] output cell owner lock not match, fallback to input owner cell");
-                        invalid_output_found = true;
-                        break;
+            // the liquidity provider pays the owner in the same transaction,
+            // the price is CKB only, the sUDT of the withdrawal is priced in CKB as well
+            let sum_owner_capacity = |source: Source| -> Result<u128, Error> {
+                let mut capacity = 0u128;
+                for (index, lock_hash) in QueryIter::new(load_cell_lock_hash, source).enumerate() {
+                    if lock_hash == prev_owner_lock_hash {
+                        capacity += u128::from(load_cell_capacity(index, source)?);
                     }
                 }
+                Ok(capacity)
+            };
+            let paid_capacity = sum_owner_capacity(Source::Output)?
+                .saturating_sub(sum_owner_capacity(Source::Input)?);
+            // withdrawal groups of the owner share the payment, it must cover all their prices
+            let payment_capacity =
+                sum_trade_payments(&script, &rollup_type_hash, &prev_owner_lock_hash)?;
+            if paid_capacity < payment_capacity {
+                debug!(
+                    "[via trade] insufficient payment {} < {}",
+                    paid_capacity, payment_capacity
+                );
+                return Err(Error::InvalidOutput);
+            }
+
+            Ok(())
+        }
+        UnlockWithdrawalWitnessUnion::UnlockWithdrawalViaEscape(unlock_args) => {
+            let AbandonedRollup { global_state, .. } = load_abandoned_rollup(&rollup_type_hash)?;
+            let withdrawal_block_number: u64 = lock_args.withdrawal_block_number().unpack();
+            if !is_finalized(&global_state, withdrawal_block_number) {
+                debug!("[via escape] withdrawal block isn't finalized");
+                return Err(Error::InvalidArgs);
+            }
+
+            // check the withdrawal block isn't reverted
+            let valid = {
+                let merkle_proof = CompiledMerkleProof(unlock_args.block_proof().unpack());
+                let leaves = vec![(
+                    RawL2Block::compute_smt_key(withdrawal_block_number).into(),
+                    lock_args.withdrawal_block_hash().unpack(),
+                )];
+                merkle_proof
+                    .verify::<Blake2bHasher>(&global_state.block().merkle_root().unpack(), leaves)?
+            };
+            if !valid {
+                debug!("[via escape] withdrawal block merkle proof error");
+                return Err(Error::MerkleProof);
+            }
+
+            unlock_for_owner(&owner_lock_hash)
+        }
+        UnlockWithdrawalWitnessUnion::UnlockWithdrawalViaMerge(_unlock_args) => {
+            let global_state = load_rollup_state(&rollup_type_hash)?;
 
-                if !invalid_output_found {
-                    return Ok(());
+            // collect all withdrawal cells of the owner, they must be finalized
+            let mut withdrawal_assets = Assets::default();
+            for (index, lock) in QueryIter::new(load_cell_lock, Source::Input).enumerate() {
+                if !is_withdrawal_lock(&script, &rollup_type_hash, &lock) {
+                    continue;
                 }
+                let args: Bytes = lock.args().unpack();
+                let parsed = gw_utils::withdrawal::parse_lock_args(&args)?;
+                if parsed.owner_lock.hash() != owner_lock_hash {
+                    continue;
+                }
+                let withdrawal_block_number: u64 =
+                    parsed.lock_args.withdrawal_block_number().unpack();
+                if !is_finalized(&global_state, withdrawal_block_number) {
+                    debug!("[via merge] withdrawal {} isn't finalized", index);
+                    return Err(Error::InvalidArgs);
+                }
+                withdrawal_assets.add_cell(index, Source::Input)?;
             }
 
-            // fallback to input owner cell way
-            if search_lock_hash(&lock_args.owner_lock_hash().unpack(), Source::Input).is_none() {
-                return Err(Error::OwnerCellNotFound);
+            // the owner outputs must cover the total assets of the withdrawals
+            let mut owner_assets = Assets::default();
+            for (index, lock_hash) in
+                QueryIter::new(load_cell_lock_hash, Source::Output).enumerate()
+            {
+                if lock_hash == owner_lock_hash {
+                    owner_assets.add_output_cell(index, &withdrawal_assets)?;
+                }
+            }
+            if !owner_assets.covers(&withdrawal_assets) {
+                debug!("[via merge] owner outputs don't cover the withdrawals");
+                return Err(Error::InvalidOutput);
             }
 
             Ok(())
@@ -211,6 +297,158 @@ pub fn main() -> Result<(), Error> {
     }
 }
 
+/// Unlock the withdrawal cells for the owner, the output cells at the same indexes
+/// must only change the lock to the owner lock, otherwise an input owner cell is required
+fn unlock_for_owner(owner_lock_hash: &[u8; 32]) -> Result<(), Error> {
+    // check whether output cell at same index only change lock script
+    let withdrawal_lock_hash = load_cell_lock_hash(0, Source::GroupInput)?;
+
+    let mut invalid_output_found = false;
+    for (index, _) in QueryIter::new(load_cell_lock_hash, Source::Input)
+        .enumerate()
+        .filter(|(_idx, lock_hash)| lock_hash == &withdrawal_lock_hash)
+    {
+        if check_output_cell_has_same_content(index, Source::Input, index).is_err() {
+            debug!(
+                "[unlock for owner] output cell content not match, fallback to input owner cell"
+            );
+            invalid_output_found = true;
+            break;
+        }
+
+        let maybe_output_lock_hash = load_cell_lock_hash(index, Source::Output);
+        if maybe_output_lock_hash != Ok(*owner_lock_hash) {
+            debug!(
+                "[unlock for owner] output cell owner lock not match, fallback to input owner cell"
+            );
+            invalid_output_found = true;
+            break;
+        }
+    }
+
+    if !invalid_output_found {
+        return Ok(());
+    }
+
+    // fallback to input owner cell way
+    if search_lock_hash(owner_lock_hash, Source::Input).is_none() {
+        return Err(Error::OwnerCellNotFound);
+    }
+
+    Ok(())
+}
+
+/// Return true if the lock is a withdrawal lock of the rollup
+fn is_withdrawal_lock(
+    script: &ckb_types::packed::Script,
+    rollup_type_hash: &[u8; 32],
+    lock: &ckb_types::packed::Script,
+) -> bool {
+    let args: Bytes = lock.args().unpack();
+    lock.code_hash().as_slice() == script.code_hash().as_slice()
+        && lock.hash_type() == script.hash_type()
+        && args.len() >= rollup_type_hash.len()
+        && &args[..32] == rollup_type_hash
+}
+
+/// Sum the prices of the owner's withdrawal cells traded in the transaction,
+/// the price of a withdrawal group is in the trade witness of its first cell
+fn sum_trade_payments(
+    script: &ckb_types::packed::Script,
+    rollup_type_hash: &[u8; 32],
+    owner_lock_hash: &[u8; 32],
+) -> Result<u128, Error> {
+    let mut payment_capacity = 0u128;
+    for (index, lock) in QueryIter::new(load_cell_lock, Source::Input).enumerate() {
+        if !is_withdrawal_lock(script, rollup_type_hash, &lock) {
+            continue;
+        }
+        let args: Bytes = lock.args().unpack();
+        let parsed = gw_utils::withdrawal::parse_lock_args(&args)?;
+        if parsed.lock_args.owner_lock_hash().as_slice() != owner_lock_hash {
+            continue;
+        }
+        let witness_lock: Bytes = match load_witness_args(index, Source::Input)
+            .ok()
+            .and_then(|witness_args| witness_args.lock().to_opt())
+        {
+            Some(witness_lock) => witness_lock.unpack(),
+            None => continue,
+        };
+        if UnlockWithdrawalWitnessReader::verify(&witness_lock, false).is_err() {
+            continue;
+        }
+        if let UnlockWithdrawalWitnessUnion::UnlockWithdrawalViaTrade(unlock_args) =
+            UnlockWithdrawalWitness::new_unchecked(witness_lock).to_enum()
+        {
+            let price: u64 = unlock_args.payment_capacity().unpack();
+            payment_capacity = payment_capacity
+                .checked_add(price.into())
+                .ok_or(Error::AmountOverflow)?;
+        }
+    }
+    Ok(payment_capacity)
+}
+
+/// Search rollup state from deps, then from inputs
+fn load_rollup_state(rollup_type_hash: &[u8; 32]) -> Result<GlobalState, Error> {
+    match search_rollup_state(rollup_type_hash, Source::CellDep)? {
+        Some(state) => Ok(state),
+        None => {
+            search_rollup_state(rollup_type_hash, Source::Input)?.ok_or(Error::RollupCellNotFound)
+        }
+    }
+}
+
+/// Total capacity and sUDT amounts of cells
+#[derive(Default)]
+struct Assets {
+    capacity: u128,
+    sudt_amounts: BTreeMap<[u8; 32], u128>,
+}
+
+impl Assets {
+    fn add_cell(&mut self, index: usize, source: Source) -> Result<(), Error> {
+        let capacity = load_cell_capacity(index, source)?;
+        self.capacity = self
+            .capacity
+            .checked_add(capacity.into())
+            .ok_or(Error::AmountOverflow)?;
+        if let Some(type_hash) = load_cell_type_hash(index, source)? {
+            // sUDT amount is the first 16 bytes of data
+            let data = load_cell_data(index, source)?;
+            if data.len() >= 16 {
+                let mut buf = [0u8; 16];
+                buf.copy_from_slice(&data[..16]);
+                let amount = self.sudt_amounts.entry(type_hash).or_insert(0);
+                *amount = amount
+                    .checked_add(u128::from_le_bytes(buf))
+                    .ok_or(Error::AmountOverflow)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Add an output cell, a typed cell is counted only if it is an sUDT of the `withdrawals`,
+    /// the capacity of a cell under a foreign type script isn't freely spendable by the owner
+    fn add_output_cell(&mut self, index: usize, withdrawals: &Assets) -> Result<(), Error> {
+        if let Some(type_hash) = load_cell_type_hash(index, Source::Output)? {
+            if !withdrawals.sudt_amounts.contains_key(&type_hash) {
+                debug!("[via merge] skip output {} of a foreign type", index);
+                return Ok(());
+            }
+        }
+        self.add_cell(index, Source::Output)
+    }
+
+    fn covers(&self, other: &Assets) -> bool {
+        self.capacity >= other.capacity
+            && other.sudt_amounts.iter().all(|(type_hash, amount)| {
+                self.sudt_amounts.get(type_hash).copied().unwrap_or(0) >= *amount
+            })
+    }
+}
+
 fn check_output_cell_has_same_content(
     input_index: usize,
     input_source: Source,
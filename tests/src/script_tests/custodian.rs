@@ -0,0 +1,159 @@
+use super::utils::init_env_log;
+use super::utils::rollup::{build_rollup_locked_cell, CellContext, CellContextParam};
+use crate::testing_tool::programs::ALWAYS_SUCCESS_CODE_HASH;
+use ckb_error::assert_error_eq;
+use ckb_script::ScriptError;
+use ckb_types::{
+    packed::{CellDep, CellInput, CellOutput, Script},
+    prelude::{Pack as CKBPack, Unpack as CKBUnpack},
+};
+use gw_types::bytes::Bytes;
+use gw_types::core::Status;
+use gw_types::packed::{CustodianLockArgs, DepositLockArgs, GlobalState, RollupConfig};
+use gw_types::prelude::*;
+
+const INVALID_ARGS_ERROR: i8 = 5;
+const OWNER_CELL_NOT_FOUND_ERROR: i8 = 8;
+const INVALID_STATUS_ERROR: i8 = 21;
+
+const CUSTODIAN_CAPACITY: u64 = 1000 * 10u64.pow(8);
+const DEPOSIT_BLOCK_NUMBER: u64 = 100;
+
+fn random_always_success_script() -> Script {
+    let random_bytes: [u8; 32] = rand::random();
+    Script::new_builder()
+        .code_hash(CKBPack::pack(&*ALWAYS_SUCCESS_CODE_HASH))
+        .args(CKBPack::pack(&Bytes::from(random_bytes.to_vec())))
+        .build()
+}
+
+/// Reclaim the custodian of the deposit by the escape hatch
+fn verify_unlock_via_escape(
+    param: CellContextParam,
+    status: Status,
+    last_finalized_block_number: u64,
+    with_owner_cell: bool,
+) -> Result<ckb_types::core::Cycle, ckb_error::Error> {
+    init_env_log();
+
+    let custodian_script_type_hash: [u8; 32] =
+        param.custodian_lock_type.calc_script_hash().unpack();
+    let rollup_config = RollupConfig::new_builder()
+        .custodian_script_type_hash(Pack::pack(&custodian_script_type_hash))
+        .finality_blocks(Pack::pack(&10u64))
+        .escape_timeout_blocks(Pack::pack(&1000u64))
+        .build();
+    let rollup_config_hash: [u8; 32] =
+        CellOutput::calc_data_hash(&rollup_config.as_bytes()).unpack();
+    let mut ctx = CellContext::new(&rollup_config, param);
+
+    let rollup_type_script = random_always_success_script();
+    let rollup_type_hash: [u8; 32] = rollup_type_script.calc_script_hash().unpack();
+    let rollup_dep = {
+        let status: u8 = status.into();
+        let global_state = GlobalState::new_builder()
+            .rollup_config_hash(Pack::pack(&rollup_config_hash))
+            .last_finalized_block_number(Pack::pack(&last_finalized_block_number))
+            .status(status.into())
+            .build();
+        let output = CellOutput::new_builder()
+            .lock(random_always_success_script())
+            .type_(CKBPack::pack(&Some(rollup_type_script)))
+            .capacity(CKBPack::pack(&CUSTODIAN_CAPACITY))
+            .build();
+        let out_point = ctx.insert_cell(output, global_state.as_bytes());
+        CellDep::new_builder().out_point(out_point).build()
+    };
+
+    let owner_lock = random_always_success_script();
+    let input_custodian_cell = {
+        let owner_lock_hash: [u8; 32] = owner_lock.calc_script_hash().unpack();
+        let deposit_lock_args = DepositLockArgs::new_builder()
+            .owner_lock_hash(Pack::pack(&owner_lock_hash))
+            .build();
+        let custodian_args = CustodianLockArgs::new_builder()
+            .deposit_lock_args(deposit_lock_args)
+            .deposit_block_number(Pack::pack(&DEPOSIT_BLOCK_NUMBER))
+            .build();
+        let cell = build_rollup_locked_cell(
+            &rollup_type_hash,
+            &custodian_script_type_hash,
+            CUSTODIAN_CAPACITY,
+            custodian_args.as_bytes(),
+        );
+        let out_point = ctx.insert_cell(cell, Bytes::new());
+        CellInput::new_builder().previous_output(out_point).build()
+    };
+    let owner_output = CellOutput::new_builder()
+        .lock(owner_lock.clone())
+        .capacity(CKBPack::pack(&CUSTODIAN_CAPACITY))
+        .build();
+
+    let mut tx = ckb_types::core::TransactionBuilder::default()
+        .input(input_custodian_cell)
+        .output(owner_output)
+        .output_data(CKBPack::pack(&Bytes::new()))
+        .cell_dep(ctx.custodian_lock_dep.clone())
+        .cell_dep(ctx.always_success_dep.clone())
+        .cell_dep(ctx.rollup_config_dep.clone())
+        .cell_dep(rollup_dep);
+    if with_owner_cell {
+        let owner_cell = CellOutput::new_builder()
+            .lock(owner_lock)
+            .capacity(CKBPack::pack(&CUSTODIAN_CAPACITY))
+            .build();
+        let out_point = ctx.insert_cell(owner_cell, Bytes::new());
+        tx = tx.input(CellInput::new_builder().previous_output(out_point).build());
+    }
+    ctx.verify_tx(tx.build())
+}
+
+fn expected_custodian_lock_error(param: &CellContextParam, code: i8) -> ckb_error::Error {
+    let custodian_script_type_hash: [u8; 32] =
+        param.custodian_lock_type.calc_script_hash().unpack();
+    ScriptError::ValidationFailure(
+        format!(
+            "by-type-hash/{}",
+            ckb_types::H256(custodian_script_type_hash)
+        ),
+        code,
+    )
+    .input_lock_script(0)
+    .into()
+}
+
+#[test]
+fn test_unlock_custodian_via_escape() {
+    // the deposit block isn't finalized in the abandoned rollup
+    let param = CellContextParam::default();
+    verify_unlock_via_escape(param, Status::Abandoned, DEPOSIT_BLOCK_NUMBER - 1, true)
+        .expect("return success");
+}
+
+#[test]
+fn test_unlock_custodian_via_escape_without_owner_cell() {
+    let param = CellContextParam::default();
+    let expected_err = expected_custodian_lock_error(&param, OWNER_CELL_NOT_FOUND_ERROR);
+    let err = verify_unlock_via_escape(param, Status::Abandoned, DEPOSIT_BLOCK_NUMBER - 1, false)
+        .unwrap_err();
+    assert_error_eq!(err, expected_err);
+}
+
+#[test]
+fn test_unlock_finalized_custodian_via_escape() {
+    // the finalized custodian belongs to the rollup
+    let param = CellContextParam::default();
+    let expected_err = expected_custodian_lock_error(&param, INVALID_ARGS_ERROR);
+    let err =
+        verify_unlock_via_escape(param, Status::Abandoned, DEPOSIT_BLOCK_NUMBER, true).unwrap_err();
+    assert_error_eq!(err, expected_err);
+}
+
+#[test]
+fn test_unlock_custodian_via_escape_before_abandonment() {
+    let param = CellContextParam::default();
+    let expected_err = expected_custodian_lock_error(&param, INVALID_STATUS_ERROR);
+    let err = verify_unlock_via_escape(param, Status::Running, DEPOSIT_BLOCK_NUMBER - 1, true)
+        .unwrap_err();
+    assert_error_eq!(err, expected_err);
+}
@@ -1,6 +1,8 @@
 mod account_lock_scripts;
+mod custodian;
 mod l2_scripts;
 mod l2_scripts_validator;
+mod stake;
 mod state_validator;
 pub mod utils;
 mod withdrawal;
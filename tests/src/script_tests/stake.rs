@@ -0,0 +1,203 @@
+use super::utils::init_env_log;
+use super::utils::layer1::always_success_script;
+use super::utils::rollup::{build_rollup_locked_cell, CellContext, CellContextParam};
+use ckb_error::assert_error_eq;
+use ckb_script::ScriptError;
+use ckb_types::{
+    packed::{CellDep, CellInput, CellOutput, Script},
+    prelude::{Pack as CKBPack, Unpack as CKBUnpack},
+};
+use gw_types::bytes::Bytes;
+use gw_types::packed::{DelegatorShare, GlobalState, RollupConfig, StakeLockArgs, StakePool};
+use gw_types::prelude::*;
+
+const INVALID_OUTPUT_ERROR: i8 = 7;
+const INVALID_STAKE_CELL_UNLOCK_ERROR: i8 = 22;
+
+const STAKE_CAPACITY: u64 = 4000 * 10u64.pow(8);
+const AUTHORIZER_CAPACITY: u64 = 1000 * 10u64.pow(8);
+const LAST_FINALIZED_BLOCK_NUMBER: u64 = 100;
+
+fn build_lock(name: &[u8]) -> Script {
+    always_success_script()
+        .as_builder()
+        .args(CKBPack::pack(&Bytes::from(name.to_vec())))
+        .build()
+}
+
+/// Unlock a stake pool cell of the delegators, the shares are 1:3
+fn verify_unlock_stake_pool(
+    param: CellContextParam,
+    stake_block_number: u64,
+    authorizer_lock: Script,
+    underpaid_capacity: u64,
+) -> Result<ckb_types::core::Cycle, ckb_error::Error> {
+    init_env_log();
+
+    let stake_script_type_hash: [u8; 32] = param.stake_lock_type.calc_script_hash().unpack();
+    let rollup_config = RollupConfig::new_builder()
+        .stake_script_type_hash(Pack::pack(&stake_script_type_hash))
+        .finality_blocks(Pack::pack(&10u64))
+        .build();
+    let mut ctx = CellContext::new(&rollup_config, param);
+
+    let rollup_type_script = build_lock(b"rollup_type");
+    let rollup_type_hash: [u8; 32] = rollup_type_script.calc_script_hash().unpack();
+    let rollup_dep = {
+        let global_state = GlobalState::new_builder()
+            .last_finalized_block_number(Pack::pack(&LAST_FINALIZED_BLOCK_NUMBER))
+            .build();
+        let output = CellOutput::new_builder()
+            .lock(always_success_script())
+            .type_(CKBPack::pack(&Some(rollup_type_script)))
+            .capacity(CKBPack::pack(&STAKE_CAPACITY))
+            .build();
+        let out_point = ctx.insert_cell(output, global_state.as_bytes());
+        CellDep::new_builder().out_point(out_point).build()
+    };
+
+    let delegators = [
+        (build_lock(b"delegator_a"), 1u64),
+        (build_lock(b"delegator_b"), 3),
+    ];
+    let input_stake_cell = {
+        let owner_lock_hash: [u8; 32] = build_lock(b"owner").calc_script_hash().unpack();
+        let lock_args = StakeLockArgs::new_builder()
+            .owner_lock_hash(Pack::pack(&owner_lock_hash))
+            .stake_block_number(Pack::pack(&stake_block_number))
+            .build();
+        let pool = {
+            let delegators: Vec<_> = delegators
+                .iter()
+                .map(|(lock, share)| {
+                    let owner_lock_hash: [u8; 32] = lock.calc_script_hash().unpack();
+                    DelegatorShare::new_builder()
+                        .owner_lock_hash(Pack::pack(&owner_lock_hash))
+                        .share(Pack::pack(share))
+                        .build()
+                })
+                .collect();
+            StakePool::new_builder()
+                .delegators(delegators.pack())
+                .build()
+        };
+        let cell = build_rollup_locked_cell(
+            &rollup_type_hash,
+            &stake_script_type_hash,
+            STAKE_CAPACITY,
+            lock_args.as_bytes(),
+        );
+        let out_point = ctx.insert_cell(cell, pool.as_bytes());
+        CellInput::new_builder().previous_output(out_point).build()
+    };
+    let input_authorizer_cell = {
+        let cell = CellOutput::new_builder()
+            .lock(authorizer_lock.clone())
+            .capacity(CKBPack::pack(&AUTHORIZER_CAPACITY))
+            .build();
+        let out_point = ctx.insert_cell(cell, Bytes::new());
+        CellInput::new_builder().previous_output(out_point).build()
+    };
+
+    // the authorizer takes its cell back, and each delegator receives its share,
+    // the last delegator is underpaid
+    let mut outputs = vec![CellOutput::new_builder()
+        .lock(authorizer_lock)
+        .capacity(CKBPack::pack(&AUTHORIZER_CAPACITY))
+        .build()];
+    for (index, (lock, share)) in delegators.iter().enumerate() {
+        let mut capacity = STAKE_CAPACITY * share / 4;
+        if index == delegators.len() - 1 {
+            capacity -= underpaid_capacity;
+        }
+        outputs.push(
+            CellOutput::new_builder()
+                .lock(lock.clone())
+                .capacity(CKBPack::pack(&capacity))
+                .build(),
+        );
+    }
+
+    let tx = ckb_types::core::TransactionBuilder::default()
+        .input(input_stake_cell)
+        .input(input_authorizer_cell)
+        .outputs_data(outputs.iter().map(|_| CKBPack::pack(&Bytes::new())))
+        .outputs(outputs)
+        .cell_dep(ctx.stake_lock_dep.clone())
+        .cell_dep(ctx.always_success_dep.clone())
+        .cell_dep(rollup_dep)
+        .build();
+    ctx.verify_tx(tx)
+}
+
+fn expected_stake_lock_error(param: &CellContextParam, code: i8) -> ckb_error::Error {
+    let stake_script_type_hash: [u8; 32] = param.stake_lock_type.calc_script_hash().unpack();
+    ScriptError::ValidationFailure(
+        format!("by-type-hash/{}", ckb_types::H256(stake_script_type_hash)),
+        code,
+    )
+    .input_lock_script(0)
+    .into()
+}
+
+#[test]
+fn test_unlock_stake_pool_by_delegator() {
+    let param = CellContextParam::default();
+    verify_unlock_stake_pool(
+        param,
+        LAST_FINALIZED_BLOCK_NUMBER,
+        build_lock(b"delegator_a"),
+        0,
+    )
+    .expect("return success");
+}
+
+#[test]
+fn test_unlock_stake_pool_by_owner() {
+    // the owner unlocks the stake pool, the delegators still receive their shares
+    let param = CellContextParam::default();
+    verify_unlock_stake_pool(param, LAST_FINALIZED_BLOCK_NUMBER, build_lock(b"owner"), 0)
+        .expect("return success");
+}
+
+#[test]
+fn test_unlock_stake_pool_with_underpaid_delegator() {
+    let param = CellContextParam::default();
+    let expected_err = expected_stake_lock_error(&param, INVALID_OUTPUT_ERROR);
+    let err = verify_unlock_stake_pool(
+        param,
+        LAST_FINALIZED_BLOCK_NUMBER,
+        build_lock(b"delegator_a"),
+        1,
+    )
+    .unwrap_err();
+    assert_error_eq!(err, expected_err);
+}
+
+#[test]
+fn test_unlock_unfinalized_stake_pool() {
+    let param = CellContextParam::default();
+    let expected_err = expected_stake_lock_error(&param, INVALID_STAKE_CELL_UNLOCK_ERROR);
+    let err = verify_unlock_stake_pool(
+        param,
+        LAST_FINALIZED_BLOCK_NUMBER + 1,
+        build_lock(b"delegator_a"),
+        0,
+    )
+    .unwrap_err();
+    assert_error_eq!(err, expected_err);
+}
+
+#[test]
+fn test_unlock_stake_pool_without_delegator() {
+    let param = CellContextParam::default();
+    let expected_err = expected_stake_lock_error(&param, INVALID_STAKE_CELL_UNLOCK_ERROR);
+    let err = verify_unlock_stake_pool(
+        param,
+        LAST_FINALIZED_BLOCK_NUMBER,
+        build_lock(b"someone"),
+        0,
+    )
+    .unwrap_err();
+    assert_error_eq!(err, expected_err);
+}
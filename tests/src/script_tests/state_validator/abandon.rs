@@ -0,0 +1,148 @@
+use crate::script_tests::utils::init_env_log;
+use crate::script_tests::utils::layer1::{
+    build_simple_tx_with_out_point_and_since, random_out_point,
+};
+use crate::script_tests::utils::rollup::{
+    build_always_success_cell, calculate_state_validator_type_id, expected_state_validator_error,
+    CellContext, CellContextParam,
+};
+use crate::testing_tool::programs::STATE_VALIDATOR_CODE_HASH;
+use ckb_error::assert_error_eq;
+use ckb_types::{
+    packed::CellOutput,
+    prelude::{Pack as CKBPack, Unpack as CKBUnpack},
+};
+use gw_types::{
+    bytes::Bytes,
+    core::{ScriptHashType, Status},
+    packed::{GlobalState, RollupAbandon, RollupAction, RollupActionUnion, RollupConfig, Script},
+    prelude::*,
+};
+
+const INVALID_SINCE_ERROR: i8 = 6;
+const INVALID_STATUS_ERROR: i8 = 21;
+const INVALID_ROLLUP_CONFIG_ERROR: i8 = 45;
+
+const FINALITY_BLOCKS: u64 = 10;
+const ESCAPE_TIMEOUT_BLOCKS: u64 = 1000;
+
+fn verify_abandon(
+    escape_timeout_blocks: u64,
+    prev_status: Status,
+    alive_blocks: u64,
+) -> Result<ckb_types::core::Cycle, ckb_error::Error> {
+    init_env_log();
+    let input_out_point = random_out_point();
+    let type_id = calculate_state_validator_type_id(input_out_point.clone());
+    let rollup_type_script = {
+        Script::new_builder()
+            .code_hash(Pack::pack(&*STATE_VALIDATOR_CODE_HASH))
+            .hash_type(ScriptHashType::Data.into())
+            .args(Pack::pack(&Bytes::from(type_id.to_vec())))
+            .build()
+    };
+    let rollup_config = RollupConfig::new_builder()
+        .finality_blocks(Pack::pack(&FINALITY_BLOCKS))
+        .escape_timeout_blocks(Pack::pack(&escape_timeout_blocks))
+        .build();
+    let rollup_config_hash: [u8; 32] =
+        CellOutput::calc_data_hash(&rollup_config.as_bytes()).unpack();
+    // deploy scripts
+    let mut ctx = CellContext::new(&rollup_config, CellContextParam::default());
+    // create a rollup cell
+    let capacity = 1000_00000000u64;
+    let rollup_cell = build_always_success_cell(
+        capacity,
+        Some(ckb_types::packed::Script::new_unchecked(
+            rollup_type_script.as_bytes(),
+        )),
+    );
+    let global_state = {
+        let status: u8 = prev_status.into();
+        GlobalState::new_builder()
+            .rollup_config_hash(Pack::pack(&rollup_config_hash))
+            .status(status.into())
+            .version(1u8.into())
+            .build()
+    };
+    let post_global_state = {
+        let status: u8 = Status::Abandoned.into();
+        global_state
+            .clone()
+            .as_builder()
+            .status(status.into())
+            .build()
+    };
+    let witness = {
+        let rollup_action = RollupAction::new_builder()
+            .set(RollupActionUnion::RollupAbandon(
+                RollupAbandon::new_builder().build(),
+            ))
+            .build();
+        ckb_types::packed::WitnessArgs::new_builder()
+            .output_type(CKBPack::pack(&Some(rollup_action.as_bytes())))
+            .build()
+    };
+    // the rollup cell has been alive for the blocks
+    let since: u64 = (1 << 63) | alive_blocks;
+    let tx = build_simple_tx_with_out_point_and_since(
+        &mut ctx.inner,
+        (rollup_cell.clone(), global_state.as_bytes()),
+        (input_out_point, CKBPack::pack(&since)),
+        (rollup_cell, post_global_state.as_bytes()),
+    )
+    .as_advanced_builder()
+    .cell_dep(ctx.always_success_dep.clone())
+    .cell_dep(ctx.state_validator_dep.clone())
+    .cell_dep(ctx.rollup_config_dep.clone())
+    .witness(CKBPack::pack(&witness.as_bytes()))
+    .build();
+    ctx.verify_tx(tx)
+}
+
+#[test]
+fn test_abandon_rollup() {
+    verify_abandon(
+        ESCAPE_TIMEOUT_BLOCKS,
+        Status::Running,
+        ESCAPE_TIMEOUT_BLOCKS,
+    )
+    .expect("return success");
+    verify_abandon(
+        ESCAPE_TIMEOUT_BLOCKS,
+        Status::Halting,
+        ESCAPE_TIMEOUT_BLOCKS,
+    )
+    .expect("return success");
+}
+
+#[test]
+fn test_abandon_rollup_before_timeout() {
+    let err = verify_abandon(
+        ESCAPE_TIMEOUT_BLOCKS,
+        Status::Running,
+        ESCAPE_TIMEOUT_BLOCKS - 1,
+    )
+    .unwrap_err();
+    assert_error_eq!(err, expected_state_validator_error(INVALID_SINCE_ERROR));
+}
+
+#[test]
+fn test_abandon_rollup_with_escape_timeout_within_finality() {
+    let err = verify_abandon(FINALITY_BLOCKS, Status::Running, FINALITY_BLOCKS).unwrap_err();
+    assert_error_eq!(
+        err,
+        expected_state_validator_error(INVALID_ROLLUP_CONFIG_ERROR)
+    );
+}
+
+#[test]
+fn test_abandoned_rollup_is_terminal() {
+    let err = verify_abandon(
+        ESCAPE_TIMEOUT_BLOCKS,
+        Status::Abandoned,
+        ESCAPE_TIMEOUT_BLOCKS,
+    )
+    .unwrap_err();
+    assert_error_eq!(err, expected_state_validator_error(INVALID_STATUS_ERROR));
+}
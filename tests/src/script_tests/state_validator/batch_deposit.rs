@@ -0,0 +1,242 @@
+use crate::script_tests::utils::layer1::{
+    build_simple_tx_with_out_point_and_since, random_out_point, since_timestamp,
+};
+use crate::script_tests::utils::rollup::{
+    build_always_success_cell, build_rollup_locked_cell, build_type_id_script,
+    calculate_state_validator_type_id, expected_state_validator_error, CellContext,
+    CellContextParam,
+};
+use crate::testing_tool::chain::{construct_block, setup_chain};
+use crate::testing_tool::programs::{ALWAYS_SUCCESS_CODE_HASH, STATE_VALIDATOR_CODE_HASH};
+use ckb_error::assert_error_eq;
+use ckb_types::{
+    packed::CellInput,
+    prelude::{Pack as CKBPack, Unpack},
+};
+use gw_types::core::AllowedEoaType;
+use gw_types::prelude::{Pack as GWPack, Unpack as GWUnpack, *};
+use gw_types::{
+    bytes::Bytes,
+    core::ScriptHashType,
+    packed::{
+        AllowedTypeHash, BatchDepositEntry, BatchDepositLockArgs, CustodianLockArgs,
+        DepositRequest, RollupAction, RollupActionUnion, RollupConfig, RollupSubmitBlock, Script,
+        StakeLockArgs,
+    },
+};
+
+const INVALID_DEPOSIT_CELL_ERROR: i8 = 26;
+
+const DEPOSIT_CAPACITY: u64 = 1000_00000000u64;
+
+/// Submit a block which contains a batch deposit cell of `batch_capacity`,
+/// the batch deposit funds two recipients of `DEPOSIT_CAPACITY`
+async fn verify_batch_deposit(
+    batch_capacity: u64,
+) -> Result<ckb_types::core::Cycle, ckb_error::Error> {
+    let capacity = 1000_00000000u64;
+    let input_out_point = random_out_point();
+    let type_id = calculate_state_validator_type_id(input_out_point.clone());
+    let rollup_type_script = {
+        Script::new_builder()
+            .code_hash(Pack::pack(&*STATE_VALIDATOR_CODE_HASH))
+            .hash_type(ScriptHashType::Data.into())
+            .args(Pack::pack(&Bytes::from(type_id.to_vec())))
+            .build()
+    };
+    // rollup lock & config
+    let stake_lock_type = build_type_id_script(b"stake_lock_type_id");
+    let stake_script_type_hash: [u8; 32] = stake_lock_type.calc_script_hash().unpack();
+    let batch_deposit_lock_type = build_type_id_script(b"batch_deposit_lock_type_id");
+    let batch_deposit_script_type_hash: [u8; 32] =
+        batch_deposit_lock_type.calc_script_hash().unpack();
+    let custodian_lock_type = build_type_id_script(b"custodian_lock_type_id");
+    let custodian_script_type_hash: [u8; 32] = custodian_lock_type.calc_script_hash().unpack();
+    let rollup_config = RollupConfig::new_builder()
+        .stake_script_type_hash(Pack::pack(&stake_script_type_hash))
+        .batch_deposit_script_type_hash(Pack::pack(&batch_deposit_script_type_hash))
+        .custodian_script_type_hash(Pack::pack(&custodian_script_type_hash))
+        .finality_blocks(Pack::pack(&10u64))
+        .allowed_eoa_type_hashes(PackVec::pack(vec![AllowedTypeHash::new(
+            AllowedEoaType::Eth,
+            *ALWAYS_SUCCESS_CODE_HASH,
+        )]))
+        .build();
+    // setup chain
+    let chain = setup_chain(rollup_type_script.clone(), rollup_config.clone()).await;
+    // deploy scripts
+    let param = CellContextParam {
+        stake_lock_type,
+        batch_deposit_lock_type,
+        custodian_lock_type,
+        ..Default::default()
+    };
+    let mut ctx = CellContext::new(&rollup_config, param);
+    let stake_capacity = 10000_00000000u64;
+    let input_stake_cell = {
+        let cell = build_rollup_locked_cell(
+            &rollup_type_script.hash(),
+            &stake_script_type_hash,
+            stake_capacity,
+            StakeLockArgs::default().as_bytes(),
+        );
+        let out_point = ctx.insert_cell(cell, Bytes::default());
+        CellInput::new_builder().previous_output(out_point).build()
+    };
+    let output_stake_cell = {
+        let lock_args = StakeLockArgs::new_builder()
+            .stake_block_number(Pack::pack(&1))
+            .build();
+        build_rollup_locked_cell(
+            &rollup_type_script.hash(),
+            &stake_script_type_hash,
+            stake_capacity,
+            lock_args.as_bytes(),
+        )
+    };
+    // create a rollup cell
+    let rollup_cell = build_always_success_cell(
+        capacity,
+        Some(ckb_types::packed::Script::new_unchecked(
+            rollup_type_script.as_bytes(),
+        )),
+    );
+    let global_state = chain.local_state().last_global_state();
+    let initial_rollup_cell_data = global_state
+        .as_builder()
+        .version(1u8.into())
+        .build()
+        .as_bytes();
+    // deposits
+    let eth_registry_id = gw_common::builtins::ETH_REGISTRY_ACCOUNT_ID;
+    let entries: Vec<_> = (0..2u8)
+        .map(|i| {
+            let mut args = rollup_type_script.hash().to_vec();
+            args.extend_from_slice(&[i; 20]);
+            let account_script = Script::new_builder()
+                .code_hash(Pack::pack(&ALWAYS_SUCCESS_CODE_HASH.clone()))
+                .hash_type(ScriptHashType::Type.into())
+                .args(Pack::pack(&Bytes::from(args)))
+                .build();
+            BatchDepositEntry::new_builder()
+                .layer2_lock(account_script)
+                .registry_id(Pack::pack(&eth_registry_id))
+                .capacity(Pack::pack(&DEPOSIT_CAPACITY))
+                .build()
+        })
+        .collect();
+    let deposit_requests: Vec<_> = entries
+        .iter()
+        .map(|entry| {
+            DepositRequest::new_builder()
+                .capacity(Pack::pack(&DEPOSIT_CAPACITY))
+                .script(entry.layer2_lock())
+                .registry_id(Pack::pack(&eth_registry_id))
+                .build()
+        })
+        .collect();
+    let batch_deposit_lock_args = BatchDepositLockArgs::new_builder()
+        .owner_lock_hash(Pack::pack(&[0u8; 32]))
+        .cancel_timeout(Pack::pack(&0))
+        .entries(PackVec::pack(entries))
+        .build();
+    // submit a new block
+    let block_result = {
+        let mem_pool = chain.mem_pool().as_ref().unwrap();
+        let mut mem_pool = mem_pool.lock().await;
+        construct_block(&chain, &mut mem_pool, deposit_requests)
+            .await
+            .unwrap()
+    };
+    let block_number = block_result.block.raw().number();
+    let block_hash = block_result.block.raw().hash();
+    let input_batch_deposit_cell = {
+        let cell = build_rollup_locked_cell(
+            &rollup_type_script.hash(),
+            &batch_deposit_script_type_hash,
+            batch_capacity,
+            batch_deposit_lock_args.as_bytes(),
+        );
+        let out_point = ctx.insert_cell(cell, Bytes::default());
+        CellInput::new_builder().previous_output(out_point).build()
+    };
+    let output_custodian_cell = {
+        // args: custodian lock args | batch deposit lock args
+        let custodian_args = CustodianLockArgs::new_builder()
+            .deposit_block_hash(Pack::pack(&block_hash))
+            .deposit_block_number(block_number)
+            .build();
+        let mut args = custodian_args.as_slice().to_vec();
+        args.extend_from_slice(batch_deposit_lock_args.as_slice());
+        build_rollup_locked_cell(
+            &rollup_type_script.hash(),
+            &custodian_script_type_hash,
+            batch_capacity,
+            Bytes::from(args),
+        )
+    };
+    // verify submit block
+    let tip_block_timestamp = block_result.block.raw().timestamp();
+    let rollup_cell_data = block_result
+        .global_state
+        .as_builder()
+        .tip_block_timestamp(tip_block_timestamp.clone())
+        .version(1u8.into())
+        .build()
+        .as_bytes();
+    let witness = {
+        let rollup_action = RollupAction::new_builder()
+            .set(RollupActionUnion::RollupSubmitBlock(
+                RollupSubmitBlock::new_builder()
+                    .block(block_result.block)
+                    .build(),
+            ))
+            .build();
+        ckb_types::packed::WitnessArgs::new_builder()
+            .output_type(CKBPack::pack(&Some(rollup_action.as_bytes())))
+            .build()
+    };
+    let tx = build_simple_tx_with_out_point_and_since(
+        &mut ctx.inner,
+        (rollup_cell.clone(), initial_rollup_cell_data),
+        (
+            input_out_point,
+            since_timestamp(GWUnpack::unpack(&tip_block_timestamp)),
+        ),
+        (rollup_cell, rollup_cell_data),
+    )
+    .as_advanced_builder()
+    .input(input_stake_cell)
+    .output(output_stake_cell)
+    .output_data(CKBPack::pack(&Bytes::default()))
+    .input(input_batch_deposit_cell)
+    .output(output_custodian_cell)
+    .output_data(CKBPack::pack(&Bytes::default()))
+    .cell_dep(ctx.stake_lock_dep.clone())
+    .cell_dep(ctx.batch_deposit_lock_dep.clone())
+    .cell_dep(ctx.custodian_lock_dep.clone())
+    .cell_dep(ctx.always_success_dep.clone())
+    .cell_dep(ctx.state_validator_dep.clone())
+    .cell_dep(ctx.rollup_config_dep.clone())
+    .witness(CKBPack::pack(&witness.as_bytes()))
+    .build();
+    ctx.verify_tx(tx)
+}
+
+#[tokio::test]
+async fn test_batch_deposit() {
+    verify_batch_deposit(2 * DEPOSIT_CAPACITY)
+        .await
+        .expect("return success");
+}
+
+#[tokio::test]
+async fn test_batch_deposit_mismatch_entries() {
+    let err = verify_batch_deposit(2 * DEPOSIT_CAPACITY + 1)
+        .await
+        .unwrap_err();
+    assert_error_eq!(
+        err,
+        expected_state_validator_error(INVALID_DEPOSIT_CELL_ERROR)
+    );
+}
@@ -0,0 +1,424 @@
+use crate::script_tests::utils::init_env_log;
+use crate::script_tests::utils::layer1::always_success_script;
+use crate::script_tests::utils::rollup::{
+    build_always_success_cell, build_rollup_locked_cell, build_type_id_script, CellContext,
+    CellContextParam,
+};
+use ckb_error::assert_error_eq;
+use ckb_script::ScriptError;
+use ckb_types::{
+    core::TransactionBuilder,
+    packed::{CellDep, CellInput, CellOutput},
+    prelude::{Pack as CKBPack, Unpack as CKBUnpack},
+};
+use gw_types::{
+    bytes::Bytes,
+    core::{ChallengeTargetType, Status},
+    packed::{
+        BisectionState, BisectionStepWitness, Byte32Vec, ChallengeLockArgs, ChallengeTarget,
+        GlobalState, OutPoint, RawL2Block, RollupAction, RollupActionUnion, RollupCancelChallenge,
+        RollupConfig, SubmitTransactions, SubmitWithdrawals,
+    },
+    prelude::*,
+};
+
+const INVALID_OUTPUT_ERROR: i8 = 7;
+const ACCOUNT_SCRIPT_CELL_NOT_FOUND_ERROR: i8 = 38;
+
+const BISECTION_TIMEOUT_BLOCKS: u64 = 100;
+
+const PRODUCER_TURN: u8 = 0;
+const CHALLENGER_TURN: u8 = 1;
+
+#[test]
+fn test_bisection_moves() {
+    init_env_log();
+    let rollup_type_script = build_type_id_script(b"rollup_type_id");
+    let rollup_type_hash: [u8; 32] = rollup_type_script.calc_script_hash().unpack();
+    let challenge_lock_type = build_type_id_script(b"challenge_lock_type_id");
+    let challenge_script_type_hash: [u8; 32] = challenge_lock_type.calc_script_hash().unpack();
+    let rollup_config = RollupConfig::new_builder()
+        .challenge_script_type_hash(challenge_script_type_hash.pack())
+        .bisection_timeout_blocks(BISECTION_TIMEOUT_BLOCKS.pack())
+        .build();
+    let param = CellContextParam {
+        challenge_lock_type,
+        ..Default::default()
+    };
+    let mut ctx = CellContext::new(&rollup_config, param);
+
+    // the rollup cell is referenced by the bisection moves
+    let rollup_config_hash: [u8; 32] = {
+        let out_point = ctx.rollup_config_dep.out_point();
+        let (_, data) = ctx.inner.cells.get(&out_point).unwrap();
+        CellOutput::calc_data_hash(data).unpack()
+    };
+    let rollup_dep = {
+        let global_state = GlobalState::new_builder()
+            .rollup_config_hash(rollup_config_hash.pack())
+            .status(Status::Halting.into())
+            .build();
+        let cell = build_always_success_cell(1000_00000000u64, Some(rollup_type_script));
+        let out_point = ctx.insert_cell(cell, global_state.as_bytes());
+        CellDep::new_builder().out_point(out_point).build()
+    };
+
+    // the challenged tx is the second tx after a withdrawal
+    let producer_lock = always_success_script()
+        .as_builder()
+        .args(CKBPack::pack(&Bytes::from(b"producer_lock".to_vec())))
+        .build();
+    let challenger_lock = always_success_script()
+        .as_builder()
+        .args(CKBPack::pack(&Bytes::from(b"challenger_lock".to_vec())))
+        .build();
+    let producer_lock_hash: [u8; 32] = producer_lock.calc_script_hash().unpack();
+    let checkpoints: Vec<[u8; 32]> = vec![[1u8; 32], [2u8; 32], [3u8; 32]];
+    let raw_block = RawL2Block::new_builder()
+        .number(42u64.pack())
+        .stake_cell_owner_lock_hash(producer_lock_hash.pack())
+        .state_checkpoint_list(
+            Byte32Vec::new_builder()
+                .set(checkpoints.iter().map(|c| c.pack()).collect())
+                .build(),
+        )
+        .submit_withdrawals(
+            SubmitWithdrawals::new_builder()
+                .withdrawal_count(1u32.pack())
+                .build(),
+        )
+        .submit_transactions(
+            SubmitTransactions::new_builder()
+                .tx_count(2u32.pack())
+                .build(),
+        )
+        .build();
+    let challenge_cell = {
+        let lock_args = ChallengeLockArgs::new_builder()
+            .target(
+                ChallengeTarget::new_builder()
+                    .target_index(1u32.pack())
+                    .target_type(ChallengeTargetType::TxExecution.into())
+                    .block_hash(raw_block.hash().pack())
+                    .build(),
+            )
+            .rewards_receiver_lock(gw_types::packed::Script::new_unchecked(
+                challenger_lock.as_bytes(),
+            ))
+            .build();
+        build_rollup_locked_cell(
+            &rollup_type_hash,
+            &challenge_script_type_hash,
+            10000_00000000u64,
+            lock_args.as_bytes(),
+        )
+    };
+
+    let build_move_tx = |ctx: &mut CellContext,
+                         mover_lock: &ckb_types::packed::Script,
+                         state: Option<&BisectionState>,
+                         next_state: &BisectionState,
+                         witness: Bytes| {
+        let input_data = state.map(|s| s.as_bytes()).unwrap_or_default();
+        let challenge_out_point = ctx.insert_cell(challenge_cell.clone(), input_data);
+        let mover_cell = CellOutput::new_builder()
+            .lock(mover_lock.clone())
+            .capacity(CKBPack::pack(&1000_00000000u64))
+            .build();
+        let mover_out_point = ctx.insert_cell(mover_cell.clone(), Bytes::new());
+        let witness_args = ckb_types::packed::WitnessArgs::new_builder()
+            .lock(CKBPack::pack(&Some(witness)))
+            .build();
+        TransactionBuilder::default()
+            .input(
+                CellInput::new_builder()
+                    .previous_output(challenge_out_point)
+                    .build(),
+            )
+            .input(
+                CellInput::new_builder()
+                    .previous_output(mover_out_point)
+                    .build(),
+            )
+            .output(challenge_cell.clone())
+            .output_data(CKBPack::pack(&next_state.as_bytes()))
+            .output(mover_cell)
+            .output_data(CKBPack::pack(&Bytes::new()))
+            .witness(CKBPack::pack(&witness_args.as_bytes()))
+            .witness(Default::default())
+            .cell_dep(ctx.challenge_lock_dep.clone())
+            .cell_dep(ctx.always_success_dep.clone())
+            .cell_dep(ctx.rollup_config_dep.clone())
+            .cell_dep(rollup_dep.clone())
+            .build()
+    };
+
+    let expected_err = || {
+        ScriptError::ValidationFailure(
+            format!(
+                "by-type-hash/{}",
+                ckb_types::H256(challenge_script_type_hash)
+            ),
+            INVALID_OUTPUT_ERROR,
+        )
+        .input_lock_script(0)
+    };
+
+    // the challenger opens the game from the checkpoints around the tx
+    let opened_state = BisectionState::new_builder()
+        .producer_lock_hash(producer_lock_hash.pack())
+        .start_step(0u64.pack())
+        .start_commitment(checkpoints[1].pack())
+        .end_commitment(checkpoints[2].pack())
+        .turn(PRODUCER_TURN.into())
+        .build();
+    let tx = build_move_tx(
+        &mut ctx,
+        &challenger_lock,
+        None,
+        &opened_state,
+        raw_block.as_bytes(),
+    );
+    ctx.verify_tx(tx).expect("open bisection");
+
+    // the challenger can't choose the trace length
+    let invalid_state = opened_state
+        .clone()
+        .as_builder()
+        .end_step(1u64.pack())
+        .build();
+    let tx = build_move_tx(
+        &mut ctx,
+        &challenger_lock,
+        None,
+        &invalid_state,
+        raw_block.as_bytes(),
+    );
+    let err = ctx.verify_tx(tx).unwrap_err();
+    assert_error_eq!(err, expected_err());
+
+    // the producer commits a trace of 8 steps and posts the commitment at step 4
+    let mid_commitment = [4u8; 32];
+    let bisected_state = opened_state
+        .clone()
+        .as_builder()
+        .end_step(8u64.pack())
+        .mid_commitment(mid_commitment.pack())
+        .turn(CHALLENGER_TURN.into())
+        .build();
+    let tx = build_move_tx(
+        &mut ctx,
+        &producer_lock,
+        Some(&opened_state),
+        &bisected_state,
+        Bytes::new(),
+    );
+    ctx.verify_tx(tx).expect("producer move");
+
+    // a trace of one step has no middle step, the producer keeps the turn to prove it
+    let single_step_state = opened_state
+        .clone()
+        .as_builder()
+        .end_step(1u64.pack())
+        .build();
+    let tx = build_move_tx(
+        &mut ctx,
+        &producer_lock,
+        Some(&opened_state),
+        &single_step_state,
+        Bytes::new(),
+    );
+    ctx.verify_tx(tx).expect("producer commits a single step");
+
+    // the challenger agrees with step 4 and disputes the second half
+    let agreed_state = bisected_state
+        .clone()
+        .as_builder()
+        .start_step(4u64.pack())
+        .start_commitment(mid_commitment.pack())
+        .mid_commitment(Default::default())
+        .turn(PRODUCER_TURN.into())
+        .build();
+    let tx = build_move_tx(
+        &mut ctx,
+        &challenger_lock,
+        Some(&bisected_state),
+        &agreed_state,
+        Bytes::new(),
+    );
+    ctx.verify_tx(tx).expect("challenger move");
+
+    // the challenger can't move the start step without the agreed commitment
+    let invalid_state = agreed_state
+        .as_builder()
+        .start_commitment(checkpoints[1].pack())
+        .build();
+    let tx = build_move_tx(
+        &mut ctx,
+        &challenger_lock,
+        Some(&bisected_state),
+        &invalid_state,
+        Bytes::new(),
+    );
+    let err = ctx.verify_tx(tx).unwrap_err();
+    assert_error_eq!(err, expected_err());
+}
+
+#[test]
+fn test_bisection_cancel_by_step_verifier() {
+    init_env_log();
+    let rollup_type_script = build_type_id_script(b"rollup_type_id");
+    let rollup_type_hash: [u8; 32] = rollup_type_script.calc_script_hash().unpack();
+    let challenge_lock_type = build_type_id_script(b"challenge_lock_type_id");
+    let challenge_script_type_hash: [u8; 32] = challenge_lock_type.calc_script_hash().unpack();
+    let step_verifier_lock = always_success_script()
+        .as_builder()
+        .args(CKBPack::pack(&Bytes::from(b"step_verifier_lock".to_vec())))
+        .build();
+    let step_verifier_lock_hash: [u8; 32] = step_verifier_lock.calc_script_hash().unpack();
+    let rollup_config = RollupConfig::new_builder()
+        .challenge_script_type_hash(challenge_script_type_hash.pack())
+        .bisection_timeout_blocks(BISECTION_TIMEOUT_BLOCKS.pack())
+        .bisection_step_verifier_lock_hash(step_verifier_lock_hash.pack())
+        .build();
+    let param = CellContextParam {
+        challenge_lock_type,
+        ..Default::default()
+    };
+    let mut ctx = CellContext::new(&rollup_config, param);
+
+    let rollup_config_hash: [u8; 32] = {
+        let out_point = ctx.rollup_config_dep.out_point();
+        let (_, data) = ctx.inner.cells.get(&out_point).unwrap();
+        CellOutput::calc_data_hash(data).unpack()
+    };
+    let global_state = GlobalState::new_builder()
+        .rollup_config_hash(rollup_config_hash.pack())
+        .status(Status::Halting.into())
+        .build();
+    let rollup_cell = build_always_success_cell(1000_00000000u64, Some(rollup_type_script));
+
+    // the producer proves the single disputed step 4
+    let block_hash = [42u8; 32];
+    let target_index = 1u32;
+    let challenge_cell = {
+        let lock_args = ChallengeLockArgs::new_builder()
+            .target(
+                ChallengeTarget::new_builder()
+                    .target_index(target_index.pack())
+                    .target_type(ChallengeTargetType::TxExecution.into())
+                    .block_hash(block_hash.pack())
+                    .build(),
+            )
+            .build();
+        build_rollup_locked_cell(
+            &rollup_type_hash,
+            &challenge_script_type_hash,
+            10000_00000000u64,
+            lock_args.as_bytes(),
+        )
+    };
+    let state = BisectionState::new_builder()
+        .start_step(4u64.pack())
+        .end_step(5u64.pack())
+        .start_commitment([4u8; 32].pack())
+        .end_commitment([5u8; 32].pack())
+        .turn(PRODUCER_TURN.into())
+        .build();
+
+    let build_cancel_tx =
+        |ctx: &mut CellContext, step_witness: &dyn Fn(&ckb_types::packed::OutPoint) -> Bytes| {
+            let rollup_out_point = ctx.insert_cell(rollup_cell.clone(), global_state.as_bytes());
+            let challenge_out_point = ctx.insert_cell(challenge_cell.clone(), state.as_bytes());
+            let verifier_cell = CellOutput::new_builder()
+                .lock(step_verifier_lock.clone())
+                .capacity(CKBPack::pack(&1000_00000000u64))
+                .build();
+            let verifier_out_point = ctx.insert_cell(verifier_cell.clone(), Bytes::new());
+            let rollup_action = RollupAction::new_builder()
+                .set(RollupActionUnion::RollupCancelChallenge(
+                    RollupCancelChallenge::default(),
+                ))
+                .build();
+            let rollup_witness = ckb_types::packed::WitnessArgs::new_builder()
+                .output_type(CKBPack::pack(&Some(rollup_action.as_bytes())))
+                .build();
+            let verifier_witness = ckb_types::packed::WitnessArgs::new_builder()
+                .lock(CKBPack::pack(&Some(step_witness(&challenge_out_point))))
+                .build();
+            TransactionBuilder::default()
+                .input(
+                    CellInput::new_builder()
+                        .previous_output(rollup_out_point)
+                        .build(),
+                )
+                .input(
+                    CellInput::new_builder()
+                        .previous_output(challenge_out_point)
+                        .build(),
+                )
+                .input(
+                    CellInput::new_builder()
+                        .previous_output(verifier_out_point)
+                        .build(),
+                )
+                .output(rollup_cell.clone())
+                .output_data(CKBPack::pack(&global_state.as_bytes()))
+                .output(verifier_cell)
+                .output_data(CKBPack::pack(&Bytes::new()))
+                .witness(CKBPack::pack(&rollup_witness.as_bytes()))
+                .witness(Default::default())
+                .witness(CKBPack::pack(&verifier_witness.as_bytes()))
+                .cell_dep(ctx.challenge_lock_dep.clone())
+                .cell_dep(ctx.always_success_dep.clone())
+                .cell_dep(ctx.rollup_config_dep.clone())
+                .build()
+        };
+    let build_step_witness = |challenge_out_point: &ckb_types::packed::OutPoint| {
+        BisectionStepWitness::new_builder()
+            .challenge_out_point(OutPoint::new_unchecked(challenge_out_point.as_bytes()))
+            .block_hash(block_hash.pack())
+            .target_index(target_index.pack())
+            .step(state.start_step())
+            .start_commitment(state.start_commitment())
+            .end_commitment(state.end_commitment())
+            .build()
+    };
+
+    let tx = build_cancel_tx(&mut ctx, &|out_point| {
+        build_step_witness(out_point).as_bytes()
+    });
+    ctx.verify_tx(tx).expect("cancel by the step verifier");
+
+    let expected_err = || {
+        ScriptError::ValidationFailure(
+            format!(
+                "by-type-hash/{}",
+                ckb_types::H256(challenge_script_type_hash)
+            ),
+            ACCOUNT_SCRIPT_CELL_NOT_FOUND_ERROR,
+        )
+        .input_lock_script(1)
+    };
+
+    // a step verifier cell of another challenge can't cancel this one
+    let unrelated_out_point = ckb_types::packed::OutPoint::new_builder()
+        .tx_hash(CKBPack::pack(&[9u8; 32]))
+        .build();
+    let tx = build_cancel_tx(&mut ctx, &|_out_point| {
+        build_step_witness(&unrelated_out_point).as_bytes()
+    });
+    let err = ctx.verify_tx(tx).unwrap_err();
+    assert_error_eq!(err, expected_err());
+
+    // the step verifier must verify the disputed step
+    let tx = build_cancel_tx(&mut ctx, &|out_point| {
+        build_step_witness(out_point)
+            .as_builder()
+            .step(5u64.pack())
+            .build()
+            .as_bytes()
+    });
+    let err = ctx.verify_tx(tx).unwrap_err();
+    assert_error_eq!(err, expected_err());
+}
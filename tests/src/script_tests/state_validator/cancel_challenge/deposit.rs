@@ -0,0 +1,313 @@
+#![allow(clippy::mutable_key_type)]
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use crate::script_tests::utils::init_env_log;
+use crate::script_tests::utils::layer1::build_simple_tx_with_out_point;
+use crate::script_tests::utils::layer1::random_out_point;
+use crate::script_tests::utils::rollup::{
+    build_always_success_cell, build_rollup_locked_cell, build_type_id_script,
+    calculate_state_validator_type_id, CellContext, CellContextParam,
+};
+use crate::testing_tool::chain::setup_chain;
+use crate::testing_tool::chain::{apply_block_result, construct_block};
+use crate::testing_tool::programs::{ALWAYS_SUCCESS_CODE_HASH, STATE_VALIDATOR_CODE_HASH};
+use ckb_types::{
+    packed::CellInput,
+    prelude::{Pack as CKBPack, Unpack},
+};
+use gw_common::blake2b::new_blake2b;
+use gw_common::builtins::{CKB_SUDT_ACCOUNT_ID, ETH_REGISTRY_ACCOUNT_ID};
+use gw_common::ckb_decimal::CKBCapacity;
+use gw_common::merkle_utils::{
+    calculate_ckb_merkle_root, calculate_state_checkpoint, ckb_merkle_leaf_hash,
+};
+use gw_common::registry_address::RegistryAddress;
+use gw_common::{state::State, H256};
+use gw_store::mem_pool_state::MemPoolState;
+use gw_store::mem_pool_state::MemStore;
+use gw_store::state::state_db::StateContext;
+use gw_types::core::{AllowedContractType, AllowedEoaType};
+use gw_types::packed::{AllowedTypeHash, Byte32Vec, CCDepositWitness, DepositRequestVec};
+use gw_types::prelude::*;
+use gw_types::{
+    bytes::Bytes,
+    core::{ChallengeTargetType, ScriptHashType, Status},
+    packed::{
+        ChallengeLockArgs, ChallengeTarget, DepositRequest, RollupAction, RollupActionUnion,
+        RollupCancelChallenge, RollupConfig, Script,
+    },
+};
+
+/// hash(deposit_root | deposit_count) of the deposit requests in the order of minting
+fn calculate_deposit_checkpoint(requests: &[DepositRequest]) -> H256 {
+    let leaves = requests
+        .iter()
+        .enumerate()
+        .map(|(idx, request)| {
+            let mut hash = [0u8; 32];
+            let mut hasher = new_blake2b();
+            hasher.update(request.as_slice());
+            hasher.finalize(&mut hash);
+            ckb_merkle_leaf_hash(idx as u32, &hash.into())
+        })
+        .collect();
+    let deposit_root = calculate_ckb_merkle_root(leaves).unwrap();
+    calculate_state_checkpoint(&deposit_root, requests.len() as u32)
+}
+
+#[tokio::test]
+async fn test_cancel_deposit() {
+    init_env_log();
+    let input_out_point = random_out_point();
+    let type_id = calculate_state_validator_type_id(input_out_point.clone());
+    let rollup_type_script = {
+        Script::new_builder()
+            .code_hash(Pack::pack(&*STATE_VALIDATOR_CODE_HASH))
+            .hash_type(ScriptHashType::Data.into())
+            .args(Pack::pack(&Bytes::from(type_id.to_vec())))
+            .build()
+    };
+    // rollup lock & config
+    let stake_lock_type = build_type_id_script(b"stake_lock_type_id");
+    let challenge_lock_type = build_type_id_script(b"challenge_lock_type_id");
+    let eoa_lock_type = build_type_id_script(b"eoa_lock_type_id");
+    let l2_sudt_type = build_type_id_script(b"l2_sudt_type_id");
+    let challenge_script_type_hash: [u8; 32] = challenge_lock_type.calc_script_hash().unpack();
+    let l2_sudt_type_hash: [u8; 32] = l2_sudt_type.calc_script_hash().unpack();
+    let rollup_config = RollupConfig::new_builder()
+        .challenge_script_type_hash(Pack::pack(&challenge_script_type_hash))
+        .l2_sudt_validator_script_type_hash(Pack::pack(&l2_sudt_type_hash))
+        .allowed_contract_type_hashes(
+            vec![AllowedTypeHash::new(
+                AllowedContractType::Sudt,
+                l2_sudt_type_hash,
+            )]
+            .pack(),
+        )
+        .finality_blocks(Pack::pack(&10u64))
+        .allowed_eoa_type_hashes(
+            vec![AllowedTypeHash::new(
+                AllowedEoaType::Eth,
+                *ALWAYS_SUCCESS_CODE_HASH,
+            )]
+            .pack(),
+        )
+        .build();
+    // setup chain
+    let mut chain = setup_chain(rollup_type_script.clone(), rollup_config.clone()).await;
+    // create a rollup cell
+    let capacity = 1000_00000000u64;
+    let rollup_cell = build_always_success_cell(
+        capacity,
+        Some(ckb_types::packed::Script::new_unchecked(
+            rollup_type_script.as_bytes(),
+        )),
+    );
+    let rollup_script_hash = rollup_type_script.hash();
+    // produce a block with deposits so we can challenge it
+    let deposit_requests: Vec<DepositRequest> = [[1u8; 20], [2u8; 20]]
+        .iter()
+        .zip([300_00000000u64, 450_00000000u64].iter())
+        .map(|(address, capacity)| {
+            let mut args = rollup_script_hash.to_vec();
+            args.extend_from_slice(address);
+            let script = Script::new_builder()
+                .code_hash(Pack::pack(&ALWAYS_SUCCESS_CODE_HASH.clone()))
+                .hash_type(ScriptHashType::Type.into())
+                .args(Pack::pack(&Bytes::from(args)))
+                .build();
+            DepositRequest::new_builder()
+                .capacity(Pack::pack(capacity))
+                .script(script)
+                .registry_id(Pack::pack(&ETH_REGISTRY_ACCOUNT_ID))
+                .build()
+        })
+        .collect();
+    {
+        let produce_block_result = {
+            let mem_pool = chain.mem_pool().as_ref().unwrap();
+            let mut mem_pool = mem_pool.lock().await;
+            construct_block(&chain, &mut mem_pool, deposit_requests.clone())
+                .await
+                .unwrap()
+        };
+        let rollup_cell = gw_types::packed::CellOutput::new_unchecked(rollup_cell.as_bytes());
+        apply_block_result(
+            &mut chain,
+            rollup_cell,
+            produce_block_result,
+            deposit_requests.clone(),
+            HashSet::new(),
+        )
+        .await;
+    }
+    // deploy scripts
+    let param = CellContextParam {
+        stake_lock_type,
+        challenge_lock_type,
+        eoa_lock_type,
+        l2_sudt_type,
+        ..Default::default()
+    };
+    let mut ctx = CellContext::new(&rollup_config, param);
+    let challenged_block = chain.local_state().tip().clone();
+    // the block of version 2 commits the deposits at the end of the state checkpoints
+    let challenged_raw_block = {
+        let raw_block = challenged_block.raw();
+        let mut checkpoint_list: Vec<_> = raw_block.state_checkpoint_list().into_iter().collect();
+        let deposit_checkpoint: [u8; 32] = calculate_deposit_checkpoint(&deposit_requests).into();
+        checkpoint_list.push(Pack::pack(&deposit_checkpoint));
+        raw_block
+            .as_builder()
+            .state_checkpoint_list(Byte32Vec::new_builder().set(checkpoint_list).build())
+            .build()
+    };
+    let input_challenge_cell = {
+        let lock_args = ChallengeLockArgs::new_builder()
+            .target(
+                ChallengeTarget::new_builder()
+                    .target_index(Pack::pack(&0u32))
+                    .target_type(ChallengeTargetType::Deposit.into())
+                    .block_hash(Pack::pack(&challenged_raw_block.hash()))
+                    .build(),
+            )
+            .build();
+        let cell = build_rollup_locked_cell(
+            &rollup_type_script.hash(),
+            &challenge_script_type_hash,
+            10000_00000000u64,
+            lock_args.as_bytes(),
+        );
+        let out_point = ctx.insert_cell(cell, Bytes::new());
+        CellInput::new_builder().previous_output(out_point).build()
+    };
+    let global_state = chain
+        .local_state()
+        .last_global_state()
+        .clone()
+        .as_builder()
+        .status(Status::Halting.into())
+        .build();
+    let initial_rollup_cell_data = global_state.as_bytes();
+    let witness = {
+        let rollup_action = RollupAction::new_builder()
+            .set(RollupActionUnion::RollupCancelChallenge(
+                RollupCancelChallenge::default(),
+            ))
+            .build();
+        ckb_types::packed::WitnessArgs::new_builder()
+            .output_type(CKBPack::pack(&Some(rollup_action.as_bytes())))
+            .build()
+    };
+    let challenge_witness = {
+        let challenged_block_number =
+            gw_types::prelude::Unpack::unpack(&challenged_block.raw().number());
+
+        // Detach block to get the state before deposits
+        let db = chain.store().begin_transaction();
+        {
+            db.detach_block(&challenged_block).unwrap();
+            {
+                let mut tree = db
+                    .state_tree(StateContext::DetachBlock(challenged_block_number))
+                    .unwrap();
+                tree.detach_block_state().unwrap();
+            }
+        }
+        db.commit().unwrap();
+
+        let load_state = || {
+            let mem_store = MemStore::new(chain.store().get_snapshot());
+            MemPoolState::new(Arc::new(mem_store), true)
+        };
+        // mint the deposits to collect the touched keys
+        let touched_keys: Vec<H256> = {
+            let state = load_state();
+            let snap = state.load();
+            let mut tree = snap.state().unwrap();
+            tree.tracker_mut().enable();
+            for request in deposit_requests.iter() {
+                let script_hash: H256 = request.script().hash().into();
+                let address = RegistryAddress::new(
+                    ETH_REGISTRY_ACCOUNT_ID,
+                    request.script().args().raw_data()[32..].to_vec(),
+                );
+                tree.get_account_id_by_script_hash(&script_hash).unwrap();
+                tree.create_account(script_hash).unwrap();
+                tree.mapping_registry_address_to_script_hash(address.clone(), script_hash)
+                    .unwrap();
+                let capacity: u64 = gw_types::prelude::Unpack::unpack(&request.capacity());
+                tree.mint_sudt(
+                    CKB_SUDT_ACCOUNT_ID,
+                    &address,
+                    CKBCapacity::from_layer1(capacity).to_layer2(),
+                )
+                .unwrap();
+            }
+            let keys = tree.tracker_mut().touched_keys().unwrap();
+            let unlock = keys.lock().unwrap();
+            unlock.clone().into_iter().collect()
+        };
+        // the kv state before deposits
+        let state = load_state();
+        let snap = state.load();
+        let tree = snap.state().unwrap();
+        let account_count = tree.get_account_count().unwrap();
+        let kv_state = touched_keys
+            .iter()
+            .map(|k| {
+                let v = tree.get_raw(k).unwrap();
+                (*k, v)
+            })
+            .collect::<Vec<(H256, H256)>>();
+        let kv_state_proof: Bytes = {
+            let account_smt = db.account_smt().unwrap();
+            account_smt
+                .merkle_proof(touched_keys)
+                .unwrap()
+                .compile(kv_state.clone())
+                .unwrap()
+                .0
+                .into()
+        };
+        let witness = CCDepositWitness::new_builder()
+            .raw_l2block(challenged_raw_block)
+            .deposit_requests(
+                DepositRequestVec::new_builder()
+                    .set(deposit_requests.clone())
+                    .build(),
+            )
+            .kv_state_proof(Pack::pack(&kv_state_proof))
+            .kv_state(kv_state.pack())
+            .account_count(Pack::pack(&account_count))
+            .build();
+        ckb_types::packed::WitnessArgs::new_builder()
+            .lock(CKBPack::pack(&Some(witness.as_bytes())))
+            .build()
+    };
+
+    let rollup_cell_data = global_state
+        .as_builder()
+        .status(Status::Running.into())
+        .build()
+        .as_bytes();
+    let tx = build_simple_tx_with_out_point(
+        &mut ctx.inner,
+        (rollup_cell.clone(), initial_rollup_cell_data),
+        input_out_point,
+        (rollup_cell, rollup_cell_data),
+    )
+    .as_advanced_builder()
+    .witness(CKBPack::pack(&witness.as_bytes()))
+    .input(input_challenge_cell)
+    .witness(CKBPack::pack(&challenge_witness.as_bytes()))
+    .cell_dep(ctx.challenge_lock_dep.clone())
+    .cell_dep(ctx.stake_lock_dep.clone())
+    .cell_dep(ctx.always_success_dep.clone())
+    .cell_dep(ctx.state_validator_dep.clone())
+    .cell_dep(ctx.rollup_config_dep.clone())
+    .build();
+    ctx.verify_tx(tx).expect("return success");
+}
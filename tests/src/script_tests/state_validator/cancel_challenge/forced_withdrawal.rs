@@ -0,0 +1,273 @@
+#![allow(clippy::mutable_key_type)]
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use crate::script_tests::utils::init_env_log;
+use crate::script_tests::utils::layer1::build_simple_tx_with_out_point;
+use crate::script_tests::utils::layer1::random_out_point;
+use crate::script_tests::utils::rollup::{
+    build_always_success_cell, build_rollup_locked_cell, build_type_id_script,
+    calculate_state_validator_type_id, CellContext, CellContextParam,
+};
+use crate::testing_tool::chain::setup_chain;
+use crate::testing_tool::chain::{apply_block_result, construct_block};
+use crate::testing_tool::programs::{ALWAYS_SUCCESS_CODE_HASH, STATE_VALIDATOR_CODE_HASH};
+use ckb_error::assert_error_eq;
+use ckb_script::ScriptError;
+use ckb_types::{
+    packed::CellInput,
+    prelude::{Pack as CKBPack, Unpack},
+};
+use gw_common::builtins::{CKB_SUDT_ACCOUNT_ID, ETH_REGISTRY_ACCOUNT_ID};
+use gw_common::{state::State, H256};
+use gw_store::mem_pool_state::MemPoolState;
+use gw_store::mem_pool_state::MemStore;
+use gw_types::core::AllowedEoaType;
+use gw_types::packed::{AllowedTypeHash, ForcedWithdrawalRejection};
+use gw_types::prelude::*;
+use gw_types::{
+    bytes::Bytes,
+    core::{ChallengeTargetType, ScriptHashType, Status},
+    packed::{
+        ChallengeLockArgs, ChallengeTarget, DepositRequest, RawWithdrawalRequest, RollupAction,
+        RollupActionUnion, RollupCancelChallenge, RollupConfig, Script, WithdrawalRequest,
+    },
+};
+
+const INVALID_FORCED_WITHDRAWAL_CELL_ERROR: i8 = 54;
+
+const DEPOSIT_CAPACITY: u64 = 1000_00000000u64;
+
+/// Cancel a forced withdrawal challenge of the request withdrawing `withdrawal_capacity`
+async fn verify_cancel_forced_withdrawal(
+    withdrawal_capacity: u64,
+) -> Result<ckb_types::core::Cycle, ckb_error::Error> {
+    init_env_log();
+    let input_out_point = random_out_point();
+    let type_id = calculate_state_validator_type_id(input_out_point.clone());
+    let rollup_type_script = {
+        Script::new_builder()
+            .code_hash(Pack::pack(&*STATE_VALIDATOR_CODE_HASH))
+            .hash_type(ScriptHashType::Data.into())
+            .args(Pack::pack(&Bytes::from(type_id.to_vec())))
+            .build()
+    };
+    // rollup lock & config
+    let stake_lock_type = build_type_id_script(b"stake_lock_type_id");
+    let challenge_lock_type = build_type_id_script(b"challenge_lock_type_id");
+    let challenge_script_type_hash: [u8; 32] = challenge_lock_type.calc_script_hash().unpack();
+    let rollup_config = RollupConfig::new_builder()
+        .challenge_script_type_hash(Pack::pack(&challenge_script_type_hash))
+        .finality_blocks(Pack::pack(&10u64))
+        .allowed_eoa_type_hashes(
+            vec![AllowedTypeHash::new(
+                AllowedEoaType::Eth,
+                *ALWAYS_SUCCESS_CODE_HASH,
+            )]
+            .pack(),
+        )
+        .build();
+    // setup chain
+    let mut chain = setup_chain(rollup_type_script.clone(), rollup_config.clone()).await;
+    // create a rollup cell
+    let capacity = 1000_00000000u64;
+    let rollup_cell = build_always_success_cell(
+        capacity,
+        Some(ckb_types::packed::Script::new_unchecked(
+            rollup_type_script.as_bytes(),
+        )),
+    );
+    // deposit to the sender
+    let sender_script = {
+        let mut args = rollup_type_script.hash().to_vec();
+        args.extend_from_slice(&[1u8; 20]);
+        Script::new_builder()
+            .code_hash(Pack::pack(&ALWAYS_SUCCESS_CODE_HASH.clone()))
+            .hash_type(ScriptHashType::Type.into())
+            .args(Pack::pack(&Bytes::from(args)))
+            .build()
+    };
+    {
+        let deposit_requests = vec![DepositRequest::new_builder()
+            .capacity(Pack::pack(&DEPOSIT_CAPACITY))
+            .script(sender_script.clone())
+            .registry_id(Pack::pack(&ETH_REGISTRY_ACCOUNT_ID))
+            .build()];
+        let produce_block_result = {
+            let mem_pool = chain.mem_pool().as_ref().unwrap();
+            let mut mem_pool = mem_pool.lock().await;
+            construct_block(&chain, &mut mem_pool, deposit_requests.clone())
+                .await
+                .unwrap()
+        };
+        let rollup_cell = gw_types::packed::CellOutput::new_unchecked(rollup_cell.as_bytes());
+        apply_block_result(
+            &mut chain,
+            rollup_cell,
+            produce_block_result,
+            deposit_requests,
+            HashSet::new(),
+        )
+        .await;
+    }
+    // deploy scripts
+    let param = CellContextParam {
+        stake_lock_type,
+        challenge_lock_type,
+        ..Default::default()
+    };
+    let mut ctx = CellContext::new(&rollup_config, param);
+    // the forced withdrawal challenge carries the request
+    let request = {
+        let raw = RawWithdrawalRequest::new_builder()
+            .nonce(Pack::pack(&0u32))
+            .capacity(Pack::pack(&withdrawal_capacity))
+            .account_script_hash(Pack::pack(&sender_script.hash()))
+            .registry_id(Pack::pack(&ETH_REGISTRY_ACCOUNT_ID))
+            .build();
+        WithdrawalRequest::new_builder().raw(raw).build()
+    };
+    let challenged_block = chain.local_state().tip().clone();
+    let input_challenge_cell = {
+        let lock_args = ChallengeLockArgs::new_builder()
+            .target(
+                ChallengeTarget::new_builder()
+                    .target_index(Pack::pack(&0u32))
+                    .target_type(ChallengeTargetType::ForcedWithdrawal.into())
+                    .block_hash(Pack::pack(&challenged_block.hash()))
+                    .build(),
+            )
+            .build();
+        let cell = build_rollup_locked_cell(
+            &rollup_type_script.hash(),
+            &challenge_script_type_hash,
+            500_00000000u64,
+            lock_args.as_bytes(),
+        );
+        let out_point = ctx.insert_cell(cell, request.as_bytes());
+        CellInput::new_builder().previous_output(out_point).build()
+    };
+    let global_state = chain
+        .local_state()
+        .last_global_state()
+        .clone()
+        .as_builder()
+        .status(Status::Halting.into())
+        .build();
+    let initial_rollup_cell_data = global_state.as_bytes();
+    let witness = {
+        let rollup_action = RollupAction::new_builder()
+            .set(RollupActionUnion::RollupCancelChallenge(
+                RollupCancelChallenge::default(),
+            ))
+            .build();
+        ckb_types::packed::WitnessArgs::new_builder()
+            .output_type(CKBPack::pack(&Some(rollup_action.as_bytes())))
+            .build()
+    };
+    let challenge_witness = {
+        let state = {
+            let mem_store = MemStore::new(chain.store().get_snapshot());
+            MemPoolState::new(Arc::new(mem_store), true)
+        };
+        let snap = state.load();
+        let mut tree = snap.state().unwrap();
+        tree.tracker_mut().enable();
+        // read the state which the request is processed on
+        let sender_script_hash: H256 = sender_script.hash().into();
+        let sender_id = tree
+            .get_account_id_by_script_hash(&sender_script_hash)
+            .unwrap()
+            .unwrap();
+        let address = tree
+            .get_registry_address_by_script_hash(ETH_REGISTRY_ACCOUNT_ID, &sender_script_hash)
+            .unwrap()
+            .unwrap();
+        tree.get_nonce(sender_id).unwrap();
+        let ckb_sudt_script_hash = tree.get_script_hash(CKB_SUDT_ACCOUNT_ID).unwrap();
+        tree.get_account_id_by_script_hash(&ckb_sudt_script_hash)
+            .unwrap();
+        tree.get_sudt_balance(CKB_SUDT_ACCOUNT_ID, &address)
+            .unwrap();
+        let touched_keys: Vec<H256> = {
+            let keys = tree.tracker_mut().touched_keys().unwrap();
+            let unlock = keys.lock().unwrap();
+            unlock.clone().into_iter().collect()
+        };
+        let kv_state = touched_keys
+            .iter()
+            .map(|k| {
+                let v = tree.get_raw(k).unwrap();
+                (*k, v)
+            })
+            .collect::<Vec<(H256, H256)>>();
+        let kv_state_proof: Bytes = {
+            let db = chain.store().begin_transaction();
+            let account_smt = db.account_smt().unwrap();
+            account_smt
+                .merkle_proof(touched_keys)
+                .unwrap()
+                .compile(kv_state.clone())
+                .unwrap()
+                .0
+                .into()
+        };
+        let rejection = ForcedWithdrawalRejection::new_builder()
+            .kv_state(kv_state.pack())
+            .kv_state_proof(Pack::pack(&kv_state_proof))
+            .build();
+        ckb_types::packed::WitnessArgs::new_builder()
+            .lock(CKBPack::pack(&Some(rejection.as_bytes())))
+            .build()
+    };
+    let rollup_cell_data = global_state
+        .as_builder()
+        .status(Status::Running.into())
+        .build()
+        .as_bytes();
+    let tx = build_simple_tx_with_out_point(
+        &mut ctx.inner,
+        (rollup_cell.clone(), initial_rollup_cell_data),
+        input_out_point,
+        (rollup_cell, rollup_cell_data),
+    )
+    .as_advanced_builder()
+    .witness(CKBPack::pack(&witness.as_bytes()))
+    .input(input_challenge_cell)
+    .witness(CKBPack::pack(&challenge_witness.as_bytes()))
+    .cell_dep(ctx.challenge_lock_dep.clone())
+    .cell_dep(ctx.stake_lock_dep.clone())
+    .cell_dep(ctx.always_success_dep.clone())
+    .cell_dep(ctx.state_validator_dep.clone())
+    .cell_dep(ctx.rollup_config_dep.clone())
+    .build();
+    ctx.verify_tx(tx)
+}
+
+#[tokio::test]
+async fn test_cancel_forced_withdrawal() {
+    // the request withdraws more than the balance of the sender
+    verify_cancel_forced_withdrawal(DEPOSIT_CAPACITY + 1)
+        .await
+        .expect("return success");
+}
+
+#[tokio::test]
+async fn test_cancel_valid_forced_withdrawal() {
+    let err = verify_cancel_forced_withdrawal(DEPOSIT_CAPACITY)
+        .await
+        .unwrap_err();
+    let challenge_script_type_hash: [u8; 32] = build_type_id_script(b"challenge_lock_type_id")
+        .calc_script_hash()
+        .unpack();
+    let expected_err = ScriptError::ValidationFailure(
+        format!(
+            "by-type-hash/{}",
+            ckb_types::H256(challenge_script_type_hash)
+        ),
+        INVALID_FORCED_WITHDRAWAL_CELL_ERROR,
+    )
+    .input_lock_script(1);
+    assert_error_eq!(err, expected_err);
+}
@@ -47,6 +47,9 @@ use gw_types::{
     },
 };
 
+mod bisection;
+mod deposit;
+mod forced_withdrawal;
 mod tx_execution;
 mod tx_signature;
 mod withdrawal;
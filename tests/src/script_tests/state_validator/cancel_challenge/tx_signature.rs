@@ -18,8 +18,10 @@ use ckb_types::{
     prelude::{Pack as CKBPack, Unpack as CKBUnpack},
 };
 use gw_common::builtins::ETH_REGISTRY_ACCOUNT_ID;
-use gw_common::merkle_utils::ckb_merkle_leaf_hash;
+use gw_common::error::Error as StateError;
+use gw_common::merkle_utils::{calculate_state_checkpoint, ckb_merkle_leaf_hash, CBMT};
 use gw_common::registry_address::RegistryAddress;
+use gw_common::sparse_merkle_tree::default_store::DefaultStore;
 use gw_common::{state::State, H256};
 use gw_generator::account_lock_manage::always_success::AlwaysSuccess;
 use gw_generator::account_lock_manage::eip712;
@@ -35,6 +37,7 @@ use gw_types::core::AllowedEoaType;
 use gw_types::core::SigningType;
 use gw_types::packed::AllowedTypeHash;
 use gw_types::packed::CCTransactionSignatureWitness;
+use gw_types::packed::EoaRegistry;
 use gw_types::packed::Fee;
 use gw_types::prelude::*;
 use gw_types::U256;
@@ -42,12 +45,46 @@ use gw_types::{
     bytes::Bytes,
     core::{ChallengeTargetType, ScriptHashType, Status},
     packed::{
-        ChallengeLockArgs, ChallengeTarget, DepositRequest, L2Transaction, RawL2Transaction,
-        RollupAction, RollupActionUnion, RollupCancelChallenge, RollupConfig, SUDTArgs,
-        SUDTTransfer, Script,
+        ChallengeLockArgs, ChallengeTarget, DepositRequest, GlobalState, L2Transaction, RawL2Block,
+        RawL2Transaction, RollupAction, RollupActionUnion, RollupCancelChallenge, RollupConfig,
+        SUDTArgs, SUDTTransfer, Script, SubmitTransactions,
     },
 };
 
+const NATIVE_REGISTRY_ID: u32 = 42;
+
+/// The layer2 state which records the touched keys,
+/// the chain can't build the state since the generator only supports the ETH registry
+#[derive(Default)]
+struct TouchedState {
+    tree: gw_common::smt::SMT<DefaultStore<H256>>,
+    account_count: u32,
+    touched_keys: Vec<H256>,
+}
+
+impl State for TouchedState {
+    fn get_raw(&self, key: &H256) -> Result<H256, StateError> {
+        Ok(self.tree.get(key)?)
+    }
+    fn update_raw(&mut self, key: H256, value: H256) -> Result<(), StateError> {
+        self.tree.update(key, value)?;
+        if !self.touched_keys.contains(&key) {
+            self.touched_keys.push(key);
+        }
+        Ok(())
+    }
+    fn get_account_count(&self) -> Result<u32, StateError> {
+        Ok(self.account_count)
+    }
+    fn set_account_count(&mut self, count: u32) -> Result<(), StateError> {
+        self.account_count = count;
+        Ok(())
+    }
+    fn calculate_root(&self) -> Result<H256, StateError> {
+        Ok(*self.tree.root())
+    }
+}
+
 #[tokio::test]
 async fn test_cancel_tx_signature() {
     init_env_log();
@@ -422,3 +459,229 @@ async fn test_cancel_tx_signature() {
     .build();
     ctx.verify_tx(tx).expect("return success");
 }
+
+#[tokio::test]
+async fn test_cancel_native_registry_tx_signature() {
+    init_env_log();
+    let input_out_point = random_out_point();
+    let type_id = calculate_state_validator_type_id(input_out_point.clone());
+    let rollup_type_script = {
+        Script::new_builder()
+            .code_hash(Pack::pack(&*STATE_VALIDATOR_CODE_HASH))
+            .hash_type(ScriptHashType::Data.into())
+            .args(Pack::pack(&Bytes::from(type_id.to_vec())))
+            .build()
+    };
+    // rollup lock & config
+    let stake_lock_type = build_type_id_script(b"stake_lock_type_id");
+    let challenge_lock_type = build_type_id_script(b"challenge_lock_type_id");
+    let eoa_lock_type = build_type_id_script(b"eoa_lock_type_id");
+    let l2_sudt_type = build_type_id_script(b"l2_sudt_type_id");
+    let challenge_script_type_hash: [u8; 32] = challenge_lock_type.calc_script_hash().unpack();
+    let eoa_lock_type_hash: [u8; 32] = eoa_lock_type.calc_script_hash().unpack();
+    let l2_sudt_type_hash: [u8; 32] = l2_sudt_type.calc_script_hash().unpack();
+    // the EOA type hash is registered in the native registry instead of the ETH registry
+    let rollup_config = RollupConfig::new_builder()
+        .challenge_script_type_hash(Pack::pack(&challenge_script_type_hash))
+        .allowed_eoa_type_hashes(PackVec::pack(vec![AllowedTypeHash::new_builder()
+            .hash(Pack::pack(&eoa_lock_type_hash))
+            .build()]))
+        .eoa_registries(PackVec::pack(vec![EoaRegistry::new_builder()
+            .registry_id(Pack::pack(&NATIVE_REGISTRY_ID))
+            .eoa_type_hash(Pack::pack(&eoa_lock_type_hash))
+            .build()]))
+        .l2_sudt_validator_script_type_hash(Pack::pack(&l2_sudt_type_hash))
+        .allowed_contract_type_hashes(
+            vec![AllowedTypeHash::new(
+                AllowedContractType::Sudt,
+                l2_sudt_type_hash,
+            )]
+            .pack(),
+        )
+        .finality_blocks(Pack::pack(&10u64))
+        .build();
+    let rollup_config_hash: [u8; 32] =
+        CellOutput::calc_data_hash(&rollup_config.as_bytes()).unpack();
+    // create a rollup cell
+    let capacity = 1000_00000000u64;
+    let rollup_cell = build_always_success_cell(
+        capacity,
+        Some(ckb_types::packed::Script::new_unchecked(
+            rollup_type_script.as_bytes(),
+        )),
+    );
+    let rollup_script_hash = rollup_type_script.hash();
+    // build the state before the challenged tx
+    let sudt_script = {
+        let mut args = rollup_script_hash.to_vec();
+        args.extend_from_slice(&[0u8; 32]);
+        Script::new_builder()
+            .code_hash(Pack::pack(&l2_sudt_type_hash))
+            .hash_type(ScriptHashType::Type.into())
+            .args(Pack::pack(&Bytes::from(args)))
+            .build()
+    };
+    let sender_script = {
+        let mut args = rollup_script_hash.to_vec();
+        args.extend_from_slice(&[1u8; 20]);
+        Script::new_builder()
+            .code_hash(Pack::pack(&eoa_lock_type_hash))
+            .hash_type(ScriptHashType::Type.into())
+            .args(Pack::pack(&Bytes::from(args)))
+            .build()
+    };
+    let mut tree = TouchedState::default();
+    let sudt_id = tree.create_account(sudt_script.hash().into()).unwrap();
+    let sender_id = tree.create_account(sender_script.hash().into()).unwrap();
+    tree.mapping_registry_address_to_script_hash(
+        RegistryAddress::new(NATIVE_REGISTRY_ID, vec![1u8; 20]),
+        sender_script.hash().into(),
+    )
+    .unwrap();
+    let account_count = tree.get_account_count().unwrap();
+    let kv_state = tree
+        .touched_keys
+        .iter()
+        .map(|k| (*k, tree.get_raw(k).unwrap()))
+        .collect::<Vec<(H256, H256)>>();
+    let kv_state_proof: Bytes = tree
+        .tree
+        .merkle_proof(tree.touched_keys.clone())
+        .unwrap()
+        .compile(kv_state.clone())
+        .unwrap()
+        .0
+        .into();
+    // the challenged block contains the tx
+    let tx = L2Transaction::new_builder()
+        .raw(
+            RawL2Transaction::new_builder()
+                .from_id(Pack::pack(&sender_id))
+                .to_id(Pack::pack(&sudt_id))
+                .nonce(Pack::pack(&0u32))
+                .build(),
+        )
+        .build();
+    let challenge_target_index = 0u32;
+    let leaves = vec![ckb_merkle_leaf_hash(
+        challenge_target_index,
+        &tx.witness_hash().into(),
+    )];
+    let tx_proof = super::build_merkle_proof(&leaves, &[challenge_target_index]);
+    let raw_block = RawL2Block::new_builder()
+        .number(Pack::pack(&1u64))
+        .submit_transactions(
+            SubmitTransactions::new_builder()
+                .tx_witness_root(Pack::pack(&CBMT::build_merkle_root(&leaves)))
+                .tx_count(Pack::pack(&1u32))
+                .prev_state_checkpoint(Pack::pack(&calculate_state_checkpoint(
+                    &tree.calculate_root().unwrap(),
+                    account_count,
+                )))
+                .build(),
+        )
+        .build();
+    // deploy scripts
+    let param = CellContextParam {
+        stake_lock_type,
+        challenge_lock_type,
+        eoa_lock_type,
+        l2_sudt_type,
+        ..Default::default()
+    };
+    let mut ctx = CellContext::new(&rollup_config, param);
+    let input_challenge_cell = {
+        let lock_args = ChallengeLockArgs::new_builder()
+            .target(
+                ChallengeTarget::new_builder()
+                    .target_index(Pack::pack(&challenge_target_index))
+                    .target_type(ChallengeTargetType::TxSignature.into())
+                    .block_hash(Pack::pack(&raw_block.hash()))
+                    .build(),
+            )
+            .build();
+        let cell = build_rollup_locked_cell(
+            &rollup_type_script.hash(),
+            &challenge_script_type_hash,
+            10000_00000000u64,
+            lock_args.as_bytes(),
+        );
+        let out_point = ctx.insert_cell(cell, Bytes::default());
+        CellInput::new_builder().previous_output(out_point).build()
+    };
+    let global_state = GlobalState::new_builder()
+        .rollup_config_hash(Pack::pack(&rollup_config_hash))
+        .status(Status::Halting.into())
+        .version(1u8.into())
+        .build();
+    let initial_rollup_cell_data = global_state.as_bytes();
+    let witness = {
+        let rollup_action = RollupAction::new_builder()
+            .set(RollupActionUnion::RollupCancelChallenge(
+                RollupCancelChallenge::default(),
+            ))
+            .build();
+        ckb_types::packed::WitnessArgs::new_builder()
+            .output_type(CKBPack::pack(&Some(rollup_action.as_bytes())))
+            .build()
+    };
+    let challenge_witness = {
+        let witness = CCTransactionSignatureWitness::new_builder()
+            .l2tx(tx.clone())
+            .raw_l2block(raw_block)
+            .kv_state_proof(Pack::pack(&kv_state_proof))
+            .tx_proof(tx_proof)
+            .sender(sender_script.clone())
+            .receiver(sudt_script)
+            .account_count(Pack::pack(&account_count))
+            .kv_state(kv_state.pack())
+            .build();
+        ckb_types::packed::WitnessArgs::new_builder()
+            .lock(CKBPack::pack(&Some(witness.as_bytes())))
+            .build()
+    };
+    // the EOA of the native registry signs the raw tx hash
+    let input_unlock_cell = {
+        let cell = CellOutput::new_builder()
+            .lock(ckb_types::packed::Script::new_unchecked(
+                sender_script.as_bytes(),
+            ))
+            .capacity(CKBPack::pack(&42u64))
+            .build();
+        let owner_lock_hash = vec![42u8; 32];
+        let data: Bytes = {
+            let mut buf = owner_lock_hash.to_vec();
+            buf.push(SigningType::Raw.into());
+            buf.extend_from_slice(&tx.raw().hash());
+            buf.into()
+        };
+        let out_point = ctx.insert_cell(cell, data);
+        CellInput::new_builder().previous_output(out_point).build()
+    };
+    let rollup_cell_data = global_state
+        .as_builder()
+        .status(Status::Running.into())
+        .build()
+        .as_bytes();
+    let tx = build_simple_tx_with_out_point(
+        &mut ctx.inner,
+        (rollup_cell.clone(), initial_rollup_cell_data),
+        input_out_point,
+        (rollup_cell, rollup_cell_data),
+    )
+    .as_advanced_builder()
+    .witness(CKBPack::pack(&witness.as_bytes()))
+    .input(input_challenge_cell)
+    .witness(CKBPack::pack(&challenge_witness.as_bytes()))
+    .input(input_unlock_cell)
+    .witness(Default::default())
+    .cell_dep(ctx.challenge_lock_dep.clone())
+    .cell_dep(ctx.stake_lock_dep.clone())
+    .cell_dep(ctx.always_success_dep.clone())
+    .cell_dep(ctx.state_validator_dep.clone())
+    .cell_dep(ctx.rollup_config_dep.clone())
+    .cell_dep(ctx.eoa_lock_dep.clone())
+    .cell_dep(ctx.l2_sudt_dep.clone())
+    .build();
+    ctx.verify_tx(tx).expect("return success");
+}
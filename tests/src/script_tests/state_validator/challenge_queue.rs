@@ -0,0 +1,313 @@
+#![allow(clippy::mutable_key_type)]
+
+use std::collections::HashSet;
+
+use crate::script_tests::utils::init_env_log;
+use crate::script_tests::utils::layer1::{build_simple_tx_with_out_point, random_out_point};
+use crate::script_tests::utils::rollup::{
+    build_always_success_cell, build_rollup_locked_cell, build_type_id_script,
+    calculate_state_validator_type_id, expected_state_validator_error, CellContext,
+    CellContextParam,
+};
+use crate::testing_tool::chain::{apply_block_result, construct_block, setup_chain};
+use crate::testing_tool::programs::{ALWAYS_SUCCESS_CODE_HASH, STATE_VALIDATOR_CODE_HASH};
+use ckb_error::assert_error_eq;
+use ckb_types::packed::CellInput;
+use ckb_types::prelude::{Pack as CKBPack, Unpack};
+use gw_common::registry_address::RegistryAddress;
+use gw_common::{builtins::CKB_SUDT_ACCOUNT_ID, state::State};
+use gw_store::state::state_db::StateContext;
+use gw_types::core::{AllowedContractType, AllowedEoaType};
+use gw_types::packed::{AllowedTypeHash, Fee};
+use gw_types::prelude::*;
+use gw_types::U256;
+use gw_types::{
+    bytes::Bytes,
+    core::{ChallengeTargetType, ScriptHashType, Status},
+    packed::{
+        ChallengeLockArgs, ChallengeQueue, ChallengeTarget, ChallengeTargetVec, ChallengeWitness,
+        DepositRequest, L2Transaction, RawL2Transaction, RollupAction, RollupActionUnion,
+        RollupConfig, RollupEnterChallenge, SUDTArgs, SUDTArgsUnion, SUDTTransfer, Script,
+    },
+};
+
+const INVALID_CHALLENGE_CELL_ERROR: i8 = 24;
+
+/// Enter a tx execution challenge against the tip block during the rollup halting,
+/// the challenge queue contains an open challenge of `open_target_type` against the same tx
+async fn verify_enter_queued_challenge(
+    open_target_type: ChallengeTargetType,
+) -> Result<ckb_types::core::Cycle, ckb_error::Error> {
+    init_env_log();
+    let input_out_point = random_out_point();
+    let type_id = calculate_state_validator_type_id(input_out_point.clone());
+    let rollup_type_script = {
+        Script::new_builder()
+            .code_hash(Pack::pack(&*STATE_VALIDATOR_CODE_HASH))
+            .hash_type(ScriptHashType::Data.into())
+            .args(Pack::pack(&Bytes::from(type_id.to_vec())))
+            .build()
+    };
+    // rollup lock & config
+    let stake_lock_type = build_type_id_script(b"stake_lock_type_id");
+    let challenge_lock_type = build_type_id_script(b"challenge_lock_type_id");
+    let challenge_script_type_hash: [u8; 32] = challenge_lock_type.calc_script_hash().unpack();
+    let queue_cell_type = build_type_id_script(b"challenge_queue_type_id");
+    let challenge_queue_type_hash: [u8; 32] = queue_cell_type.calc_script_hash().unpack();
+    let rollup_bound_lock_type = build_type_id_script(b"rollup_bound_lock_type_id");
+    let rollup_bound_script_type_hash: [u8; 32] =
+        rollup_bound_lock_type.calc_script_hash().unpack();
+    let rollup_config = RollupConfig::new_builder()
+        .challenge_script_type_hash(Pack::pack(&challenge_script_type_hash))
+        .challenge_queue_type_hash(Pack::pack(&challenge_queue_type_hash))
+        .rollup_bound_script_type_hash(Pack::pack(&rollup_bound_script_type_hash))
+        .finality_blocks(Pack::pack(&10u64))
+        .allowed_eoa_type_hashes(
+            vec![AllowedTypeHash::new(
+                AllowedEoaType::Eth,
+                *ALWAYS_SUCCESS_CODE_HASH,
+            )]
+            .pack(),
+        )
+        .allowed_contract_type_hashes(
+            vec![AllowedTypeHash::new(AllowedContractType::Sudt, [0u8; 32])].pack(),
+        )
+        .build();
+    // setup chain
+    let mut chain = setup_chain(rollup_type_script.clone(), rollup_config.clone()).await;
+    // create a rollup cell
+    let capacity = 1000_00000000u64;
+    let rollup_cell = build_always_success_cell(
+        capacity,
+        Some(ckb_types::packed::Script::new_unchecked(
+            rollup_type_script.as_bytes(),
+        )),
+    );
+    // produce a block so we can challenge it
+    {
+        // deposit two account
+        let rollup_script_hash = rollup_type_script.hash();
+        let mut sender_args = rollup_script_hash.to_vec();
+        sender_args.extend_from_slice(&[1u8; 20]);
+        let sender_script = Script::new_builder()
+            .code_hash(Pack::pack(&ALWAYS_SUCCESS_CODE_HASH.clone()))
+            .hash_type(ScriptHashType::Type.into())
+            .args(Pack::pack(&Bytes::from(sender_args)))
+            .build();
+        let mut receiver_args = rollup_script_hash.to_vec();
+        receiver_args.extend_from_slice(&[2u8; 20]);
+        let receiver_script = Script::new_builder()
+            .code_hash(Pack::pack(&ALWAYS_SUCCESS_CODE_HASH.clone()))
+            .hash_type(ScriptHashType::Type.into())
+            .args(Pack::pack(&Bytes::from(receiver_args)))
+            .build();
+        let deposit_requests = vec![
+            DepositRequest::new_builder()
+                .capacity(Pack::pack(&300_00000000u64))
+                .script(sender_script.clone())
+                .registry_id(Pack::pack(&gw_common::builtins::ETH_REGISTRY_ACCOUNT_ID))
+                .build(),
+            DepositRequest::new_builder()
+                .capacity(Pack::pack(&450_00000000u64))
+                .script(receiver_script.clone())
+                .registry_id(Pack::pack(&gw_common::builtins::ETH_REGISTRY_ACCOUNT_ID))
+                .build(),
+        ];
+        let produce_block_result = {
+            let mem_pool = chain.mem_pool().as_ref().unwrap();
+            let mut mem_pool = mem_pool.lock().await;
+            construct_block(&chain, &mut mem_pool, deposit_requests.clone())
+                .await
+                .unwrap()
+        };
+        let rollup_cell = gw_types::packed::CellOutput::new_unchecked(rollup_cell.as_bytes());
+        apply_block_result(
+            &mut chain,
+            rollup_cell.clone(),
+            produce_block_result,
+            deposit_requests,
+            HashSet::new(),
+        )
+        .await;
+        let db = chain.store().begin_transaction();
+        let tree = db.state_tree(StateContext::ReadOnly).unwrap();
+        let sender_id = tree
+            .get_account_id_by_script_hash(&sender_script.hash().into())
+            .unwrap()
+            .unwrap();
+        let receiver_address = RegistryAddress::new(
+            gw_common::builtins::ETH_REGISTRY_ACCOUNT_ID,
+            receiver_script.hash()[..20].to_vec(),
+        );
+        let produce_block_result = {
+            let args = SUDTArgs::new_builder()
+                .set(SUDTArgsUnion::SUDTTransfer(
+                    SUDTTransfer::new_builder()
+                        .amount(Pack::pack(&U256::from(150_00000000u128)))
+                        .to_address(Pack::pack(&Bytes::from(receiver_address.to_bytes())))
+                        .fee(
+                            Fee::new_builder()
+                                .registry_id(Pack::pack(
+                                    &gw_common::builtins::ETH_REGISTRY_ACCOUNT_ID,
+                                ))
+                                .build(),
+                        )
+                        .build(),
+                ))
+                .build()
+                .as_bytes();
+            let tx = L2Transaction::new_builder()
+                .raw(
+                    RawL2Transaction::new_builder()
+                        .from_id(Pack::pack(&sender_id))
+                        .to_id(Pack::pack(&CKB_SUDT_ACCOUNT_ID))
+                        .nonce(Pack::pack(&0u32))
+                        .args(Pack::pack(&args))
+                        .build(),
+                )
+                .build();
+            let mem_pool = chain.mem_pool().as_ref().unwrap();
+            let mut mem_pool = mem_pool.lock().await;
+            mem_pool.push_transaction(tx).await.unwrap();
+            construct_block(&chain, &mut mem_pool, Vec::default())
+                .await
+                .unwrap()
+        };
+        apply_block_result(
+            &mut chain,
+            rollup_cell,
+            produce_block_result,
+            vec![],
+            HashSet::new(),
+        )
+        .await;
+    }
+    // deploy scripts
+    let param = CellContextParam {
+        stake_lock_type,
+        rollup_bound_lock_type,
+        ..Default::default()
+    };
+    let mut ctx = CellContext::new(&rollup_config, param);
+    let challenged_block = chain.local_state().tip().clone();
+    let build_target = |target_type: ChallengeTargetType| {
+        ChallengeTarget::new_builder()
+            .target_index(Pack::pack(&0u32))
+            .target_type(target_type.into())
+            .block_hash(Pack::pack(&challenged_block.hash()))
+            .build()
+    };
+    let open_target = build_target(open_target_type);
+    let challenge_target = build_target(ChallengeTargetType::TxExecution);
+    let challenge_cell = {
+        let lock_args = ChallengeLockArgs::new_builder()
+            .target(challenge_target.clone())
+            .reward_weight(Pack::pack(&1u32))
+            .build();
+        build_rollup_locked_cell(
+            &rollup_type_script.hash(),
+            &challenge_script_type_hash,
+            10000_00000000u64,
+            lock_args.as_bytes(),
+        )
+    };
+    // challenge queue cells
+    let queue_cell = build_rollup_locked_cell(
+        &rollup_type_script.hash(),
+        &rollup_bound_script_type_hash,
+        capacity,
+        Bytes::default(),
+    )
+    .as_builder()
+    .type_(CKBPack::pack(&Some(
+        ckb_types::packed::Script::new_unchecked(queue_cell_type.as_bytes()),
+    )))
+    .build();
+    let build_queue = |targets: Vec<ChallengeTarget>| {
+        ChallengeQueue::new_builder()
+            .targets(ChallengeTargetVec::new_builder().extend(targets).build())
+            .build()
+            .as_bytes()
+    };
+    let input_queue_cell = {
+        let out_point = ctx.insert_cell(queue_cell.clone(), build_queue(vec![open_target.clone()]));
+        CellInput::new_builder().previous_output(out_point).build()
+    };
+    let output_queue_data = build_queue(vec![open_target, challenge_target]);
+    let global_state = chain
+        .local_state()
+        .last_global_state()
+        .clone()
+        .as_builder()
+        .status(Status::Halting.into())
+        .build();
+    // verify enter challenge
+    let witness = {
+        let block_proof: Bytes = {
+            let db = chain.store().begin_transaction();
+            let proof = db
+                .block_smt()
+                .unwrap()
+                .merkle_proof(vec![challenged_block.smt_key().into()])
+                .unwrap();
+            proof
+                .compile(vec![(
+                    challenged_block.smt_key().into(),
+                    challenged_block.hash().into(),
+                )])
+                .unwrap()
+                .0
+                .into()
+        };
+        let witness = ChallengeWitness::new_builder()
+            .raw_l2block(challenged_block.raw())
+            .block_proof(Pack::pack(&block_proof))
+            .build();
+        let rollup_action = RollupAction::new_builder()
+            .set(RollupActionUnion::RollupEnterChallenge(
+                RollupEnterChallenge::new_builder().witness(witness).build(),
+            ))
+            .build();
+        ckb_types::packed::WitnessArgs::new_builder()
+            .output_type(CKBPack::pack(&Some(rollup_action.as_bytes())))
+            .build()
+    };
+    let tx = build_simple_tx_with_out_point(
+        &mut ctx.inner,
+        (rollup_cell.clone(), global_state.as_bytes()),
+        input_out_point,
+        (rollup_cell, global_state.as_bytes()),
+    )
+    .as_advanced_builder()
+    .output(challenge_cell)
+    .output_data(CKBPack::pack(&Bytes::default()))
+    .input(input_queue_cell)
+    .output(queue_cell)
+    .output_data(CKBPack::pack(&output_queue_data))
+    .cell_dep(ctx.rollup_bound_lock_dep.clone())
+    .cell_dep(ctx.stake_lock_dep.clone())
+    .cell_dep(ctx.always_success_dep.clone())
+    .cell_dep(ctx.state_validator_dep.clone())
+    .cell_dep(ctx.rollup_config_dep.clone())
+    .witness(CKBPack::pack(&witness.as_bytes()))
+    .build();
+    ctx.verify_tx(tx)
+}
+
+#[tokio::test]
+async fn test_enter_challenge_during_halting() {
+    verify_enter_queued_challenge(ChallengeTargetType::TxSignature)
+        .await
+        .expect("return success");
+}
+
+#[tokio::test]
+async fn test_enter_duplicated_challenge() {
+    let err = verify_enter_queued_challenge(ChallengeTargetType::TxExecution)
+        .await
+        .unwrap_err();
+    assert_error_eq!(
+        err,
+        expected_state_validator_error(INVALID_CHALLENGE_CELL_ERROR)
+    );
+}
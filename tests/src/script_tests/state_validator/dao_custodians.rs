@@ -0,0 +1,318 @@
+use crate::script_tests::utils::init_env_log;
+use crate::script_tests::utils::layer1::{
+    always_success_script, build_simple_tx_with_out_point, random_out_point,
+};
+use crate::script_tests::utils::rollup::{
+    build_always_success_cell, build_rollup_locked_cell, build_type_id_script,
+    calculate_state_validator_type_id, expected_state_validator_error, CellContext,
+    CellContextParam,
+};
+use crate::testing_tool::programs::STATE_VALIDATOR_CODE_HASH;
+use ckb_error::assert_error_eq;
+use ckb_types::{
+    packed::{CellInput, CellOutput},
+    prelude::{Pack as CKBPack, Unpack as CKBUnpack},
+};
+use gw_common::{
+    builtins::CKB_SUDT_ACCOUNT_ID,
+    ckb_decimal::CKBCapacity,
+    sparse_merkle_tree::default_store::DefaultStore,
+    state::{build_account_key, SUDT_TOTAL_SUPPLY_KEY},
+    H256,
+};
+use gw_types::{
+    bytes::Bytes,
+    core::{ScriptHashType, Status},
+    packed::{
+        AccountMerkleState, CustodianLockArgs, GlobalState, RollupAction, RollupActionUnion,
+        RollupConfig, RollupDaoCustodians, Script,
+    },
+    prelude::*,
+};
+
+const INVALID_OUTPUT_ERROR: i8 = 7;
+const INSUFFICIENT_OUTPUT_FINALIZED_ASSETS_ERROR: i8 = 17;
+const INVALID_CUSTODIAN_CELL_ERROR: i8 = 28;
+const INSUFFICIENT_LIQUIDITY_ERROR: i8 = 59;
+
+const LAST_FINALIZED_BLOCK_NUMBER: u64 = 10;
+
+const DAO_DEPOSIT: u8 = 0;
+const DAO_CLAIM: u8 = 2;
+
+const DAO_MIN_LIQUID_RATIO: u8 = 20;
+
+struct DaoParam {
+    operation: u8,
+    input_dao_data: Option<u64>,
+    output_dao_data: Option<u64>,
+    input_capacities: Vec<u64>,
+    output_capacities: Vec<u64>,
+    // plain custodian outputs
+    liquid_capacities: Vec<u64>,
+    // layer1 capacity of the layer2 CKB total supply
+    ckb_total_supply: u64,
+    // (capacity, sent to the interest lock)
+    interest_cell: Option<(u64, bool)>,
+}
+
+impl Default for DaoParam {
+    fn default() -> Self {
+        Self {
+            operation: DAO_DEPOSIT,
+            input_dao_data: None,
+            output_dao_data: Some(0),
+            input_capacities: vec![300_00000000u64; 3],
+            output_capacities: vec![300_00000000u64; 2],
+            liquid_capacities: vec![300_00000000u64],
+            ckb_total_supply: 1500_00000000u64,
+            interest_cell: None,
+        }
+    }
+}
+
+fn verify_dao_custodians(param: DaoParam) -> Result<ckb_types::core::Cycle, ckb_error::Error> {
+    init_env_log();
+    let input_out_point = random_out_point();
+    let type_id = calculate_state_validator_type_id(input_out_point.clone());
+    let rollup_type_script = {
+        Script::new_builder()
+            .code_hash(Pack::pack(&*STATE_VALIDATOR_CODE_HASH))
+            .hash_type(ScriptHashType::Data.into())
+            .args(Pack::pack(&Bytes::from(type_id.to_vec())))
+            .build()
+    };
+    // rollup lock & config
+    let custodian_lock_type = build_type_id_script(b"custodian_lock_type_id");
+    let custodian_script_type_hash: [u8; 32] = custodian_lock_type.calc_script_hash().unpack();
+    // use an always success type script as the Nervos DAO
+    let dao_type = build_type_id_script(b"dao_type_id");
+    let dao_script_type_hash: [u8; 32] = dao_type.calc_script_hash().unpack();
+    let dao_interest_lock_hash: [u8; 32] = always_success_script().calc_script_hash().unpack();
+    let rollup_config = RollupConfig::new_builder()
+        .custodian_script_type_hash(Pack::pack(&custodian_script_type_hash))
+        .dao_script_type_hash(Pack::pack(&dao_script_type_hash))
+        .dao_interest_lock_hash(Pack::pack(&dao_interest_lock_hash))
+        .dao_min_liquid_ratio(DAO_MIN_LIQUID_RATIO.into())
+        .build();
+    let rollup_config_hash: [u8; 32] =
+        CellOutput::calc_data_hash(&rollup_config.as_bytes()).unpack();
+    // deploy scripts
+    let param_ctx = CellContextParam {
+        custodian_lock_type,
+        always_success_type: dao_type,
+        ..Default::default()
+    };
+    let mut ctx = CellContext::new(&rollup_config, param_ctx);
+    // build custodian cells
+    let build_custodian_cell = |capacity: u64, dao_data: Option<u64>| {
+        let args = CustodianLockArgs::new_builder()
+            .deposit_block_hash(Pack::pack(&[0u8; 32]))
+            .deposit_block_number(Pack::pack(&LAST_FINALIZED_BLOCK_NUMBER))
+            .build();
+        let cell = build_rollup_locked_cell(
+            &rollup_type_script.hash(),
+            &custodian_script_type_hash,
+            capacity,
+            args.as_bytes(),
+        );
+        match dao_data {
+            Some(data) => {
+                let dao_type_script = ckb_types::packed::Script::new_builder()
+                    .code_hash(CKBPack::pack(&dao_script_type_hash))
+                    .hash_type(ScriptHashType::Type.into())
+                    .build();
+                let cell = cell
+                    .as_builder()
+                    .type_(CKBPack::pack(&Some(dao_type_script)))
+                    .build();
+                (cell, Bytes::from(data.to_le_bytes().to_vec()))
+            }
+            None => (cell, Bytes::new()),
+        }
+    };
+    let input_custodian_cells: Vec<_> = param
+        .input_capacities
+        .iter()
+        .map(|capacity| {
+            let (cell, data) = build_custodian_cell(*capacity, param.input_dao_data);
+            let out_point = ctx.insert_cell(cell, data);
+            CellInput::new_builder().previous_output(out_point).build()
+        })
+        .collect();
+    let (output_custodian_cells, output_custodian_data): (Vec<_>, Vec<_>) = param
+        .output_capacities
+        .iter()
+        .map(|capacity| build_custodian_cell(*capacity, param.output_dao_data))
+        .chain(
+            param
+                .liquid_capacities
+                .iter()
+                .map(|capacity| build_custodian_cell(*capacity, None)),
+        )
+        .map(|(cell, data)| (cell, CKBPack::pack(&data)))
+        .unzip();
+    // the layer2 CKB total supply
+    let kv_state = {
+        let total_supply = CKBCapacity::from_layer1(param.ckb_total_supply).to_layer2();
+        let mut buf = [0u8; 32];
+        total_supply.to_little_endian(&mut buf);
+        vec![(
+            build_account_key(CKB_SUDT_ACCOUNT_ID, &SUDT_TOTAL_SUPPLY_KEY),
+            H256::from(buf),
+        )]
+    };
+    let mut tree: gw_common::smt::SMT<DefaultStore<H256>> = Default::default();
+    for (k, v) in kv_state.iter() {
+        tree.update(*k, *v).unwrap();
+    }
+    let kv_state_proof: Bytes = tree
+        .merkle_proof(kv_state.iter().map(|(k, _v)| *k).collect())
+        .unwrap()
+        .compile(kv_state.clone())
+        .unwrap()
+        .0
+        .into();
+    // create a rollup cell
+    let capacity = 1000_00000000u64;
+    let rollup_cell = build_always_success_cell(
+        capacity,
+        Some(ckb_types::packed::Script::new_unchecked(
+            rollup_type_script.as_bytes(),
+        )),
+    );
+    let status: u8 = Status::Running.into();
+    let global_state = GlobalState::new_builder()
+        .rollup_config_hash(Pack::pack(&rollup_config_hash))
+        .account(
+            AccountMerkleState::new_builder()
+                .merkle_root((*tree.root()).pack())
+                .count(2u32.pack())
+                .build(),
+        )
+        .last_finalized_block_number(Pack::pack(&LAST_FINALIZED_BLOCK_NUMBER))
+        .status(status.into())
+        .version(1u8.into())
+        .build();
+    let witness = {
+        let rollup_action = RollupAction::new_builder()
+            .set(RollupActionUnion::RollupDaoCustodians(
+                RollupDaoCustodians::new_builder()
+                    .operation(param.operation.into())
+                    .kv_state(kv_state.pack())
+                    .kv_state_proof(kv_state_proof.pack())
+                    .build(),
+            ))
+            .build();
+        ckb_types::packed::WitnessArgs::new_builder()
+            .output_type(CKBPack::pack(&Some(rollup_action.as_bytes())))
+            .build()
+    };
+    let mut tx = build_simple_tx_with_out_point(
+        &mut ctx.inner,
+        (rollup_cell.clone(), global_state.as_bytes()),
+        input_out_point,
+        (rollup_cell, global_state.as_bytes()),
+    )
+    .as_advanced_builder()
+    .inputs(input_custodian_cells)
+    .outputs(output_custodian_cells)
+    .outputs_data(output_custodian_data)
+    .cell_dep(ctx.custodian_lock_dep.clone())
+    .cell_dep(ctx.always_success_dep.clone())
+    .cell_dep(ctx.state_validator_dep.clone())
+    .cell_dep(ctx.rollup_config_dep.clone())
+    .witness(CKBPack::pack(&witness.as_bytes()));
+    if let Some((capacity, to_interest_lock)) = param.interest_cell {
+        let lock = if to_interest_lock {
+            always_success_script()
+        } else {
+            always_success_script()
+                .as_builder()
+                .args(CKBPack::pack(&Bytes::from(vec![42u8; 20])))
+                .build()
+        };
+        let interest_cell = CellOutput::new_builder()
+            .lock(lock)
+            .capacity(CKBPack::pack(&capacity))
+            .build();
+        tx = tx
+            .output(interest_cell)
+            .output_data(CKBPack::pack(&Bytes::default()));
+    }
+    ctx.verify_tx(tx.build())
+}
+
+#[test]
+fn test_dao_deposit_custodians() {
+    verify_dao_custodians(DaoParam::default()).expect("return success");
+}
+
+#[test]
+fn test_dao_deposit_custodians_with_insufficient_outputs() {
+    let param = DaoParam {
+        output_capacities: vec![250_00000000u64; 2],
+        ..Default::default()
+    };
+    let err = verify_dao_custodians(param).unwrap_err();
+    assert_error_eq!(
+        err,
+        expected_state_validator_error(INSUFFICIENT_OUTPUT_FINALIZED_ASSETS_ERROR)
+    );
+}
+
+#[test]
+fn test_dao_deposit_custodians_with_insufficient_liquidity() {
+    // 300 CKB liquid custodians are less than 20% of 2000 CKB
+    let param = DaoParam {
+        ckb_total_supply: 2000_00000000u64,
+        ..Default::default()
+    };
+    let err = verify_dao_custodians(param).unwrap_err();
+    assert_error_eq!(
+        err,
+        expected_state_validator_error(INSUFFICIENT_LIQUIDITY_ERROR)
+    );
+}
+
+#[test]
+fn test_dao_deposit_withdrawing_custodians() {
+    let param = DaoParam {
+        output_dao_data: Some(LAST_FINALIZED_BLOCK_NUMBER),
+        ..Default::default()
+    };
+    let err = verify_dao_custodians(param).unwrap_err();
+    assert_error_eq!(
+        err,
+        expected_state_validator_error(INVALID_CUSTODIAN_CELL_ERROR)
+    );
+}
+
+#[test]
+fn test_dao_claim_custodians() {
+    let param = DaoParam {
+        operation: DAO_CLAIM,
+        input_dao_data: Some(LAST_FINALIZED_BLOCK_NUMBER),
+        output_dao_data: None,
+        output_capacities: vec![450_00000000u64; 2],
+        liquid_capacities: vec![],
+        interest_cell: Some((10_00000000u64, true)),
+        ..Default::default()
+    };
+    verify_dao_custodians(param).expect("return success");
+}
+
+#[test]
+fn test_dao_claim_interest_to_other_lock() {
+    let param = DaoParam {
+        operation: DAO_CLAIM,
+        input_dao_data: Some(LAST_FINALIZED_BLOCK_NUMBER),
+        output_dao_data: None,
+        output_capacities: vec![450_00000000u64; 2],
+        liquid_capacities: vec![],
+        interest_cell: Some((10_00000000u64, false)),
+        ..Default::default()
+    };
+    let err = verify_dao_custodians(param).unwrap_err();
+    assert_error_eq!(err, expected_state_validator_error(INVALID_OUTPUT_ERROR));
+}
@@ -0,0 +1,234 @@
+use crate::script_tests::utils::rollup::{
+    build_rollup_locked_cell, build_type_id_script, expected_state_validator_error, CellContext,
+    CellContextParam, RollupFixture,
+};
+use crate::testing_tool::chain::{construct_block, setup_chain};
+use crate::testing_tool::programs::ALWAYS_SUCCESS_CODE_HASH;
+use ckb_error::assert_error_eq;
+use ckb_types::{
+    packed::CellInput,
+    prelude::{Pack as CKBPack, Unpack},
+};
+use gw_types::core::AllowedEoaType;
+use gw_types::prelude::{Pack as GWPack, *};
+use gw_types::{
+    bytes::Bytes,
+    core::ScriptHashType,
+    packed::{
+        AllowedTypeHash, Byte32Vec, CustodianLockArgs, DepositLockArgs, DepositPolicy,
+        DepositRequest, RollupConfig, Script,
+    },
+};
+
+const DEPOSIT_CAPACITY_TOO_LOW_ERROR: i8 = 46;
+const DEPOSIT_SUDT_NOT_ALLOWED_ERROR: i8 = 47;
+const TOO_MANY_DEPOSITS_ERROR: i8 = 48;
+
+const DEPOSIT_CAPACITY: u64 = 1000_00000000u64;
+const DEPOSIT_SUDT_AMOUNT: u128 = 1000;
+
+/// Submit a block which contains `deposit_count` deposits under the `deposit_policy`,
+/// the deposit cells carry the sUDT of `sudt_type_args` if any
+async fn verify_deposit_policy(
+    deposit_policy: DepositPolicy,
+    deposit_count: u8,
+    sudt_type_args: Option<&[u8]>,
+) -> Result<ckb_types::core::Cycle, ckb_error::Error> {
+    let rollup = RollupFixture::default();
+    // rollup lock & config
+    let stake_lock_type = build_type_id_script(b"stake_lock_type_id");
+    let stake_script_type_hash: [u8; 32] = stake_lock_type.calc_script_hash().unpack();
+    let deposit_lock_type = build_type_id_script(b"deposit_lock_type_id");
+    let deposit_script_type_hash: [u8; 32] = deposit_lock_type.calc_script_hash().unpack();
+    let custodian_lock_type = build_type_id_script(b"custodian_lock_type_id");
+    let custodian_script_type_hash: [u8; 32] = custodian_lock_type.calc_script_hash().unpack();
+    let l1_sudt_type = build_type_id_script(b"l1_sudt_type_id");
+    let l1_sudt_script_type_hash: [u8; 32] = l1_sudt_type.calc_script_hash().unpack();
+    let rollup_config = RollupConfig::new_builder()
+        .stake_script_type_hash(Pack::pack(&stake_script_type_hash))
+        .deposit_script_type_hash(Pack::pack(&deposit_script_type_hash))
+        .custodian_script_type_hash(Pack::pack(&custodian_script_type_hash))
+        .l1_sudt_script_type_hash(Pack::pack(&l1_sudt_script_type_hash))
+        .finality_blocks(Pack::pack(&10u64))
+        .allowed_eoa_type_hashes(PackVec::pack(vec![AllowedTypeHash::new(
+            AllowedEoaType::Eth,
+            *ALWAYS_SUCCESS_CODE_HASH,
+        )]))
+        .deposit_policy(deposit_policy)
+        .build();
+    // setup chain
+    let chain = setup_chain(rollup.rollup_type_script.clone(), rollup_config.clone()).await;
+    // deploy scripts, the l1 sUDT type is always success
+    let param = CellContextParam {
+        stake_lock_type,
+        deposit_lock_type,
+        custodian_lock_type,
+        always_success_type: l1_sudt_type,
+        ..Default::default()
+    };
+    let mut ctx = CellContext::new(&rollup_config, param);
+    let global_state = chain
+        .local_state()
+        .last_global_state()
+        .as_builder()
+        .version(1u8.into())
+        .build();
+    // deposits
+    let eth_registry_id = gw_common::builtins::ETH_REGISTRY_ACCOUNT_ID;
+    let deposit_lock_args: Vec<_> = (0..deposit_count)
+        .map(|i| {
+            let account_script = {
+                let mut args = rollup.rollup_type_hash().to_vec();
+                args.extend_from_slice(&[i; 20]);
+                Script::new_builder()
+                    .code_hash(Pack::pack(&ALWAYS_SUCCESS_CODE_HASH.clone()))
+                    .hash_type(ScriptHashType::Type.into())
+                    .args(Pack::pack(&Bytes::from(args)))
+                    .build()
+            };
+            DepositLockArgs::new_builder()
+                .owner_lock_hash(Pack::pack(&[0u8; 32]))
+                .layer2_lock(account_script)
+                .cancel_timeout(Pack::pack(&0))
+                .registry_id(Pack::pack(&eth_registry_id))
+                .build()
+        })
+        .collect();
+    let deposit_requests: Vec<_> = deposit_lock_args
+        .iter()
+        .map(|args| {
+            DepositRequest::new_builder()
+                .capacity(Pack::pack(&DEPOSIT_CAPACITY))
+                .script(args.layer2_lock())
+                .registry_id(Pack::pack(&eth_registry_id))
+                .build()
+        })
+        .collect();
+    // submit a new block
+    let block_result = {
+        let mem_pool = chain.mem_pool().as_ref().unwrap();
+        let mut mem_pool = mem_pool.lock().await;
+        construct_block(&chain, &mut mem_pool, deposit_requests)
+            .await
+            .unwrap()
+    };
+    let block_number = block_result.block.raw().number();
+    let block_hash = block_result.block.raw().hash();
+    let sudt_type = sudt_type_args.map(|args| {
+        ckb_types::packed::Script::new_builder()
+            .code_hash(CKBPack::pack(&l1_sudt_script_type_hash))
+            .hash_type(ScriptHashType::Type.into())
+            .args(CKBPack::pack(&Bytes::from(args.to_vec())))
+            .build()
+    });
+    let deposit_data = match sudt_type {
+        Some(_) => Bytes::from(DEPOSIT_SUDT_AMOUNT.to_le_bytes().to_vec()),
+        None => Bytes::default(),
+    };
+    let input_deposit_cells: Vec<_> = deposit_lock_args
+        .iter()
+        .map(|args| {
+            let cell = build_rollup_locked_cell(
+                &rollup.rollup_type_hash(),
+                &deposit_script_type_hash,
+                DEPOSIT_CAPACITY,
+                args.as_bytes(),
+            )
+            .as_builder()
+            .type_(CKBPack::pack(&sudt_type.clone()))
+            .build();
+            let out_point = ctx.insert_cell(cell, deposit_data.clone());
+            CellInput::new_builder().previous_output(out_point).build()
+        })
+        .collect();
+    let output_custodian_cells: Vec<_> = deposit_lock_args
+        .into_iter()
+        .map(|args| {
+            let custodian_args = CustodianLockArgs::new_builder()
+                .deposit_lock_args(args)
+                .deposit_block_hash(Pack::pack(&block_hash))
+                .deposit_block_number(block_number.clone())
+                .build();
+            build_rollup_locked_cell(
+                &rollup.rollup_type_hash(),
+                &custodian_script_type_hash,
+                DEPOSIT_CAPACITY,
+                custodian_args.as_bytes(),
+            )
+        })
+        .collect();
+    // verify submit block
+    let tx = rollup
+        .build_submit_block_tx(
+            &mut ctx,
+            &stake_script_type_hash,
+            &global_state,
+            block_result,
+        )
+        .inputs(input_deposit_cells)
+        .outputs(output_custodian_cells.clone())
+        .outputs_data(
+            (0..output_custodian_cells.len())
+                .into_iter()
+                .map(|_| CKBPack::pack(&Bytes::new())),
+        )
+        .cell_dep(ctx.deposit_lock_dep.clone())
+        .cell_dep(ctx.custodian_lock_dep.clone())
+        .build();
+    ctx.verify_tx(tx)
+}
+
+#[tokio::test]
+async fn test_deposit_policy() {
+    let policy = DepositPolicy::new_builder()
+        .min_deposit_capacity(GWPack::pack(&DEPOSIT_CAPACITY))
+        .allowed_sudt_script_hashes(
+            Byte32Vec::new_builder()
+                .push(GWPack::pack(&[42u8; 32]))
+                .build(),
+        )
+        .max_deposits_per_block(GWPack::pack(&2u32))
+        .build();
+    verify_deposit_policy(policy, 2, None)
+        .await
+        .expect("return success");
+}
+
+#[tokio::test]
+async fn test_deposit_capacity_too_low() {
+    let policy = DepositPolicy::new_builder()
+        .min_deposit_capacity(GWPack::pack(&(DEPOSIT_CAPACITY + 1)))
+        .build();
+    let err = verify_deposit_policy(policy, 1, None).await.unwrap_err();
+    assert_error_eq!(
+        err,
+        expected_state_validator_error(DEPOSIT_CAPACITY_TOO_LOW_ERROR)
+    );
+}
+
+#[tokio::test]
+async fn test_too_many_deposits() {
+    let policy = DepositPolicy::new_builder()
+        .max_deposits_per_block(GWPack::pack(&1u32))
+        .build();
+    let err = verify_deposit_policy(policy, 2, None).await.unwrap_err();
+    assert_error_eq!(err, expected_state_validator_error(TOO_MANY_DEPOSITS_ERROR));
+}
+
+#[tokio::test]
+async fn test_deposit_sudt_not_allowed() {
+    let policy = DepositPolicy::new_builder()
+        .allowed_sudt_script_hashes(
+            Byte32Vec::new_builder()
+                .push(GWPack::pack(&[42u8; 32]))
+                .build(),
+        )
+        .build();
+    let err = verify_deposit_policy(policy, 1, Some(b"foreign_sudt"))
+        .await
+        .unwrap_err();
+    assert_error_eq!(
+        err,
+        expected_state_validator_error(DEPOSIT_SUDT_NOT_ALLOWED_ERROR)
+    );
+}
@@ -201,6 +201,7 @@ async fn test_enter_challenge() {
                     .block_hash(Pack::pack(&challenged_block.hash()))
                     .build(),
             )
+            .reward_weight(Pack::pack(&1u32))
             .build();
         build_rollup_locked_cell(
             &rollup_type_script.hash(),
@@ -404,6 +405,7 @@ async fn test_enter_challenge_finalized_block() {
                     .block_hash(Pack::pack(&challenged_block.hash()))
                     .build(),
             )
+            .reward_weight(Pack::pack(&1u32))
             .build();
         build_rollup_locked_cell(
             &rollup_type_script.hash(),
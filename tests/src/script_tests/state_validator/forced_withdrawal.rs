@@ -0,0 +1,546 @@
+#![allow(clippy::mutable_key_type)]
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use crate::script_tests::utils::init_env_log;
+use crate::script_tests::utils::layer1::{
+    always_success_script, build_simple_tx_with_out_point,
+    build_simple_tx_with_out_point_and_since, random_out_point, since_timestamp,
+};
+use crate::script_tests::utils::rollup::{
+    build_always_success_cell, build_rollup_locked_cell, build_type_id_script,
+    calculate_state_validator_type_id, expected_state_validator_error, CellContext,
+    CellContextParam,
+};
+use crate::testing_tool::chain::{
+    apply_block_result, construct_block, setup_chain_with_account_lock_manage,
+};
+use crate::testing_tool::programs::STATE_VALIDATOR_CODE_HASH;
+use ckb_error::assert_error_eq;
+use ckb_types::{
+    packed::{CellInput, CellOutput, OutPoint},
+    prelude::{Pack as CKBPack, Unpack as CKBUnpack},
+};
+use gw_chain::chain::Chain;
+use gw_common::builtins::{CKB_SUDT_ACCOUNT_ID, ETH_REGISTRY_ACCOUNT_ID};
+use gw_common::state::State;
+use gw_common::H256;
+use gw_generator::account_lock_manage::{
+    always_success::AlwaysSuccess,
+    eip712::{
+        traits::EIP712Encode,
+        types::{EIP712Domain, Withdrawal},
+    },
+    AccountLockManage,
+};
+use gw_store::mem_pool_state::{MemPoolState, MemStore};
+use gw_types::prelude::{Unpack as GWUnpack, *};
+use gw_types::{
+    bytes::Bytes,
+    core::{AllowedEoaType, ChallengeTargetType, ScriptHashType, SigningType, Status},
+    packed::{
+        AllowedTypeHash, ChallengeLockArgs, ChallengeTarget, DepositRequest,
+        ForcedWithdrawalHaltWitness, ForcedWithdrawalLockArgs, ForcedWithdrawalRejection,
+        GlobalState, RawWithdrawalRequest, RollupAction, RollupActionUnion, RollupConfig,
+        RollupForceHalt, RollupSubmitBlock, Script, StakeLockArgs, WithdrawalRequest,
+    },
+};
+
+const INVALID_SINCE_ERROR: i8 = 6;
+const ACCOUNT_LOCK_CELL_NOT_FOUND_ERROR: i8 = 37;
+const INVALID_FORCED_WITHDRAWAL_CELL_ERROR: i8 = 54;
+
+const TIMEOUT_BLOCKS: u64 = 100;
+const BOND_CAPACITY: u64 = 500_00000000u64;
+const DEPOSIT_CAPACITY: u64 = 1000_00000000u64;
+
+struct ForcedWithdrawalContext {
+    chain: Chain,
+    ctx: CellContext,
+    rollup_type_script: Script,
+    rollup_cell: CellOutput,
+    input_out_point: OutPoint,
+    sender_script: Script,
+    owner_lock: Script,
+    stake_script_type_hash: [u8; 32],
+    challenge_script_type_hash: [u8; 32],
+    forced_withdrawal_script_type_hash: [u8; 32],
+}
+
+/// Setup a chain which deposited `DEPOSIT_CAPACITY` to the sender in the tip block
+async fn setup_sender() -> ForcedWithdrawalContext {
+    init_env_log();
+    let input_out_point = random_out_point();
+    let type_id = calculate_state_validator_type_id(input_out_point.clone());
+    let rollup_type_script = {
+        Script::new_builder()
+            .code_hash(Pack::pack(&*STATE_VALIDATOR_CODE_HASH))
+            .hash_type(ScriptHashType::Data.into())
+            .args(Pack::pack(&Bytes::from(type_id.to_vec())))
+            .build()
+    };
+    // rollup lock & config
+    let stake_lock_type = build_type_id_script(b"stake_lock_type_id");
+    let stake_script_type_hash: [u8; 32] = stake_lock_type.calc_script_hash().unpack();
+    let challenge_lock_type = build_type_id_script(b"challenge_lock_type_id");
+    let challenge_script_type_hash: [u8; 32] = challenge_lock_type.calc_script_hash().unpack();
+    let forced_withdrawal_lock_type = build_type_id_script(b"forced_withdrawal_lock_type_id");
+    let forced_withdrawal_script_type_hash: [u8; 32] =
+        forced_withdrawal_lock_type.calc_script_hash().unpack();
+    let eoa_lock_type = build_type_id_script(b"eoa_lock_type_id");
+    let eoa_lock_type_hash: [u8; 32] = eoa_lock_type.calc_script_hash().unpack();
+    let rollup_config = RollupConfig::new_builder()
+        .stake_script_type_hash(Pack::pack(&stake_script_type_hash))
+        .challenge_script_type_hash(Pack::pack(&challenge_script_type_hash))
+        .forced_withdrawal_script_type_hash(Pack::pack(&forced_withdrawal_script_type_hash))
+        .forced_withdrawal_timeout_l1_blocks(Pack::pack(&TIMEOUT_BLOCKS))
+        .forced_withdrawal_bond_capacity(Pack::pack(&BOND_CAPACITY))
+        .allowed_eoa_type_hashes(PackVec::pack(vec![AllowedTypeHash::new(
+            AllowedEoaType::Eth,
+            eoa_lock_type_hash,
+        )]))
+        .build();
+    // setup chain
+    let mut account_lock_manage = AccountLockManage::default();
+    account_lock_manage.register_lock_algorithm(eoa_lock_type_hash.into(), Box::new(AlwaysSuccess));
+    let mut chain = setup_chain_with_account_lock_manage(
+        rollup_type_script.clone(),
+        rollup_config.clone(),
+        account_lock_manage,
+    )
+    .await;
+    chain.complete_initial_syncing().await.unwrap();
+    // create a rollup cell
+    let rollup_cell = build_always_success_cell(
+        1000_00000000u64,
+        Some(ckb_types::packed::Script::new_unchecked(
+            rollup_type_script.as_bytes(),
+        )),
+    );
+    // deposit to the sender
+    let sender_script = {
+        let mut args = rollup_type_script.hash().to_vec();
+        args.extend_from_slice(&[1u8; 20]);
+        Script::new_builder()
+            .code_hash(Pack::pack(&eoa_lock_type_hash))
+            .hash_type(ScriptHashType::Type.into())
+            .args(Pack::pack(&Bytes::from(args)))
+            .build()
+    };
+    let deposit_requests = vec![DepositRequest::new_builder()
+        .capacity(Pack::pack(&DEPOSIT_CAPACITY))
+        .script(sender_script.clone())
+        .registry_id(Pack::pack(&ETH_REGISTRY_ACCOUNT_ID))
+        .build()];
+    let produce_block_result = {
+        let mem_pool = chain.mem_pool().as_ref().unwrap();
+        let mut mem_pool = mem_pool.lock().await;
+        construct_block(&chain, &mut mem_pool, deposit_requests.clone())
+            .await
+            .unwrap()
+    };
+    apply_block_result(
+        &mut chain,
+        gw_types::packed::CellOutput::new_unchecked(rollup_cell.as_bytes()),
+        produce_block_result,
+        deposit_requests,
+        HashSet::new(),
+    )
+    .await;
+    // deploy scripts
+    let param = CellContextParam {
+        stake_lock_type,
+        challenge_lock_type,
+        forced_withdrawal_lock_type,
+        eoa_lock_type,
+        ..Default::default()
+    };
+    let ctx = CellContext::new(&rollup_config, param);
+    let owner_lock = {
+        let lock = always_success_script()
+            .as_builder()
+            .args(CKBPack::pack(&Bytes::from(b"owner".to_vec())))
+            .build();
+        Script::new_unchecked(lock.as_bytes())
+    };
+    ForcedWithdrawalContext {
+        chain,
+        ctx,
+        rollup_type_script,
+        rollup_cell,
+        input_out_point,
+        sender_script,
+        owner_lock,
+        stake_script_type_hash,
+        challenge_script_type_hash,
+        forced_withdrawal_script_type_hash,
+    }
+}
+
+impl ForcedWithdrawalContext {
+    fn build_request(&self, capacity: u64) -> WithdrawalRequest {
+        let raw = RawWithdrawalRequest::new_builder()
+            .nonce(Pack::pack(&0u32))
+            .capacity(Pack::pack(&capacity))
+            .account_script_hash(Pack::pack(&self.sender_script.hash()))
+            .owner_lock_hash(Pack::pack(&self.owner_lock.hash()))
+            .registry_id(Pack::pack(&ETH_REGISTRY_ACCOUNT_ID))
+            .build();
+        WithdrawalRequest::new_builder().raw(raw).build()
+    }
+
+    /// Insert a forced withdrawal cell which has been alive for `alive_blocks`
+    fn insert_forced_withdrawal_cell(
+        &mut self,
+        cell_capacity: u64,
+        request: &WithdrawalRequest,
+        alive_blocks: u64,
+    ) -> CellInput {
+        let lock_args = ForcedWithdrawalLockArgs::new_builder()
+            .owner_lock_hash(Pack::pack(&self.owner_lock.hash()))
+            .build();
+        let cell = build_rollup_locked_cell(
+            &self.rollup_type_script.hash(),
+            &self.forced_withdrawal_script_type_hash,
+            cell_capacity,
+            lock_args.as_bytes(),
+        );
+        let out_point = self.ctx.insert_cell(cell, request.as_bytes());
+        let since: u64 = (1 << 63) | alive_blocks;
+        CellInput::new_builder()
+            .previous_output(out_point)
+            .since(CKBPack::pack(&since))
+            .build()
+    }
+
+    /// Build the kv state which the sender's request is processed on, and its proof on the tip state
+    fn build_sender_kv_state(&self) -> (Vec<(H256, H256)>, Bytes) {
+        let state = {
+            let mem_store = MemStore::new(self.chain.store().get_snapshot());
+            MemPoolState::new(Arc::new(mem_store), true)
+        };
+        let snap = state.load();
+        let mut tree = snap.state().unwrap();
+        tree.tracker_mut().enable();
+        let sender_script_hash: H256 = self.sender_script.hash().into();
+        let sender_id = tree
+            .get_account_id_by_script_hash(&sender_script_hash)
+            .unwrap()
+            .unwrap();
+        let address = tree
+            .get_registry_address_by_script_hash(ETH_REGISTRY_ACCOUNT_ID, &sender_script_hash)
+            .unwrap()
+            .unwrap();
+        tree.get_nonce(sender_id).unwrap();
+        let ckb_sudt_script_hash = tree.get_script_hash(CKB_SUDT_ACCOUNT_ID).unwrap();
+        tree.get_account_id_by_script_hash(&ckb_sudt_script_hash)
+            .unwrap();
+        tree.get_sudt_balance(CKB_SUDT_ACCOUNT_ID, &address)
+            .unwrap();
+        let touched_keys: Vec<H256> = {
+            let keys = tree.tracker_mut().touched_keys().unwrap();
+            let unlock = keys.lock().unwrap();
+            unlock.clone().into_iter().collect()
+        };
+        let kv_state = touched_keys
+            .iter()
+            .map(|k| {
+                let v = tree.get_raw(k).unwrap();
+                (*k, v)
+            })
+            .collect::<Vec<(H256, H256)>>();
+        let kv_state_proof: Bytes = {
+            let db = self.chain.store().begin_transaction();
+            let account_smt = db.account_smt().unwrap();
+            account_smt
+                .merkle_proof(touched_keys)
+                .unwrap()
+                .compile(kv_state.clone())
+                .unwrap()
+                .0
+                .into()
+        };
+        (kv_state, kv_state_proof)
+    }
+
+    /// Insert the sender's account lock cell which signs the request
+    fn insert_signature_cell(&mut self, request: &WithdrawalRequest) -> CellInput {
+        let address = {
+            let state = {
+                let mem_store = MemStore::new(self.chain.store().get_snapshot());
+                MemPoolState::new(Arc::new(mem_store), true)
+            };
+            let snap = state.load();
+            let tree = snap.state().unwrap();
+            tree.get_registry_address_by_script_hash(
+                ETH_REGISTRY_ACCOUNT_ID,
+                &self.sender_script.hash().into(),
+            )
+            .unwrap()
+            .unwrap()
+        };
+        let message = {
+            let withdrawal =
+                Withdrawal::from_raw(request.raw(), self.owner_lock.clone(), address).unwrap();
+            let domain = EIP712Domain {
+                name: "Godwoken".to_string(),
+                version: "1".to_string(),
+                chain_id: GWUnpack::unpack(&request.raw().chain_id()),
+                verifying_contract: None,
+                salt: None,
+            };
+            withdrawal.eip712_message(domain.hash_struct())
+        };
+        let cell = CellOutput::new_builder()
+            .lock(ckb_types::packed::Script::new_unchecked(
+                self.sender_script.as_bytes(),
+            ))
+            .capacity(CKBPack::pack(&42u64))
+            .build();
+        let mut buf = vec![42u8; 32];
+        buf.push(SigningType::Raw.into());
+        buf.extend_from_slice(&message);
+        let out_point = self.ctx.insert_cell(cell, Bytes::from(buf));
+        CellInput::new_builder().previous_output(out_point).build()
+    }
+}
+
+/// Halt the rollup by a forced withdrawal cell which has been alive for `alive_blocks`,
+/// the request of the cell is signed by the sender if `signed`
+async fn verify_force_halt(
+    alive_blocks: u64,
+    cell_capacity: u64,
+    signed: bool,
+) -> Result<ckb_types::core::Cycle, ckb_error::Error> {
+    let mut context = setup_sender().await;
+    let request = context.build_request(DEPOSIT_CAPACITY);
+    let forced_withdrawal_input =
+        context.insert_forced_withdrawal_cell(cell_capacity, &request, alive_blocks);
+    let halt_witness = {
+        let (kv_state, kv_state_proof) = context.build_sender_kv_state();
+        let witness = ForcedWithdrawalHaltWitness::new_builder()
+            .owner_lock(context.owner_lock.clone())
+            .kv_state(kv_state.pack())
+            .kv_state_proof(Pack::pack(&kv_state_proof))
+            .build();
+        ckb_types::packed::WitnessArgs::new_builder()
+            .lock(CKBPack::pack(&Some(witness.as_bytes())))
+            .build()
+    };
+    // the request becomes a challenge of the tip block
+    let global_state = context.chain.local_state().last_global_state().clone();
+    let output_challenge_cell = {
+        let lock_args = ChallengeLockArgs::new_builder()
+            .target(
+                ChallengeTarget::new_builder()
+                    .block_hash(global_state.tip_block_hash())
+                    .target_index(Pack::pack(&0u32))
+                    .target_type(ChallengeTargetType::ForcedWithdrawal.into())
+                    .build(),
+            )
+            .rewards_receiver_lock(context.owner_lock.clone())
+            .build();
+        build_rollup_locked_cell(
+            &context.rollup_type_script.hash(),
+            &context.challenge_script_type_hash,
+            cell_capacity,
+            lock_args.as_bytes(),
+        )
+    };
+    let post_global_state = global_state
+        .clone()
+        .as_builder()
+        .status(Status::Halting.into())
+        .build();
+    let witness = {
+        let rollup_action = RollupAction::new_builder()
+            .set(RollupActionUnion::RollupForceHalt(
+                RollupForceHalt::default(),
+            ))
+            .build();
+        ckb_types::packed::WitnessArgs::new_builder()
+            .output_type(CKBPack::pack(&Some(rollup_action.as_bytes())))
+            .build()
+    };
+    let mut tx = build_simple_tx_with_out_point(
+        &mut context.ctx.inner,
+        (context.rollup_cell.clone(), global_state.as_bytes()),
+        context.input_out_point.clone(),
+        (context.rollup_cell.clone(), post_global_state.as_bytes()),
+    )
+    .as_advanced_builder()
+    .witness(CKBPack::pack(&witness.as_bytes()))
+    .input(forced_withdrawal_input)
+    .witness(CKBPack::pack(&halt_witness.as_bytes()))
+    .output(output_challenge_cell)
+    .output_data(CKBPack::pack(&request.as_bytes()))
+    .cell_dep(context.ctx.forced_withdrawal_lock_dep.clone())
+    .cell_dep(context.ctx.always_success_dep.clone())
+    .cell_dep(context.ctx.state_validator_dep.clone())
+    .cell_dep(context.ctx.rollup_config_dep.clone())
+    .cell_dep(context.ctx.eoa_lock_dep.clone());
+    if signed {
+        tx = tx
+            .input(context.insert_signature_cell(&request))
+            .witness(Default::default());
+    }
+    context.ctx.verify_tx(tx.build())
+}
+
+#[tokio::test]
+async fn test_force_halt() {
+    verify_force_halt(TIMEOUT_BLOCKS, BOND_CAPACITY, true)
+        .await
+        .expect("return success");
+}
+
+#[tokio::test]
+async fn test_force_halt_before_timeout() {
+    let err = verify_force_halt(TIMEOUT_BLOCKS - 1, BOND_CAPACITY, true)
+        .await
+        .unwrap_err();
+    assert_error_eq!(err, expected_state_validator_error(INVALID_SINCE_ERROR));
+}
+
+#[tokio::test]
+async fn test_force_halt_without_bond() {
+    let err = verify_force_halt(TIMEOUT_BLOCKS, BOND_CAPACITY - 1, true)
+        .await
+        .unwrap_err();
+    assert_error_eq!(
+        err,
+        expected_state_validator_error(INVALID_FORCED_WITHDRAWAL_CELL_ERROR)
+    );
+}
+
+#[tokio::test]
+async fn test_force_halt_without_signature() {
+    let err = verify_force_halt(TIMEOUT_BLOCKS, BOND_CAPACITY, false)
+        .await
+        .unwrap_err();
+    assert_error_eq!(
+        err,
+        expected_state_validator_error(ACCOUNT_LOCK_CELL_NOT_FOUND_ERROR)
+    );
+}
+
+/// Submit an empty block which rejects the forced withdrawal request of `request_capacity`
+async fn verify_reject_forced_withdrawal(
+    request_capacity: u64,
+) -> Result<ckb_types::core::Cycle, ckb_error::Error> {
+    let mut context = setup_sender().await;
+    let request = context.build_request(request_capacity);
+    let forced_withdrawal_input = context.insert_forced_withdrawal_cell(BOND_CAPACITY, &request, 0);
+    let rejection_witness = {
+        let (kv_state, kv_state_proof) = context.build_sender_kv_state();
+        let rejection = ForcedWithdrawalRejection::new_builder()
+            .kv_state(kv_state.pack())
+            .kv_state_proof(Pack::pack(&kv_state_proof))
+            .build();
+        ckb_types::packed::WitnessArgs::new_builder()
+            .lock(CKBPack::pack(&Some(rejection.as_bytes())))
+            .build()
+    };
+    // submit an empty block
+    let block_result = {
+        let mem_pool = context.chain.mem_pool().as_ref().unwrap();
+        let mut mem_pool = mem_pool.lock().await;
+        construct_block(&context.chain, &mut mem_pool, Vec::default())
+            .await
+            .unwrap()
+    };
+    let block_number: u64 = GWUnpack::unpack(&block_result.block.raw().number());
+    // build stake input and output
+    let stake_capacity = 10000_00000000u64;
+    let input_stake_cell = {
+        let cell = build_rollup_locked_cell(
+            &context.rollup_type_script.hash(),
+            &context.stake_script_type_hash,
+            stake_capacity,
+            StakeLockArgs::default().as_bytes(),
+        );
+        let out_point = context.ctx.insert_cell(cell, Bytes::default());
+        CellInput::new_builder().previous_output(out_point).build()
+    };
+    let output_stake_cell = {
+        let lock_args = StakeLockArgs::new_builder()
+            .stake_block_number(Pack::pack(&block_number))
+            .build();
+        build_rollup_locked_cell(
+            &context.rollup_type_script.hash(),
+            &context.stake_script_type_hash,
+            stake_capacity,
+            lock_args.as_bytes(),
+        )
+    };
+    let global_state: GlobalState = context.chain.local_state().last_global_state().clone();
+    let initial_rollup_cell_data = global_state
+        .as_builder()
+        .version(1u8.into())
+        .build()
+        .as_bytes();
+    let tip_block_timestamp = block_result.block.raw().timestamp();
+    let rollup_cell_data = block_result
+        .global_state
+        .as_builder()
+        .tip_block_timestamp(tip_block_timestamp.clone())
+        .version(1u8.into())
+        .build()
+        .as_bytes();
+    let witness = {
+        let rollup_action = RollupAction::new_builder()
+            .set(RollupActionUnion::RollupSubmitBlock(
+                RollupSubmitBlock::new_builder()
+                    .block(block_result.block)
+                    .build(),
+            ))
+            .build();
+        ckb_types::packed::WitnessArgs::new_builder()
+            .output_type(CKBPack::pack(&Some(rollup_action.as_bytes())))
+            .build()
+    };
+    let tx = build_simple_tx_with_out_point_and_since(
+        &mut context.ctx.inner,
+        (context.rollup_cell.clone(), initial_rollup_cell_data),
+        (
+            context.input_out_point.clone(),
+            since_timestamp(GWUnpack::unpack(&tip_block_timestamp)),
+        ),
+        (context.rollup_cell.clone(), rollup_cell_data),
+    )
+    .as_advanced_builder()
+    .witness(CKBPack::pack(&witness.as_bytes()))
+    .input(input_stake_cell)
+    .witness(Default::default())
+    .output(output_stake_cell)
+    .output_data(CKBPack::pack(&Bytes::default()))
+    .input(forced_withdrawal_input)
+    .witness(CKBPack::pack(&rejection_witness.as_bytes()))
+    .cell_dep(context.ctx.stake_lock_dep.clone())
+    .cell_dep(context.ctx.forced_withdrawal_lock_dep.clone())
+    .cell_dep(context.ctx.always_success_dep.clone())
+    .cell_dep(context.ctx.state_validator_dep.clone())
+    .cell_dep(context.ctx.rollup_config_dep.clone())
+    .build();
+    context.ctx.verify_tx(tx)
+}
+
+#[tokio::test]
+async fn test_reject_invalid_forced_withdrawal() {
+    // the request withdraws more than the balance of the sender
+    verify_reject_forced_withdrawal(DEPOSIT_CAPACITY + 1)
+        .await
+        .expect("return success");
+}
+
+#[tokio::test]
+async fn test_reject_valid_forced_withdrawal() {
+    let err = verify_reject_forced_withdrawal(DEPOSIT_CAPACITY)
+        .await
+        .unwrap_err();
+    assert_error_eq!(
+        err,
+        expected_state_validator_error(INVALID_FORCED_WITHDRAWAL_CELL_ERROR)
+    );
+}
@@ -1,4 +1,21 @@
+mod abandon;
+mod batch_deposit;
 mod cancel_challenge;
+mod challenge_queue;
+mod dao_custodians;
+mod deposit_call;
+mod deposit_policy;
 mod enter_challenge;
+mod forced_withdrawal;
+mod pause;
+mod producer_schedule;
+mod proof_of_reserves;
+mod prune_reverted_blocks;
+mod rebalance_custodians;
+mod registry;
 mod revert;
 mod submit_block;
+mod submit_blocks;
+mod time_finality;
+mod upgrade_config;
+mod withdrawal_limit;
@@ -0,0 +1,127 @@
+use crate::script_tests::utils::init_env_log;
+use crate::script_tests::utils::layer1::always_success_script;
+use crate::script_tests::utils::rollup::{
+    expected_state_validator_error, CellContext, CellContextParam, RollupFixture,
+};
+use ckb_error::assert_error_eq;
+use ckb_types::{
+    packed::{CellInput, CellOutput},
+    prelude::{Pack as CKBPack, Unpack as CKBUnpack},
+};
+use gw_types::{
+    bytes::Bytes,
+    core::Status,
+    packed::{GlobalState, RollupActionUnion, RollupConfig, RollupSetPaused},
+    prelude::*,
+};
+
+const OWNER_CELL_NOT_FOUND_ERROR: i8 = 8;
+const INVALID_STATUS_ERROR: i8 = 21;
+
+fn build_lock(args: &[u8]) -> ckb_types::packed::Script {
+    always_success_script()
+        .as_builder()
+        .args(CKBPack::pack(&Bytes::from(args.to_vec())))
+        .build()
+}
+
+fn verify_set_paused(
+    paused: bool,
+    prev_status: Status,
+    post_status: Status,
+    authorizer_lock_args: &[u8],
+) -> Result<ckb_types::core::Cycle, ckb_error::Error> {
+    init_env_log();
+    let rollup = RollupFixture::default();
+    // rollup config & governance & guardian
+    let governance_lock_hash: [u8; 32] = build_lock(b"governance_lock").calc_script_hash().unpack();
+    let guardian_lock_hash: [u8; 32] = build_lock(b"guardian_lock").calc_script_hash().unpack();
+    let rollup_config = RollupConfig::new_builder()
+        .governance_lock_hash(Pack::pack(&governance_lock_hash))
+        .guardian_lock_hash(Pack::pack(&guardian_lock_hash))
+        .build();
+    let rollup_config_hash: [u8; 32] =
+        CellOutput::calc_data_hash(&rollup_config.as_bytes()).unpack();
+    // deploy scripts
+    let mut ctx = CellContext::new(&rollup_config, CellContextParam::default());
+    let authorizer_input = {
+        let cell = CellOutput::new_builder()
+            .lock(build_lock(authorizer_lock_args))
+            .capacity(CKBPack::pack(&1000_00000000u64))
+            .build();
+        let out_point = ctx.insert_cell(cell, Bytes::new());
+        CellInput::new_builder().previous_output(out_point).build()
+    };
+    let global_state = {
+        let status: u8 = prev_status.into();
+        GlobalState::new_builder()
+            .rollup_config_hash(Pack::pack(&rollup_config_hash))
+            .status(status.into())
+            .version(1u8.into())
+            .build()
+    };
+    let post_global_state = {
+        let status: u8 = post_status.into();
+        global_state
+            .clone()
+            .as_builder()
+            .status(status.into())
+            .build()
+    };
+    let action = RollupActionUnion::RollupSetPaused(
+        RollupSetPaused::new_builder()
+            .paused((paused as u8).into())
+            .build(),
+    );
+    let tx = rollup
+        .build_action_tx(
+            &mut ctx,
+            &global_state,
+            &post_global_state,
+            Default::default(),
+            action,
+        )
+        .input(authorizer_input)
+        .build();
+    ctx.verify_tx(tx)
+}
+
+#[test]
+fn test_pause_rollup() {
+    verify_set_paused(true, Status::Running, Status::Paused, b"governance_lock")
+        .expect("return success");
+    verify_set_paused(true, Status::Running, Status::Paused, b"guardian_lock")
+        .expect("return success");
+}
+
+#[test]
+fn test_resume_rollup() {
+    verify_set_paused(false, Status::Paused, Status::Running, b"governance_lock")
+        .expect("return success");
+}
+
+#[test]
+fn test_resume_rollup_by_guardian() {
+    let err =
+        verify_set_paused(false, Status::Paused, Status::Running, b"guardian_lock").unwrap_err();
+    assert_error_eq!(
+        err,
+        expected_state_validator_error(OWNER_CELL_NOT_FOUND_ERROR)
+    );
+}
+
+#[test]
+fn test_pause_rollup_without_authorization() {
+    let err = verify_set_paused(true, Status::Running, Status::Paused, b"someone").unwrap_err();
+    assert_error_eq!(
+        err,
+        expected_state_validator_error(OWNER_CELL_NOT_FOUND_ERROR)
+    );
+}
+
+#[test]
+fn test_pause_halting_rollup() {
+    let err =
+        verify_set_paused(true, Status::Halting, Status::Paused, b"governance_lock").unwrap_err();
+    assert_error_eq!(err, expected_state_validator_error(INVALID_STATUS_ERROR));
+}
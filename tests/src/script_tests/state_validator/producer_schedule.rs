@@ -0,0 +1,151 @@
+use crate::script_tests::utils::layer1::since_timestamp;
+use crate::script_tests::utils::rollup::{
+    build_always_success_cell, build_type_id_script, expected_state_validator_error, CellContext,
+    CellContextParam, RollupFixture,
+};
+use crate::testing_tool::chain::{construct_block_from_timestamp, setup_chain};
+use crate::testing_tool::programs::ALWAYS_SUCCESS_CODE_HASH;
+use ckb_error::assert_error_eq;
+use ckb_types::{packed::CellDep, prelude::Unpack};
+use gw_types::core::AllowedEoaType;
+use gw_types::packed::{
+    AllowedTypeHash, Byte32Vec, ProducerRegistry, ProducerScheduleConfig, RollupConfig,
+};
+use gw_types::prelude::{Pack as GWPack, *};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const INVALID_BLOCK_PRODUCER_ERROR: i8 = 52;
+
+const SLOT_DURATION: u64 = 10_000;
+
+/// Submit a block timestamped in the `slot` of the registry at the layer-1 time `layer1_delay`
+/// milliseconds after the block timestamp, the block producer is always the first producer
+async fn verify_producer_schedule(
+    slot: u64,
+    layer1_delay: u64,
+    fallback_timeout: u64,
+) -> Result<ckb_types::core::Cycle, ckb_error::Error> {
+    let rollup = RollupFixture::default();
+    // rollup lock & config
+    let stake_lock_type = build_type_id_script(b"stake_lock_type_id");
+    let stake_script_type_hash: [u8; 32] = stake_lock_type.calc_script_hash().unpack();
+    let registry_type = build_type_id_script(b"producer_registry_type_id");
+    let registry_type_hash: [u8; 32] = registry_type.calc_script_hash().unpack();
+    let producer_schedule = ProducerScheduleConfig::new_builder()
+        .registry_type_hash(Pack::pack(&registry_type_hash))
+        .slot_duration(Pack::pack(&SLOT_DURATION))
+        .fallback_timeout(Pack::pack(&fallback_timeout))
+        .build();
+    let rollup_config = RollupConfig::new_builder()
+        .stake_script_type_hash(Pack::pack(&stake_script_type_hash))
+        .allowed_eoa_type_hashes(PackVec::pack(vec![AllowedTypeHash::new(
+            AllowedEoaType::Eth,
+            *ALWAYS_SUCCESS_CODE_HASH,
+        )]))
+        .producer_schedule(producer_schedule)
+        .build();
+    // setup chain
+    let chain = setup_chain(rollup.rollup_type_script.clone(), rollup_config.clone()).await;
+    // deploy scripts
+    let param = CellContextParam {
+        stake_lock_type,
+        ..Default::default()
+    };
+    let mut ctx = CellContext::new(&rollup_config, param);
+    // the test chain produces blocks with a zero stake owner lock hash
+    let registry_dep = {
+        let registry = ProducerRegistry::new_builder()
+            .producers(
+                Byte32Vec::new_builder()
+                    .push(GWPack::pack(&[0u8; 32]))
+                    .push(GWPack::pack(&[42u8; 32]))
+                    .build(),
+            )
+            .build();
+        let cell = build_always_success_cell(
+            1000_00000000u64,
+            Some(ckb_types::packed::Script::new_unchecked(
+                registry_type.as_bytes(),
+            )),
+        );
+        let out_point = ctx.insert_cell(cell, registry.as_bytes());
+        CellDep::new_builder().out_point(out_point).build()
+    };
+    let global_state = chain
+        .local_state()
+        .last_global_state()
+        .as_builder()
+        .version(1u8.into())
+        .build();
+    // submit a new block in the slot
+    let timestamp = {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("timestamp")
+            .as_millis() as u64;
+        let round = SLOT_DURATION * 2;
+        now / round * round + slot * SLOT_DURATION
+    };
+    let block_result = {
+        let mem_pool = chain.mem_pool().as_ref().unwrap();
+        let mut mem_pool = mem_pool.lock().await;
+        construct_block_from_timestamp(&chain, &mut mem_pool, Default::default(), timestamp)
+            .await
+            .unwrap()
+    };
+    // verify submit block
+    let tx = rollup
+        .build_submit_block_tx_with_since(
+            &mut ctx,
+            &stake_script_type_hash,
+            &global_state,
+            block_result,
+            since_timestamp(timestamp + layer1_delay),
+        )
+        .cell_dep(registry_dep)
+        .build();
+    ctx.verify_tx(tx)
+}
+
+#[tokio::test]
+async fn test_scheduled_producer() {
+    verify_producer_schedule(0, 0, u64::MAX)
+        .await
+        .expect("return success");
+}
+
+#[tokio::test]
+async fn test_unscheduled_producer() {
+    let err = verify_producer_schedule(1, 0, u64::MAX).await.unwrap_err();
+    assert_error_eq!(
+        err,
+        expected_state_validator_error(INVALID_BLOCK_PRODUCER_ERROR)
+    );
+}
+
+#[tokio::test]
+async fn test_fallback_producer() {
+    verify_producer_schedule(1, 0, 1)
+        .await
+        .expect("return success");
+}
+
+#[tokio::test]
+async fn test_backdated_block() {
+    // the block is timestamped in the producer's slot, but submitted in the next slot
+    let err = verify_producer_schedule(0, SLOT_DURATION, u64::MAX)
+        .await
+        .unwrap_err();
+    assert_error_eq!(
+        err,
+        expected_state_validator_error(INVALID_BLOCK_PRODUCER_ERROR)
+    );
+    // the block is timestamped in the previous round of the producer's slot
+    let err = verify_producer_schedule(1, SLOT_DURATION, u64::MAX)
+        .await
+        .unwrap_err();
+    assert_error_eq!(
+        err,
+        expected_state_validator_error(INVALID_BLOCK_PRODUCER_ERROR)
+    );
+}
@@ -0,0 +1,254 @@
+use crate::script_tests::utils::init_env_log;
+use crate::script_tests::utils::layer1::{build_simple_tx_with_out_point, random_out_point};
+use crate::script_tests::utils::rollup::{
+    build_always_success_cell, build_rollup_locked_cell, build_type_id_script,
+    calculate_state_validator_type_id, expected_state_validator_error, CellContext,
+    CellContextParam,
+};
+use crate::testing_tool::programs::STATE_VALIDATOR_CODE_HASH;
+use ckb_error::assert_error_eq;
+use ckb_types::{
+    packed::{CellInput, CellOutput},
+    prelude::{Pack as CKBPack, Unpack as CKBUnpack},
+};
+use gw_common::{
+    builtins::CKB_SUDT_ACCOUNT_ID,
+    sparse_merkle_tree::default_store::DefaultStore,
+    state::{
+        build_account_field_key, build_account_key, GW_ACCOUNT_SCRIPT_HASH_TYPE,
+        SUDT_TOTAL_SUPPLY_KEY,
+    },
+    CKB_SUDT_SCRIPT_ARGS, H256,
+};
+use gw_types::{
+    bytes::Bytes,
+    core::{ScriptHashType, Status},
+    packed::{
+        AccountMerkleState, CustodianLockArgs, GlobalState, ReservesAttestation, RollupAction,
+        RollupActionUnion, RollupConfig, RollupProveReserves, Script, SudtReserve,
+    },
+    prelude::*,
+    U256,
+};
+
+const INVALID_OUTPUT_ERROR: i8 = 7;
+const INSUFFICIENT_RESERVES_ERROR: i8 = 58;
+
+const LAST_FINALIZED_BLOCK_NUMBER: u64 = 10;
+
+struct ReservesParam {
+    input_capacities: Vec<u64>,
+    // layer2 CKB total supply in shannons
+    total_supply: u64,
+    with_attestation_cell: bool,
+}
+
+impl Default for ReservesParam {
+    fn default() -> Self {
+        Self {
+            input_capacities: vec![300_00000000u64; 3],
+            total_supply: 800_00000000u64,
+            with_attestation_cell: true,
+        }
+    }
+}
+
+fn verify_prove_reserves(param: ReservesParam) -> Result<ckb_types::core::Cycle, ckb_error::Error> {
+    init_env_log();
+    let input_out_point = random_out_point();
+    let type_id = calculate_state_validator_type_id(input_out_point.clone());
+    let rollup_type_script = {
+        Script::new_builder()
+            .code_hash(Pack::pack(&*STATE_VALIDATOR_CODE_HASH))
+            .hash_type(ScriptHashType::Data.into())
+            .args(Pack::pack(&Bytes::from(type_id.to_vec())))
+            .build()
+    };
+    // rollup lock & config
+    let custodian_lock_type = build_type_id_script(b"custodian_lock_type_id");
+    let custodian_script_type_hash: [u8; 32] = custodian_lock_type.calc_script_hash().unpack();
+    let l2_sudt_type = build_type_id_script(b"l2_sudt_type_id");
+    let l2_sudt_script_type_hash: [u8; 32] = l2_sudt_type.calc_script_hash().unpack();
+    let rollup_config = RollupConfig::new_builder()
+        .custodian_script_type_hash(Pack::pack(&custodian_script_type_hash))
+        .l2_sudt_validator_script_type_hash(Pack::pack(&l2_sudt_script_type_hash))
+        .build();
+    let rollup_config_hash: [u8; 32] =
+        CellOutput::calc_data_hash(&rollup_config.as_bytes()).unpack();
+    // deploy scripts
+    let param_ctx = CellContextParam {
+        custodian_lock_type,
+        l2_sudt_type,
+        ..Default::default()
+    };
+    let mut ctx = CellContext::new(&rollup_config, param_ctx);
+    // build custodian cells
+    let build_custodian_cell = |capacity: u64| {
+        let args = CustodianLockArgs::new_builder()
+            .deposit_block_hash(Pack::pack(&[0u8; 32]))
+            .deposit_block_number(Pack::pack(&LAST_FINALIZED_BLOCK_NUMBER))
+            .build();
+        build_rollup_locked_cell(
+            &rollup_type_script.hash(),
+            &custodian_script_type_hash,
+            capacity,
+            args.as_bytes(),
+        )
+    };
+    let input_custodian_cells: Vec<_> = param
+        .input_capacities
+        .iter()
+        .map(|capacity| {
+            let out_point = ctx.insert_cell(build_custodian_cell(*capacity), Bytes::new());
+            CellInput::new_builder().previous_output(out_point).build()
+        })
+        .collect();
+    let output_custodian_cells: Vec<_> = param
+        .input_capacities
+        .iter()
+        .map(|capacity| build_custodian_cell(*capacity))
+        .collect();
+    // build the layer2 state of the CKB sUDT
+    let l2_ckb_script_hash: H256 = {
+        let mut args = rollup_type_script.hash().to_vec();
+        args.extend_from_slice(&CKB_SUDT_SCRIPT_ARGS);
+        Script::new_builder()
+            .code_hash(Pack::pack(&l2_sudt_script_type_hash))
+            .hash_type(ScriptHashType::Type.into())
+            .args(Pack::pack(&Bytes::from(args)))
+            .build()
+            .hash()
+            .into()
+    };
+    let total_supply = U256::from(param.total_supply) * U256::from(10_000_000_000u64);
+    let total_supply_value: H256 = {
+        let mut buf = [0u8; 32];
+        total_supply.to_little_endian(&mut buf);
+        buf.into()
+    };
+    let kv_state = vec![
+        (
+            build_account_field_key(CKB_SUDT_ACCOUNT_ID, GW_ACCOUNT_SCRIPT_HASH_TYPE),
+            l2_ckb_script_hash,
+        ),
+        (
+            build_account_key(CKB_SUDT_ACCOUNT_ID, &SUDT_TOTAL_SUPPLY_KEY),
+            total_supply_value,
+        ),
+    ];
+    let mut tree: gw_common::smt::SMT<DefaultStore<H256>> = Default::default();
+    for (k, v) in kv_state.iter() {
+        tree.update(*k, *v).unwrap();
+    }
+    let account_root = *tree.root();
+    let kv_state_proof: Bytes = tree
+        .merkle_proof(kv_state.iter().map(|(k, _v)| *k).collect())
+        .unwrap()
+        .compile(kv_state.clone())
+        .unwrap()
+        .0
+        .into();
+    // create a rollup cell
+    let capacity = 1000_00000000u64;
+    let rollup_cell = build_always_success_cell(
+        capacity,
+        Some(ckb_types::packed::Script::new_unchecked(
+            rollup_type_script.as_bytes(),
+        )),
+    );
+    let status: u8 = Status::Running.into();
+    let global_state = GlobalState::new_builder()
+        .rollup_config_hash(Pack::pack(&rollup_config_hash))
+        .account(
+            AccountMerkleState::new_builder()
+                .merkle_root(account_root.pack())
+                .count(2u32.pack())
+                .build(),
+        )
+        .last_finalized_block_number(Pack::pack(&LAST_FINALIZED_BLOCK_NUMBER))
+        .status(status.into())
+        .version(1u8.into())
+        .build();
+    let total_reserve: u64 = param.input_capacities.iter().sum();
+    let attestation = ReservesAttestation::new_builder()
+        .account_root(account_root.pack())
+        .last_finalized_block_number(LAST_FINALIZED_BLOCK_NUMBER.pack())
+        .reserves(
+            vec![SudtReserve::new_builder()
+                .sudt_id(CKB_SUDT_ACCOUNT_ID.pack())
+                .total_supply(total_supply_value.pack())
+                .reserve((total_reserve as u128).pack())
+                .build()]
+            .pack(),
+        )
+        .build();
+    let witness = {
+        let rollup_action = RollupAction::new_builder()
+            .set(RollupActionUnion::RollupProveReserves(
+                RollupProveReserves::new_builder()
+                    .attestation(attestation.clone())
+                    .sudt_script_hashes(vec![H256::from(CKB_SUDT_SCRIPT_ARGS).pack()].pack())
+                    .kv_state(kv_state.pack())
+                    .kv_state_proof(kv_state_proof.pack())
+                    .build(),
+            ))
+            .build();
+        ckb_types::packed::WitnessArgs::new_builder()
+            .output_type(CKBPack::pack(&Some(rollup_action.as_bytes())))
+            .build()
+    };
+    let mut tx = build_simple_tx_with_out_point(
+        &mut ctx.inner,
+        (rollup_cell.clone(), global_state.as_bytes()),
+        input_out_point,
+        (rollup_cell, global_state.as_bytes()),
+    )
+    .as_advanced_builder()
+    .inputs(input_custodian_cells)
+    .outputs(output_custodian_cells.clone())
+    .outputs_data(
+        (0..output_custodian_cells.len())
+            .into_iter()
+            .map(|_| CKBPack::pack(&Bytes::new())),
+    )
+    .cell_dep(ctx.custodian_lock_dep.clone())
+    .cell_dep(ctx.always_success_dep.clone())
+    .cell_dep(ctx.state_validator_dep.clone())
+    .cell_dep(ctx.rollup_config_dep.clone())
+    .witness(CKBPack::pack(&witness.as_bytes()));
+    if param.with_attestation_cell {
+        let attestation_cell = build_always_success_cell(1000_00000000u64, None);
+        tx = tx
+            .output(attestation_cell)
+            .output_data(CKBPack::pack(&attestation.as_bytes()));
+    }
+    ctx.verify_tx(tx.build())
+}
+
+#[test]
+fn test_prove_reserves() {
+    verify_prove_reserves(ReservesParam::default()).expect("return success");
+}
+
+#[test]
+fn test_prove_insufficient_reserves() {
+    let param = ReservesParam {
+        total_supply: 1000_00000000u64,
+        ..Default::default()
+    };
+    let err = verify_prove_reserves(param).unwrap_err();
+    assert_error_eq!(
+        err,
+        expected_state_validator_error(INSUFFICIENT_RESERVES_ERROR)
+    );
+}
+
+#[test]
+fn test_prove_reserves_without_attestation_cell() {
+    let param = ReservesParam {
+        with_attestation_cell: false,
+        ..Default::default()
+    };
+    let err = verify_prove_reserves(param).unwrap_err();
+    assert_error_eq!(err, expected_state_validator_error(INVALID_OUTPUT_ERROR));
+}
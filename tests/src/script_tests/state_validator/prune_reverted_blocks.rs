@@ -0,0 +1,129 @@
+use crate::script_tests::utils::init_env_log;
+use crate::script_tests::utils::layer1::{build_simple_tx_with_out_point, random_out_point};
+use crate::script_tests::utils::rollup::{
+    build_always_success_cell, calculate_state_validator_type_id, expected_state_validator_error,
+    CellContext, CellContextParam,
+};
+use crate::testing_tool::programs::STATE_VALIDATOR_CODE_HASH;
+use ckb_error::assert_error_eq;
+use ckb_types::{
+    packed::CellOutput,
+    prelude::{Pack as CKBPack, Unpack as CKBUnpack},
+};
+use gw_common::{h256_ext::H256Ext, sparse_merkle_tree::default_store::DefaultStore, H256};
+use gw_types::{
+    bytes::Bytes,
+    core::ScriptHashType,
+    packed::{
+        GlobalState, RawL2Block, RollupAction, RollupActionUnion, RollupConfig,
+        RollupPruneRevertedBlocks, Script,
+    },
+    prelude::*,
+};
+
+const INVALID_REVERTED_BLOCKS_ERROR: i8 = 29;
+
+const LAST_FINALIZED_BLOCK_NUMBER: u64 = 10;
+
+/// Prune the reverted block of `number`, another reverted block is kept in the tree
+fn verify_prune_reverted_block(number: u64) -> Result<ckb_types::core::Cycle, ckb_error::Error> {
+    init_env_log();
+    let input_out_point = random_out_point();
+    let type_id = calculate_state_validator_type_id(input_out_point.clone());
+    let rollup_type_script = {
+        Script::new_builder()
+            .code_hash(Pack::pack(&*STATE_VALIDATOR_CODE_HASH))
+            .hash_type(ScriptHashType::Data.into())
+            .args(Pack::pack(&Bytes::from(type_id.to_vec())))
+            .build()
+    };
+    let rollup_config = RollupConfig::default();
+    let rollup_config_hash: [u8; 32] =
+        CellOutput::calc_data_hash(&rollup_config.as_bytes()).unpack();
+    // deploy scripts
+    let mut ctx = CellContext::new(&rollup_config, CellContextParam::default());
+    // create a rollup cell
+    let capacity = 1000_00000000u64;
+    let rollup_cell = build_always_success_cell(
+        capacity,
+        Some(ckb_types::packed::Script::new_unchecked(
+            rollup_type_script.as_bytes(),
+        )),
+    );
+    // reverted blocks
+    let pruned_block = RawL2Block::new_builder()
+        .number(Pack::pack(&number))
+        .build();
+    let kept_block = RawL2Block::new_builder()
+        .number(Pack::pack(&(LAST_FINALIZED_BLOCK_NUMBER + 1)))
+        .build();
+    let mut reverted_block_tree: gw_common::smt::SMT<DefaultStore<H256>> = Default::default();
+    for block in [&pruned_block, &kept_block] {
+        reverted_block_tree
+            .update(block.hash().into(), H256::one())
+            .unwrap();
+    }
+    let reverted_block_proof: Bytes = reverted_block_tree
+        .merkle_proof(vec![pruned_block.hash().into()])
+        .unwrap()
+        .compile(vec![(pruned_block.hash().into(), H256::one())])
+        .unwrap()
+        .0
+        .into();
+    let global_state = GlobalState::new_builder()
+        .rollup_config_hash(Pack::pack(&rollup_config_hash))
+        .reverted_block_root(Pack::pack(reverted_block_tree.root()))
+        .last_finalized_block_number(Pack::pack(&LAST_FINALIZED_BLOCK_NUMBER))
+        .version(1u8.into())
+        .build();
+    let post_global_state = {
+        reverted_block_tree
+            .update(pruned_block.hash().into(), H256::zero())
+            .unwrap();
+        global_state
+            .clone()
+            .as_builder()
+            .reverted_block_root(Pack::pack(reverted_block_tree.root()))
+            .build()
+    };
+    let witness = {
+        let rollup_action = RollupAction::new_builder()
+            .set(RollupActionUnion::RollupPruneRevertedBlocks(
+                RollupPruneRevertedBlocks::new_builder()
+                    .reverted_blocks(vec![pruned_block].pack())
+                    .reverted_block_proof(Pack::pack(&reverted_block_proof))
+                    .build(),
+            ))
+            .build();
+        ckb_types::packed::WitnessArgs::new_builder()
+            .output_type(CKBPack::pack(&Some(rollup_action.as_bytes())))
+            .build()
+    };
+    let tx = build_simple_tx_with_out_point(
+        &mut ctx.inner,
+        (rollup_cell.clone(), global_state.as_bytes()),
+        input_out_point,
+        (rollup_cell, post_global_state.as_bytes()),
+    )
+    .as_advanced_builder()
+    .cell_dep(ctx.always_success_dep.clone())
+    .cell_dep(ctx.state_validator_dep.clone())
+    .cell_dep(ctx.rollup_config_dep.clone())
+    .witness(CKBPack::pack(&witness.as_bytes()))
+    .build();
+    ctx.verify_tx(tx)
+}
+
+#[test]
+fn test_prune_finalized_reverted_block() {
+    verify_prune_reverted_block(LAST_FINALIZED_BLOCK_NUMBER).expect("return success");
+}
+
+#[test]
+fn test_prune_unfinalized_reverted_block() {
+    let err = verify_prune_reverted_block(LAST_FINALIZED_BLOCK_NUMBER + 2).unwrap_err();
+    assert_error_eq!(
+        err,
+        expected_state_validator_error(INVALID_REVERTED_BLOCKS_ERROR)
+    );
+}
@@ -0,0 +1,189 @@
+use crate::script_tests::utils::init_env_log;
+use crate::script_tests::utils::rollup::{
+    build_rollup_locked_cell, build_type_id_script, expected_state_validator_error, CellContext,
+    CellContextParam, RollupFixture,
+};
+use ckb_error::assert_error_eq;
+use ckb_script::ScriptError;
+use ckb_types::{
+    packed::{CellInput, CellOutput},
+    prelude::{Pack as CKBPack, Unpack as CKBUnpack},
+};
+use gw_types::{
+    bytes::Bytes,
+    core::Status,
+    packed::{
+        CustodianLockArgs, GlobalState, RollupActionUnion, RollupConfig, RollupRebalanceCustodians,
+        StakeLockArgs,
+    },
+    prelude::*,
+};
+
+const INDEX_OUT_OF_BOUND_ERROR: i8 = 1;
+const INSUFFICIENT_OUTPUT_FINALIZED_ASSETS_ERROR: i8 = 17;
+const INVALID_STAKE_CELL_ERROR: i8 = 25;
+
+const LAST_FINALIZED_BLOCK_NUMBER: u64 = 10;
+
+struct RebalanceParam {
+    input_deposit_block_number: u64,
+    input_capacities: Vec<u64>,
+    output_capacities: Vec<u64>,
+    with_stake_cell: bool,
+}
+
+impl Default for RebalanceParam {
+    fn default() -> Self {
+        Self {
+            input_deposit_block_number: LAST_FINALIZED_BLOCK_NUMBER,
+            input_capacities: vec![300_00000000u64; 3],
+            output_capacities: vec![450_00000000u64; 2],
+            with_stake_cell: false,
+        }
+    }
+}
+
+fn verify_rebalance_custodians(
+    param: RebalanceParam,
+) -> Result<ckb_types::core::Cycle, ckb_error::Error> {
+    init_env_log();
+    let rollup = RollupFixture::default();
+    // rollup lock & config
+    let stake_lock_type = build_type_id_script(b"stake_lock_type_id");
+    let stake_script_type_hash: [u8; 32] = stake_lock_type.calc_script_hash().unpack();
+    let custodian_lock_type = build_type_id_script(b"custodian_lock_type_id");
+    let custodian_script_type_hash: [u8; 32] = custodian_lock_type.calc_script_hash().unpack();
+    let rollup_config = RollupConfig::new_builder()
+        .stake_script_type_hash(Pack::pack(&stake_script_type_hash))
+        .custodian_script_type_hash(Pack::pack(&custodian_script_type_hash))
+        .build();
+    let rollup_config_hash: [u8; 32] =
+        CellOutput::calc_data_hash(&rollup_config.as_bytes()).unpack();
+    // deploy scripts
+    let param_ctx = CellContextParam {
+        stake_lock_type,
+        custodian_lock_type,
+        ..Default::default()
+    };
+    let mut ctx = CellContext::new(&rollup_config, param_ctx);
+    // build custodian cells
+    let build_custodian_cell = |capacity: u64, deposit_block_number: u64| {
+        let args = CustodianLockArgs::new_builder()
+            .deposit_block_hash(Pack::pack(&[0u8; 32]))
+            .deposit_block_number(Pack::pack(&deposit_block_number))
+            .build();
+        build_rollup_locked_cell(
+            &rollup.rollup_type_hash(),
+            &custodian_script_type_hash,
+            capacity,
+            args.as_bytes(),
+        )
+    };
+    let input_custodian_cells: Vec<_> = param
+        .input_capacities
+        .iter()
+        .map(|capacity| {
+            let cell = build_custodian_cell(*capacity, param.input_deposit_block_number);
+            let out_point = ctx.insert_cell(cell, Bytes::new());
+            CellInput::new_builder().previous_output(out_point).build()
+        })
+        .collect();
+    let output_custodian_cells: Vec<_> = param
+        .output_capacities
+        .iter()
+        .map(|capacity| build_custodian_cell(*capacity, LAST_FINALIZED_BLOCK_NUMBER))
+        .collect();
+    let status: u8 = Status::Running.into();
+    let global_state = GlobalState::new_builder()
+        .rollup_config_hash(Pack::pack(&rollup_config_hash))
+        .last_finalized_block_number(Pack::pack(&LAST_FINALIZED_BLOCK_NUMBER))
+        .status(status.into())
+        .version(1u8.into())
+        .build();
+    let action = RollupActionUnion::RollupRebalanceCustodians(RollupRebalanceCustodians::default());
+    let mut tx = rollup
+        .build_action_tx(
+            &mut ctx,
+            &global_state,
+            &global_state,
+            Default::default(),
+            action,
+        )
+        .inputs(input_custodian_cells)
+        .outputs(output_custodian_cells.clone())
+        .outputs_data(
+            (0..output_custodian_cells.len())
+                .into_iter()
+                .map(|_| CKBPack::pack(&Bytes::new())),
+        )
+        .cell_dep(ctx.custodian_lock_dep.clone());
+    if param.with_stake_cell {
+        let stake_cell = build_rollup_locked_cell(
+            &rollup.rollup_type_hash(),
+            &stake_script_type_hash,
+            10000_00000000u64,
+            StakeLockArgs::default().as_bytes(),
+        );
+        let out_point = ctx.insert_cell(stake_cell.clone(), Bytes::default());
+        tx = tx
+            .input(CellInput::new_builder().previous_output(out_point).build())
+            .output(stake_cell)
+            .output_data(CKBPack::pack(&Bytes::default()))
+            .cell_dep(ctx.stake_lock_dep.clone());
+    }
+    ctx.verify_tx(tx.build())
+}
+
+#[test]
+fn test_rebalance_custodians() {
+    verify_rebalance_custodians(RebalanceParam::default()).expect("return success");
+}
+
+#[test]
+fn test_rebalance_custodians_with_insufficient_outputs() {
+    let param = RebalanceParam {
+        output_capacities: vec![400_00000000u64; 2],
+        ..Default::default()
+    };
+    let err = verify_rebalance_custodians(param).unwrap_err();
+    assert_error_eq!(
+        err,
+        expected_state_validator_error(INSUFFICIENT_OUTPUT_FINALIZED_ASSETS_ERROR)
+    );
+}
+
+#[test]
+fn test_rebalance_unfinalized_custodians() {
+    let param = RebalanceParam {
+        input_deposit_block_number: LAST_FINALIZED_BLOCK_NUMBER + 1,
+        ..Default::default()
+    };
+    let err = verify_rebalance_custodians(param).unwrap_err();
+    // the unfinalized custodians can only be unlocked by proving the deposit block is reverted,
+    // the custodian lock doesn't find the revert witness
+    let custodian_script_type_hash: [u8; 32] = build_type_id_script(b"custodian_lock_type_id")
+        .calc_script_hash()
+        .unpack();
+    let expected_err = ScriptError::ValidationFailure(
+        format!(
+            "by-type-hash/{}",
+            ckb_types::H256(custodian_script_type_hash)
+        ),
+        INDEX_OUT_OF_BOUND_ERROR,
+    )
+    .input_lock_script(1);
+    assert_error_eq!(err, expected_err);
+}
+
+#[test]
+fn test_rebalance_custodians_with_stake_cell() {
+    let param = RebalanceParam {
+        with_stake_cell: true,
+        ..Default::default()
+    };
+    let err = verify_rebalance_custodians(param).unwrap_err();
+    assert_error_eq!(
+        err,
+        expected_state_validator_error(INVALID_STAKE_CELL_ERROR)
+    );
+}
@@ -7,10 +7,12 @@ use crate::script_tests::utils::layer1::build_simple_tx_with_out_point;
 use crate::script_tests::utils::layer1::{always_success_script, random_out_point};
 use crate::script_tests::utils::rollup::{
     build_always_success_cell, build_rollup_locked_cell, build_type_id_script,
-    calculate_state_validator_type_id, CellContext, CellContextParam,
+    calculate_state_validator_type_id, expected_state_validator_error, CellContext,
+    CellContextParam,
 };
 use crate::testing_tool::chain::{apply_block_result, construct_block, setup_chain};
 use crate::testing_tool::programs::{ALWAYS_SUCCESS_CODE_HASH, STATE_VALIDATOR_CODE_HASH};
+use ckb_error::assert_error_eq;
 use ckb_types::{
     packed::{CellInput, CellOutput},
     prelude::{Pack as CKBPack, Unpack as CKBUnpack},
@@ -29,16 +31,43 @@ use gw_types::{
     bytes::Bytes,
     core::{ChallengeTargetType, ScriptHashType, Status},
     packed::{
-        ChallengeLockArgs, ChallengeTarget, DepositRequest, L2Transaction, RawL2Transaction,
-        RollupAction, RollupActionUnion, RollupConfig, RollupRevert, SUDTArgs, SUDTArgsUnion,
-        SUDTTransfer, Script,
+        ChallengeLockArgs, ChallengeQueue, ChallengeTarget, ChallengeTargetVec, DelegatorShare,
+        DepositRequest, L2Transaction, RawL2Transaction, RollupAction, RollupActionUnion,
+        RollupConfig, RollupRevert, SUDTArgs, SUDTArgsUnion, SUDTTransfer, Script, StakePool,
     },
 };
 use gw_types::{packed::StakeLockArgs, prelude::*};
 
-#[tokio::test]
-async fn test_revert() {
+const INVALID_CHALLENGE_REWARD_ERROR: i8 = 30;
+
+const REQUIRED_STAKING_CAPACITY: u64 = 6000_00000000u64;
+
+// (delegator lock, share, capacity refunded out of the slash)
+type Delegators = Vec<(ckb_types::packed::Script, u64, u64)>;
+
+#[derive(Default)]
+struct RevertParam {
+    delegators: Option<Delegators>,
+    // close a later challenge together, the rewards are split by the reward weights
+    later_challenge: bool,
+    // reward weights of the first and the later challenge, 2:1 by default
+    reward_weights: Option<(u32, u32)>,
+    // the later challenge is in front of the challenge queue
+    reversed_queue: bool,
+    // the later challenge is immature, it's closed without rewards
+    immature_later_challenge: bool,
+}
+
+async fn verify_revert(param: RevertParam) -> Result<ckb_types::core::Cycle, ckb_error::Error> {
     init_env_log();
+    let RevertParam {
+        delegators,
+        later_challenge,
+        reward_weights,
+        reversed_queue,
+        immature_later_challenge,
+    } = param;
+    let (first_reward_weight, later_reward_weight) = reward_weights.unwrap_or((2, 1));
     let input_out_point = random_out_point();
     let type_id = calculate_state_validator_type_id(input_out_point.clone());
     let rollup_type_script = {
@@ -69,6 +98,7 @@ async fn test_revert() {
         .reward_burn_rate(50u8.into())
         .burn_lock_hash(Pack::pack(&reward_burn_lock_hash))
         .finality_blocks(Pack::pack(&finality_blocks))
+        .required_staking_capacity(Pack::pack(&REQUIRED_STAKING_CAPACITY))
         .allowed_eoa_type_hashes(
             vec![AllowedTypeHash::new(
                 AllowedEoaType::Eth,
@@ -80,6 +110,20 @@ async fn test_revert() {
             vec![AllowedTypeHash::new(AllowedContractType::Sudt, [0u8; 32])].pack(),
         )
         .build();
+    let queue_cell_type = build_type_id_script(b"challenge_queue_type_id");
+    let rollup_bound_lock_type = build_type_id_script(b"rollup_bound_lock_type_id");
+    let rollup_bound_script_type_hash: [u8; 32] =
+        rollup_bound_lock_type.calc_script_hash().unpack();
+    let rollup_config = if later_challenge {
+        let challenge_queue_type_hash: [u8; 32] = queue_cell_type.calc_script_hash().unpack();
+        rollup_config
+            .as_builder()
+            .challenge_queue_type_hash(Pack::pack(&challenge_queue_type_hash))
+            .rollup_bound_script_type_hash(Pack::pack(&rollup_bound_script_type_hash))
+            .build()
+    } else {
+        rollup_config
+    };
     // setup chain
     let mut chain = setup_chain(rollup_type_script.clone(), rollup_config.clone()).await;
     // create a rollup cell
@@ -194,10 +238,30 @@ async fn test_revert() {
     let param = CellContextParam {
         stake_lock_type,
         challenge_lock_type,
+        rollup_bound_lock_type,
         ..Default::default()
     };
     let mut ctx = CellContext::new(&rollup_config, param);
     let stake_capacity = 10000_00000000u64;
+    let stake_pool_data = match delegators {
+        Some(ref delegators) => {
+            let delegators: Vec<_> = delegators
+                .iter()
+                .map(|(lock, share, _)| {
+                    let owner_lock_hash: [u8; 32] = lock.calc_script_hash().unpack();
+                    DelegatorShare::new_builder()
+                        .owner_lock_hash(Pack::pack(&owner_lock_hash))
+                        .share(Pack::pack(share))
+                        .build()
+                })
+                .collect();
+            StakePool::new_builder()
+                .delegators(delegators.pack())
+                .build()
+                .as_bytes()
+        }
+        None => Bytes::default(),
+    };
     let input_stake_cell = {
         let cell = build_rollup_locked_cell(
             &rollup_type_script.hash(),
@@ -205,23 +269,27 @@ async fn test_revert() {
             stake_capacity,
             StakeLockArgs::default().as_bytes(),
         );
-        let out_point = ctx.insert_cell(cell, Bytes::default());
+        let out_point = ctx.insert_cell(cell, stake_pool_data);
         CellInput::new_builder().previous_output(out_point).build()
     };
     let challenge_capacity = 10000_00000000u64;
     let challenged_block = chain.local_state().tip().clone();
-    let input_challenge_cell = {
+    let build_challenge_cell = |ctx: &mut CellContext,
+                                target_index: u32,
+                                receiver_lock: &ckb_types::packed::Script,
+                                reward_weight: u32,
+                                mature: bool| {
+        let target = ChallengeTarget::new_builder()
+            .target_index(Pack::pack(&target_index))
+            .target_type(ChallengeTargetType::TxExecution.into())
+            .block_hash(Pack::pack(&challenged_block.hash()))
+            .build();
         let lock_args = ChallengeLockArgs::new_builder()
-            .target(
-                ChallengeTarget::new_builder()
-                    .target_index(Pack::pack(&0u32))
-                    .target_type(ChallengeTargetType::TxExecution.into())
-                    .block_hash(Pack::pack(&challenged_block.hash()))
-                    .build(),
-            )
+            .target(target.clone())
             .rewards_receiver_lock(gw_types::packed::Script::new_unchecked(
-                reward_receive_lock.as_bytes(),
+                receiver_lock.as_bytes(),
             ))
+            .reward_weight(Pack::pack(&reward_weight))
             .build();
         let cell = build_rollup_locked_cell(
             &rollup_type_script.hash(),
@@ -230,24 +298,73 @@ async fn test_revert() {
             lock_args.as_bytes(),
         );
         let out_point = ctx.insert_cell(cell, Bytes::new());
-        let since: u64 = {
+        let since: u64 = if mature {
             let mut since = 1 << 63;
             since |= rollup_config.challenge_maturity_blocks().unpack();
             since
+        } else {
+            0
         };
-        CellInput::new_builder()
+        let input = CellInput::new_builder()
             .since(CKBPack::pack(&since))
             .previous_output(out_point)
-            .build()
+            .build();
+        (input, target)
     };
+    let (input_challenge_cell, challenge_target) =
+        build_challenge_cell(&mut ctx, 0, &reward_receive_lock, first_reward_weight, true);
+    let later_receive_lock = always_success_script()
+        .as_builder()
+        .args(CKBPack::pack(&Bytes::from(b"later_receive_lock".to_vec())))
+        .build();
+    let (later_challenge_cell, later_challenge_target) = build_challenge_cell(
+        &mut ctx,
+        1,
+        &later_receive_lock,
+        later_reward_weight,
+        !immature_later_challenge,
+    );
+    // the whole stake is slashed, a stake pool loses each delegator share pro rata
+    let slashed_capacity = stake_capacity;
+    let delegator_cells: Vec<_> = delegators
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|(_, _, refund_capacity)| *refund_capacity > 0)
+        .map(|(lock, _, refund_capacity)| {
+            CellOutput::new_builder()
+                .capacity(CKBPack::pack(&refund_capacity))
+                .lock(lock)
+                .build()
+        })
+        .collect();
+    let total_refund_capacity: u64 = delegator_cells
+        .iter()
+        .map(|cell| CKBUnpack::<u64>::unpack(&cell.capacity()))
+        .sum();
     let burn_rate: u8 = rollup_config.reward_burn_rate().into();
-    let reward_capacity: u64 = stake_capacity * burn_rate as u64 / 100;
-    let received_capacity: u64 = reward_capacity + challenge_capacity;
-    let burned_capacity: u64 = stake_capacity - reward_capacity;
+    let reward_capacity: u64 = slashed_capacity * burn_rate as u64 / 100;
+    let burned_capacity: u64 = slashed_capacity - reward_capacity - total_refund_capacity;
+    let (first_reward_capacity, later_reward_capacity) = if later_challenge
+        && !immature_later_challenge
+    {
+        // the later weight is capped by the first weight
+        let later_reward_weight = later_reward_weight.min(first_reward_weight) as u64;
+        let total_weights = first_reward_weight as u64 + later_reward_weight;
+        let later_reward_capacity = reward_capacity * later_reward_weight / total_weights;
+        let first_reward_capacity = reward_capacity * first_reward_weight as u64 / total_weights;
+        let remainder = reward_capacity - first_reward_capacity - later_reward_capacity;
+        (first_reward_capacity + remainder, later_reward_capacity)
+    } else {
+        (reward_capacity, 0)
+    };
     let receive_cell = CellOutput::new_builder()
-        .capacity(CKBPack::pack(&received_capacity))
+        .capacity(CKBPack::pack(&(first_reward_capacity + challenge_capacity)))
         .lock(reward_receive_lock)
         .build();
+    let later_receive_cell = CellOutput::new_builder()
+        .capacity(CKBPack::pack(&(later_reward_capacity + challenge_capacity)))
+        .lock(later_receive_lock)
+        .build();
     let reward_burned_cell = CellOutput::new_builder()
         .capacity(CKBPack::pack(&burned_capacity))
         .lock(reward_burn_lock)
@@ -265,7 +382,12 @@ async fn test_revert() {
         let maybe_block = db.get_block(&challenged_block.raw().parent_block_hash().unpack());
         maybe_block.unwrap().unwrap().raw()
     };
-    let new_tip_block_timestamp = new_tip_block.timestamp();
+    // version 0 doesn't have the tip block timestamp
+    let new_tip_block_timestamp = if global_state.version() == 0.into() {
+        Pack::pack(&0u64)
+    } else {
+        new_tip_block.timestamp()
+    };
     let mut reverted_block_tree: gw_common::smt::SMT<DefaultStore<H256>> = Default::default();
     // verify enter challenge
     let witness = {
@@ -317,7 +439,10 @@ async fn test_revert() {
     let last_finalized_block_number = {
         let number: u64 = challenged_block.raw().number().unpack();
         let finalize_blocks = rollup_config.finality_blocks().unpack();
-        (number - 1).saturating_sub(finalize_blocks)
+        let prev_finalized_number: u64 = global_state.last_finalized_block_number().unpack();
+        (number - 1)
+            .saturating_sub(finalize_blocks)
+            .max(prev_finalized_number)
     };
     let rollup_cell_data = global_state
         .as_builder()
@@ -330,7 +455,7 @@ async fn test_revert() {
         .tip_block_timestamp(new_tip_block_timestamp)
         .build()
         .as_bytes();
-    let tx = build_simple_tx_with_out_point(
+    let mut tx = build_simple_tx_with_out_point(
         &mut ctx.inner,
         (rollup_cell.clone(), initial_rollup_cell_data),
         input_out_point,
@@ -343,13 +468,161 @@ async fn test_revert() {
     .output_data(Default::default())
     .output(reward_burned_cell)
     .output_data(Default::default())
+    .outputs(delegator_cells.clone())
+    .outputs_data(delegator_cells.iter().map(|_| Default::default()))
     .cell_dep(ctx.challenge_lock_dep.clone())
     .cell_dep(ctx.stake_lock_dep.clone())
     .cell_dep(ctx.always_success_dep.clone())
     .cell_dep(ctx.state_validator_dep.clone())
     .cell_dep(ctx.rollup_config_dep.clone())
     .witness(CKBPack::pack(&witness.as_bytes()))
-    .witness(CKBPack::pack(&Bytes::new()))
-    .build();
-    ctx.verify_tx(tx).expect("return success");
+    .witness(CKBPack::pack(&Bytes::new()));
+    if later_challenge {
+        let queue_cell = build_rollup_locked_cell(
+            &rollup_type_script.hash(),
+            &rollup_bound_script_type_hash,
+            capacity,
+            Bytes::default(),
+        )
+        .as_builder()
+        .type_(CKBPack::pack(&Some(
+            ckb_types::packed::Script::new_unchecked(queue_cell_type.as_bytes()),
+        )))
+        .build();
+        let build_queue = |targets: Vec<ChallengeTarget>| {
+            ChallengeQueue::new_builder()
+                .targets(ChallengeTargetVec::new_builder().extend(targets).build())
+                .build()
+                .as_bytes()
+        };
+        let queued_targets = if reversed_queue {
+            vec![later_challenge_target, challenge_target]
+        } else {
+            vec![challenge_target, later_challenge_target]
+        };
+        let input_queue_cell = {
+            let out_point = ctx.insert_cell(queue_cell.clone(), build_queue(queued_targets));
+            CellInput::new_builder().previous_output(out_point).build()
+        };
+        tx = tx
+            .input(later_challenge_cell)
+            .input(input_queue_cell)
+            .output(later_receive_cell)
+            .output_data(Default::default())
+            .output(queue_cell)
+            .output_data(CKBPack::pack(&build_queue(vec![])))
+            .cell_dep(ctx.rollup_bound_lock_dep.clone());
+    }
+    ctx.verify_tx(tx.build())
+}
+
+fn delegator_lock(name: &[u8]) -> ckb_types::packed::Script {
+    always_success_script()
+        .as_builder()
+        .args(CKBPack::pack(&Bytes::from(name.to_vec())))
+        .build()
+}
+
+#[tokio::test]
+async fn test_revert() {
+    verify_revert(RevertParam::default())
+        .await
+        .expect("return success");
+}
+
+#[tokio::test]
+async fn test_revert_stake_pool() {
+    // the shares are 1:3
+    let delegators = vec![
+        (delegator_lock(b"delegator_a"), 1, 0),
+        (delegator_lock(b"delegator_b"), 3, 0),
+    ];
+    let param = RevertParam {
+        delegators: Some(delegators),
+        ..Default::default()
+    };
+    verify_revert(param).await.expect("return success");
+}
+
+#[tokio::test]
+async fn test_revert_single_delegator_stake_pool() {
+    // a stake pool of one delegator is slashed the same as a solo stake
+    let delegators = vec![(delegator_lock(b"delegator_a"), 1, 0)];
+    let param = RevertParam {
+        delegators: Some(delegators),
+        ..Default::default()
+    };
+    verify_revert(param).await.expect("return success");
+}
+
+#[tokio::test]
+async fn test_revert_stake_pool_refunding_delegator() {
+    // a delegator can't take its share back out of the slash
+    let delegators = vec![
+        (delegator_lock(b"delegator_a"), 1, 0),
+        (delegator_lock(b"delegator_b"), 3, 1000_00000000),
+    ];
+    let param = RevertParam {
+        delegators: Some(delegators),
+        ..Default::default()
+    };
+    let err = verify_revert(param).await.unwrap_err();
+    assert_error_eq!(
+        err,
+        expected_state_validator_error(INVALID_CHALLENGE_REWARD_ERROR)
+    );
+}
+
+#[tokio::test]
+async fn test_revert_split_rewards() {
+    let param = RevertParam {
+        later_challenge: true,
+        ..Default::default()
+    };
+    verify_revert(param).await.expect("return success");
+}
+
+#[tokio::test]
+async fn test_revert_uneven_split_rewards() {
+    let param = RevertParam {
+        later_challenge: true,
+        reward_weights: Some((3, 1)),
+        ..Default::default()
+    };
+    verify_revert(param).await.expect("return success");
+}
+
+#[tokio::test]
+async fn test_revert_split_rewards_capped_by_challenge_order() {
+    // the later challenger claims a larger weight, it's capped to an even split
+    let param = RevertParam {
+        later_challenge: true,
+        reward_weights: Some((1, 3)),
+        ..Default::default()
+    };
+    verify_revert(param).await.expect("return success");
+}
+
+#[tokio::test]
+async fn test_revert_immature_challenge_without_rewards() {
+    let param = RevertParam {
+        later_challenge: true,
+        immature_later_challenge: true,
+        ..Default::default()
+    };
+    verify_revert(param).await.expect("return success");
+}
+
+#[tokio::test]
+async fn test_revert_split_rewards_against_challenge_order() {
+    let param = RevertParam {
+        later_challenge: true,
+        reversed_queue: true,
+        ..Default::default()
+    };
+    let err = verify_revert(param).await.unwrap_err();
+    assert_error_eq!(
+        err,
+        expected_state_validator_error(INVALID_CHALLENGE_REWARD_ERROR)
+    );
 }
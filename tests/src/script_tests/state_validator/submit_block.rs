@@ -20,6 +20,7 @@ use ckb_types::{
     prelude::{Pack as CKBPack, Unpack},
 };
 use gw_chain::chain::{L1Action, L1ActionContext, SyncParam};
+use gw_common::{sparse_merkle_tree::default_store::DefaultStore, H256};
 use gw_types::core::AllowedEoaType;
 use gw_types::packed::{
     AllowedTypeHash, DepositRequest, L2BlockCommittedInfo, RawWithdrawalRequest, WithdrawalRequest,
@@ -30,13 +31,17 @@ use gw_types::{
     bytes::Bytes,
     core::ScriptHashType,
     packed::{
-        CustodianLockArgs, DepositLockArgs, RollupAction, RollupActionUnion, RollupConfig,
-        RollupSubmitBlock, Script, StakeLockArgs, WithdrawalLockArgs,
+        Byte32Vec, CustodianLockArgs, DepositLockArgs, RollupAction, RollupActionUnion,
+        RollupConfig, RollupSubmitBlock, Script, StakeLockArgs, UnlockCustodianViaRevertWitness,
+        WithdrawalLockArgs,
     },
 };
 
 const INVALID_BLOCK_ERROR: i8 = 20;
 const INVALID_POST_GLOBAL_STATE: i8 = 23;
+const INVALID_STAKE_CELL_ERROR: i8 = 25;
+
+const REQUIRED_STAKING_CAPACITY: u64 = 5000_00000000u64;
 
 #[tokio::test]
 async fn test_submit_block() {
@@ -157,6 +162,160 @@ async fn test_submit_block() {
     ctx.verify_tx(tx).expect("return success");
 }
 
+#[tokio::test]
+async fn test_submit_block_with_partial_unstake() {
+    // calculate type id
+    let capacity = 1000_00000000u64;
+    let spend_cell = build_always_success_cell(capacity, None);
+    let input_out_point = random_out_point();
+    let type_id = calculate_state_validator_type_id(input_out_point.clone());
+    let rollup_type_script = {
+        Script::new_builder()
+            .code_hash(Pack::pack(&*STATE_VALIDATOR_CODE_HASH))
+            .hash_type(ScriptHashType::Data.into())
+            .args(Pack::pack(&Bytes::from(type_id.to_vec())))
+            .build()
+    };
+    // rollup lock & config
+    let stake_lock_type = build_type_id_script(b"stake_lock_type_id");
+    let stake_script_type_hash: [u8; 32] = stake_lock_type.calc_script_hash().unpack();
+    let rollup_config = RollupConfig::new_builder()
+        .stake_script_type_hash(Pack::pack(&stake_script_type_hash))
+        .required_staking_capacity(Pack::pack(&REQUIRED_STAKING_CAPACITY))
+        .finality_blocks(Pack::pack(&10u64))
+        .build();
+    // setup chain
+    let chain = setup_chain(rollup_type_script.clone(), rollup_config.clone()).await;
+    // deploy scripts
+    let param = CellContextParam {
+        stake_lock_type,
+        ..Default::default()
+    };
+    let mut ctx = CellContext::new(&rollup_config, param);
+    let stake_capacity = 10000_00000000u64;
+    let build_input_stake_cell = |ctx: &mut CellContext, stake_block_number: u64| {
+        let lock_args = StakeLockArgs::new_builder()
+            .stake_block_number(Pack::pack(&stake_block_number))
+            .build();
+        let cell = build_rollup_locked_cell(
+            &rollup_type_script.hash(),
+            &stake_script_type_hash,
+            stake_capacity,
+            lock_args.as_bytes(),
+        );
+        let out_point = ctx.insert_cell(cell, Bytes::default());
+        CellInput::new_builder().previous_output(out_point).build()
+    };
+    // split the stake into the active stake of the block and the unstaked part,
+    // the unstaked part keeps the stake block number of the input
+    let build_output_stake_cell = |stake_block_number: u64, capacity: u64| {
+        let lock_args = StakeLockArgs::new_builder()
+            .stake_block_number(Pack::pack(&stake_block_number))
+            .build();
+        build_rollup_locked_cell(
+            &rollup_type_script.hash(),
+            &stake_script_type_hash,
+            capacity,
+            lock_args.as_bytes(),
+        )
+    };
+    // create a rollup cell
+    let rollup_cell = build_always_success_cell(
+        capacity,
+        Some(ckb_types::packed::Script::new_unchecked(
+            rollup_type_script.as_bytes(),
+        )),
+    );
+    let global_state = chain.local_state().last_global_state();
+    let initial_rollup_cell_data = global_state.as_bytes();
+    let tx = build_simple_tx_with_out_point(
+        &mut ctx.inner,
+        (spend_cell, Default::default()),
+        input_out_point,
+        (rollup_cell.clone(), initial_rollup_cell_data.clone()),
+    )
+    .as_advanced_builder()
+    .cell_dep(ctx.always_success_dep.clone())
+    .cell_dep(ctx.state_validator_dep.clone())
+    .cell_dep(ctx.rollup_config_dep.clone())
+    .build();
+    ctx.verify_tx(tx).expect("return success");
+    // submit a new block
+    let block_result = {
+        let mem_pool = chain.mem_pool().as_ref().unwrap();
+        let mut mem_pool = mem_pool.lock().await;
+        construct_block(&chain, &mut mem_pool, Vec::default())
+            .await
+            .unwrap()
+    };
+    // verify submit block
+    let tip_block_timestamp = block_result.block.raw().timestamp();
+    let rollup_cell_data = block_result
+        .global_state
+        .as_builder()
+        .tip_block_timestamp(tip_block_timestamp.clone())
+        .version(1u8.into())
+        .build()
+        .as_bytes();
+    let witness = {
+        let rollup_action = RollupAction::new_builder()
+            .set(RollupActionUnion::RollupSubmitBlock(
+                RollupSubmitBlock::new_builder()
+                    .block(block_result.block)
+                    .build(),
+            ))
+            .build();
+        ckb_types::packed::WitnessArgs::new_builder()
+            .output_type(CKBPack::pack(&Some(rollup_action.as_bytes())))
+            .build()
+    };
+    let build_unstake_tx =
+        |ctx: &mut CellContext, input_stake_block_number: u64, active_capacity: u64| {
+            let input_stake_cell = build_input_stake_cell(ctx, input_stake_block_number);
+            build_simple_tx(
+                &mut ctx.inner,
+                (rollup_cell.clone(), initial_rollup_cell_data.clone()),
+                since_timestamp(GWUnpack::unpack(&tip_block_timestamp)),
+                (rollup_cell.clone(), rollup_cell_data.clone()),
+            )
+            .as_advanced_builder()
+            .input(input_stake_cell)
+            .output(build_output_stake_cell(1, active_capacity))
+            .output_data(CKBPack::pack(&Bytes::default()))
+            .output(build_output_stake_cell(
+                input_stake_block_number,
+                stake_capacity - active_capacity,
+            ))
+            .output_data(CKBPack::pack(&Bytes::default()))
+            .cell_dep(ctx.stake_lock_dep.clone())
+            .cell_dep(ctx.always_success_dep.clone())
+            .cell_dep(ctx.state_validator_dep.clone())
+            .cell_dep(ctx.rollup_config_dep.clone())
+            .witness(CKBPack::pack(&witness.as_bytes()))
+            .build()
+        };
+    let tx = build_unstake_tx(&mut ctx, 0, REQUIRED_STAKING_CAPACITY);
+    ctx.verify_tx(tx).expect("return success");
+    let expected_err = || {
+        ScriptError::ValidationFailure(
+            format!(
+                "by-data-hash/{}",
+                ckb_types::H256(*STATE_VALIDATOR_CODE_HASH)
+            ),
+            INVALID_STAKE_CELL_ERROR,
+        )
+        .input_type_script(0)
+    };
+    // the active stake doesn't cover the required staking capacity
+    let tx = build_unstake_tx(&mut ctx, 0, REQUIRED_STAKING_CAPACITY - 1);
+    let err = ctx.verify_tx(tx).unwrap_err();
+    assert_error_eq!(err, expected_err());
+    // the input stake block isn't finalized, the stake may be slashed by reverting it
+    let tx = build_unstake_tx(&mut ctx, 1, REQUIRED_STAKING_CAPACITY);
+    let err = ctx.verify_tx(tx).unwrap_err();
+    assert_error_eq!(err, expected_err());
+}
+
 #[tokio::test]
 async fn test_downgrade_rollup_cell() {
     // calculate type id
@@ -291,6 +450,140 @@ async fn test_downgrade_rollup_cell() {
     assert_error_eq!(err, expected_err);
 }
 
+#[tokio::test]
+async fn test_unsupported_rollup_version() {
+    // calculate type id
+    let capacity = 1000_00000000u64;
+    let spend_cell = build_always_success_cell(capacity, None);
+    let input_out_point = random_out_point();
+    let type_id = calculate_state_validator_type_id(input_out_point.clone());
+    let rollup_type_script = {
+        Script::new_builder()
+            .code_hash(Pack::pack(&*STATE_VALIDATOR_CODE_HASH))
+            .hash_type(ScriptHashType::Data.into())
+            .args(Pack::pack(&Bytes::from(type_id.to_vec())))
+            .build()
+    };
+    // rollup lock & config
+    let stake_lock_type = build_type_id_script(b"stake_lock_type_id");
+    let stake_script_type_hash: [u8; 32] = stake_lock_type.calc_script_hash().unpack();
+    let rollup_config = RollupConfig::new_builder()
+        .stake_script_type_hash(Pack::pack(&stake_script_type_hash))
+        .build();
+    // setup chain
+    let chain = setup_chain(rollup_type_script.clone(), rollup_config.clone()).await;
+    // deploy scripts
+    let param = CellContextParam {
+        stake_lock_type,
+        ..Default::default()
+    };
+    let mut ctx = CellContext::new(&rollup_config, param);
+    let stake_capacity = 10000_00000000u64;
+    let input_stake_cell = {
+        let cell = build_rollup_locked_cell(
+            &rollup_type_script.hash(),
+            &stake_script_type_hash,
+            stake_capacity,
+            StakeLockArgs::default().as_bytes(),
+        );
+        let out_point = ctx.insert_cell(cell, Bytes::default());
+        CellInput::new_builder().previous_output(out_point).build()
+    };
+    let output_stake_cell = {
+        let lock_args = StakeLockArgs::new_builder()
+            .stake_block_number(Pack::pack(&1))
+            .build();
+        build_rollup_locked_cell(
+            &rollup_type_script.hash(),
+            &stake_script_type_hash,
+            stake_capacity,
+            lock_args.as_bytes(),
+        )
+    };
+    // create a rollup cell
+    let rollup_cell = build_always_success_cell(
+        capacity,
+        Some(ckb_types::packed::Script::new_unchecked(
+            rollup_type_script.as_bytes(),
+        )),
+    );
+    let global_state = chain.local_state().last_global_state();
+    let initial_rollup_cell_data = global_state
+        .clone()
+        .as_builder()
+        .version(1u8.into())
+        .build()
+        .as_bytes();
+    let tx = build_simple_tx_with_out_point(
+        &mut ctx.inner,
+        (spend_cell, Default::default()),
+        input_out_point,
+        (rollup_cell.clone(), initial_rollup_cell_data.clone()),
+    )
+    .as_advanced_builder()
+    .cell_dep(ctx.always_success_dep.clone())
+    .cell_dep(ctx.state_validator_dep.clone())
+    .cell_dep(ctx.rollup_config_dep.clone())
+    .build();
+    ctx.verify_tx(tx).expect("return success");
+    // submit a new block
+    let block_result = {
+        let mem_pool = chain.mem_pool().as_ref().unwrap();
+        let mut mem_pool = mem_pool.lock().await;
+        construct_block(&chain, &mut mem_pool, Vec::default())
+            .await
+            .unwrap()
+    };
+    // verify submit block
+    let tip_block_timestamp = block_result.block.raw().timestamp();
+    let rollup_cell_data = block_result
+        .global_state
+        .as_builder()
+        .tip_block_timestamp(tip_block_timestamp.clone())
+        .version(3u8.into())
+        .build()
+        .as_bytes();
+    let witness = {
+        let rollup_action = RollupAction::new_builder()
+            .set(RollupActionUnion::RollupSubmitBlock(
+                RollupSubmitBlock::new_builder()
+                    .block(block_result.block)
+                    .build(),
+            ))
+            .build();
+        ckb_types::packed::WitnessArgs::new_builder()
+            .output_type(CKBPack::pack(&Some(rollup_action.as_bytes())))
+            .build()
+    };
+    let tx = build_simple_tx(
+        &mut ctx.inner,
+        (rollup_cell.clone(), initial_rollup_cell_data),
+        since_timestamp(GWUnpack::unpack(&tip_block_timestamp)),
+        (rollup_cell, rollup_cell_data),
+    )
+    .as_advanced_builder()
+    .input(input_stake_cell)
+    .output(output_stake_cell)
+    .output_data(CKBPack::pack(&Bytes::default()))
+    .cell_dep(ctx.stake_lock_dep.clone())
+    .cell_dep(ctx.always_success_dep.clone())
+    .cell_dep(ctx.state_validator_dep.clone())
+    .cell_dep(ctx.rollup_config_dep.clone())
+    .witness(CKBPack::pack(&witness.as_bytes()))
+    .build();
+
+    let err = ctx.verify_tx(tx).unwrap_err();
+    let expected_err = ScriptError::ValidationFailure(
+        format!(
+            "by-data-hash/{}",
+            ckb_types::H256(*STATE_VALIDATOR_CODE_HASH)
+        ),
+        INVALID_POST_GLOBAL_STATE,
+    )
+    .input_type_script(0);
+    assert_error_eq!(err, expected_err);
+}
+
 #[tokio::test]
 async fn test_v1_block_timestamp_smaller_or_equal_than_previous_block_in_submit_block() {
     // calculate type id
@@ -910,10 +1203,26 @@ async fn test_check_reverted_cells_in_submit_block() {
         )),
     );
 
+    // the reverted block is in the reverted block root
+    let revert_block_hash = [42u8; 32];
+    let revert_block_number = 2u64;
+    let mut reverted_block_tree: gw_common::smt::SMT<DefaultStore<H256>> = Default::default();
+    reverted_block_tree
+        .update(revert_block_hash.into(), H256::one())
+        .unwrap();
+    let reverted_block_root = *reverted_block_tree.root();
+    let reverted_block_proof: Bytes = reverted_block_tree
+        .merkle_proof(vec![revert_block_hash.into()])
+        .unwrap()
+        .compile(vec![(revert_block_hash.into(), H256::one())])
+        .unwrap()
+        .0
+        .into();
     let global_state = chain.local_state().last_global_state();
     let initial_rollup_cell_data = global_state
         .clone()
         .as_builder()
+        .reverted_block_root(GWPack::pack(&reverted_block_root))
         .version(1u8.into())
         .build()
         .as_bytes();
@@ -929,8 +1238,6 @@ async fn test_check_reverted_cells_in_submit_block() {
         .layer2_lock(depositer_lock_script)
         .cancel_timeout(Pack::pack(&0))
         .build();
-    let revert_block_hash = [42u8; 32];
-    let revert_block_number = 2u64;
     // build reverted deposit cell
     let input_reverted_custodian_cell = {
         let args = CustodianLockArgs::new_builder()
@@ -955,6 +1262,18 @@ async fn test_check_reverted_cells_in_submit_block() {
             deposit_args.as_bytes(),
         )
     };
+    let reverted_custodian_witness = {
+        let deposit_lock_hash: [u8; 32] = output_reverted_deposit_cell
+            .lock()
+            .calc_script_hash()
+            .unpack();
+        let unlock_args = UnlockCustodianViaRevertWitness::new_builder()
+            .deposit_lock_hash(Pack::pack(&deposit_lock_hash))
+            .build();
+        ckb_types::packed::WitnessArgs::new_builder()
+            .lock(CKBPack::pack(&Some(unlock_args.as_bytes())))
+            .build()
+    };
     // build reverted withdrawal cell
     let reverted_withdrawal_capacity: u64 = 130_00000000u64;
     let input_reverted_withdrawal_cell = {
@@ -1043,6 +1362,7 @@ async fn test_check_reverted_cells_in_submit_block() {
         .global_state
         .as_builder()
         .tip_block_timestamp(tip_block_timestamp.clone())
+        .reverted_block_root(GWPack::pack(&reverted_block_root))
         .version(1u8.into())
         .build()
         .as_bytes();
@@ -1051,6 +1371,12 @@ async fn test_check_reverted_cells_in_submit_block() {
             .set(RollupActionUnion::RollupSubmitBlock(
                 RollupSubmitBlock::new_builder()
                     .block(block_result.block)
+                    .reverted_block_hashes(
+                        Byte32Vec::new_builder()
+                            .push(Pack::pack(&revert_block_hash))
+                            .build(),
+                    )
+                    .reverted_block_proof(Pack::pack(&reverted_block_proof))
                     .build(),
             ))
             .build();
@@ -1092,6 +1418,8 @@ async fn test_check_reverted_cells_in_submit_block() {
     .cell_dep(ctx.state_validator_dep.clone())
     .cell_dep(ctx.rollup_config_dep.clone())
     .witness(CKBPack::pack(&witness.as_bytes()))
+    .witness(Default::default())
+    .witness(CKBPack::pack(&reverted_custodian_witness.as_bytes()))
     .build();
     ctx.verify_tx(tx).expect("return success");
 }
@@ -0,0 +1,189 @@
+use std::collections::HashSet;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::script_tests::utils::layer1::{
+    build_simple_tx, build_simple_tx_with_out_point, random_out_point, since_timestamp,
+};
+use crate::script_tests::utils::rollup::{
+    build_always_success_cell, build_rollup_locked_cell, build_type_id_script,
+    calculate_state_validator_type_id, CellContext, CellContextParam,
+};
+use crate::testing_tool::chain::{apply_block_result, construct_block_from_timestamp, setup_chain};
+use crate::testing_tool::programs::STATE_VALIDATOR_CODE_HASH;
+use ckb_error::assert_error_eq;
+use ckb_script::ScriptError;
+use ckb_types::{
+    packed::CellInput,
+    prelude::{Pack as CKBPack, Unpack},
+};
+use gw_types::prelude::{Unpack as GWUnpack, *};
+use gw_types::{
+    bytes::Bytes,
+    core::ScriptHashType,
+    packed::{
+        L2Block, L2BlockVec, RollupAction, RollupActionUnion, RollupConfig, RollupSubmitBlocks,
+        Script, StakeLockArgs,
+    },
+};
+
+const INVALID_BLOCK_ERROR: i8 = 20;
+
+/// Produce two blocks and submit them in one transaction,
+/// `reorder` reverses the blocks in the witness
+async fn verify_submit_blocks(reorder: bool) -> Result<ckb_types::core::Cycle, ckb_error::Error> {
+    // calculate type id
+    let capacity = 1000_00000000u64;
+    let spend_cell = build_always_success_cell(capacity, None);
+    let input_out_point = random_out_point();
+    let type_id = calculate_state_validator_type_id(input_out_point.clone());
+    let rollup_type_script = {
+        Script::new_builder()
+            .code_hash(Pack::pack(&*STATE_VALIDATOR_CODE_HASH))
+            .hash_type(ScriptHashType::Data.into())
+            .args(Pack::pack(&Bytes::from(type_id.to_vec())))
+            .build()
+    };
+    // rollup lock & config
+    let stake_lock_type = build_type_id_script(b"stake_lock_type_id");
+    let stake_script_type_hash: [u8; 32] = stake_lock_type.calc_script_hash().unpack();
+    let rollup_config = RollupConfig::new_builder()
+        .stake_script_type_hash(Pack::pack(&stake_script_type_hash))
+        .build();
+    // setup chain
+    let mut chain = setup_chain(rollup_type_script.clone(), rollup_config.clone()).await;
+    // deploy scripts
+    let param = CellContextParam {
+        stake_lock_type,
+        ..Default::default()
+    };
+    let mut ctx = CellContext::new(&rollup_config, param);
+    let stake_capacity = 10000_00000000u64;
+    let input_stake_cell = {
+        let cell = build_rollup_locked_cell(
+            &rollup_type_script.hash(),
+            &stake_script_type_hash,
+            stake_capacity,
+            StakeLockArgs::default().as_bytes(),
+        );
+        let out_point = ctx.insert_cell(cell, Bytes::default());
+        CellInput::new_builder().previous_output(out_point).build()
+    };
+    // the stake cell is updated to the last block of the batch
+    let output_stake_cell = {
+        let lock_args = StakeLockArgs::new_builder()
+            .stake_block_number(Pack::pack(&2))
+            .build();
+        build_rollup_locked_cell(
+            &rollup_type_script.hash(),
+            &stake_script_type_hash,
+            stake_capacity,
+            lock_args.as_bytes(),
+        )
+    };
+    // create a rollup cell
+    let rollup_cell = build_always_success_cell(
+        capacity,
+        Some(ckb_types::packed::Script::new_unchecked(
+            rollup_type_script.as_bytes(),
+        )),
+    );
+    let global_state = chain.local_state().last_global_state();
+    let initial_rollup_cell_data = global_state.as_bytes();
+    let tx = build_simple_tx_with_out_point(
+        &mut ctx.inner,
+        (spend_cell, Default::default()),
+        input_out_point,
+        (rollup_cell.clone(), initial_rollup_cell_data.clone()),
+    )
+    .as_advanced_builder()
+    .cell_dep(ctx.always_success_dep.clone())
+    .cell_dep(ctx.state_validator_dep.clone())
+    .cell_dep(ctx.rollup_config_dep.clone())
+    .build();
+    ctx.verify_tx(tx).expect("return success");
+    // produce two blocks
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("timestamp")
+        .as_millis() as u64;
+    let mut blocks: Vec<L2Block> = Vec::new();
+    let mut post_global_state = global_state;
+    for block_timestamp in [timestamp, timestamp + 1000].iter() {
+        let block_result = {
+            let mem_pool = chain.mem_pool().as_ref().unwrap();
+            let mut mem_pool = mem_pool.lock().await;
+            construct_block_from_timestamp(&chain, &mut mem_pool, Vec::default(), *block_timestamp)
+                .await
+                .unwrap()
+        };
+        blocks.push(block_result.block.clone());
+        post_global_state = block_result.global_state.clone();
+        apply_block_result(
+            &mut chain,
+            gw_types::packed::CellOutput::new_unchecked(rollup_cell.as_bytes()),
+            block_result,
+            Vec::default(),
+            HashSet::new(),
+        )
+        .await;
+    }
+    // verify submit blocks
+    let tip_block_timestamp = blocks.last().unwrap().raw().timestamp();
+    let rollup_cell_data = post_global_state
+        .as_builder()
+        .tip_block_timestamp(tip_block_timestamp.clone())
+        .version(1u8.into())
+        .build()
+        .as_bytes();
+    if reorder {
+        blocks.reverse();
+    }
+    let witness = {
+        let rollup_action = RollupAction::new_builder()
+            .set(RollupActionUnion::RollupSubmitBlocks(
+                RollupSubmitBlocks::new_builder()
+                    .blocks(L2BlockVec::new_builder().set(blocks).build())
+                    .build(),
+            ))
+            .build();
+        ckb_types::packed::WitnessArgs::new_builder()
+            .output_type(CKBPack::pack(&Some(rollup_action.as_bytes())))
+            .build()
+    };
+    let tx = build_simple_tx(
+        &mut ctx.inner,
+        (rollup_cell.clone(), initial_rollup_cell_data),
+        since_timestamp(GWUnpack::unpack(&tip_block_timestamp)),
+        (rollup_cell, rollup_cell_data),
+    )
+    .as_advanced_builder()
+    .input(input_stake_cell)
+    .output(output_stake_cell)
+    .output_data(CKBPack::pack(&Bytes::default()))
+    .cell_dep(ctx.stake_lock_dep.clone())
+    .cell_dep(ctx.always_success_dep.clone())
+    .cell_dep(ctx.state_validator_dep.clone())
+    .cell_dep(ctx.rollup_config_dep.clone())
+    .witness(CKBPack::pack(&witness.as_bytes()))
+    .build();
+    ctx.verify_tx(tx)
+}
+
+#[tokio::test]
+async fn test_submit_blocks() {
+    verify_submit_blocks(false).await.expect("return success");
+}
+
+#[tokio::test]
+async fn test_submit_blocks_out_of_order() {
+    let err = verify_submit_blocks(true).await.unwrap_err();
+    let expected_err = ScriptError::ValidationFailure(
+        format!(
+            "by-data-hash/{}",
+            ckb_types::H256(*STATE_VALIDATOR_CODE_HASH)
+        ),
+        INVALID_BLOCK_ERROR,
+    )
+    .input_type_script(0);
+    assert_error_eq!(err, expected_err);
+}
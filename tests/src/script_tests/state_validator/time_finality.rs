@@ -0,0 +1,212 @@
+#![allow(clippy::mutable_key_type)]
+
+use std::collections::HashSet;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::script_tests::utils::layer1::{
+    build_simple_tx_with_out_point_and_since, random_out_point, since_timestamp,
+};
+use crate::script_tests::utils::rollup::{
+    build_always_success_cell, build_rollup_locked_cell, build_type_id_script,
+    calculate_state_validator_type_id, expected_state_validator_error, CellContext,
+    CellContextParam,
+};
+use crate::testing_tool::chain::{apply_block_result, construct_block_from_timestamp, setup_chain};
+use crate::testing_tool::programs::{ALWAYS_SUCCESS_CODE_HASH, STATE_VALIDATOR_CODE_HASH};
+use ckb_error::assert_error_eq;
+use ckb_types::{
+    packed::CellInput,
+    prelude::{Pack as CKBPack, Unpack},
+};
+use gw_types::core::AllowedEoaType;
+use gw_types::prelude::{Pack as GWPack, *};
+use gw_types::{
+    bytes::Bytes,
+    core::ScriptHashType,
+    packed::{
+        AllowedTypeHash, RollupAction, RollupActionUnion, RollupConfig, RollupSubmitBlock, Script,
+        StakeLockArgs, TimeFinalityProof,
+    },
+};
+
+const INVALID_TIME_FINALITY_PROOF_ERROR: i8 = 55;
+
+const FINALITY_TIME_MS: u64 = 60_000;
+
+/// Submit the block 2 `interval` milliseconds after the block 1,
+/// and prove the block 1 is finalized by the time-based finality rule
+async fn verify_time_finality(interval: u64) -> Result<ckb_types::core::Cycle, ckb_error::Error> {
+    let capacity = 1000_00000000u64;
+    let input_out_point = random_out_point();
+    let type_id = calculate_state_validator_type_id(input_out_point.clone());
+    let rollup_type_script = {
+        Script::new_builder()
+            .code_hash(Pack::pack(&*STATE_VALIDATOR_CODE_HASH))
+            .hash_type(ScriptHashType::Data.into())
+            .args(Pack::pack(&Bytes::from(type_id.to_vec())))
+            .build()
+    };
+    // rollup lock & config
+    let stake_lock_type = build_type_id_script(b"stake_lock_type_id");
+    let stake_script_type_hash: [u8; 32] = stake_lock_type.calc_script_hash().unpack();
+    let rollup_config = RollupConfig::new_builder()
+        .stake_script_type_hash(Pack::pack(&stake_script_type_hash))
+        .allowed_eoa_type_hashes(PackVec::pack(vec![AllowedTypeHash::new(
+            AllowedEoaType::Eth,
+            *ALWAYS_SUCCESS_CODE_HASH,
+        )]))
+        .finality_blocks(Pack::pack(&100u64))
+        .finality_time_ms(Pack::pack(&FINALITY_TIME_MS))
+        .build();
+    // setup chain
+    let mut chain = setup_chain(rollup_type_script.clone(), rollup_config.clone()).await;
+    // create a rollup cell
+    let rollup_cell = build_always_success_cell(
+        capacity,
+        Some(ckb_types::packed::Script::new_unchecked(
+            rollup_type_script.as_bytes(),
+        )),
+    );
+    // produce the block 1 and the block 2
+    let block_1_timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("timestamp")
+        .as_millis() as u64
+        - 2 * FINALITY_TIME_MS;
+    let block_2_timestamp = block_1_timestamp + interval;
+    let mut blocks = Vec::new();
+    for timestamp in [block_1_timestamp, block_2_timestamp] {
+        let block_result = {
+            let mem_pool = chain.mem_pool().as_ref().unwrap();
+            let mut mem_pool = mem_pool.lock().await;
+            construct_block_from_timestamp(&chain, &mut mem_pool, Default::default(), timestamp)
+                .await
+                .unwrap()
+        };
+        let prev_global_state = chain.local_state().last_global_state().clone();
+        apply_block_result(
+            &mut chain,
+            gw_types::packed::CellOutput::new_unchecked(rollup_cell.as_bytes()),
+            block_result,
+            vec![],
+            HashSet::new(),
+        )
+        .await;
+        blocks.push((chain.local_state().tip().clone(), prev_global_state));
+    }
+    let (finalized_block, _) = blocks[0].clone();
+    let (submitted_block, global_state) = blocks[1].clone();
+    // deploy scripts
+    let param = CellContextParam {
+        stake_lock_type,
+        ..Default::default()
+    };
+    let mut ctx = CellContext::new(&rollup_config, param);
+    let stake_capacity = 10000_00000000u64;
+    let input_stake_cell = {
+        let cell = build_rollup_locked_cell(
+            &rollup_type_script.hash(),
+            &stake_script_type_hash,
+            stake_capacity,
+            StakeLockArgs::default().as_bytes(),
+        );
+        let out_point = ctx.insert_cell(cell, Bytes::default());
+        CellInput::new_builder().previous_output(out_point).build()
+    };
+    let output_stake_cell = {
+        let lock_args = StakeLockArgs::new_builder()
+            .stake_block_number(Pack::pack(&2))
+            .build();
+        build_rollup_locked_cell(
+            &rollup_type_script.hash(),
+            &stake_script_type_hash,
+            stake_capacity,
+            lock_args.as_bytes(),
+        )
+    };
+    let initial_rollup_cell_data = global_state
+        .as_builder()
+        .tip_block_timestamp(GWPack::pack(&block_1_timestamp))
+        .version(1u8.into())
+        .build()
+        .as_bytes();
+    // the block 1 is finalized by the time-based rule after submitting the block 2
+    let rollup_cell_data = chain
+        .local_state()
+        .last_global_state()
+        .clone()
+        .as_builder()
+        .tip_block_timestamp(GWPack::pack(&block_2_timestamp))
+        .last_finalized_block_number(GWPack::pack(&1u64))
+        .version(1u8.into())
+        .build()
+        .as_bytes();
+    let witness = {
+        let block_proof: Bytes = {
+            let db = chain.store().begin_transaction();
+            let proof = db
+                .block_smt()
+                .unwrap()
+                .merkle_proof(vec![finalized_block.smt_key().into()])
+                .unwrap();
+            proof
+                .compile(vec![(
+                    finalized_block.smt_key().into(),
+                    finalized_block.hash().into(),
+                )])
+                .unwrap()
+                .0
+                .into()
+        };
+        let proof = TimeFinalityProof::new_builder()
+            .block(finalized_block.raw())
+            .block_proof(Pack::pack(&block_proof))
+            .build();
+        let rollup_action = RollupAction::new_builder()
+            .set(RollupActionUnion::RollupSubmitBlock(
+                RollupSubmitBlock::new_builder()
+                    .block(submitted_block)
+                    .build(),
+            ))
+            .build();
+        ckb_types::packed::WitnessArgs::new_builder()
+            .input_type(CKBPack::pack(&Some(proof.as_bytes())))
+            .output_type(CKBPack::pack(&Some(rollup_action.as_bytes())))
+            .build()
+    };
+    let tx = build_simple_tx_with_out_point_and_since(
+        &mut ctx.inner,
+        (rollup_cell.clone(), initial_rollup_cell_data),
+        (input_out_point, since_timestamp(block_2_timestamp)),
+        (rollup_cell, rollup_cell_data),
+    )
+    .as_advanced_builder()
+    .input(input_stake_cell)
+    .output(output_stake_cell)
+    .output_data(CKBPack::pack(&Bytes::default()))
+    .cell_dep(ctx.stake_lock_dep.clone())
+    .cell_dep(ctx.always_success_dep.clone())
+    .cell_dep(ctx.state_validator_dep.clone())
+    .cell_dep(ctx.rollup_config_dep.clone())
+    .witness(CKBPack::pack(&witness.as_bytes()))
+    .build();
+    ctx.verify_tx(tx)
+}
+
+#[tokio::test]
+async fn test_time_finalized_block() {
+    verify_time_finality(FINALITY_TIME_MS)
+        .await
+        .expect("return success");
+}
+
+#[tokio::test]
+async fn test_time_unfinalized_block() {
+    let err = verify_time_finality(FINALITY_TIME_MS - 1)
+        .await
+        .unwrap_err();
+    assert_error_eq!(
+        err,
+        expected_state_validator_error(INVALID_TIME_FINALITY_PROOF_ERROR)
+    );
+}
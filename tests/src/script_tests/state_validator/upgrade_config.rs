@@ -0,0 +1,340 @@
+use crate::script_tests::utils::init_env_log;
+use crate::script_tests::utils::layer1::always_success_script;
+use crate::script_tests::utils::rollup::{
+    expected_state_validator_error, CellContext, CellContextParam, RollupFixture,
+};
+use ckb_error::assert_error_eq;
+use ckb_types::{
+    packed::{CellDep, CellInput, CellOutput},
+    prelude::{Pack as CKBPack, Unpack as CKBUnpack},
+};
+use gw_types::{
+    bytes::Bytes,
+    core::Status,
+    packed::{GlobalState, RollupActionUnion, RollupConfig, RollupConfigV0, RollupUpgradeConfig},
+    prelude::*,
+};
+
+const INVALID_SINCE_ERROR: i8 = 6;
+const OWNER_CELL_NOT_FOUND_ERROR: i8 = 8;
+const INVALID_STATUS_ERROR: i8 = 21;
+const INVALID_ROLLUP_CONFIG_ERROR: i8 = 45;
+
+const CONFIG_UPGRADE_DELAY_BLOCKS: u64 = 100;
+
+fn relative_block_number_since(number: u64) -> u64 {
+    (1 << 63) | number
+}
+
+struct UpgradeConfigParam {
+    status: Status,
+    legacy_config: bool,
+    proposal_lock_args: &'static [u8],
+    proposal_since: u64,
+    new_rollup_config: fn(RollupConfig) -> RollupConfig,
+}
+
+impl Default for UpgradeConfigParam {
+    fn default() -> Self {
+        Self {
+            status: Status::Running,
+            legacy_config: false,
+            proposal_lock_args: b"governance_lock",
+            proposal_since: relative_block_number_since(CONFIG_UPGRADE_DELAY_BLOCKS),
+            new_rollup_config: |config| {
+                config
+                    .as_builder()
+                    .finality_blocks(Pack::pack(&20u64))
+                    .build()
+            },
+        }
+    }
+}
+
+fn verify_upgrade_config(
+    param: UpgradeConfigParam,
+) -> Result<ckb_types::core::Cycle, ckb_error::Error> {
+    init_env_log();
+    let rollup = RollupFixture::default();
+    // rollup config & governance
+    let governance_lock = always_success_script()
+        .as_builder()
+        .args(CKBPack::pack(&Bytes::from(b"governance_lock".to_vec())))
+        .build();
+    let governance_lock_hash: [u8; 32] = governance_lock.calc_script_hash().unpack();
+    // the legacy config is decoded with the fields introduced later set to zero
+    let (rollup_config, rollup_config_data) = if param.legacy_config {
+        let legacy_config = RollupConfigV0::new_builder()
+            .finality_blocks(Pack::pack(&10u64))
+            .build();
+        let rollup_config = RollupConfig::new_builder()
+            .finality_blocks(Pack::pack(&10u64))
+            .build();
+        (rollup_config, legacy_config.as_bytes())
+    } else {
+        let rollup_config = RollupConfig::new_builder()
+            .finality_blocks(Pack::pack(&10u64))
+            .governance_lock_hash(Pack::pack(&governance_lock_hash))
+            .config_upgrade_delay(Pack::pack(&relative_block_number_since(
+                CONFIG_UPGRADE_DELAY_BLOCKS,
+            )))
+            .build();
+        let data = rollup_config.as_bytes();
+        (rollup_config, data)
+    };
+    let rollup_config_hash: [u8; 32] = CellOutput::calc_data_hash(&rollup_config_data).unpack();
+    let new_rollup_config = (param.new_rollup_config)(rollup_config.clone());
+    let new_rollup_config_hash: [u8; 32] =
+        CellOutput::calc_data_hash(&new_rollup_config.as_bytes()).unpack();
+    // deploy scripts
+    let mut ctx = CellContext::new(&rollup_config, CellContextParam::default());
+    let new_rollup_config_dep = {
+        let cell = CellOutput::new_builder()
+            .capacity(CKBPack::pack(&(new_rollup_config.as_bytes().len() as u64)))
+            .build();
+        let out_point = ctx.insert_cell(cell, new_rollup_config.as_bytes());
+        CellDep::new_builder().out_point(out_point).build()
+    };
+    let proposal_input = {
+        let lock = governance_lock
+            .as_builder()
+            .args(CKBPack::pack(&Bytes::from(
+                param.proposal_lock_args.to_vec(),
+            )))
+            .build();
+        let cell = CellOutput::new_builder()
+            .lock(lock)
+            .capacity(CKBPack::pack(&1000_00000000u64))
+            .build();
+        let out_point = ctx.insert_cell(cell, Bytes::from(new_rollup_config_hash.to_vec()));
+        CellInput::new_builder()
+            .previous_output(out_point)
+            .since(CKBPack::pack(&param.proposal_since))
+            .build()
+    };
+    let status: u8 = param.status.into();
+    let global_state = GlobalState::new_builder()
+        .rollup_config_hash(Pack::pack(&rollup_config_hash))
+        .status(status.into())
+        .version(1u8.into())
+        .build();
+    let post_global_state = global_state
+        .clone()
+        .as_builder()
+        .rollup_config_hash(Pack::pack(&new_rollup_config_hash))
+        .build();
+    let action = RollupActionUnion::RollupUpgradeConfig(RollupUpgradeConfig::default());
+    let mut tx = rollup
+        .build_action_tx(
+            &mut ctx,
+            &global_state,
+            &post_global_state,
+            Default::default(),
+            action,
+        )
+        .cell_dep(new_rollup_config_dep);
+    // the legacy config is migrated without the proposal
+    if param.legacy_config {
+        let cell = CellOutput::new_builder()
+            .capacity(CKBPack::pack(&(rollup_config_data.len() as u64)))
+            .build();
+        let out_point = ctx.insert_cell(cell, rollup_config_data);
+        tx = tx.cell_dep(CellDep::new_builder().out_point(out_point).build());
+    } else {
+        tx = tx.input(proposal_input);
+    }
+    ctx.verify_tx(tx.build())
+}
+
+#[test]
+fn test_upgrade_rollup_config() {
+    verify_upgrade_config(UpgradeConfigParam::default()).expect("return success");
+}
+
+#[test]
+fn test_upgrade_rollup_config_while_halting() {
+    let param = UpgradeConfigParam {
+        status: Status::Halting,
+        ..Default::default()
+    };
+    let err = verify_upgrade_config(param).unwrap_err();
+    assert_error_eq!(err, expected_state_validator_error(INVALID_STATUS_ERROR));
+}
+
+#[test]
+fn test_upgrade_rollup_config_before_delay() {
+    let param = UpgradeConfigParam {
+        proposal_since: relative_block_number_since(CONFIG_UPGRADE_DELAY_BLOCKS - 1),
+        ..Default::default()
+    };
+    let err = verify_upgrade_config(param).unwrap_err();
+    assert_error_eq!(err, expected_state_validator_error(INVALID_SINCE_ERROR));
+}
+
+#[test]
+fn test_upgrade_rollup_config_without_governance() {
+    let param = UpgradeConfigParam {
+        proposal_lock_args: b"not_governance_lock",
+        ..Default::default()
+    };
+    let err = verify_upgrade_config(param).unwrap_err();
+    assert_error_eq!(
+        err,
+        expected_state_validator_error(OWNER_CELL_NOT_FOUND_ERROR)
+    );
+}
+
+#[test]
+fn test_upgrade_rollup_config_change_script_type_hash() {
+    let param = UpgradeConfigParam {
+        new_rollup_config: |config| {
+            config
+                .as_builder()
+                .custodian_script_type_hash(Pack::pack(&[42u8; 32]))
+                .build()
+        },
+        ..Default::default()
+    };
+    let err = verify_upgrade_config(param).unwrap_err();
+    assert_error_eq!(
+        err,
+        expected_state_validator_error(INVALID_ROLLUP_CONFIG_ERROR)
+    );
+}
+
+#[test]
+fn test_upgrade_rollup_config_change_lock_type_hashes() {
+    // every type hash which the rollup lock cells are bound to
+    let new_rollup_configs: Vec<fn(RollupConfig) -> RollupConfig> = vec![
+        |config| {
+            config
+                .as_builder()
+                .l1_sudt_script_type_hash(Pack::pack(&[42u8; 32]))
+                .build()
+        },
+        |config| {
+            config
+                .as_builder()
+                .custodian_script_type_hash(Pack::pack(&[42u8; 32]))
+                .build()
+        },
+        |config| {
+            config
+                .as_builder()
+                .deposit_script_type_hash(Pack::pack(&[42u8; 32]))
+                .build()
+        },
+        |config| {
+            config
+                .as_builder()
+                .withdrawal_script_type_hash(Pack::pack(&[42u8; 32]))
+                .build()
+        },
+        |config| {
+            config
+                .as_builder()
+                .challenge_script_type_hash(Pack::pack(&[42u8; 32]))
+                .build()
+        },
+        |config| {
+            config
+                .as_builder()
+                .stake_script_type_hash(Pack::pack(&[42u8; 32]))
+                .build()
+        },
+        |config| {
+            config
+                .as_builder()
+                .l2_sudt_validator_script_type_hash(Pack::pack(&[42u8; 32]))
+                .build()
+        },
+        |config| {
+            config
+                .as_builder()
+                .rollup_bound_script_type_hash(Pack::pack(&[42u8; 32]))
+                .build()
+        },
+        |config| {
+            let withdrawal_limit = config
+                .withdrawal_limit()
+                .as_builder()
+                .state_type_hash(Pack::pack(&[42u8; 32]))
+                .build();
+            config
+                .as_builder()
+                .withdrawal_limit(withdrawal_limit)
+                .build()
+        },
+        |config| {
+            config
+                .as_builder()
+                .challenge_queue_type_hash(Pack::pack(&[42u8; 32]))
+                .build()
+        },
+        |config| {
+            config
+                .as_builder()
+                .forced_withdrawal_script_type_hash(Pack::pack(&[42u8; 32]))
+                .build()
+        },
+        |config| {
+            config
+                .as_builder()
+                .batch_deposit_script_type_hash(Pack::pack(&[42u8; 32]))
+                .build()
+        },
+        |config| {
+            config
+                .as_builder()
+                .dao_script_type_hash(Pack::pack(&[42u8; 32]))
+                .build()
+        },
+    ];
+    for new_rollup_config in new_rollup_configs {
+        let param = UpgradeConfigParam {
+            new_rollup_config,
+            ..Default::default()
+        };
+        let err = verify_upgrade_config(param).unwrap_err();
+        assert_error_eq!(
+            err,
+            expected_state_validator_error(INVALID_ROLLUP_CONFIG_ERROR)
+        );
+    }
+}
+
+#[test]
+fn test_migrate_legacy_rollup_config() {
+    // the lock type hashes introduced later are set by the migration
+    let param = UpgradeConfigParam {
+        legacy_config: true,
+        new_rollup_config: |config| {
+            config
+                .as_builder()
+                .rollup_bound_script_type_hash(Pack::pack(&[42u8; 32]))
+                .governance_lock_hash(Pack::pack(&[1u8; 32]))
+                .build()
+        },
+        ..Default::default()
+    };
+    verify_upgrade_config(param).expect("return success");
+}
+
+#[test]
+fn test_migrate_legacy_rollup_config_change_script_type_hash() {
+    let param = UpgradeConfigParam {
+        legacy_config: true,
+        new_rollup_config: |config| {
+            config
+                .as_builder()
+                .custodian_script_type_hash(Pack::pack(&[42u8; 32]))
+                .build()
+        },
+        ..Default::default()
+    };
+    let err = verify_upgrade_config(param).unwrap_err();
+    assert_error_eq!(
+        err,
+        expected_state_validator_error(INVALID_ROLLUP_CONFIG_ERROR)
+    );
+}
@@ -0,0 +1,425 @@
+use crate::script_tests::utils::rollup::{
+    build_always_success_cell, build_rollup_locked_cell, build_type_id_script,
+    expected_state_validator_error, CellContext, CellContextParam, RollupFixture,
+};
+use crate::testing_tool::chain::{build_sync_tx, construct_block, setup_chain};
+use crate::testing_tool::programs::ALWAYS_SUCCESS_CODE_HASH;
+use ckb_error::assert_error_eq;
+use ckb_types::{
+    packed::CellInput,
+    prelude::{Pack as CKBPack, Unpack},
+};
+use gw_chain::chain::{L1Action, L1ActionContext, SyncParam};
+use gw_common::CKB_SUDT_SCRIPT_ARGS;
+use gw_types::core::AllowedEoaType;
+use gw_types::packed::{
+    AllowedTypeHash, DepositRequest, L2BlockCommittedInfo, RawWithdrawalRequest, WithdrawalLimit,
+    WithdrawalLimitConfig, WithdrawalLimitState, WithdrawalLimitVec, WithdrawalRecord,
+    WithdrawalRecordVec, WithdrawalRequest, WithdrawalRequestExtra,
+};
+use gw_types::prelude::{Unpack as GWUnpack, *};
+use gw_types::{
+    bytes::Bytes,
+    core::{ScriptHashType, Status},
+    packed::{
+        CustodianLockArgs, GlobalState, RollupActionUnion, RollupConfig, RollupSetPaused, Script,
+        WithdrawalLockArgs,
+    },
+};
+
+const INVALID_WITHDRAWAL_LIMIT_CELL_ERROR: i8 = 49;
+const WITHDRAWAL_LIMIT_EXCEEDED_ERROR: i8 = 50;
+
+const WINDOW_BLOCKS: u64 = 100;
+
+/// Submit a block which withdraws the deposited CKB under the `ckb_limit`,
+/// `prev_records` are the CKB withdrawal records before the block
+async fn verify_withdrawal_limit(
+    ckb_limit: u128,
+    prev_records: Vec<(u64, u128)>,
+) -> Result<ckb_types::core::Cycle, ckb_error::Error> {
+    let _ = env_logger::builder().is_test(true).try_init();
+
+    let capacity = 1000_00000000u64;
+    let rollup = RollupFixture::default();
+
+    // rollup lock & config
+    let stake_lock_type = build_type_id_script(b"stake_lock_type_id");
+    let stake_script_type_hash: [u8; 32] = stake_lock_type.calc_script_hash().unpack();
+    let custodian_lock_type = build_type_id_script(b"custodian_lock_type_id");
+    let custodian_script_type_hash: [u8; 32] = custodian_lock_type.calc_script_hash().unpack();
+    let withdrawal_lock_type = build_type_id_script(b"withdrawal_lock_type_id");
+    let withdrawal_script_type_hash: [u8; 32] = withdrawal_lock_type.calc_script_hash().unpack();
+    let state_cell_type = build_type_id_script(b"withdrawal_limit_state_type_id");
+    let state_type_hash: [u8; 32] = state_cell_type.calc_script_hash().unpack();
+    let rollup_bound_lock_type = build_type_id_script(b"rollup_bound_lock_type_id");
+    let rollup_bound_script_type_hash: [u8; 32] =
+        rollup_bound_lock_type.calc_script_hash().unpack();
+    let withdrawal_limit = WithdrawalLimitConfig::new_builder()
+        .window_blocks(Pack::pack(&WINDOW_BLOCKS))
+        .state_type_hash(Pack::pack(&state_type_hash))
+        .limits(
+            WithdrawalLimitVec::new_builder()
+                .push(
+                    WithdrawalLimit::new_builder()
+                        .sudt_script_hash(Pack::pack(&CKB_SUDT_SCRIPT_ARGS))
+                        .amount(Pack::pack(&ckb_limit))
+                        .build(),
+                )
+                .build(),
+        )
+        .build();
+    let rollup_config = RollupConfig::new_builder()
+        .stake_script_type_hash(Pack::pack(&stake_script_type_hash))
+        .custodian_script_type_hash(Pack::pack(&custodian_script_type_hash))
+        .withdrawal_script_type_hash(Pack::pack(&withdrawal_script_type_hash))
+        .allowed_eoa_type_hashes(PackVec::pack(vec![AllowedTypeHash::new(
+            AllowedEoaType::Eth,
+            *ALWAYS_SUCCESS_CODE_HASH,
+        )]))
+        .withdrawal_limit(withdrawal_limit)
+        .rollup_bound_script_type_hash(Pack::pack(&rollup_bound_script_type_hash))
+        .build();
+
+    // setup chain
+    let mut chain = setup_chain(rollup.rollup_type_script.clone(), rollup_config.clone()).await;
+
+    let eth_registry_id = gw_common::builtins::ETH_REGISTRY_ACCOUNT_ID;
+
+    // Deposit account
+    let deposit_capacity: u64 = 1000000 * 10u64.pow(8);
+    let deposit_lock_args = {
+        let mut args = rollup.rollup_type_hash().to_vec();
+        args.extend_from_slice(&[1u8; 20]);
+        Pack::pack(&Bytes::from(args))
+    };
+    let account_script = Script::new_builder()
+        .code_hash(Pack::pack(&ALWAYS_SUCCESS_CODE_HASH.clone()))
+        .hash_type(ScriptHashType::Type.into())
+        .args(deposit_lock_args)
+        .build();
+    let deposit = DepositRequest::new_builder()
+        .capacity(Pack::pack(&deposit_capacity))
+        .script(account_script.to_owned())
+        .registry_id(Pack::pack(&eth_registry_id))
+        .build();
+
+    let block_result = {
+        let mem_pool = chain.mem_pool().as_ref().unwrap();
+        let mut mem_pool = mem_pool.lock().await;
+        construct_block(&chain, &mut mem_pool, vec![deposit.clone()])
+            .await
+            .unwrap()
+    };
+    let apply_deposits = L1Action {
+        context: L1ActionContext::SubmitBlock {
+            l2block: block_result.block.clone(),
+            deposit_requests: vec![deposit],
+            deposit_asset_scripts: Default::default(),
+            withdrawals: Default::default(),
+        },
+        transaction: build_sync_tx(
+            gw_types::packed::CellOutput::new_unchecked(rollup.rollup_cell.as_bytes()),
+            block_result,
+        ),
+        l2block_committed_info: L2BlockCommittedInfo::new_builder()
+            .number(Pack::pack(&1u64))
+            .build(),
+    };
+    let param = SyncParam {
+        updates: vec![apply_deposits],
+        reverts: Default::default(),
+    };
+    chain.sync(param).await.unwrap();
+    assert!(chain.last_sync_event().is_success());
+
+    // Withdraw
+    let withdrawal = {
+        let raw = RawWithdrawalRequest::new_builder()
+            .capacity(Pack::pack(&deposit_capacity))
+            .account_script_hash(Pack::pack(&account_script.hash()))
+            .owner_lock_hash(Pack::pack(&account_script.hash()))
+            .registry_id(Pack::pack(&eth_registry_id))
+            .build();
+        let request = WithdrawalRequest::new_builder().raw(raw).build();
+        WithdrawalRequestExtra::new_builder()
+            .request(request)
+            .owner_lock(account_script.clone())
+            .build()
+    };
+
+    // submit a new block
+    let block_result = {
+        let mem_pool = chain.mem_pool().as_ref().unwrap();
+        let mut mem_pool = mem_pool.lock().await;
+        mem_pool.push_withdrawal_request(withdrawal).await.unwrap();
+        mem_pool.reset_mem_block().await.unwrap();
+        construct_block(&chain, &mut mem_pool, Vec::default())
+            .await
+            .unwrap()
+    };
+    assert_eq!(block_result.block.withdrawals().len(), 1);
+    let block_number: u64 = GWUnpack::unpack(&block_result.block.raw().number());
+
+    // deploy scripts
+    let param = CellContextParam {
+        stake_lock_type,
+        custodian_lock_type,
+        withdrawal_lock_type,
+        rollup_bound_lock_type,
+        ..Default::default()
+    };
+    let mut ctx = CellContext::new(&rollup_config, param);
+
+    // build withdrawal limit state input and output
+    let build_state = |records: &[(u64, u128)]| {
+        let records = records.iter().map(|(number, amount)| {
+            WithdrawalRecord::new_builder()
+                .block_number(Pack::pack(number))
+                .sudt_script_hash(Pack::pack(&CKB_SUDT_SCRIPT_ARGS))
+                .amount(Pack::pack(amount))
+                .build()
+        });
+        WithdrawalLimitState::new_builder()
+            .records(WithdrawalRecordVec::new_builder().extend(records).build())
+            .build()
+            .as_bytes()
+    };
+    let state_cell = build_rollup_locked_cell(
+        &rollup.rollup_type_hash(),
+        &rollup_bound_script_type_hash,
+        capacity,
+        Bytes::default(),
+    )
+    .as_builder()
+    .type_(CKBPack::pack(&Some(state_cell_type)))
+    .build();
+    let input_state_cell = {
+        let out_point = ctx.insert_cell(state_cell.clone(), build_state(&prev_records));
+        CellInput::new_builder().previous_output(out_point).build()
+    };
+    let output_state_data = {
+        let mut records: Vec<_> = prev_records
+            .into_iter()
+            .filter(|(number, _)| number + WINDOW_BLOCKS > block_number)
+            .collect();
+        records.push((block_number, deposit_capacity.into()));
+        build_state(&records)
+    };
+
+    let global_state = chain
+        .local_state()
+        .last_global_state()
+        .as_builder()
+        .version(1u8.into())
+        .build();
+
+    // build custodian input
+    let input_custodian_cell = {
+        let cell = build_rollup_locked_cell(
+            &rollup.rollup_type_hash(),
+            &custodian_script_type_hash,
+            deposit_capacity,
+            CustodianLockArgs::default().as_bytes(),
+        );
+
+        let out_point = ctx.insert_cell(cell, Bytes::default());
+        CellInput::new_builder().previous_output(out_point).build()
+    };
+
+    // build withdrawal output
+    let output_withdrawal_cell = {
+        let lock_args = WithdrawalLockArgs::new_builder()
+            .withdrawal_block_number(block_result.block.raw().number())
+            .withdrawal_block_hash(Pack::pack(&block_result.block.raw().hash()))
+            .account_script_hash(Pack::pack(&account_script.hash()))
+            .owner_lock_hash(Pack::pack(&account_script.hash()))
+            .build();
+
+        let mut args = lock_args.as_slice().to_vec();
+        args.extend_from_slice(&(account_script.as_bytes().len() as u32).to_be_bytes());
+        args.extend_from_slice(&account_script.as_bytes());
+
+        build_rollup_locked_cell(
+            &rollup.rollup_type_hash(),
+            &withdrawal_script_type_hash,
+            deposit_capacity,
+            Bytes::from(args),
+        )
+    };
+
+    // verify submit block
+    let tx = rollup
+        .build_submit_block_tx(
+            &mut ctx,
+            &stake_script_type_hash,
+            &global_state,
+            block_result,
+        )
+        .input(input_custodian_cell)
+        .output(output_withdrawal_cell)
+        .output_data(CKBPack::pack(&Bytes::default()))
+        .input(input_state_cell)
+        .output(state_cell)
+        .output_data(CKBPack::pack(&output_state_data))
+        .cell_dep(ctx.custodian_lock_dep.clone())
+        .cell_dep(ctx.withdrawal_lock_dep.clone())
+        .cell_dep(ctx.rollup_bound_lock_dep.clone())
+        .build();
+    ctx.verify_tx(tx)
+}
+
+#[tokio::test]
+async fn test_withdrawal_limit() {
+    let limit = 1000000 * 10u128.pow(8);
+    verify_withdrawal_limit(limit, vec![])
+        .await
+        .expect("return success");
+}
+
+#[tokio::test]
+async fn test_withdrawal_limit_exceeded() {
+    let limit = 1000000 * 10u128.pow(8);
+    // withdrew 1 CKB in the previous block, which is still in the window
+    let err = verify_withdrawal_limit(limit, vec![(1, 10u128.pow(8))])
+        .await
+        .unwrap_err();
+    assert_error_eq!(
+        err,
+        expected_state_validator_error(WITHDRAWAL_LIMIT_EXCEEDED_ERROR)
+    );
+}
+
+/// Pause the rollup with the withdrawal limit state cell in the transaction,
+/// the state cell is locked by the rollup bound lock if `bound`
+fn verify_pause_with_state_cell(
+    bound: bool,
+    post_records: Vec<(u64, u128)>,
+) -> Result<ckb_types::core::Cycle, ckb_error::Error> {
+    let _ = env_logger::builder().is_test(true).try_init();
+
+    let capacity = 1000_00000000u64;
+    let rollup = RollupFixture::default();
+
+    // rollup config & governance
+    let governance_lock = build_type_id_script(b"governance_lock");
+    let governance_lock_hash: [u8; 32] = governance_lock.calc_script_hash().unpack();
+    let state_cell_type = build_type_id_script(b"withdrawal_limit_state_type_id");
+    let state_type_hash: [u8; 32] = state_cell_type.calc_script_hash().unpack();
+    let rollup_bound_lock_type = build_type_id_script(b"rollup_bound_lock_type_id");
+    let rollup_bound_script_type_hash: [u8; 32] =
+        rollup_bound_lock_type.calc_script_hash().unpack();
+    let withdrawal_limit = WithdrawalLimitConfig::new_builder()
+        .window_blocks(Pack::pack(&WINDOW_BLOCKS))
+        .state_type_hash(Pack::pack(&state_type_hash))
+        .build();
+    let rollup_config = RollupConfig::new_builder()
+        .governance_lock_hash(Pack::pack(&governance_lock_hash))
+        .withdrawal_limit(withdrawal_limit)
+        .rollup_bound_script_type_hash(Pack::pack(&rollup_bound_script_type_hash))
+        .build();
+    let rollup_config_hash: [u8; 32] =
+        ckb_types::packed::CellOutput::calc_data_hash(&rollup_config.as_bytes()).unpack();
+    let param = CellContextParam {
+        rollup_bound_lock_type,
+        ..Default::default()
+    };
+    let mut ctx = CellContext::new(&rollup_config, param);
+
+    let global_state = GlobalState::new_builder()
+        .rollup_config_hash(Pack::pack(&rollup_config_hash))
+        .version(1u8.into())
+        .build();
+    let post_global_state = {
+        let status: u8 = Status::Paused.into();
+        global_state
+            .clone()
+            .as_builder()
+            .status(status.into())
+            .build()
+    };
+
+    // the governance authorizes the pause
+    let governance_input = {
+        let cell = build_always_success_cell(capacity, None)
+            .as_builder()
+            .lock(governance_lock)
+            .build();
+        let out_point = ctx.insert_cell(cell, Bytes::default());
+        CellInput::new_builder().previous_output(out_point).build()
+    };
+
+    // build withdrawal limit state input and output
+    let build_state = |records: &[(u64, u128)]| {
+        let records = records.iter().map(|(number, amount)| {
+            WithdrawalRecord::new_builder()
+                .block_number(Pack::pack(number))
+                .sudt_script_hash(Pack::pack(&CKB_SUDT_SCRIPT_ARGS))
+                .amount(Pack::pack(amount))
+                .build()
+        });
+        WithdrawalLimitState::new_builder()
+            .records(WithdrawalRecordVec::new_builder().extend(records).build())
+            .build()
+            .as_bytes()
+    };
+    let state_cell = if bound {
+        build_rollup_locked_cell(
+            &rollup.rollup_type_hash(),
+            &rollup_bound_script_type_hash,
+            capacity,
+            Bytes::default(),
+        )
+        .as_builder()
+        .type_(CKBPack::pack(&Some(state_cell_type)))
+        .build()
+    } else {
+        build_always_success_cell(capacity, Some(state_cell_type))
+    };
+    let input_state_cell = {
+        let out_point = ctx.insert_cell(state_cell.clone(), build_state(&[(1, 10u128.pow(8))]));
+        CellInput::new_builder().previous_output(out_point).build()
+    };
+
+    let action = RollupActionUnion::RollupSetPaused(
+        RollupSetPaused::new_builder().paused(1u8.into()).build(),
+    );
+    let tx = rollup
+        .build_action_tx(
+            &mut ctx,
+            &global_state,
+            &post_global_state,
+            Default::default(),
+            action,
+        )
+        .input(governance_input)
+        .input(input_state_cell)
+        .output(state_cell)
+        .output_data(CKBPack::pack(&build_state(&post_records)))
+        .cell_dep(ctx.rollup_bound_lock_dep.clone())
+        .build();
+    ctx.verify_tx(tx)
+}
+
+#[test]
+fn test_withdrawal_limit_state_cell_unchanged() {
+    verify_pause_with_state_cell(true, vec![(1, 10u128.pow(8))]).expect("return success");
+}
+
+#[test]
+fn test_withdrawal_limit_state_cell_changed_by_other_actions() {
+    let err = verify_pause_with_state_cell(true, vec![]).unwrap_err();
+    assert_error_eq!(
+        err,
+        expected_state_validator_error(INVALID_WITHDRAWAL_LIMIT_CELL_ERROR)
+    );
+}
+
+#[test]
+fn test_withdrawal_limit_state_cell_unbound_lock() {
+    let err = verify_pause_with_state_cell(false, vec![(1, 10u128.pow(8))]).unwrap_err();
+    assert_error_eq!(
+        err,
+        expected_state_validator_error(INVALID_WITHDRAWAL_LIMIT_CELL_ERROR)
+    );
+}
@@ -1,21 +1,33 @@
 use crate::script_tests::utils::layer1::{
-    always_success_script, build_resolved_tx, random_out_point, DummyDataLoader, MAX_CYCLES,
+    always_success_script, build_resolved_tx, build_simple_tx_with_out_point_and_since,
+    random_out_point, since_timestamp, DummyDataLoader, MAX_CYCLES,
 };
 use crate::testing_tool::programs::{
-    ALWAYS_SUCCESS_CODE_HASH, ALWAYS_SUCCESS_PROGRAM, CHALLENGE_LOCK_PROGRAM,
-    ETH_ACCOUNT_LOCK_PROGRAM, SECP256K1_DATA, STATE_VALIDATOR_PROGRAM,
+    ALWAYS_SUCCESS_CODE_HASH, ALWAYS_SUCCESS_PROGRAM, BATCH_DEPOSIT_LOCK_PROGRAM,
+    CHALLENGE_LOCK_PROGRAM, CUSTODIAN_LOCK_PROGRAM, ETH_ACCOUNT_LOCK_PROGRAM,
+    FORCED_WITHDRAWAL_LOCK_PROGRAM, ROLLUP_BOUND_LOCK_PROGRAM, SECP256K1_DATA, STAKE_LOCK_PROGRAM,
+    STATE_VALIDATOR_CODE_HASH, STATE_VALIDATOR_PROGRAM,
 };
 use ckb_chain_spec::consensus::ConsensusBuilder;
-use ckb_script::{TransactionScriptsVerifier, TxVerifyEnv};
+use ckb_script::{ScriptError, TransactionScriptsVerifier, TxVerifyEnv};
 use ckb_types::core::hardfork::HardForkSwitch;
 use ckb_types::core::HeaderView;
 use ckb_types::{
-    packed::{CellDep, CellOutput},
+    packed::{CellDep, CellInput, CellOutput},
     prelude::Pack as CKBPack,
 };
+use gw_block_producer::produce_block::ProduceBlockResult;
 use gw_ckb_hardfork::{GLOBAL_CURRENT_EPOCH_NUMBER, GLOBAL_HARDFORK_SWITCH};
 use gw_common::blake2b::new_blake2b;
-use gw_types::{bytes::Bytes, core::ScriptHashType, packed::RollupConfig, prelude::*};
+use gw_types::{
+    bytes::Bytes,
+    core::ScriptHashType,
+    packed::{
+        GlobalState, RollupAction, RollupActionUnion, RollupConfig, RollupSubmitBlock, Script,
+        StakeLockArgs,
+    },
+    prelude::*,
+};
 
 use std::sync::atomic::Ordering;
 
@@ -23,8 +35,11 @@ pub struct CellContextParam {
     pub stake_lock_type: ckb_types::packed::Script,
     pub challenge_lock_type: ckb_types::packed::Script,
     pub deposit_lock_type: ckb_types::packed::Script,
+    pub batch_deposit_lock_type: ckb_types::packed::Script,
     pub custodian_lock_type: ckb_types::packed::Script,
     pub withdrawal_lock_type: ckb_types::packed::Script,
+    pub forced_withdrawal_lock_type: ckb_types::packed::Script,
+    pub rollup_bound_lock_type: ckb_types::packed::Script,
     pub l2_sudt_type: ckb_types::packed::Script,
     pub always_success_type: ckb_types::packed::Script,
     pub eoa_lock_type: ckb_types::packed::Script,
@@ -37,8 +52,11 @@ impl Default for CellContextParam {
             stake_lock_type: random_type_id_script(),
             challenge_lock_type: random_type_id_script(),
             deposit_lock_type: random_type_id_script(),
+            batch_deposit_lock_type: random_type_id_script(),
             custodian_lock_type: random_type_id_script(),
             withdrawal_lock_type: random_type_id_script(),
+            forced_withdrawal_lock_type: random_type_id_script(),
+            rollup_bound_lock_type: random_type_id_script(),
             l2_sudt_type: random_type_id_script(),
             always_success_type: random_type_id_script(),
             eoa_lock_type: random_type_id_script(),
@@ -54,8 +72,11 @@ pub struct CellContext {
     pub stake_lock_dep: CellDep,
     pub challenge_lock_dep: CellDep,
     pub deposit_lock_dep: CellDep,
+    pub batch_deposit_lock_dep: CellDep,
     pub custodian_lock_dep: CellDep,
     pub withdrawal_lock_dep: CellDep,
+    pub forced_withdrawal_lock_dep: CellDep,
+    pub rollup_bound_lock_dep: CellDep,
     pub always_success_dep: CellDep,
     pub l2_sudt_dep: CellDep,
     /// default EoA lock(always success)
@@ -177,10 +198,10 @@ impl CellContext {
                 stake_out_point.clone(),
                 (
                     CellOutput::new_builder()
-                        .capacity(CKBPack::pack(&(ALWAYS_SUCCESS_PROGRAM.len() as u64)))
+                        .capacity(CKBPack::pack(&(STAKE_LOCK_PROGRAM.len() as u64)))
                         .type_(CKBPack::pack(&Some(param.stake_lock_type.clone())))
                         .build(),
-                    ALWAYS_SUCCESS_PROGRAM.clone(),
+                    STAKE_LOCK_PROGRAM.clone(),
                 ),
             );
             CellDep::new_builder().out_point(stake_out_point).build()
@@ -213,16 +234,30 @@ impl CellContext {
             );
             CellDep::new_builder().out_point(out_point).build()
         };
+        let batch_deposit_lock_dep = {
+            let out_point = random_out_point();
+            data_loader.cells.insert(
+                out_point.clone(),
+                (
+                    CellOutput::new_builder()
+                        .capacity(CKBPack::pack(&(BATCH_DEPOSIT_LOCK_PROGRAM.len() as u64)))
+                        .type_(CKBPack::pack(&Some(param.batch_deposit_lock_type.clone())))
+                        .build(),
+                    BATCH_DEPOSIT_LOCK_PROGRAM.clone(),
+                ),
+            );
+            CellDep::new_builder().out_point(out_point).build()
+        };
         let custodian_lock_dep = {
             let out_point = random_out_point();
             data_loader.cells.insert(
                 out_point.clone(),
                 (
                     CellOutput::new_builder()
-                        .capacity(CKBPack::pack(&(ALWAYS_SUCCESS_PROGRAM.len() as u64)))
+                        .capacity(CKBPack::pack(&(CUSTODIAN_LOCK_PROGRAM.len() as u64)))
                         .type_(CKBPack::pack(&Some(param.custodian_lock_type.clone())))
                         .build(),
-                    ALWAYS_SUCCESS_PROGRAM.clone(),
+                    CUSTODIAN_LOCK_PROGRAM.clone(),
                 ),
             );
             CellDep::new_builder().out_point(out_point).build()
@@ -241,6 +276,38 @@ impl CellContext {
             );
             CellDep::new_builder().out_point(out_point).build()
         };
+        let forced_withdrawal_lock_dep = {
+            let out_point = random_out_point();
+            data_loader.cells.insert(
+                out_point.clone(),
+                (
+                    CellOutput::new_builder()
+                        .capacity(CKBPack::pack(
+                            &(FORCED_WITHDRAWAL_LOCK_PROGRAM.len() as u64),
+                        ))
+                        .type_(CKBPack::pack(&Some(
+                            param.forced_withdrawal_lock_type.clone(),
+                        )))
+                        .build(),
+                    FORCED_WITHDRAWAL_LOCK_PROGRAM.clone(),
+                ),
+            );
+            CellDep::new_builder().out_point(out_point).build()
+        };
+        let rollup_bound_lock_dep = {
+            let out_point = random_out_point();
+            data_loader.cells.insert(
+                out_point.clone(),
+                (
+                    CellOutput::new_builder()
+                        .capacity(CKBPack::pack(&(ROLLUP_BOUND_LOCK_PROGRAM.len() as u64)))
+                        .type_(CKBPack::pack(&Some(param.rollup_bound_lock_type.clone())))
+                        .build(),
+                    ROLLUP_BOUND_LOCK_PROGRAM.clone(),
+                ),
+            );
+            CellDep::new_builder().out_point(out_point).build()
+        };
         CellContext {
             inner: data_loader,
             rollup_config_dep,
@@ -249,8 +316,11 @@ impl CellContext {
             state_validator_dep,
             challenge_lock_dep,
             deposit_lock_dep,
+            batch_deposit_lock_dep,
             custodian_lock_dep,
             withdrawal_lock_dep,
+            forced_withdrawal_lock_dep,
+            rollup_bound_lock_dep,
             l2_sudt_dep,
             eoa_lock_dep,
             eth_lock_dep,
@@ -303,6 +373,151 @@ impl CellContext {
     }
 }
 
+/// A rollup cell typed by the state validator, the common fixture of the rollup action tests
+pub struct RollupFixture {
+    pub input_out_point: ckb_types::packed::OutPoint,
+    pub rollup_type_script: Script,
+    pub rollup_cell: CellOutput,
+}
+
+impl Default for RollupFixture {
+    fn default() -> Self {
+        let input_out_point = random_out_point();
+        let type_id = calculate_state_validator_type_id(input_out_point.clone());
+        let rollup_type_script = Script::new_builder()
+            .code_hash(Pack::pack(&*STATE_VALIDATOR_CODE_HASH))
+            .hash_type(ScriptHashType::Data.into())
+            .args(Pack::pack(&Bytes::from(type_id.to_vec())))
+            .build();
+        let rollup_cell = build_always_success_cell(
+            1000_00000000u64,
+            Some(ckb_types::packed::Script::new_unchecked(
+                rollup_type_script.as_bytes(),
+            )),
+        );
+        RollupFixture {
+            input_out_point,
+            rollup_type_script,
+            rollup_cell,
+        }
+    }
+}
+
+impl RollupFixture {
+    pub fn rollup_type_hash(&self) -> [u8; 32] {
+        self.rollup_type_script.hash()
+    }
+
+    /// Build a tx updating the rollup cell from `prev_state` to `post_state` by the `action`,
+    /// the caller appends the cells and deps of the action
+    pub fn build_action_tx(
+        &self,
+        ctx: &mut CellContext,
+        prev_state: &GlobalState,
+        post_state: &GlobalState,
+        since: ckb_types::packed::Uint64,
+        action: RollupActionUnion,
+    ) -> ckb_types::core::TransactionBuilder {
+        let witness = {
+            let rollup_action = RollupAction::new_builder().set(action).build();
+            ckb_types::packed::WitnessArgs::new_builder()
+                .output_type(CKBPack::pack(&Some(rollup_action.as_bytes())))
+                .build()
+        };
+        build_simple_tx_with_out_point_and_since(
+            &mut ctx.inner,
+            (self.rollup_cell.clone(), prev_state.as_bytes()),
+            (self.input_out_point.clone(), since),
+            (self.rollup_cell.clone(), post_state.as_bytes()),
+        )
+        .as_advanced_builder()
+        .cell_dep(ctx.always_success_dep.clone())
+        .cell_dep(ctx.state_validator_dep.clone())
+        .cell_dep(ctx.rollup_config_dep.clone())
+        .witness(CKBPack::pack(&witness.as_bytes()))
+    }
+
+    /// Build the input stake cell of the test chain producer and the output staking
+    /// on the `block_number`
+    pub fn build_stake_cells(
+        &self,
+        ctx: &mut CellContext,
+        stake_script_type_hash: &[u8; 32],
+        block_number: u64,
+    ) -> (CellInput, CellOutput) {
+        let stake_capacity = 10000_00000000u64;
+        let input_stake_cell = {
+            let cell = build_rollup_locked_cell(
+                &self.rollup_type_hash(),
+                stake_script_type_hash,
+                stake_capacity,
+                StakeLockArgs::default().as_bytes(),
+            );
+            let out_point = ctx.insert_cell(cell, Bytes::default());
+            CellInput::new_builder().previous_output(out_point).build()
+        };
+        let output_stake_cell = {
+            let lock_args = StakeLockArgs::new_builder()
+                .stake_block_number(Pack::pack(&block_number))
+                .build();
+            build_rollup_locked_cell(
+                &self.rollup_type_hash(),
+                stake_script_type_hash,
+                stake_capacity,
+                lock_args.as_bytes(),
+            )
+        };
+        (input_stake_cell, output_stake_cell)
+    }
+
+    /// Build a tx submitting the block produced on the `prev_state`, the rollup input since
+    /// is the block timestamp
+    pub fn build_submit_block_tx(
+        &self,
+        ctx: &mut CellContext,
+        stake_script_type_hash: &[u8; 32],
+        prev_state: &GlobalState,
+        block_result: ProduceBlockResult,
+    ) -> ckb_types::core::TransactionBuilder {
+        let since = since_timestamp(block_result.block.raw().timestamp().unpack());
+        self.build_submit_block_tx_with_since(
+            ctx,
+            stake_script_type_hash,
+            prev_state,
+            block_result,
+            since,
+        )
+    }
+
+    /// Build a tx submitting the block produced on the `prev_state` at the layer-1 time `since`
+    pub fn build_submit_block_tx_with_since(
+        &self,
+        ctx: &mut CellContext,
+        stake_script_type_hash: &[u8; 32],
+        prev_state: &GlobalState,
+        block_result: ProduceBlockResult,
+        since: ckb_types::packed::Uint64,
+    ) -> ckb_types::core::TransactionBuilder {
+        let block = block_result.block;
+        let post_state = block_result
+            .global_state
+            .as_builder()
+            .tip_block_timestamp(block.raw().timestamp())
+            .version(1u8.into())
+            .build();
+        let (input_stake_cell, output_stake_cell) =
+            self.build_stake_cells(ctx, stake_script_type_hash, block.raw().number().unpack());
+        let action = RollupActionUnion::RollupSubmitBlock(
+            RollupSubmitBlock::new_builder().block(block).build(),
+        );
+        self.build_action_tx(ctx, prev_state, &post_state, since, action)
+            .input(input_stake_cell)
+            .output(output_stake_cell)
+            .output_data(CKBPack::pack(&Bytes::default()))
+            .cell_dep(ctx.stake_lock_dep.clone())
+    }
+}
+
 pub fn build_type_id_script(name: &[u8]) -> ckb_types::packed::Script {
     ckb_types::packed::Script::new_builder()
         .code_hash(CKBPack::pack(&ALWAYS_SUCCESS_CODE_HASH.clone()))
@@ -360,3 +575,15 @@ pub fn calculate_state_validator_type_id(input_out_point: ckb_types::packed::Out
     hasher.finalize(&mut expected_type_id);
     expected_type_id
 }
+
+pub fn expected_state_validator_error(err_code: i8) -> ckb_error::Error {
+    ScriptError::ValidationFailure(
+        format!(
+            "by-data-hash/{}",
+            ckb_types::H256(*STATE_VALIDATOR_CODE_HASH)
+        ),
+        err_code,
+    )
+    .input_type_script(0)
+    .into()
+}
@@ -12,18 +12,29 @@ use ckb_script::ScriptError;
 use ckb_types::core::TransactionView;
 use ckb_types::prelude::{Builder, Entity};
 use gw_common::blake2b::new_blake2b;
+use gw_common::h256_ext::H256Ext;
+use gw_common::sparse_merkle_tree::default_store::DefaultStore;
+use gw_common::H256;
 use gw_types::bytes::Bytes;
-use gw_types::core::ScriptHashType;
+use gw_types::core::{ScriptHashType, Status};
 use gw_types::packed::{
-    CellDep, CellInput, CellOutput, GlobalState, OutPoint, RollupConfig, Script,
-    UnlockWithdrawalViaFinalize, UnlockWithdrawalWitness, UnlockWithdrawalWitnessUnion,
-    WithdrawalLockArgs, WitnessArgs,
+    BlockMerkleState, Byte32Vec, CellDep, CellInput, CellOutput, CustodianLockArgs, GlobalState,
+    OutPoint, RawL2Block, RollupAction, RollupActionUnion, RollupConfig, RollupSubmitBlock, Script,
+    UnlockWithdrawalViaEscape, UnlockWithdrawalViaFinalize, UnlockWithdrawalViaMerge,
+    UnlockWithdrawalViaRevert, UnlockWithdrawalViaTrade, UnlockWithdrawalWitness,
+    UnlockWithdrawalWitnessUnion, WithdrawalLockArgs, WitnessArgs,
 };
 use gw_types::prelude::Pack;
 use secp256k1::rand::rngs::OsRng;
 use secp256k1::{Message, Secp256k1, SecretKey};
 
+const INVALID_ARGS_ERROR: i8 = 5;
+const INVALID_OUTPUT_ERROR: i8 = 7;
 const OWNER_CELL_NOT_FOUND_ERROR: i8 = 8;
+const INVALID_STATUS_ERROR: i8 = 21;
+const PRUNED_REVERTED_BLOCK_ERROR: i8 = 56;
+
+const ESCAPE_TIMEOUT_BLOCKS: u64 = 1000;
 
 #[test]
 fn test_unlock_withdrawal_via_finalize_by_input_owner_cell() {
@@ -482,6 +493,651 @@ fn test_unlock_withdrawal_via_finalize_fallback_to_input_owner_cell() {
     verify_ctx.verify_tx(sign_tx).expect("success");
 }
 
+#[test]
+fn test_unlock_withdrawal_via_trade() {
+    init_env_log();
+
+    const DEFAULT_CAPACITY: u64 = 1000 * 10u64.pow(8);
+    const PAYMENT_CAPACITY: u64 = 990 * 10u64.pow(8);
+    const WITHDRAWAL_BLOCK_NUMBER: u64 = 42;
+
+    let rollup_type_script = random_always_success_script();
+    let rollup_type_hash = rollup_type_script.hash();
+    let (mut verify_ctx, script_ctx) = build_verify_context();
+
+    let build_rollup_dep = |verify_ctx: &mut CellContext, last_finalized_block_number: u64| {
+        let global_state = GlobalState::new_builder()
+            .last_finalized_block_number(last_finalized_block_number.pack())
+            .build();
+        let output = CellOutput::new_builder()
+            .lock(random_always_success_script())
+            .type_(Some(rollup_type_script.clone()).pack())
+            .capacity(DEFAULT_CAPACITY.pack())
+            .build();
+        let out_point = verify_ctx.insert_cell(output.to_ckb(), global_state.as_bytes());
+        CellDep::new_builder().out_point(out_point.to_gw()).build()
+    };
+
+    let owner_lock = random_always_success_script();
+    let provider_lock = random_always_success_script();
+    // withdrawal cells of different block hashes are in different lock groups
+    let build_withdrawal_cell = |owner_lock: &Script, block_hash: [u8; 32]| {
+        let lock_args = WithdrawalLockArgs::new_builder()
+            .account_script_hash([1u8; 32].pack())
+            .withdrawal_block_hash(block_hash.pack())
+            .withdrawal_block_number(WITHDRAWAL_BLOCK_NUMBER.pack())
+            .owner_lock_hash(owner_lock.hash().pack())
+            .build();
+
+        let mut args = Vec::new();
+        args.extend_from_slice(&lock_args.as_bytes());
+        args.extend_from_slice(&(owner_lock.as_bytes().len() as u32).to_be_bytes());
+        args.extend_from_slice(&owner_lock.as_bytes());
+        build_rollup_locked_cell(
+            &rollup_type_hash,
+            &script_ctx.withdrawal.script.hash(),
+            DEFAULT_CAPACITY,
+            Bytes::from(args),
+        )
+    };
+    let owner_input = {
+        let output = CellOutput::new_builder()
+            .capacity(DEFAULT_CAPACITY.pack())
+            .lock(owner_lock.clone())
+            .build();
+
+        let out_point = verify_ctx.insert_cell(output.to_ckb(), Bytes::new());
+        CellInput::new_builder()
+            .previous_output(out_point.to_gw())
+            .build()
+    };
+    let provider_input = {
+        let output = CellOutput::new_builder()
+            .capacity((DEFAULT_CAPACITY * 3).pack())
+            .lock(provider_lock.clone())
+            .build();
+
+        let out_point = verify_ctx.insert_cell(output.to_ckb(), Bytes::new());
+        CellInput::new_builder()
+            .previous_output(out_point.to_gw())
+            .build()
+    };
+    let unlock_via_trade_witness = {
+        let unlock_args = UnlockWithdrawalViaTrade::new_builder()
+            .owner_lock(provider_lock.clone())
+            .payment_capacity(PAYMENT_CAPACITY.pack())
+            .build();
+        let unlock_witness = UnlockWithdrawalWitness::new_builder()
+            .set(UnlockWithdrawalWitnessUnion::UnlockWithdrawalViaTrade(
+                unlock_args,
+            ))
+            .build();
+        WitnessArgs::new_builder()
+            .lock(Some(unlock_witness.as_bytes()).pack())
+            .build()
+    };
+    // trade the withdrawals of `block_hashes`, each is re-created with the provider as
+    // the new owner at the same index
+    let build_trade_tx = |verify_ctx: &mut CellContext,
+                          block_hashes: &[[u8; 32]],
+                          owner_capacity: u64,
+                          last_finalized_block_number: u64| {
+        let mut tx = ckb_types::core::TransactionBuilder::default();
+        for block_hash in block_hashes {
+            let withdrawal_cell = build_withdrawal_cell(&owner_lock, *block_hash);
+            let out_point = verify_ctx.insert_cell(withdrawal_cell, 0u128.pack().as_bytes());
+            let traded_withdrawal_cell = build_withdrawal_cell(&provider_lock, *block_hash);
+            tx = tx
+                .input(
+                    CellInput::new_builder()
+                        .previous_output(out_point.to_gw())
+                        .build()
+                        .to_ckb(),
+                )
+                .witness(unlock_via_trade_witness.as_bytes().to_ckb())
+                .output(traded_withdrawal_cell)
+                .output_data(0u128.pack().as_bytes().to_ckb());
+        }
+        let owner_output = CellOutput::new_builder()
+            .capacity(owner_capacity.pack())
+            .lock(owner_lock.clone())
+            .build();
+        let provider_output = CellOutput::new_builder()
+            .capacity((DEFAULT_CAPACITY * 4 - owner_capacity).pack())
+            .lock(provider_lock.clone())
+            .build();
+        let rollup_dep = build_rollup_dep(verify_ctx, last_finalized_block_number);
+        tx.input(owner_input.to_ckb())
+            .witness(Default::default())
+            .input(provider_input.to_ckb())
+            .witness(Default::default())
+            .output(owner_output.to_ckb())
+            .output_data(Bytes::new().to_ckb())
+            .output(provider_output.to_ckb())
+            .output_data(Bytes::new().to_ckb())
+            .cell_dep(script_ctx.withdrawal.dep.to_ckb())
+            .cell_dep(rollup_dep.to_ckb())
+            .build()
+    };
+
+    let expected_err = |err_code: i8| {
+        ScriptError::ValidationFailure(
+            format!(
+                "by-type-hash/{}",
+                ckb_types::H256(script_ctx.withdrawal.script.hash())
+            ),
+            err_code,
+        )
+        .input_lock_script(0)
+    };
+    let unfinalized_number = WITHDRAWAL_BLOCK_NUMBER - 1;
+    // the provider pays less than the agreed capacity
+    let owner_capacity = DEFAULT_CAPACITY + PAYMENT_CAPACITY - 1;
+    let tx = build_trade_tx(
+        &mut verify_ctx,
+        &[[2u8; 32]],
+        owner_capacity,
+        unfinalized_number,
+    );
+    let err = verify_ctx.verify_tx(tx).unwrap_err();
+    assert_error_eq!(err, expected_err(INVALID_OUTPUT_ERROR));
+    // the owner output has the agreed capacity, but the owner input isn't returned
+    let tx = build_trade_tx(
+        &mut verify_ctx,
+        &[[2u8; 32]],
+        PAYMENT_CAPACITY,
+        unfinalized_number,
+    );
+    let err = verify_ctx.verify_tx(tx).unwrap_err();
+    assert_error_eq!(err, expected_err(INVALID_OUTPUT_ERROR));
+    // two withdrawal groups can't share the payment of one
+    let owner_capacity = DEFAULT_CAPACITY + PAYMENT_CAPACITY;
+    let block_hashes = [[2u8; 32], [3u8; 32]];
+    let tx = build_trade_tx(
+        &mut verify_ctx,
+        &block_hashes,
+        owner_capacity,
+        unfinalized_number,
+    );
+    let err = verify_ctx.verify_tx(tx).unwrap_err();
+    assert_error_eq!(err, expected_err(INVALID_OUTPUT_ERROR));
+    // a finalized withdrawal can't be traded
+    let finalized_number = WITHDRAWAL_BLOCK_NUMBER;
+    let tx = build_trade_tx(
+        &mut verify_ctx,
+        &[[2u8; 32]],
+        owner_capacity,
+        finalized_number,
+    );
+    let err = verify_ctx.verify_tx(tx).unwrap_err();
+    assert_error_eq!(err, expected_err(INVALID_ARGS_ERROR));
+
+    let tx = build_trade_tx(
+        &mut verify_ctx,
+        &[[2u8; 32]],
+        owner_capacity,
+        unfinalized_number,
+    );
+    verify_ctx.verify_tx(tx).expect("success");
+    let owner_capacity = DEFAULT_CAPACITY + PAYMENT_CAPACITY * 2;
+    let tx = build_trade_tx(
+        &mut verify_ctx,
+        &block_hashes,
+        owner_capacity,
+        unfinalized_number,
+    );
+    verify_ctx.verify_tx(tx).expect("success");
+}
+
+#[test]
+fn test_unlock_withdrawal_via_merge() {
+    init_env_log();
+
+    const DEFAULT_CAPACITY: u64 = 1000 * 10u64.pow(8);
+
+    let rollup_type_script = random_always_success_script();
+    let rollup_type_hash = rollup_type_script.hash();
+    let (mut verify_ctx, script_ctx) = build_verify_context();
+
+    let last_finalized_block_number = rand::random::<u64>() + 100;
+    let rollup_cell = {
+        let global_state = GlobalState::new_builder()
+            .last_finalized_block_number(last_finalized_block_number.pack())
+            .build();
+
+        let output = CellOutput::new_builder()
+            .lock(random_always_success_script())
+            .type_(Some(rollup_type_script).pack())
+            .capacity(DEFAULT_CAPACITY.pack())
+            .build();
+
+        (output, global_state.as_bytes())
+    };
+    let rollup_dep = {
+        let out_point = verify_ctx.insert_cell(rollup_cell.0.to_ckb(), rollup_cell.1);
+        CellDep::new_builder().out_point(out_point.to_gw()).build()
+    };
+
+    let owner_lock = random_always_success_script();
+    let sudt_type = random_always_success_script();
+    let build_withdrawal_cell = |block_number: u64| {
+        let lock_args = WithdrawalLockArgs::new_builder()
+            .account_script_hash([1u8; 32].pack())
+            .withdrawal_block_hash([2u8; 32].pack())
+            .withdrawal_block_number(block_number.pack())
+            .owner_lock_hash(owner_lock.hash().pack())
+            .build();
+
+        let mut args = Vec::new();
+        args.extend_from_slice(&lock_args.as_bytes());
+        args.extend_from_slice(&(owner_lock.as_bytes().len() as u32).to_be_bytes());
+        args.extend_from_slice(&owner_lock.as_bytes());
+
+        build_rollup_locked_cell(
+            &rollup_type_hash,
+            &script_ctx.withdrawal.script.hash(),
+            DEFAULT_CAPACITY,
+            Bytes::from(args),
+        )
+        .to_gw()
+        .as_builder()
+        .type_(Some(sudt_type.clone()).pack())
+        .build()
+        .to_ckb()
+    };
+    let unlock_via_merge_witness = {
+        let unlock_args = UnlockWithdrawalViaMerge::new_builder().build();
+        let unlock_witness = UnlockWithdrawalWitness::new_builder()
+            .set(UnlockWithdrawalWitnessUnion::UnlockWithdrawalViaMerge(
+                unlock_args,
+            ))
+            .build();
+        WitnessArgs::new_builder()
+            .lock(Some(unlock_witness.as_bytes()).pack())
+            .build()
+    };
+    // the owner outputs an sUDT cell, and a cell of `foreign_capacity` under a foreign type
+    let foreign_type = random_always_success_script();
+    let build_merge_tx = |verify_ctx: &mut CellContext,
+                          block_numbers: [u64; 2],
+                          output_amount: u128,
+                          foreign_capacity: u64| {
+        let inputs: Vec<_> = block_numbers
+            .iter()
+            .zip([100u128, 200u128].iter())
+            .map(|(block_number, amount)| {
+                let out_point = verify_ctx.insert_cell(
+                    build_withdrawal_cell(*block_number),
+                    amount.pack().as_bytes(),
+                );
+                CellInput::new_builder()
+                    .previous_output(out_point.to_gw())
+                    .build()
+                    .to_ckb()
+            })
+            .collect();
+        let owner_output = CellOutput::new_builder()
+            .capacity((DEFAULT_CAPACITY * 2 - foreign_capacity).pack())
+            .lock(owner_lock.clone())
+            .type_(Some(sudt_type.clone()).pack())
+            .build();
+        let foreign_output = CellOutput::new_builder()
+            .capacity(foreign_capacity.pack())
+            .lock(owner_lock.clone())
+            .type_(Some(foreign_type.clone()).pack())
+            .build();
+        let mut tx = ckb_types::core::TransactionBuilder::default()
+            .inputs(inputs)
+            .output(owner_output.to_ckb())
+            .output_data(output_amount.pack().as_bytes().to_ckb())
+            .witness(unlock_via_merge_witness.as_bytes().to_ckb())
+            .witness(unlock_via_merge_witness.as_bytes().to_ckb())
+            .cell_dep(script_ctx.withdrawal.dep.to_ckb())
+            .cell_dep(verify_ctx.always_success_dep.clone())
+            .cell_dep(rollup_dep.to_ckb());
+        if foreign_capacity > 0 {
+            tx = tx
+                .output(foreign_output.to_ckb())
+                .output_data(Bytes::new().to_ckb());
+        }
+        tx.build()
+    };
+
+    // merge withdrawals of different blocks
+    let block_numbers = [
+        last_finalized_block_number.saturating_sub(1),
+        last_finalized_block_number,
+    ];
+    let tx = build_merge_tx(&mut verify_ctx, block_numbers, 300, 0);
+    verify_ctx.verify_tx(tx).expect("success");
+
+    // the owner output doesn't cover the sUDT amount
+    let block_numbers = [last_finalized_block_number; 2];
+    let tx = build_merge_tx(&mut verify_ctx, block_numbers, 299, 0);
+    let err = verify_ctx.verify_tx(tx).unwrap_err();
+    let expected_err = ScriptError::ValidationFailure(
+        format!(
+            "by-type-hash/{}",
+            ckb_types::H256(script_ctx.withdrawal.script.hash())
+        ),
+        INVALID_OUTPUT_ERROR,
+    )
+    .input_lock_script(0);
+    assert_error_eq!(err, expected_err);
+
+    // the capacity under a foreign type doesn't count
+    let block_numbers = [last_finalized_block_number; 2];
+    let tx = build_merge_tx(&mut verify_ctx, block_numbers, 300, DEFAULT_CAPACITY);
+    let err = verify_ctx.verify_tx(tx).unwrap_err();
+    let expected_err = ScriptError::ValidationFailure(
+        format!(
+            "by-type-hash/{}",
+            ckb_types::H256(script_ctx.withdrawal.script.hash())
+        ),
+        INVALID_OUTPUT_ERROR,
+    )
+    .input_lock_script(0);
+    assert_error_eq!(err, expected_err);
+
+    // unfinalized withdrawals can't be merged
+    let block_numbers = [last_finalized_block_number + 1; 2];
+    let tx = build_merge_tx(&mut verify_ctx, block_numbers, 300, 0);
+    verify_ctx.verify_tx(tx).expect_err("unfinalized");
+}
+
+#[test]
+fn test_unlock_withdrawal_via_escape() {
+    init_env_log();
+
+    const DEFAULT_CAPACITY: u64 = 1000 * 10u64.pow(8);
+
+    let rollup_type_script = random_always_success_script();
+    let rollup_type_hash = rollup_type_script.hash();
+    let (mut verify_ctx, script_ctx) = build_verify_context();
+
+    // the withdrawal block is in the block smt of the abandoned rollup
+    let withdrawal_block_number = rand::random::<u32>() as u64 + 100;
+    let withdrawal_block_hash = [2u8; 32];
+    let mut block_tree: gw_common::smt::SMT<DefaultStore<H256>> = Default::default();
+    let block_key: H256 = RawL2Block::compute_smt_key(withdrawal_block_number).into();
+    block_tree
+        .update(block_key, withdrawal_block_hash.into())
+        .unwrap();
+    let block_proof: Bytes = block_tree
+        .merkle_proof(vec![block_key])
+        .unwrap()
+        .compile(vec![(block_key, withdrawal_block_hash.into())])
+        .unwrap()
+        .0
+        .into();
+
+    let rollup_config_hash: [u8; 32] = {
+        let out_point = verify_ctx.rollup_config_dep.out_point();
+        let (_, data) = verify_ctx.inner.cells.get(&out_point).unwrap();
+        ckb_types::prelude::Unpack::unpack(&ckb_types::packed::CellOutput::calc_data_hash(data))
+    };
+    let build_rollup_dep =
+        |verify_ctx: &mut CellContext, status: Status, last_finalized_block_number: u64| {
+            let status: u8 = status.into();
+            let global_state = GlobalState::new_builder()
+                .rollup_config_hash(rollup_config_hash.pack())
+                .block(
+                    BlockMerkleState::new_builder()
+                        .merkle_root((*block_tree.root()).pack())
+                        .count((withdrawal_block_number + 1).pack())
+                        .build(),
+                )
+                .last_finalized_block_number(last_finalized_block_number.pack())
+                .status(status.into())
+                .build();
+            let output = CellOutput::new_builder()
+                .lock(random_always_success_script())
+                .type_(Some(rollup_type_script.clone()).pack())
+                .capacity(DEFAULT_CAPACITY.pack())
+                .build();
+            let out_point = verify_ctx.insert_cell(output.to_ckb(), global_state.as_bytes());
+            CellDep::new_builder().out_point(out_point.to_gw()).build()
+        };
+
+    let owner_lock = random_always_success_script();
+    let withdrawal_cell = {
+        let lock_args = WithdrawalLockArgs::new_builder()
+            .account_script_hash([1u8; 32].pack())
+            .withdrawal_block_hash(withdrawal_block_hash.pack())
+            .withdrawal_block_number(withdrawal_block_number.pack())
+            .owner_lock_hash(owner_lock.hash().pack())
+            .build();
+
+        let mut args = Vec::new();
+        args.extend_from_slice(&lock_args.as_bytes());
+        args.extend_from_slice(&(owner_lock.as_bytes().len() as u32).to_be_bytes());
+        args.extend_from_slice(&owner_lock.as_bytes());
+
+        build_rollup_locked_cell(
+            &rollup_type_hash,
+            &script_ctx.withdrawal.script.hash(),
+            DEFAULT_CAPACITY,
+            Bytes::from(args),
+        )
+    };
+    let unlock_via_escape_witness = {
+        let unlock_args = UnlockWithdrawalViaEscape::new_builder()
+            .block_proof(block_proof.pack())
+            .build();
+        let unlock_witness = UnlockWithdrawalWitness::new_builder()
+            .set(UnlockWithdrawalWitnessUnion::UnlockWithdrawalViaEscape(
+                unlock_args,
+            ))
+            .build();
+        WitnessArgs::new_builder()
+            .lock(Some(unlock_witness.as_bytes()).pack())
+            .build()
+    };
+    let build_escape_tx = |verify_ctx: &mut CellContext, rollup_dep: CellDep| {
+        let out_point = verify_ctx.insert_cell(withdrawal_cell.clone(), Bytes::new());
+        let input = CellInput::new_builder()
+            .previous_output(out_point.to_gw())
+            .build();
+        let owner_output = CellOutput::new_builder()
+            .capacity(DEFAULT_CAPACITY.pack())
+            .lock(owner_lock.clone())
+            .build();
+        ckb_types::core::TransactionBuilder::default()
+            .input(input.to_ckb())
+            .output(owner_output.to_ckb())
+            .output_data(Bytes::new().to_ckb())
+            .witness(unlock_via_escape_witness.as_bytes().to_ckb())
+            .cell_dep(script_ctx.withdrawal.dep.to_ckb())
+            .cell_dep(verify_ctx.always_success_dep.clone())
+            .cell_dep(verify_ctx.rollup_config_dep.clone())
+            .cell_dep(rollup_dep.to_ckb())
+            .build()
+    };
+    let expected_err = |code: i8| {
+        ScriptError::ValidationFailure(
+            format!(
+                "by-type-hash/{}",
+                ckb_types::H256(script_ctx.withdrawal.script.hash())
+            ),
+            code,
+        )
+        .input_lock_script(0)
+    };
+
+    // the withdrawal block is finalized in the abandoned rollup
+    let rollup_dep = build_rollup_dep(&mut verify_ctx, Status::Abandoned, withdrawal_block_number);
+    let tx = build_escape_tx(&mut verify_ctx, rollup_dep);
+    verify_ctx.verify_tx(tx).expect("success");
+
+    // the rollup isn't abandoned
+    let rollup_dep = build_rollup_dep(&mut verify_ctx, Status::Running, withdrawal_block_number);
+    let tx = build_escape_tx(&mut verify_ctx, rollup_dep);
+    let err = verify_ctx.verify_tx(tx).unwrap_err();
+    assert_error_eq!(err, expected_err(INVALID_STATUS_ERROR));
+
+    // the withdrawal block isn't finalized, a challenge may be pending
+    let rollup_dep = build_rollup_dep(
+        &mut verify_ctx,
+        Status::Abandoned,
+        withdrawal_block_number - 1,
+    );
+    let tx = build_escape_tx(&mut verify_ctx, rollup_dep);
+    let err = verify_ctx.verify_tx(tx).unwrap_err();
+    assert_error_eq!(err, expected_err(INVALID_ARGS_ERROR));
+}
+
+#[test]
+fn test_unlock_withdrawal_via_revert() {
+    init_env_log();
+
+    const DEFAULT_CAPACITY: u64 = 1000 * 10u64.pow(8);
+
+    let rollup_type_script = random_always_success_script();
+    let rollup_type_hash = rollup_type_script.hash();
+    let (mut verify_ctx, script_ctx) = build_verify_context();
+
+    let withdrawal_block_hash = [2u8; 32];
+    let owner_lock = random_always_success_script();
+    let withdrawal_cell = {
+        let lock_args = WithdrawalLockArgs::new_builder()
+            .account_script_hash([1u8; 32].pack())
+            .withdrawal_block_hash(withdrawal_block_hash.pack())
+            .withdrawal_block_number(100u64.pack())
+            .owner_lock_hash(owner_lock.hash().pack())
+            .build();
+
+        let mut args = Vec::new();
+        args.extend_from_slice(&lock_args.as_bytes());
+        args.extend_from_slice(&(owner_lock.as_bytes().len() as u32).to_be_bytes());
+        args.extend_from_slice(&owner_lock.as_bytes());
+
+        build_rollup_locked_cell(
+            &rollup_type_hash,
+            &script_ctx.withdrawal.script.hash(),
+            DEFAULT_CAPACITY,
+            Bytes::from(args),
+        )
+    };
+    // the withdrawal is reverted to a finalized custodian cell
+    let custodian_cell = {
+        let mut args = rollup_type_hash.to_vec();
+        args.extend_from_slice(&CustodianLockArgs::default().as_bytes());
+        let lock = Script::new_builder()
+            .hash_type(ScriptHashType::Type.into())
+            .args(Bytes::from(args).pack())
+            .build();
+        CellOutput::new_builder()
+            .capacity(DEFAULT_CAPACITY.pack())
+            .lock(lock)
+            .build()
+    };
+    let unlock_via_revert_witness = {
+        let unlock_args = UnlockWithdrawalViaRevert::new_builder()
+            .custodian_lock_hash(custodian_cell.lock().hash().pack())
+            .build();
+        let unlock_witness = UnlockWithdrawalWitness::new_builder()
+            .set(UnlockWithdrawalWitnessUnion::UnlockWithdrawalViaRevert(
+                unlock_args,
+            ))
+            .build();
+        WitnessArgs::new_builder()
+            .lock(Some(unlock_witness.as_bytes()).pack())
+            .build()
+    };
+    let rollup_config_hash: [u8; 32] = {
+        let out_point = verify_ctx.rollup_config_dep.out_point();
+        let (_, data) = verify_ctx.inner.cells.get(&out_point).unwrap();
+        ckb_types::prelude::Unpack::unpack(&ckb_types::packed::CellOutput::calc_data_hash(data))
+    };
+    let rollup_output = CellOutput::new_builder()
+        .lock(random_always_success_script())
+        .type_(Some(rollup_type_script).pack())
+        .capacity(DEFAULT_CAPACITY.pack())
+        .build();
+    // the withdrawal block hash is in the reverted block root unless it's pruned
+    let build_revert_tx = |verify_ctx: &mut CellContext, is_pruned: bool| {
+        let mut reverted_block_tree: gw_common::smt::SMT<DefaultStore<H256>> = Default::default();
+        let reverted_block_hash: H256 = if is_pruned {
+            [3u8; 32].into()
+        } else {
+            withdrawal_block_hash.into()
+        };
+        reverted_block_tree
+            .update(reverted_block_hash, H256::one())
+            .unwrap();
+        let reverted_block_proof: Bytes = reverted_block_tree
+            .merkle_proof(vec![withdrawal_block_hash.into()])
+            .unwrap()
+            .compile(vec![(withdrawal_block_hash.into(), H256::one())])
+            .unwrap()
+            .0
+            .into();
+        let global_state = GlobalState::new_builder()
+            .rollup_config_hash(rollup_config_hash.pack())
+            .reverted_block_root((*reverted_block_tree.root()).pack())
+            .build();
+        let rollup_input = {
+            let out_point = verify_ctx.insert_cell(rollup_output.to_ckb(), global_state.as_bytes());
+            CellInput::new_builder()
+                .previous_output(out_point.to_gw())
+                .build()
+        };
+        let rollup_witness = {
+            let rollup_action = RollupAction::new_builder()
+                .set(RollupActionUnion::RollupSubmitBlock(
+                    RollupSubmitBlock::new_builder()
+                        .reverted_block_hashes(
+                            Byte32Vec::new_builder()
+                                .push(withdrawal_block_hash.pack())
+                                .build(),
+                        )
+                        .reverted_block_proof(reverted_block_proof.pack())
+                        .build(),
+                ))
+                .build();
+            WitnessArgs::new_builder()
+                .output_type(Some(rollup_action.as_bytes()).pack())
+                .build()
+        };
+        let withdrawal_input = {
+            let out_point = verify_ctx.insert_cell(withdrawal_cell.clone(), Bytes::new());
+            CellInput::new_builder()
+                .previous_output(out_point.to_gw())
+                .build()
+        };
+        // the rollup witness is at the index of the output rollup cell
+        ckb_types::core::TransactionBuilder::default()
+            .input(withdrawal_input.to_ckb())
+            .input(rollup_input.to_ckb())
+            .output(custodian_cell.to_ckb())
+            .output_data(Bytes::new().to_ckb())
+            .output(rollup_output.to_ckb())
+            .output_data(global_state.as_bytes().to_ckb())
+            .witness(unlock_via_revert_witness.as_bytes().to_ckb())
+            .witness(rollup_witness.as_bytes().to_ckb())
+            .cell_dep(script_ctx.withdrawal.dep.to_ckb())
+            .cell_dep(verify_ctx.always_success_dep.clone())
+            .cell_dep(verify_ctx.rollup_config_dep.clone())
+            .build()
+    };
+
+    let tx = build_revert_tx(&mut verify_ctx, false);
+    verify_ctx.verify_tx(tx).expect("success");
+
+    // the reverted block hash is pruned
+    let tx = build_revert_tx(&mut verify_ctx, true);
+    let err = verify_ctx.verify_tx(tx).unwrap_err();
+    let expected_err = ScriptError::ValidationFailure(
+        format!(
+            "by-type-hash/{}",
+            ckb_types::H256(script_ctx.withdrawal.script.hash())
+        ),
+        PRUNED_REVERTED_BLOCK_ERROR,
+    )
+    .input_lock_script(0);
+    assert_error_eq!(err, expected_err);
+}
+
 struct ScriptDep {
     script: Script,
     dep: CellDep,
@@ -504,6 +1160,7 @@ fn build_verify_context() -> (CellContext, ScriptContext) {
         .withdrawal_script_type_hash(withdrawal_lock_type.hash().pack())
         .l1_sudt_script_type_hash(sudt_type.hash().pack())
         .finality_blocks(10u64.pack())
+        .escape_timeout_blocks(ESCAPE_TIMEOUT_BLOCKS.pack())
         .build();
     let mut ctx = CellContext::new(&config, Default::default());
 
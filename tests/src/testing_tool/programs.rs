@@ -7,6 +7,11 @@ use std::{fs, io::Read, path::PathBuf};
 const SCRIPT_DIR: &str = "../build/debug";
 const CHALLENGE_LOCK_PATH: &str = "challenge-lock";
 const WITHDRAWAL_LOCK_PATH: &str = "withdrawal-lock";
+const ROLLUP_BOUND_LOCK_PATH: &str = "rollup-bound-lock";
+const FORCED_WITHDRAWAL_LOCK_PATH: &str = "forced-withdrawal-lock";
+const CUSTODIAN_LOCK_PATH: &str = "custodian-lock";
+const BATCH_DEPOSIT_LOCK_PATH: &str = "batch-deposit-lock";
+const STAKE_LOCK_PATH: &str = "stake-lock";
 const STATE_VALIDATOR: &str = "state-validator";
 const ALWAYS_SUCCESS_PATH: &str = "always-success";
 const SECP256K1_DATA_PATH: &str = "../c/deps/ckb-production-scripts/build/secp256k1_data";
@@ -174,4 +179,60 @@ lazy_static! {
         hasher.finalize(&mut buf);
         buf
     };
+    pub static ref ROLLUP_BOUND_LOCK_PROGRAM: Bytes = {
+        let mut buf = Vec::new();
+        let mut path = PathBuf::new();
+        path.push(&SCRIPT_DIR);
+        path.push(&ROLLUP_BOUND_LOCK_PATH);
+        let mut f = fs::File::open(&path).expect("load rollup bound lock program");
+        f.read_to_end(&mut buf)
+            .expect("read rollup bound lock program");
+        Bytes::from(buf.to_vec())
+    };
+    pub static ref ROLLUP_BOUND_LOCK_CODE_HASH: [u8; 32] = {
+        let mut buf = [0u8; 32];
+        let mut hasher = new_blake2b();
+        hasher.update(&ROLLUP_BOUND_LOCK_PROGRAM);
+        hasher.finalize(&mut buf);
+        buf
+    };
+    pub static ref FORCED_WITHDRAWAL_LOCK_PROGRAM: Bytes = {
+        let mut buf = Vec::new();
+        let mut path = PathBuf::new();
+        path.push(&SCRIPT_DIR);
+        path.push(&FORCED_WITHDRAWAL_LOCK_PATH);
+        let mut f = fs::File::open(&path).expect("load forced withdrawal lock program");
+        f.read_to_end(&mut buf)
+            .expect("read forced withdrawal lock program");
+        Bytes::from(buf.to_vec())
+    };
+    pub static ref CUSTODIAN_LOCK_PROGRAM: Bytes = {
+        let mut buf = Vec::new();
+        let mut path = PathBuf::new();
+        path.push(&SCRIPT_DIR);
+        path.push(&CUSTODIAN_LOCK_PATH);
+        let mut f = fs::File::open(&path).expect("load custodian lock program");
+        f.read_to_end(&mut buf)
+            .expect("read custodian lock program");
+        Bytes::from(buf.to_vec())
+    };
+    pub static ref BATCH_DEPOSIT_LOCK_PROGRAM: Bytes = {
+        let mut buf = Vec::new();
+        let mut path = PathBuf::new();
+        path.push(&SCRIPT_DIR);
+        path.push(&BATCH_DEPOSIT_LOCK_PATH);
+        let mut f = fs::File::open(&path).expect("load batch deposit lock program");
+        f.read_to_end(&mut buf)
+            .expect("read batch deposit lock program");
+        Bytes::from(buf.to_vec())
+    };
+    pub static ref STAKE_LOCK_PROGRAM: Bytes = {
+        let mut buf = Vec::new();
+        let mut path = PathBuf::new();
+        path.push(&SCRIPT_DIR);
+        path.push(&STAKE_LOCK_PATH);
+        let mut f = fs::File::open(&path).expect("load stake lock program");
+        f.read_to_end(&mut buf).expect("read stake lock program");
+        Bytes::from(buf.to_vec())
+    };
 }
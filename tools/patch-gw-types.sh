@@ -0,0 +1,43 @@
+#!/bin/bash
+# Patch the gw-types crate of the godwoken submodule with the schema in `c/godwoken.mol`.
+#
+# The contracts and the tests use the patched crate through the `[patch]` section of
+# `Cargo.toml`, run this script after `git submodule update` and whenever the schema changes.
+
+set -euo pipefail
+
+MOLC=${MOLC:-moleculec}
+ROOT_DIR=$(cd "$(dirname "$0")/.." && pwd)
+GW_TYPES_DIR="$ROOT_DIR/deps/godwoken/crates/types"
+CORE_RS="$GW_TYPES_DIR/src/core.rs"
+
+if [ ! -d "$GW_TYPES_DIR" ]; then
+  echo "$GW_TYPES_DIR not found, run 'git submodule update --init deps/godwoken' first"
+  exit 1
+fi
+
+# regenerate the molecule types
+cp "$ROOT_DIR/c/godwoken.mol" "$GW_TYPES_DIR/schemas/godwoken.mol"
+"$MOLC" --language rust --schema-file "$GW_TYPES_DIR/schemas/godwoken.mol" \
+  > "$GW_TYPES_DIR/src/generated/godwoken.rs"
+
+# add_variant <enum> <prev variant> <prev value> <variant> <value>
+#
+# append a variant to an enum of `core.rs`, and a match arm after each arm of the previous variant
+add_variant() {
+  local enum=$1 prev=$2 prev_value=$3 variant=$4 value=$5
+  if grep -q "$enum::$variant\b" "$CORE_RS"; then
+    return
+  fi
+  perl -0pi -e "s/(pub enum $enum \{[^}]*?\n(\s*)$prev = $prev_value,\n)/\${1}\${2}$variant = $value,\n/" "$CORE_RS"
+  perl -pi -e "if (/^(\s*)$prev_value(\s*=>\s*Ok\(\s*$enum::)$prev\)/) { \$_ .= \"\${1}$value\${2}$variant),\n\" }" "$CORE_RS"
+  if ! grep -q "$variant = $value," "$CORE_RS" || ! grep -q "$enum::$variant)" "$CORE_RS"; then
+    echo "failed to add $enum::$variant to $CORE_RS"
+    exit 1
+  fi
+}
+
+add_variant Status Halting 1 Paused 2
+add_variant Status Paused 2 Abandoned 3
+add_variant ChallengeTargetType Withdrawal 2 ForcedWithdrawal 4
+add_variant ChallengeTargetType Withdrawal 2 Deposit 3